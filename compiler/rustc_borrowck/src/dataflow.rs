@@ -49,7 +49,7 @@ impl<'tcx, $($A),*, D: Direction> ResultsVisitable<'tcx> for $T<$( Results<'tcx,
 
             fn new_flow_state(&self, body: &mir::Body<'tcx>) -> Self::FlowState {
                 $T {
-                    $( $field: self.$field.analysis.bottom_value(body) ),*
+                    $( $field: self.$field.new_flow_state(body) ),*
                 }
             }
 
@@ -91,14 +91,18 @@ fn reconstruct_before_terminator_effect(
                     .apply_before_terminator_effect(&mut state.$field, term, loc); )*
             }
 
-            fn reconstruct_terminator_effect(
+            fn reconstruct_terminator_effect<'mir>(
                 &mut self,
                 state: &mut Self::FlowState,
-                term: &mir::Terminator<'tcx>,
+                term: &'mir mir::Terminator<'tcx>,
                 loc: Location,
-            ) {
+            ) -> TerminatorEdges<'mir, 'tcx> {
                 $( self.$field.analysis
                     .apply_terminator_effect(&mut state.$field, term, loc); )*
+
+                // The analyses may disagree about which edges are feasible, so conservatively
+                // consider all of them.
+                term.edges()
             }
         }
     )* }
@@ -513,7 +517,7 @@ impl<'tcx> rustc_mir_dataflow::AnalysisDomain<'tcx> for Borrows<'_, 'tcx> {
 
     const NAME: &'static str = "borrows";
 
-    fn bottom_value(&self, _: &mir::Body<'tcx>) -> Self::Domain {
+    fn bottom_value(&self, _: TyCtxt<'tcx>, _: &mir::Body<'tcx>) -> Self::Domain {
         // bottom = nothing is reserved or activated yet;
         BitSet::new_empty(self.borrow_set.len())
     }
@@ -538,6 +542,10 @@ fn domain_size(&self, _: &mir::Body<'tcx>) -> usize {
         self.borrow_set.len()
     }
 
+    fn domain_element_name(&self, idx: Self::Idx, _: &mir::Body<'tcx>) -> String {
+        format!("{:?}", self.location(idx))
+    }
+
     fn before_statement_effect(
         &mut self,
         trans: &mut impl GenKill<Self::Idx>,
@@ -229,6 +229,7 @@ fn do_mir_borrowck<'tcx>(
     let mut flow_inits = MaybeInitializedPlaces::new(tcx, body, &mdpe)
         .into_engine(tcx, body)
         .pass_name("borrowck")
+        .phase("nll")
         .iterate_to_fixpoint()
         .into_results_cursor(body);
 
@@ -283,14 +284,17 @@ fn do_mir_borrowck<'tcx>(
     let flow_borrows = Borrows::new(tcx, body, &regioncx, &borrow_set)
         .into_engine(tcx, body)
         .pass_name("borrowck")
+        .phase("borrowck")
         .iterate_to_fixpoint();
     let flow_uninits = MaybeUninitializedPlaces::new(tcx, body, &mdpe)
         .into_engine(tcx, body)
         .pass_name("borrowck")
+        .phase("borrowck")
         .iterate_to_fixpoint();
     let flow_ever_inits = EverInitializedPlaces::new(tcx, body, &mdpe)
         .into_engine(tcx, body)
         .pass_name("borrowck")
+        .phase("borrowck")
         .iterate_to_fixpoint();
 
     let movable_coroutine =
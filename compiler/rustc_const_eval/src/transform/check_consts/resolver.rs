@@ -7,6 +7,7 @@
 use rustc_middle::mir::{
     self, BasicBlock, CallReturnPlaces, Local, Location, Statement, StatementKind, TerminatorEdges,
 };
+use rustc_middle::ty::TyCtxt;
 use rustc_mir_dataflow::fmt::DebugWithContext;
 use rustc_mir_dataflow::JoinSemiLattice;
 use rustc_mir_dataflow::{Analysis, AnalysisDomain};
@@ -322,7 +323,7 @@ impl<'tcx, Q> AnalysisDomain<'tcx> for FlowSensitiveAnalysis<'_, '_, 'tcx, Q>
 
     const NAME: &'static str = Q::ANALYSIS_NAME;
 
-    fn bottom_value(&self, body: &mir::Body<'tcx>) -> Self::Domain {
+    fn bottom_value(&self, _: TyCtxt<'tcx>, body: &mir::Body<'tcx>) -> Self::Domain {
         State {
             qualif: BitSet::new_empty(body.local_decls.len()),
             borrow: BitSet::new_empty(body.local_decls.len()),
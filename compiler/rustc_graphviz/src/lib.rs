@@ -592,6 +592,7 @@ pub enum RenderOption {
     NoNodeStyles,
 
     Fontname(String),
+    FontSize(u32),
     DarkTheme,
 }
 
@@ -629,6 +630,14 @@ pub fn render_opts<'a, N, E, G, W>(g: &'a G, w: &mut W, options: &[RenderOption]
         graph_attrs.push(&font[..]);
         content_attrs.push(&font[..]);
     }
+    let font_size;
+    if let Some(size) = options.iter().find_map(|option| {
+        if let RenderOption::FontSize(size) = option { Some(size) } else { None }
+    }) {
+        font_size = format!("fontsize={size}");
+        graph_attrs.push(&font_size[..]);
+        content_attrs.push(&font_size[..]);
+    }
     if options.contains(&RenderOption::DarkTheme) {
         graph_attrs.push(r#"bgcolor="black""#);
         graph_attrs.push(r#"fontcolor="white""#);
@@ -3,8 +3,8 @@
 use std::marker::PhantomData;
 use std::mem;
 use std::ops::{BitAnd, BitAndAssign, BitOrAssign, Bound, Not, Range, RangeBounds, Shl};
-use std::rc::Rc;
 use std::slice;
+use std::sync::Arc;
 
 use arrayvec::ArrayVec;
 use smallvec::{smallvec, SmallVec};
@@ -390,11 +390,12 @@ enum Chunk {
     /// The second field is the count of 1s set in the chunk, and must satisfy
     /// `0 < count < chunk_domain_size`.
     ///
-    /// The words are within an `Rc` because it's surprisingly common to
+    /// The words are within an `Arc` because it's surprisingly common to
     /// duplicate an entire chunk, e.g. in `ChunkedBitSet::clone_from()`, or
     /// when a `Mixed` chunk is union'd into a `Zeros` chunk. When we do need
-    /// to modify a chunk we use `Rc::make_mut`.
-    Mixed(ChunkSize, ChunkSize, Rc<[Word; CHUNK_WORDS]>),
+    /// to modify a chunk we use `Arc::make_mut`. It is an `Arc` rather than
+    /// an `Rc` so that dataflow results can be shared between threads.
+    Mixed(ChunkSize, ChunkSize, Arc<[Word; CHUNK_WORDS]>),
 }
 
 // This type is used a lot. Make sure it doesn't unintentionally get bigger.
@@ -492,10 +493,10 @@ pub fn insert(&mut self, elem: T) -> bool {
             Zeros(chunk_domain_size) => {
                 if chunk_domain_size > 1 {
                     // We take some effort to avoid copying the words.
-                    let words = Rc::<[Word; CHUNK_WORDS]>::new_zeroed();
+                    let words = Arc::<[Word; CHUNK_WORDS]>::new_zeroed();
                     // SAFETY: `words` can safely be all zeroes.
                     let mut words = unsafe { words.assume_init() };
-                    let words_ref = Rc::get_mut(&mut words).unwrap();
+                    let words_ref = Arc::get_mut(&mut words).unwrap();
 
                     let (word_index, mask) = chunk_word_index_and_mask(elem);
                     words_ref[word_index] |= mask;
@@ -512,7 +513,7 @@ pub fn insert(&mut self, elem: T) -> bool {
                 if (words[word_index] & mask) == 0 {
                     *count += 1;
                     if *count < chunk_domain_size {
-                        let words = Rc::make_mut(words);
+                        let words = Arc::make_mut(words);
                         words[word_index] |= mask;
                     } else {
                         *chunk = Ones(chunk_domain_size);
@@ -546,10 +547,10 @@ pub fn remove(&mut self, elem: T) -> bool {
             Ones(chunk_domain_size) => {
                 if chunk_domain_size > 1 {
                     // We take some effort to avoid copying the words.
-                    let words = Rc::<[Word; CHUNK_WORDS]>::new_zeroed();
+                    let words = Arc::<[Word; CHUNK_WORDS]>::new_zeroed();
                     // SAFETY: `words` can safely be all zeroes.
                     let mut words = unsafe { words.assume_init() };
-                    let words_ref = Rc::get_mut(&mut words).unwrap();
+                    let words_ref = Arc::get_mut(&mut words).unwrap();
 
                     // Set only the bits in use.
                     let num_words = num_words(chunk_domain_size as usize);
@@ -572,7 +573,7 @@ pub fn remove(&mut self, elem: T) -> bool {
                 if (words[word_index] & mask) != 0 {
                     *count -= 1;
                     if *count > 0 {
-                        let words = Rc::make_mut(words);
+                        let words = Arc::make_mut(words);
                         words[word_index] &= !mask;
                     } else {
                         *chunk = Zeros(chunk_domain_size);
@@ -625,7 +626,7 @@ fn union(&mut self, other: &ChunkedBitSet<T>) -> bool {
                         &other_chunk_words[0..num_words],
                         op,
                     ) {
-                        let self_chunk_words = Rc::make_mut(self_chunk_words);
+                        let self_chunk_words = Arc::make_mut(self_chunk_words);
                         let has_changed = bitwise(
                             &mut self_chunk_words[0..num_words],
                             &other_chunk_words[0..num_words],
@@ -651,8 +652,58 @@ fn subtract(&mut self, _other: &ChunkedBitSet<T>) -> bool {
         unimplemented!("implement if/when necessary");
     }
 
-    fn intersect(&mut self, _other: &ChunkedBitSet<T>) -> bool {
-        unimplemented!("implement if/when necessary");
+    fn intersect(&mut self, other: &ChunkedBitSet<T>) -> bool {
+        assert_eq!(self.domain_size, other.domain_size);
+        debug_assert_eq!(self.chunks.len(), other.chunks.len());
+
+        let mut changed = false;
+        for (mut self_chunk, other_chunk) in self.chunks.iter_mut().zip(other.chunks.iter()) {
+            match (&mut self_chunk, &other_chunk) {
+                (Zeros(_), _) | (_, Ones(_)) => {}
+                (Ones(self_chunk_domain_size), Zeros(other_chunk_domain_size))
+                | (Mixed(self_chunk_domain_size, ..), Zeros(other_chunk_domain_size))
+                | (Ones(self_chunk_domain_size), Mixed(other_chunk_domain_size, ..)) => {
+                    // `other_chunk` fully overwrites `self_chunk`
+                    debug_assert_eq!(self_chunk_domain_size, other_chunk_domain_size);
+                    *self_chunk = other_chunk.clone();
+                    changed = true;
+                }
+                (
+                    Mixed(
+                        self_chunk_domain_size,
+                        ref mut self_chunk_count,
+                        ref mut self_chunk_words,
+                    ),
+                    Mixed(_other_chunk_domain_size, _other_chunk_count, other_chunk_words),
+                ) => {
+                    // See the comment in `union`.
+                    let op = |a, b| a & b;
+                    let num_words = num_words(*self_chunk_domain_size as usize);
+                    if bitwise_changes(
+                        &self_chunk_words[0..num_words],
+                        &other_chunk_words[0..num_words],
+                        op,
+                    ) {
+                        let self_chunk_words = Arc::make_mut(self_chunk_words);
+                        let has_changed = bitwise(
+                            &mut self_chunk_words[0..num_words],
+                            &other_chunk_words[0..num_words],
+                            op,
+                        );
+                        debug_assert!(has_changed);
+                        *self_chunk_count = self_chunk_words[0..num_words]
+                            .iter()
+                            .map(|w| w.count_ones() as ChunkSize)
+                            .sum();
+                        if *self_chunk_count == 0 {
+                            *self_chunk = Zeros(*self_chunk_domain_size);
+                        }
+                        changed = true;
+                    }
+                }
+            }
+        }
+        changed
     }
 }
 
@@ -1603,6 +1654,11 @@ pub fn rows(&self) -> impl Iterator<Item = R> {
         (0..self.num_rows).map(R::new)
     }
 
+    /// The domain size of each row.
+    pub fn num_columns(&self) -> usize {
+        self.num_columns
+    }
+
     /// The range of bits for a given row.
     fn range(&self, row: R) -> (usize, usize) {
         let words_per_row = num_words(self.num_columns);
@@ -1698,6 +1754,14 @@ pub fn union_row_with(&mut self, with: &BitSet<C>, write: R) -> bool {
         changed
     }
 
+    /// Overwrites `set` with the bits from row `read`.
+    pub fn copy_row_into(&self, read: R, set: &mut BitSet<C>) {
+        assert!(read.index() < self.num_rows);
+        assert_eq!(set.domain_size(), self.num_columns);
+        let (read_start, read_end) = self.range(read);
+        set.words.copy_from_slice(&self.words[read_start..read_end]);
+    }
+
     /// Sets every cell in `row` to true.
     pub fn insert_all_into_row(&mut self, row: R) {
         assert!(row.index() < self.num_rows);
@@ -227,7 +227,7 @@ fn chunked_bitset() {
             100,
             97,
             #[rustfmt::skip]
-            Rc::new([
+            Arc::new([
                 0b11111111_11111111_11111110_11111111_11111111_11101111_11111111_11111111,
                 0b00000000_00000000_00000000_00000111_11111111_11111111_11111111_11111111,
                 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
@@ -294,11 +294,11 @@ fn chunked_bitset() {
         b4096.chunks(),
         #[rustfmt::skip]
         vec![
-            Mixed(2048, 1, Rc::new([
+            Mixed(2048, 1, Arc::new([
                 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
                 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0
             ])),
-            Mixed(2048, 1, Rc::new([
+            Mixed(2048, 1, Arc::new([
                 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
                 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x8000_0000_0000_0000
             ])),
@@ -326,11 +326,11 @@ fn chunked_bitset() {
         #[rustfmt::skip]
         vec![
             Zeros(2048),
-            Mixed(2048, 1, Rc::new([
+            Mixed(2048, 1, Arc::new([
                 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x0100_0000_0000_0000, 0,
                 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
             ])),
-            Mixed(2048, 1, Rc::new([
+            Mixed(2048, 1, Arc::new([
                 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x0100, 0,
                 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
             ])),
@@ -395,6 +395,29 @@ fn chunked_bitset_into_bitset_operations() {
     assert!(intersection.iter().eq(aib.iter().copied()));
 }
 
+#[test]
+fn chunked_bitset_intersect() {
+    // Chunk 0 is mixed in both, chunk 1 is full in `a` and mixed in `b`, chunk 2 is mixed in `a`
+    // and empty in `b`, and chunk 3 is full in both.
+    let mut a = with_elements_chunked(&[1, 5, 7, 11, 15, 4100], 9876);
+    let mut b = with_elements_chunked(&[3, 4, 11, 3000], 9876);
+    for i in (2048..4096).chain(6144..8192) {
+        a.insert(i);
+    }
+    for i in 6144..8192 {
+        b.insert(i);
+    }
+
+    let mut expected = vec![11, 3000];
+    expected.extend(6144..8192);
+
+    assert!(a.intersect(&b));
+    assert!(!a.intersect(&b));
+    assert!(a.iter().eq(expected.iter().copied()));
+    assert_eq!(a.count(), expected.len());
+    a.assert_valid();
+}
+
 #[test]
 fn chunked_bitset_iter() {
     fn check_iter(bit: &ChunkedBitSet<usize>, vec: &Vec<usize>) {
@@ -494,6 +517,29 @@ fn matrix_intersection() {
     assert_eq!(intersection, &[10, 64, 160]);
 }
 
+#[test]
+fn matrix_copy_row_into() {
+    let mut matrix: BitMatrix<usize, usize> = BitMatrix::new(4, 100);
+    matrix.insert(1, 3);
+    matrix.insert(1, 64);
+    matrix.insert(1, 99);
+    matrix.insert(2, 5);
+
+    let mut set = BitSet::new_filled(100);
+    matrix.copy_row_into(1, &mut set);
+    assert_eq!(set.iter().collect::<Vec<_>>(), [3, 64, 99]);
+
+    matrix.copy_row_into(0, &mut set);
+    assert!(set.is_empty());
+
+    assert!(!matrix.union_row_with(&set, 3));
+    set.insert(7);
+    assert!(matrix.union_row_with(&set, 3));
+    let mut row = BitSet::new_empty(100);
+    matrix.copy_row_into(3, &mut row);
+    assert_eq!(row, set);
+}
+
 #[test]
 fn matrix_iter() {
     let mut matrix: BitMatrix<usize, usize> = BitMatrix::new(64, 100);
@@ -665,6 +665,7 @@ macro_rules! untracked {
     untracked!(dump_dep_graph, true);
     untracked!(dump_mir, Some(String::from("abc")));
     untracked!(dump_mir_dataflow, true);
+    untracked!(dump_mir_dataflow_phases, Some(vec![String::from("abc")]));
     untracked!(dump_mir_dir, String::from("abc"));
     untracked!(dump_mir_exclude_pass_number, true);
     untracked!(dump_mir_graphviz, true);
@@ -3,8 +3,13 @@
 use rustc_hir::def_id::DefId;
 use rustc_middle::mir::*;
 use rustc_middle::ty::{self, TyCtxt};
+use rustc_span::symbol::Symbol;
+use rustc_span::Span;
+use std::ffi::OsString;
 use std::fmt::Debug;
 use std::io::{self, Write};
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
 
 use super::generic_graph::mir_fn_to_generic_graph;
 use super::pretty::dump_mir_def_ids;
@@ -129,3 +134,41 @@ fn write_graph_label<'tcx, W: std::fmt::Write>(
 fn escape<T: Debug>(t: &T) -> String {
     dot::escape_html(&format!("{t:?}"))
 }
+
+/// The options for dumping the results of a dataflow analysis, parsed from the `#[rustc_mir]`
+/// attributes of an item by `rustc_mir_dataflow`.
+///
+/// These live here rather than in `rustc_mir_dataflow` so that they can be the result of the
+/// `rustc_mir_attrs` query.
+#[derive(Clone, Debug, Default, HashStable)]
+pub struct RustcMirAttrs {
+    pub basename_and_suffix: Option<PathBuf>,
+    pub formatter: Option<Symbol>,
+    pub wrap_width: Option<usize>,
+    pub font_size: Option<u32>,
+    pub track_bit: Option<usize>,
+    /// The block given by `borrowck_uncached_block`, along with the span of the attribute. Whether
+    /// it is a block of the body is only checked when the body is analyzed.
+    pub uncached_block: Option<(BasicBlock, Span)>,
+    pub block_range: Option<RangeInclusive<BasicBlock>>,
+}
+
+impl RustcMirAttrs {
+    /// Returns the path where dataflow results should be written, or `None`
+    /// `borrowck_graphviz_postflow` was not specified.
+    ///
+    /// This performs the following transformation to the argument of `borrowck_graphviz_postflow`:
+    ///
+    /// "path/suffix.dot" -> "path/analysis_name_suffix.dot"
+    pub fn output_path(&self, analysis_name: &str) -> Option<PathBuf> {
+        let mut ret = self.basename_and_suffix.as_ref().cloned()?;
+        let suffix = ret.file_name().unwrap(); // Checked when parsing attrs
+
+        let mut file_name: OsString = analysis_name.into();
+        file_name.push("_");
+        file_name.push(suffix);
+        ret.set_file_name(file_name);
+
+        Some(ret)
+    }
+}
@@ -1015,6 +1015,14 @@
         cache_on_disk_if(tcx) { tcx.is_typeck_child(key.to_def_id()) }
     }
 
+    /// Parses the `#[rustc_mir]` attributes that control how the results of dataflow analyses on
+    /// the body of `key` are dumped, reporting any errors in them. Returns `Err` if they are
+    /// malformed.
+    query rustc_mir_attrs(key: DefId) -> &'tcx Result<mir::graphviz::RustcMirAttrs, ()> {
+        arena_cache
+        desc { |tcx| "parsing the `rustc_mir` attributes of `{}`", tcx.def_path_str(key) }
+    }
+
     /// Gets a complete map from all types to their inherent impls.
     /// Not meant to be used directly outside of coherence.
     query crate_inherent_impls(k: ()) -> &'tcx CrateInherentImpls {
@@ -1,6 +1,25 @@
+mir_dataflow_did_not_converge =
+    dataflow analysis `{$name}` did not converge
+    .note = the entry sets of these blocks kept changing: {$blocks}
+
 mir_dataflow_duplicate_values_for =
     duplicate values for `{$name}`
 
+mir_dataflow_invalid_block_range =
+    block range must be of the form `start-end`, like `5-20`
+
+mir_dataflow_invalid_font_size =
+    font size must be a positive integer
+
+mir_dataflow_invalid_tracked_bit =
+    tracked bit must be a non-negative integer
+
+mir_dataflow_invalid_uncached_block =
+    uncached block must be a basic block of this body, like `bb4`
+
+mir_dataflow_invalid_wrap_width =
+    wrap width must be a positive integer
+
 mir_dataflow_path_must_end_in_filename =
     path must end in a filename
 
@@ -22,6 +41,10 @@ mir_dataflow_peek_must_be_place_or_ref_place =
 mir_dataflow_requires_an_argument =
     `{$name}` requires an argument
 
+mir_dataflow_start_block_seed_lost =
+    dataflow analysis `{$name}` lost its start block seed
+    .note = none of the facts set by `initialize_start_block` hold on entry to `bb0` after solving
+
 mir_dataflow_stop_after_dataflow_ended_compilation =
     stop_after_dataflow ended compilation
 
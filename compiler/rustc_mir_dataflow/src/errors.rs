@@ -15,6 +15,41 @@ pub(crate) struct UnknownFormatter {
     pub span: Span,
 }
 
+#[derive(Diagnostic)]
+#[diag(mir_dataflow_invalid_block_range)]
+pub(crate) struct InvalidBlockRange {
+    #[primary_span]
+    pub span: Span,
+}
+
+#[derive(Diagnostic)]
+#[diag(mir_dataflow_invalid_tracked_bit)]
+pub(crate) struct InvalidTrackedBit {
+    #[primary_span]
+    pub span: Span,
+}
+
+#[derive(Diagnostic)]
+#[diag(mir_dataflow_invalid_uncached_block)]
+pub(crate) struct InvalidUncachedBlock {
+    #[primary_span]
+    pub span: Span,
+}
+
+#[derive(Diagnostic)]
+#[diag(mir_dataflow_invalid_font_size)]
+pub(crate) struct InvalidFontSize {
+    #[primary_span]
+    pub span: Span,
+}
+
+#[derive(Diagnostic)]
+#[diag(mir_dataflow_invalid_wrap_width)]
+pub(crate) struct InvalidWrapWidth {
+    #[primary_span]
+    pub span: Span,
+}
+
 #[derive(Diagnostic)]
 #[diag(mir_dataflow_duplicate_values_for)]
 pub(crate) struct DuplicateValuesFor {
@@ -31,6 +66,25 @@ pub(crate) struct RequiresAnArgument {
     pub name: Symbol,
 }
 
+#[derive(Diagnostic)]
+#[diag(mir_dataflow_did_not_converge)]
+#[note]
+pub(crate) struct DidNotConverge {
+    #[primary_span]
+    pub span: Span,
+    pub name: &'static str,
+    pub blocks: String,
+}
+
+#[derive(Diagnostic)]
+#[diag(mir_dataflow_start_block_seed_lost)]
+#[note]
+pub(crate) struct StartBlockSeedLost {
+    #[primary_span]
+    pub span: Span,
+    pub name: &'static str,
+}
+
 #[derive(Diagnostic)]
 #[diag(mir_dataflow_stop_after_dataflow_ended_compilation)]
 pub(crate) struct StopAfterDataFlowEndedCompilation;
@@ -0,0 +1,136 @@
+//! Gen/kill analyses defined by closures, for diagnostics and experiments.
+//!
+//! Implementing `AnalysisDomain` and `GenKillAnalysis` for a one-off question about a body is a lot
+//! of boilerplate. The constructors in this module build such an analysis from a statement effect
+//! and a terminator effect instead. For example, the following finds the blocks from which a call
+//! is reachable:
+//!
+//! ```ignore (cross-crate-imports)
+//! let analysis = adhoc::backward_bitset_analysis::<usize>(
+//!     1,
+//!     |_, _, _| {},
+//!     |trans, terminator, _| {
+//!         if let mir::TerminatorKind::Call { .. } = terminator.kind {
+//!             trans.gen(0);
+//!         }
+//!     },
+//! );
+//! let mut cursor =
+//!     analysis.into_engine(tcx, body).iterate_to_fixpoint().into_results_cursor(body);
+//! for bb in body.basic_blocks.indices() {
+//!     cursor.seek_to_block_start(bb);
+//!     if cursor.contains(0) {
+//!         // A call is reachable from `bb`.
+//!     }
+//! }
+//! ```
+//!
+//! These analyses have no edge-specific effects and start from an empty state. Since
+//! `AnalysisDomain::NAME` must be a constant, they are all named `adhoc`.
+
+use std::marker::PhantomData;
+
+use rustc_index::bit_set::BitSet;
+use rustc_index::Idx;
+use rustc_middle::mir::{self, CallReturnPlaces, Location, TerminatorEdges};
+use rustc_middle::ty::TyCtxt;
+
+use super::{AnalysisDomain, Backward, Direction, Forward, GenKill, GenKillAnalysis, GenKillSet};
+
+/// Returns a forward gen/kill analysis over a `BitSet<I>` of `domain_size` bits.
+///
+/// `statement_effect` and `terminator_effect` are the transfer functions of the primary effects of
+/// statements and terminators, respectively. See the module documentation for an example.
+pub fn forward_bitset_analysis<'tcx, I: Idx>(
+    domain_size: usize,
+    statement_effect: impl FnMut(&mut GenKillSet<I>, &mir::Statement<'tcx>, Location),
+    terminator_effect: impl FnMut(&mut GenKillSet<I>, &mir::Terminator<'tcx>, Location),
+) -> impl GenKillAnalysis<'tcx, Idx = I, Domain = BitSet<I>, Direction = Forward> {
+    AdhocAnalysis { domain_size, statement_effect, terminator_effect, _marker: PhantomData }
+}
+
+/// Like `forward_bitset_analysis`, but for a backward analysis.
+pub fn backward_bitset_analysis<'tcx, I: Idx>(
+    domain_size: usize,
+    statement_effect: impl FnMut(&mut GenKillSet<I>, &mir::Statement<'tcx>, Location),
+    terminator_effect: impl FnMut(&mut GenKillSet<I>, &mir::Terminator<'tcx>, Location),
+) -> impl GenKillAnalysis<'tcx, Idx = I, Domain = BitSet<I>, Direction = Backward> {
+    AdhocAnalysis { domain_size, statement_effect, terminator_effect, _marker: PhantomData }
+}
+
+struct AdhocAnalysis<I, D, S, T> {
+    domain_size: usize,
+    statement_effect: S,
+    terminator_effect: T,
+    _marker: PhantomData<(I, D)>,
+}
+
+/// Applies an effect recorded by one of the closures of an `AdhocAnalysis` to `trans`.
+///
+/// The closures are given a concrete `GenKillSet` since they cannot be generic over `GenKill`.
+fn apply_effect<I: Idx>(trans: &mut impl GenKill<I>, effect: GenKillSet<I>) {
+    trans.gen_all(effect.gen.iter());
+    trans.kill_all(effect.kill.iter());
+}
+
+impl<'tcx, I, D, S, T> AnalysisDomain<'tcx> for AdhocAnalysis<I, D, S, T>
+where
+    I: Idx,
+    D: Direction,
+{
+    type Domain = BitSet<I>;
+    type Direction = D;
+
+    const NAME: &'static str = "adhoc";
+
+    fn bottom_value(&self, _: TyCtxt<'tcx>, _: &mir::Body<'tcx>) -> Self::Domain {
+        BitSet::new_empty(self.domain_size)
+    }
+
+    fn initialize_start_block(&self, _: &mir::Body<'tcx>, _: &mut Self::Domain) {}
+}
+
+impl<'tcx, I, D, S, T> GenKillAnalysis<'tcx> for AdhocAnalysis<I, D, S, T>
+where
+    I: Idx,
+    D: Direction,
+    S: FnMut(&mut GenKillSet<I>, &mir::Statement<'tcx>, Location),
+    T: FnMut(&mut GenKillSet<I>, &mir::Terminator<'tcx>, Location),
+{
+    type Idx = I;
+
+    fn domain_size(&self, _: &mir::Body<'tcx>) -> usize {
+        self.domain_size
+    }
+
+    fn statement_effect(
+        &mut self,
+        trans: &mut impl GenKill<Self::Idx>,
+        statement: &mir::Statement<'tcx>,
+        location: Location,
+    ) {
+        let mut effect = GenKillSet::identity(self.domain_size);
+        (self.statement_effect)(&mut effect, statement, location);
+        apply_effect(trans, effect);
+    }
+
+    fn terminator_effect<'mir>(
+        &mut self,
+        trans: &mut Self::Domain,
+        terminator: &'mir mir::Terminator<'tcx>,
+        location: Location,
+    ) -> TerminatorEdges<'mir, 'tcx> {
+        let mut effect = GenKillSet::identity(self.domain_size);
+        (self.terminator_effect)(&mut effect, terminator, location);
+        apply_effect(trans, effect);
+        terminator.edges()
+    }
+
+    fn call_return_effect(
+        &mut self,
+        _trans: &mut impl GenKill<Self::Idx>,
+        _block: mir::BasicBlock,
+        _return_places: CallReturnPlaces<'_, 'tcx>,
+    ) {
+    }
+}
@@ -0,0 +1,167 @@
+//! A driver for pairs of interdependent forward and backward dataflow analyses.
+
+use rustc_middle::mir;
+use rustc_middle::ty::TyCtxt;
+
+use super::engine::{iterate_entry_sets_to_fixpoint, FixpointOptions};
+use super::{Analysis, AnalysisDomain, Backward, EntrySets, Forward, Results};
+
+/// An analysis whose transfer functions depend on the results of another analysis over the same
+/// body. See `BidirectionalEngine`.
+pub trait CoupledAnalysis<'tcx, Other>: Analysis<'tcx>
+where
+    Other: AnalysisDomain<'tcx>,
+{
+    /// Called before each pass of this analysis with the current entry sets of `Other`.
+    ///
+    /// Implementers should store whatever they need from `other` for use in their transfer
+    /// functions.
+    fn observe(&mut self, other: &EntrySets<'tcx, Other>);
+}
+
+/// A solver for a pair of mutually dependent dataflow problems, one forward and one backward.
+///
+/// The engine alternates between iterating the forward analysis to fixpoint and iterating the
+/// backward analysis to fixpoint, each time handing the latest entry sets of one analysis to the
+/// other (see `CoupledAnalysis::observe`). It stops once a full round leaves the entry sets of
+/// both analyses unchanged.
+///
+/// # Convergence
+///
+/// Each analysis must be monotone in the results of the other: if the entry sets passed to
+/// `observe` grow, the fixpoint of the observing analysis may only grow as well. Otherwise, the
+/// two analyses may oscillate forever. As a guard against this, the engine panics if the pair has
+/// not converged after `max_rounds` rounds.
+pub struct BidirectionalEngine<'a, 'tcx, F, B>
+where
+    F: Analysis<'tcx, Direction = Forward>,
+    B: Analysis<'tcx, Direction = Backward>,
+{
+    body: &'a mir::Body<'tcx>,
+    forward: F,
+    backward: B,
+    forward_bottom: F::Domain,
+    backward_bottom: B::Domain,
+    max_rounds: usize,
+}
+
+impl<'a, 'tcx, F, B> BidirectionalEngine<'a, 'tcx, F, B>
+where
+    F: CoupledAnalysis<'tcx, B, Direction = Forward>,
+    B: CoupledAnalysis<'tcx, F, Direction = Backward>,
+{
+    /// The default value of `max_rounds`. Chosen arbitrarily.
+    const DEFAULT_MAX_ROUNDS: usize = 100;
+
+    pub fn new(
+        tcx: TyCtxt<'tcx>,
+        body: &'a mir::Body<'tcx>,
+        mut forward: F,
+        mut backward: B,
+    ) -> Self {
+        forward.pre_iterate(body);
+        backward.pre_iterate(body);
+        let forward_bottom = forward.bottom_value(tcx, body);
+        let backward_bottom = backward.bottom_value(tcx, body);
+        Self::with_bottom_values(body, forward, backward, forward_bottom, backward_bottom)
+    }
+
+    /// Like `new`, but with the bottom values of both analyses given, and without calling their
+    /// `pre_iterate`. This is for the unit tests, which have no `TyCtxt`.
+    pub(super) fn with_bottom_values(
+        body: &'a mir::Body<'tcx>,
+        forward: F,
+        backward: B,
+        forward_bottom: F::Domain,
+        backward_bottom: B::Domain,
+    ) -> Self {
+        BidirectionalEngine {
+            body,
+            forward,
+            backward,
+            forward_bottom,
+            backward_bottom,
+            max_rounds: Self::DEFAULT_MAX_ROUNDS,
+        }
+    }
+
+    /// Sets the maximum number of rounds (one forward and one backward fixpoint iteration each)
+    /// before the engine gives up.
+    pub fn max_rounds(mut self, max_rounds: usize) -> Self {
+        self.max_rounds = max_rounds;
+        self
+    }
+
+    /// Computes the joint fixpoint of both analyses and returns it.
+    pub fn iterate_to_fixpoint(self) -> (Results<'tcx, F>, Results<'tcx, B>) {
+        let BidirectionalEngine {
+            body,
+            mut forward,
+            mut backward,
+            forward_bottom,
+            backward_bottom,
+            max_rounds,
+        } = self;
+
+        let mut forward_entry_sets = initial_entry_sets(&forward, body, &forward_bottom);
+        let mut backward_entry_sets = initial_entry_sets(&backward, body, &backward_bottom);
+
+        for _ in 0..max_rounds {
+            // Each round starts from scratch, so that every fixpoint is computed with respect to a
+            // single snapshot of the other analysis' results.
+            forward.observe(&backward_entry_sets);
+            let mut new_forward_entry_sets =
+                initial_entry_sets(&forward, body, &forward_bottom);
+            let options = FixpointOptions::default();
+            let converged = iterate_entry_sets_to_fixpoint(
+                &mut forward,
+                body,
+                &mut new_forward_entry_sets,
+                options,
+            );
+            assert!(converged.is_ok());
+
+            backward.observe(&new_forward_entry_sets);
+            let mut new_backward_entry_sets =
+                initial_entry_sets(&backward, body, &backward_bottom);
+            let options = FixpointOptions::default();
+            let converged = iterate_entry_sets_to_fixpoint(
+                &mut backward,
+                body,
+                &mut new_backward_entry_sets,
+                options,
+            );
+            assert!(converged.is_ok());
+
+            let changed = new_forward_entry_sets != forward_entry_sets
+                || new_backward_entry_sets != backward_entry_sets;
+            forward_entry_sets = new_forward_entry_sets;
+            backward_entry_sets = new_backward_entry_sets;
+
+            if !changed {
+                let forward = Results::new(forward, forward_entry_sets);
+                let backward = Results::new(backward, backward_entry_sets);
+                return (forward, backward);
+            }
+        }
+
+        bug!(
+            "`{}` and `{}` did not converge after {max_rounds} rounds",
+            <F as AnalysisDomain<'tcx>>::NAME,
+            <B as AnalysisDomain<'tcx>>::NAME,
+        );
+    }
+}
+
+fn initial_entry_sets<'tcx, A>(
+    analysis: &A,
+    body: &mir::Body<'tcx>,
+    bottom: &A::Domain,
+) -> EntrySets<'tcx, A>
+where
+    A: Analysis<'tcx>,
+{
+    let mut entry_sets = EntrySets::from_bottom_n(bottom.clone(), body.basic_blocks.len());
+    analysis.initialize_start_block(body, &mut entry_sets[mir::START_BLOCK]);
+    entry_sets
+}
@@ -0,0 +1,115 @@
+//! Memoization of dataflow results across the consumers of a single MIR body.
+
+use rustc_data_structures::fingerprint::Fingerprint;
+use rustc_data_structures::fx::FxHashMap;
+use rustc_data_structures::sync::Lrc;
+use rustc_middle::mir::{self, Promoted};
+use rustc_middle::ty::InstanceDef;
+
+use super::{Analysis, Results};
+
+/// Identifies one version of a MIR body, together with the configuration of an analysis run on it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct CacheKey<'tcx> {
+    /// The `MirSource` of the body. Promoteds and shims share the `DefId` of their item, so the
+    /// whole source is needed to tell their bodies apart.
+    instance: InstanceDef<'tcx>,
+    promoted: Option<Promoted>,
+
+    analysis: Fingerprint,
+
+    /// The version of the body. The pass manager restarts `pass_count` at each phase change, so it
+    /// only identifies a version together with the phase.
+    phase: usize,
+    pass_count: usize,
+}
+
+impl<'tcx> CacheKey<'tcx> {
+    fn new(body: &mir::Body<'tcx>, analysis: Fingerprint) -> Self {
+        CacheKey {
+            instance: body.source.instance,
+            promoted: body.source.promoted,
+            analysis,
+            phase: body.phase.phase_index(),
+            pass_count: body.pass_count,
+        }
+    }
+
+    fn is_other_version_of(&self, other: &CacheKey<'tcx>) -> bool {
+        self.instance == other.instance
+            && self.promoted == other.promoted
+            && self.analysis == other.analysis
+            && self != other
+    }
+}
+
+/// An opt-in cache for the `Results` of an analysis `A`, so that several consumers of the same
+/// version of a body can share them instead of each computing them anew.
+///
+/// Results are keyed by the `MirSource` of the body, a fingerprint of the configuration of `A`,
+/// and the phase and `pass_count` of the body. The pass manager bumps `pass_count` after every
+/// pass, so results computed before a pass are never returned for the body it produced. This does
+/// not hold *within* a pass: a pass that modifies the body must not look up results for it
+/// afterwards. Results for other versions of a body are evicted once those for its current version
+/// are computed.
+///
+/// The analysis fingerprint must distinguish every configuration of `A` that affects its results,
+/// e.g. whether `MaybeUninitializedPlaces` marks inactive enum variants as uninitialized.
+///
+/// Since the results are shared, they can only be inspected through `Results::clone_analysis`,
+/// which requires `A: CloneAnalysis`.
+pub struct DataflowCache<'tcx, A>
+where
+    A: Analysis<'tcx>,
+{
+    entries: FxHashMap<CacheKey<'tcx>, Lrc<Results<'tcx, A>>>,
+    computations: usize,
+}
+
+impl<'tcx, A> DataflowCache<'tcx, A>
+where
+    A: Analysis<'tcx>,
+{
+    pub fn new() -> Self {
+        DataflowCache { entries: FxHashMap::default(), computations: 0 }
+    }
+
+    /// Returns the cached results for `body` and `analysis_fingerprint`, or calls `compute` to
+    /// solve the analysis if there are none.
+    pub fn get_or_compute(
+        &mut self,
+        body: &mir::Body<'tcx>,
+        analysis_fingerprint: Fingerprint,
+        compute: impl FnOnce() -> Results<'tcx, A>,
+    ) -> Lrc<Results<'tcx, A>> {
+        let key = CacheKey::new(body, analysis_fingerprint);
+        if let Some(results) = self.entries.get(&key) {
+            return Lrc::clone(results);
+        }
+
+        self.entries.retain(|cached, _| !cached.is_other_version_of(&key));
+        self.computations += 1;
+        let results = Lrc::new(compute());
+        self.entries.insert(key, Lrc::clone(&results));
+        results
+    }
+
+    /// Returns the number of times `get_or_compute` had to solve the analysis.
+    pub fn computations(&self) -> usize {
+        self.computations
+    }
+
+    /// Drops all cached results.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+impl<'tcx, A> Default for DataflowCache<'tcx, A>
+where
+    A: Analysis<'tcx>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
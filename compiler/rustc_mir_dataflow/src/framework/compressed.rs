@@ -0,0 +1,191 @@
+//! Dataflow results over bitsets, with the entry set of each block stored as the difference from
+//! the entry set of its immediate dominator.
+
+use std::marker::PhantomData;
+use std::mem;
+
+use rustc_index::bit_set::HybridBitSet;
+use rustc_index::{Idx, IndexVec};
+use rustc_middle::mir::{self, BasicBlock};
+
+use super::{Analysis, BitSetExt, EntrySets, GenKill, GenKillSet, Results};
+
+/// The entry set of a single block in `CompressedResults`.
+enum CompressedEntrySet<D, T> {
+    /// The whole entry set, for blocks without an immediate dominator.
+    Full(D),
+    /// The changes to the entry set of the immediate dominator of the block.
+    Delta(GenKillSet<T>),
+}
+
+/// The results of an analysis whose domain is a bitset, with the entry set of each block stored as
+/// the elements it adds to and removes from the entry set of its immediate dominator. These are
+/// computed by `Results::compress`.
+///
+/// When most entry sets differ little from those of their dominators, e.g. because most blocks
+/// have a single predecessor, this takes much less memory than `Results`. In exchange, the entry
+/// set of a block is reconstructed on demand by `entry_set_for_block`, which applies the changes
+/// along the path from the root of the dominator tree to the block.
+pub struct CompressedResults<'tcx, A, T>
+where
+    A: Analysis<'tcx>,
+    A::Domain: BitSetExt<T> + GenKill<T>,
+    T: Idx,
+{
+    pub analysis: A,
+    entry_sets: IndexVec<BasicBlock, CompressedEntrySet<A::Domain, T>>,
+    /// The immediate dominator of each block whose entry set is stored as a `Delta`.
+    parents: IndexVec<BasicBlock, Option<BasicBlock>>,
+    /// The bottom value of the entry sets these were computed from.
+    bottom: A::Domain,
+    _marker: PhantomData<&'tcx ()>,
+}
+
+impl<'tcx, A, T> CompressedResults<'tcx, A, T>
+where
+    A: Analysis<'tcx>,
+    A::Domain: BitSetExt<T> + GenKill<T>,
+    T: Idx,
+{
+    /// Stores the entry set of each block as a delta from that of its immediate dominator.
+    ///
+    /// The entry sets of unreachable blocks, the `START_BLOCK`, and blocks whose entry set has a
+    /// different `domain_size` than that of their immediate dominator are stored in full.
+    fn from_entry_sets(
+        analysis: A,
+        body: &mir::Body<'tcx>,
+        entry_sets: &EntrySets<'tcx, A>,
+    ) -> Self {
+        let dominators = body.basic_blocks.dominators();
+        let mut parents = IndexVec::from_elem(None, &body.basic_blocks);
+        let compressed = entry_sets
+            .iter_enumerated()
+            .map(|(bb, entry_set)| {
+                let Some(parent) = dominators.immediate_dominator(bb) else {
+                    return CompressedEntrySet::Full(entry_set.clone());
+                };
+
+                let parent_set = &entry_sets[parent];
+                let domain_size = match (entry_set.domain_size(), parent_set.domain_size()) {
+                    (Some(size), Some(parent_size)) if size == parent_size => size,
+                    _ => return CompressedEntrySet::Full(entry_set.clone()),
+                };
+
+                let mut delta = GenKillSet::identity(domain_size);
+                for elem in (0..domain_size).map(T::new) {
+                    match (parent_set.contains(elem), entry_set.contains(elem)) {
+                        (false, true) => delta.gen(elem),
+                        (true, false) => delta.kill(elem),
+                        _ => {}
+                    }
+                }
+                parents[bb] = Some(parent);
+                CompressedEntrySet::Delta(delta)
+            })
+            .collect();
+
+        let bottom = entry_sets.bottom().clone();
+        CompressedResults {
+            analysis,
+            entry_sets: compressed,
+            parents,
+            bottom,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Reconstructs the entry set for the given block.
+    pub fn entry_set_for_block(&self, block: BasicBlock) -> A::Domain {
+        let mut path = vec![];
+        let mut block = block;
+        let mut state = loop {
+            match &self.entry_sets[block] {
+                CompressedEntrySet::Full(entry_set) => break entry_set.clone(),
+                CompressedEntrySet::Delta(delta) => {
+                    path.push(delta);
+                    block = self.parents[block].unwrap();
+                }
+            }
+        };
+
+        for delta in path.into_iter().rev() {
+            delta.apply(&mut state);
+        }
+        state
+    }
+
+    /// Approximately how many bytes of memory the entry sets take up, counting each bitset as if
+    /// it were dense. The entry sets of `Results` take up `domain_size / 8` bytes per block.
+    pub fn approx_size_in_bytes(&self) -> usize {
+        let bitset_size = |domain_size: usize| (domain_size + 63) / 64 * mem::size_of::<u64>();
+        let hybrid_size = |set: &HybridBitSet<T>| match set {
+            HybridBitSet::Sparse(_) => 0,
+            HybridBitSet::Dense(set) => bitset_size(set.domain_size()),
+        };
+        let heap_size: usize = self
+            .entry_sets
+            .iter()
+            .map(|entry_set| match entry_set {
+                CompressedEntrySet::Full(set) => set.domain_size().map_or(0, bitset_size),
+                CompressedEntrySet::Delta(delta) => {
+                    hybrid_size(&delta.gen) + hybrid_size(&delta.kill)
+                }
+            })
+            .sum();
+        heap_size
+            + self.entry_sets.len() * mem::size_of::<CompressedEntrySet<A::Domain, T>>()
+            + self.parents.len() * mem::size_of::<Option<BasicBlock>>()
+    }
+
+    /// Converts these results back into `Results` with the full entry set of every block, e.g. to
+    /// inspect them with a `ResultsCursor`.
+    ///
+    /// Unlike calling `entry_set_for_block` for each block, this reconstructs each entry set only
+    /// once, from that of its immediate dominator, by walking the dominator tree in preorder.
+    pub fn into_results(self) -> Results<'tcx, A> {
+        let mut children: IndexVec<BasicBlock, Vec<BasicBlock>> =
+            IndexVec::from_elem(vec![], &self.parents);
+        let mut stack = vec![];
+        for (bb, parent) in self.parents.iter_enumerated() {
+            match parent {
+                Some(parent) => children[*parent].push(bb),
+                None => stack.push(bb),
+            }
+        }
+
+        let mut full: IndexVec<BasicBlock, Option<A::Domain>> =
+            IndexVec::from_elem(None, &self.parents);
+        while let Some(bb) = stack.pop() {
+            full[bb] = Some(match &self.entry_sets[bb] {
+                CompressedEntrySet::Full(entry_set) => entry_set.clone(),
+                CompressedEntrySet::Delta(delta) => {
+                    let parent = self.parents[bb].unwrap();
+                    let mut entry_set = full[parent].clone().unwrap();
+                    delta.apply(&mut entry_set);
+                    entry_set
+                }
+            });
+            stack.extend(children[bb].iter().copied());
+        }
+
+        let full = full.into_iter().map(Option::unwrap).collect();
+        let entry_sets = EntrySets::from_sets(full, self.bottom);
+        Results::new(self.analysis, entry_sets)
+    }
+}
+
+impl<'tcx, A> Results<'tcx, A>
+where
+    A: Analysis<'tcx>,
+{
+    /// Converts these results into `CompressedResults`, which store the entry set of each block as
+    /// its difference from that of its immediate dominator in `body`.
+    pub fn compress<T>(self, body: &mir::Body<'tcx>) -> CompressedResults<'tcx, A, T>
+    where
+        A::Domain: BitSetExt<T> + GenKill<T>,
+        T: Idx,
+    {
+        let (analysis, entry_sets) = self.into_parts();
+        CompressedResults::from_entry_sets(analysis, body, &entry_sets)
+    }
+}
@@ -0,0 +1,263 @@
+//! An `Analysis` wrapper that counts how often transfer functions are invoked.
+
+use rustc_middle::mir::{self, BasicBlock, CallReturnPlaces, Location, TerminatorEdges};
+use rustc_middle::ty::TyCtxt;
+
+use super::{Analysis, AnalysisDomain, FalseEdgeSemantics, SwitchIntTarget};
+
+/// The number of times each kind of transfer function of a `CountingAnalysis` was invoked.
+///
+/// Only primary effects are counted, not "before" effects.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TransferCounts {
+    /// Calls to `apply_statement_effect`.
+    pub statement_effects: usize,
+
+    /// Calls to `apply_terminator_effect`.
+    pub terminator_effects: usize,
+
+    /// Calls to `apply_terminator_unwind_effect`, `apply_call_return_effect`,
+    /// `apply_yield_resume_effect` and `apply_switch_int_edge_effect`.
+    pub edge_effects: usize,
+}
+
+/// Wraps an `Analysis` and counts the invocations of its transfer functions, e.g. to find out
+/// where an analysis spends its time.
+///
+/// ```ignore (cross-crate-imports)
+/// let results = Engine::new_generic(tcx, body, CountingAnalysis::new(analysis))
+///     .iterate_to_fixpoint();
+/// debug!("{:?}", results.analysis.counts());
+/// ```
+///
+/// The wrapper always implements `Analysis` directly, so wrapping a `GenKillAnalysis` disables the
+/// caching of block transfer functions in the `Engine`. The counts therefore reflect the work done
+/// by a generic analysis.
+#[derive(Clone)]
+pub struct CountingAnalysis<A> {
+    inner: A,
+    counts: TransferCounts,
+}
+
+impl<A> CountingAnalysis<A> {
+    pub fn new(inner: A) -> Self {
+        CountingAnalysis { inner, counts: TransferCounts::default() }
+    }
+
+    /// Returns the number of invocations so far.
+    pub fn counts(&self) -> TransferCounts {
+        self.counts
+    }
+
+    /// Resets all counts to zero, e.g. to count the invocations of a cursor separately from those
+    /// of the fixpoint iteration.
+    pub fn reset_counts(&mut self) {
+        self.counts = TransferCounts::default();
+    }
+
+    pub fn inner(&self) -> &A {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> A {
+        self.inner
+    }
+}
+
+impl<'tcx, A> AnalysisDomain<'tcx> for CountingAnalysis<A>
+where
+    A: AnalysisDomain<'tcx>,
+{
+    type Domain = A::Domain;
+    type Direction = A::Direction;
+    type SwitchIntData = A::SwitchIntData;
+
+    const NAME: &'static str = A::NAME;
+    const REQUIRES_CLEANUP_BLOCKS: bool = A::REQUIRES_CLEANUP_BLOCKS;
+    const MERGES_CALL_RETURN_STATE: bool = A::MERGES_CALL_RETURN_STATE;
+
+    fn bottom_value(&self, tcx: TyCtxt<'tcx>, body: &mir::Body<'tcx>) -> Self::Domain {
+        self.inner.bottom_value(tcx, body)
+    }
+
+    fn initialize_start_block(&self, body: &mir::Body<'tcx>, state: &mut Self::Domain) {
+        self.inner.initialize_start_block(body, state)
+    }
+}
+
+impl<'tcx, A> Analysis<'tcx> for CountingAnalysis<A>
+where
+    A: Analysis<'tcx>,
+{
+    type BlockCache = A::BlockCache;
+
+    fn apply_statement_effect(
+        &mut self,
+        state: &mut Self::Domain,
+        statement: &mir::Statement<'tcx>,
+        location: Location,
+    ) {
+        self.counts.statement_effects += 1;
+        self.inner.apply_statement_effect(state, statement, location)
+    }
+
+    fn apply_before_statement_effect(
+        &mut self,
+        state: &mut Self::Domain,
+        statement: &mir::Statement<'tcx>,
+        location: Location,
+    ) {
+        self.inner.apply_before_statement_effect(state, statement, location)
+    }
+
+    fn apply_terminator_effect<'mir>(
+        &mut self,
+        state: &mut Self::Domain,
+        terminator: &'mir mir::Terminator<'tcx>,
+        location: Location,
+    ) -> TerminatorEdges<'mir, 'tcx> {
+        self.counts.terminator_effects += 1;
+        self.inner.apply_terminator_effect(state, terminator, location)
+    }
+
+    fn apply_before_terminator_effect(
+        &mut self,
+        state: &mut Self::Domain,
+        terminator: &mir::Terminator<'tcx>,
+        location: Location,
+    ) {
+        self.inner.apply_before_terminator_effect(state, terminator, location)
+    }
+
+    fn apply_terminator_unwind_effect(
+        &mut self,
+        state: &mut Self::Domain,
+        terminator: &mir::Terminator<'tcx>,
+        block: BasicBlock,
+    ) {
+        self.counts.edge_effects += 1;
+        self.inner.apply_terminator_unwind_effect(state, terminator, block)
+    }
+
+    fn apply_call_return_effect(
+        &mut self,
+        state: &mut Self::Domain,
+        block: BasicBlock,
+        return_places: CallReturnPlaces<'_, 'tcx>,
+    ) {
+        self.counts.edge_effects += 1;
+        self.inner.apply_call_return_effect(state, block, return_places)
+    }
+
+    fn apply_yield_resume_effect(
+        &mut self,
+        state: &mut Self::Domain,
+        resume_block: BasicBlock,
+        resume_place: mir::Place<'tcx>,
+    ) {
+        self.counts.edge_effects += 1;
+        self.inner.apply_yield_resume_effect(state, resume_block, resume_place)
+    }
+
+    fn get_switch_int_data(
+        &mut self,
+        block: BasicBlock,
+        discr: &mir::Operand<'tcx>,
+    ) -> Option<Self::SwitchIntData> {
+        self.inner.get_switch_int_data(block, discr)
+    }
+
+    fn apply_switch_int_edge_effect(
+        &mut self,
+        data: &mut Self::SwitchIntData,
+        state: &mut Self::Domain,
+        edge: SwitchIntTarget,
+    ) {
+        self.counts.edge_effects += 1;
+        self.inner.apply_switch_int_edge_effect(data, state, edge)
+    }
+
+    fn treat_false_edges(&self) -> FalseEdgeSemantics {
+        self.inner.treat_false_edges()
+    }
+
+    fn can_skip_block_given_predecessors(
+        &self,
+        block: BasicBlock,
+        body: &mir::Body<'tcx>,
+        entry: &Self::Domain,
+    ) -> bool {
+        self.inner.can_skip_block_given_predecessors(block, body, entry)
+    }
+
+    fn is_edge_feasible(
+        &self,
+        state: &Self::Domain,
+        terminator: &mir::Terminator<'tcx>,
+        source: BasicBlock,
+        target: BasicBlock,
+    ) -> bool {
+        self.inner.is_edge_feasible(state, terminator, source, target)
+    }
+
+    fn join_at(
+        &self,
+        source: BasicBlock,
+        bb: BasicBlock,
+        into: &mut Self::Domain,
+        from: &Self::Domain,
+    ) -> bool {
+        self.inner.join_at(source, bb, into, from)
+    }
+
+    fn merge_call_return_state(
+        &mut self,
+        edge_state: &Self::Domain,
+        return_state: &mut Self::Domain,
+    ) {
+        self.inner.merge_call_return_state(edge_state, return_state)
+    }
+
+    fn contains_bit(
+        &self,
+        body: &mir::Body<'tcx>,
+        state: &Self::Domain,
+        bit: usize,
+    ) -> Option<bool> {
+        self.inner.contains_bit(body, state, bit)
+    }
+
+    fn explain_state(&self, state: &Self::Domain, body: &mir::Body<'tcx>) -> Option<String> {
+        self.inner.explain_state(state, body)
+    }
+
+    fn pre_iterate(&mut self, body: &mir::Body<'tcx>) {
+        self.inner.pre_iterate(body)
+    }
+
+    fn prepare_block_cache(&mut self, body: &mir::Body<'tcx>) -> Self::BlockCache {
+        self.inner.prepare_block_cache(body)
+    }
+
+    fn apply_statement_effect_cached(
+        &mut self,
+        cache: &Self::BlockCache,
+        state: &mut Self::Domain,
+        statement: &mir::Statement<'tcx>,
+        location: Location,
+    ) {
+        self.counts.statement_effects += 1;
+        self.inner.apply_statement_effect_cached(cache, state, statement, location)
+    }
+
+    fn apply_terminator_effect_cached<'mir>(
+        &mut self,
+        cache: &Self::BlockCache,
+        state: &mut Self::Domain,
+        terminator: &'mir mir::Terminator<'tcx>,
+        location: Location,
+    ) -> TerminatorEdges<'mir, 'tcx> {
+        self.counts.terminator_effects += 1;
+        self.inner.apply_terminator_effect_cached(cache, state, terminator, location)
+    }
+}
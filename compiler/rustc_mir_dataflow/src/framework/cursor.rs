@@ -5,10 +5,12 @@
 use std::borrow::{Borrow, BorrowMut};
 use std::cmp::Ordering;
 
+use rustc_data_structures::captures::Captures;
 #[cfg(debug_assertions)]
 use rustc_index::bit_set::BitSet;
 use rustc_middle::mir::{self, BasicBlock, Location};
 
+use super::fmt::{plain_diff, DebugWithContext};
 use super::{Analysis, Direction, Effect, EffectIndex, EntrySets, Results, ResultsCloned};
 
 // `AnalysisResults` is needed as an impl such as the following has an unconstrained type
@@ -122,16 +124,16 @@ impl<'mir, 'tcx, A, R> ResultsCursor<'mir, 'tcx, A, R>
 {
     /// Returns a new cursor that can inspect `results`.
     pub fn new(body: &'mir mir::Body<'tcx>, results: R) -> Self {
-        let bottom_value = results.borrow().analysis.bottom_value(body);
+        let state = results.borrow().entry_set_for_block(mir::START_BLOCK).clone();
         ResultsCursor {
             body,
             results,
 
-            // Initialize to the `bottom_value` and set `state_needs_reset` to tell the cursor that
-            // it needs to reset to block entry before the first seek. The cursor position is
+            // Initialize to any entry set and set `state_needs_reset` to tell the cursor that it
+            // needs to reset to block entry before the first seek. The cursor position is
             // immaterial.
             state_needs_reset: true,
-            state: bottom_value,
+            state,
             pos: CursorPosition::block_entry(mir::START_BLOCK),
 
             #[cfg(debug_assertions)]
@@ -179,6 +181,7 @@ pub fn get_with_analysis(&mut self) -> (&A::Domain, &mut A) {
     pub(super) fn seek_to_block_entry(&mut self, block: BasicBlock) {
         #[cfg(debug_assertions)]
         assert!(self.reachable_blocks.contains(block));
+        assert_has_terminator(self.body, block);
 
         self.state.clone_from(self.results.borrow().entry_set_for_block(block));
         self.pos = CursorPosition::block_entry(block);
@@ -216,7 +219,9 @@ pub fn seek_to_block_end(&mut self, block: BasicBlock) {
     /// Advances the cursor to hold the dataflow state at `target` before its "primary" effect is
     /// applied.
     ///
-    /// The "before" effect at the target location *will be* applied.
+    /// The "before" effect at the target location *will be* applied. For backward analyses, this
+    /// means that the effects of every later statement in the block (and of the terminator) will
+    /// have been applied as well. See `Backward` for the exact order.
     pub fn seek_before_primary_effect(&mut self, target: Location) {
         self.seek_after(target, Effect::Before)
     }
@@ -224,11 +229,31 @@ pub fn seek_before_primary_effect(&mut self, target: Location) {
     /// Advances the cursor to hold the dataflow state at `target` after its "primary" effect is
     /// applied.
     ///
-    /// The "before" effect at the target location will be applied as well.
+    /// The "before" effect at the target location will be applied as well. If the cursor is
+    /// already at an earlier location in the same block, only the effects in between are applied,
+    /// so walking the statements of a block in dataflow order applies each effect once.
     pub fn seek_after_primary_effect(&mut self, target: Location) {
         self.seek_after(target, Effect::Primary)
     }
 
+    /// Returns an iterator over the index of each statement in `block` and a copy of the dataflow
+    /// state after its primary effect.
+    ///
+    /// The statements are visited in dataflow order, so in reverse for backward analyses. The
+    /// cursor seeks to each statement as the iterator advances and remains at the last one
+    /// visited.
+    pub fn statement_states(
+        &mut self,
+        block: BasicBlock,
+    ) -> impl Iterator<Item = (usize, A::Domain)> + Captures<'mir> + Captures<'tcx> + '_ {
+        let num_statements = self.body[block].statements.len();
+        (0..num_statements).map(move |i| {
+            let statement_index = if A::Direction::IS_FORWARD { i } else { num_statements - 1 - i };
+            self.seek_after_primary_effect(Location { block, statement_index });
+            (statement_index, self.state.clone())
+        })
+    }
+
     fn seek_after(&mut self, target: Location, effect: Effect) {
         assert!(target <= self.body.terminator_loc(target.block));
 
@@ -269,21 +294,76 @@ fn seek_after(&mut self, target: Location, effect: Effect) {
             )
         };
 
-        let analysis = &mut self.results.borrow_mut().analysis;
         let target_effect_index = effect.at_index(target.statement_index);
-
-        A::Direction::apply_effects_in_range(
-            analysis,
-            &mut self.state,
-            target.block,
-            block_data,
-            next_effect..=target_effect_index,
-        );
+        let next_effect =
+            self.apply_block_trans_in_range(target.block, next_effect, target_effect_index);
+
+        if let Some(next_effect) = next_effect {
+            let analysis = &mut self.results.borrow_mut().analysis;
+            A::Direction::apply_effects_in_range(
+                analysis,
+                &mut self.state,
+                target.block,
+                &self.body[target.block],
+                next_effect..=target_effect_index,
+            );
+        }
 
         self.pos =
             CursorPosition { block: target.block, curr_effect_index: Some(target_effect_index) };
     }
 
+    /// Applies the effects of every statement in `block` at once with the transfer function cached
+    /// by the `Engine`, if there is one and `from..=to` covers all statements.
+    ///
+    /// Returns the first effect in `from..=to` that has yet to be applied, or `None` if all of them
+    /// have. The cached transfer function includes the "before" effects of each statement, so the
+    /// resulting state is the same as when applying the effects one at a time.
+    fn apply_block_trans_in_range(
+        &mut self,
+        block: BasicBlock,
+        from: EffectIndex,
+        to: EffectIndex,
+    ) -> Option<EffectIndex> {
+        let block_data = &self.body[block];
+        let num_statements = block_data.statements.len();
+        let results = self.results.borrow_mut();
+        let Some(block_trans) = &results.block_trans else { return Some(from) };
+        if num_statements == 0 {
+            return Some(from);
+        }
+
+        if A::Direction::IS_FORWARD {
+            if from != Effect::Before.at_index(0) || to.statement_index != num_statements {
+                return Some(from);
+            }
+
+            block_trans(block, &mut self.state);
+            return Some(Effect::Before.at_index(num_statements));
+        }
+
+        // In backward order, the terminator comes first, so apply any of its effects that have yet
+        // to be applied before those of the statements.
+        let after_terminator = Effect::Before.at_index(num_statements - 1);
+        if to != Effect::Primary.at_index(0)
+            || (from.statement_index != num_statements && from != after_terminator)
+        {
+            return Some(from);
+        }
+
+        if from != after_terminator {
+            A::Direction::apply_effects_in_range(
+                &mut results.analysis,
+                &mut self.state,
+                block,
+                block_data,
+                from..=Effect::Primary.at_index(num_statements),
+            );
+        }
+        block_trans(block, &mut self.state);
+        None
+    }
+
     /// Applies `f` to the cursor's internal state.
     ///
     /// This can be used, e.g., to apply the call return effect directly to the cursor without
@@ -292,6 +372,24 @@ pub fn apply_custom_effect(&mut self, f: impl FnOnce(&mut A, &mut A::Domain)) {
         f(&mut self.results.borrow_mut().analysis, &mut self.state);
         self.state_needs_reset = true;
     }
+
+    /// Compares the state of this cursor with that of `other`, e.g. a cursor over the results of a
+    /// modified analysis.
+    ///
+    /// Both cursors are assumed to be positioned at the same location. Returns `None` if their
+    /// states are equal, and otherwise a description of the difference in which elements only in
+    /// `self` are prefixed with `+` and elements only in `other` with `-`.
+    pub fn diff_against<'other>(&self, other: &ResultsCursor<'other, 'tcx, A, R>) -> Option<String>
+    where
+        A::Domain: DebugWithContext<A>,
+    {
+        let (new, old) = (self.get(), other.get());
+        if new == old {
+            return None;
+        }
+
+        Some(plain_diff(new, old, self.analysis()))
+    }
 }
 
 impl<'mir, 'tcx, A, R> ResultsCursor<'mir, 'tcx, A, R>
@@ -0,0 +1,112 @@
+//! Dataflow results over `BitSet`s, with the entry sets stored as the rows of a `BitMatrix`.
+
+use std::marker::PhantomData;
+
+use rustc_index::bit_set::{BitMatrix, BitSet};
+use rustc_index::Idx;
+use rustc_middle::mir::{self, BasicBlock};
+
+use super::engine::BlockTrans;
+use super::{Analysis, ConvergenceInfo, EntrySets, Results};
+
+/// The results of an analysis whose domain is `BitSet<T>`, with the entry set of each block stored
+/// as a row of a single `BitMatrix` instead of in a separate `BitSet`. These are computed by
+/// `Engine::iterate_to_fixpoint_dense`.
+///
+/// Cursors and visitors work on `Results`, into which these can be converted with `into_results`.
+pub struct DenseBitSetResults<'tcx, A, T>
+where
+    A: Analysis<'tcx, Domain = BitSet<T>>,
+    T: Idx,
+{
+    pub analysis: A,
+    entry_sets: BitMatrix<BasicBlock, T>,
+    domain_size: usize,
+    /// The cached block transfer functions, passed on to the `Results` these are converted into.
+    pub(super) block_trans: Option<BlockTrans<BitSet<T>>>,
+    /// How often each block was visited, if requested by `Engine::with_convergence_tracking`.
+    pub(super) convergence: Option<ConvergenceInfo>,
+    _marker: PhantomData<&'tcx ()>,
+}
+
+impl<'tcx, A, T> DenseBitSetResults<'tcx, A, T>
+where
+    A: Analysis<'tcx, Domain = BitSet<T>>,
+    T: Idx,
+{
+    /// Copies `entry_sets` into a `BitMatrix` with one row per block.
+    pub(super) fn from_entry_sets(analysis: A, entry_sets: &EntrySets<'tcx, A>) -> Self {
+        let domain_size = entry_sets[mir::START_BLOCK].domain_size();
+        let mut matrix = BitMatrix::new(entry_sets.len(), domain_size);
+        for (bb, entry_set) in entry_sets.iter_enumerated() {
+            matrix.union_row_with(entry_set, bb);
+        }
+        DenseBitSetResults {
+            analysis,
+            entry_sets: matrix,
+            domain_size,
+            block_trans: None,
+            convergence: None,
+            _marker: PhantomData,
+        }
+    }
+
+    /// The analysis and the entry sets, which the fixpoint iteration updates in place.
+    pub(super) fn analysis_and_entry_sets_mut(
+        &mut self,
+    ) -> (&mut A, &mut BitMatrix<BasicBlock, T>) {
+        (&mut self.analysis, &mut self.entry_sets)
+    }
+
+    /// Returns whether `elem` is in the entry set for the given block.
+    pub fn contains(&self, block: BasicBlock, elem: T) -> bool {
+        self.entry_sets.contains(block, elem)
+    }
+
+    /// Overwrites `state` with the entry set for the given block, without allocating.
+    pub fn copy_entry_set_into(&self, block: BasicBlock, state: &mut BitSet<T>) {
+        self.entry_sets.copy_row_into(block, state);
+    }
+
+    /// Returns a copy of the entry set for the given block.
+    pub fn entry_set_for_block(&self, block: BasicBlock) -> BitSet<T> {
+        let mut state = BitSet::new_empty(self.domain_size);
+        self.copy_entry_set_into(block, &mut state);
+        state
+    }
+
+    /// Returns how often each block was visited while iterating to fixpoint, if the `Engine` was
+    /// asked to record it with `with_convergence_tracking`.
+    pub fn convergence_info(&self) -> Option<&ConvergenceInfo> {
+        self.convergence.as_ref()
+    }
+
+    /// Converts these results into `Results` with one `BitSet` per block, e.g. to inspect them
+    /// with a `ResultsCursor`.
+    pub fn into_results(self) -> Results<'tcx, A> {
+        let entry_sets = EntrySets::from_sets(
+            self.entry_sets.rows().map(|bb| self.entry_set_for_block(bb)).collect(),
+            BitSet::new_empty(self.domain_size),
+        );
+        let mut results = Results::new(self.analysis, entry_sets);
+        results.block_trans = self.block_trans;
+        results.convergence = self.convergence;
+        results
+    }
+}
+
+impl<'tcx, A, T> Results<'tcx, A>
+where
+    A: Analysis<'tcx, Domain = BitSet<T>>,
+    T: Idx,
+{
+    /// Converts these results into `DenseBitSetResults`, which store all entry sets in a single
+    /// `BitMatrix`.
+    pub fn into_dense(self) -> DenseBitSetResults<'tcx, A, T> {
+        let Results { analysis, entry_sets, block_trans, convergence, _marker } = self;
+        let mut dense = DenseBitSetResults::from_entry_sets(analysis, &entry_sets);
+        dense.block_trans = block_trans;
+        dense.convergence = convergence;
+        dense
+    }
+}
@@ -0,0 +1,80 @@
+//! Read-only access to the results of one analysis from the transfer functions of another.
+
+use rustc_middle::mir::{self, BasicBlock, Location};
+
+use super::{Analysis, CloneAnalysis, Results, ResultsClonedCursor};
+
+/// The results of an analysis `B`, as seen by another analysis whose transfer functions depend on
+/// them.
+///
+/// For example, `MaybeRequiresStorage` kills a local that is moved out of only if
+/// `MaybeBorrowedLocals` says that it is not borrowed at that location. An analysis like this
+/// takes a `Dependency` in its constructor and queries it from its transfer functions:
+///
+/// ```ignore (cross-crate-imports)
+/// let borrowed_locals = MaybeBorrowedLocals.into_engine(tcx, body).iterate_to_fixpoint();
+/// let requires_storage = MaybeRequiresStorage::new(Dependency::new(&borrowed_locals, body))
+///     .into_engine(tcx, body)
+///     .iterate_to_fixpoint();
+/// ```
+///
+/// Outside of the framework, `Results` are only produced by iterating an analysis to fixpoint, so a
+/// `Dependency` reflects the fixpoint of `B` unless the caller has since changed the results with
+/// `patch_entry_set` or `merge_with`. It borrows those results immutably and exposes their states
+/// by shared reference only, so they cannot change while the dependent analysis is iterated to its
+/// own fixpoint. The states can be queried at any location, in any order, although seeking forward
+/// within a block (backward for backward analyses) is cheapest.
+pub struct Dependency<'res, 'mir, 'tcx, B>
+where
+    B: Analysis<'tcx>,
+{
+    cursor: ResultsClonedCursor<'res, 'mir, 'tcx, B>,
+}
+
+impl<'res, 'mir, 'tcx, B> Dependency<'res, 'mir, 'tcx, B>
+where
+    B: Analysis<'tcx> + CloneAnalysis,
+{
+    pub fn new(results: &'res Results<'tcx, B>, body: &'mir mir::Body<'tcx>) -> Self {
+        Dependency { cursor: results.cloned_results_cursor(body) }
+    }
+
+    pub fn body(&self) -> &'mir mir::Body<'tcx> {
+        self.cursor.body()
+    }
+
+    /// Returns the entry set of `B` for `block`.
+    pub fn entry_state(&mut self, block: BasicBlock) -> &B::Domain {
+        self.cursor.seek_to_block_entry(block);
+        self.cursor.get()
+    }
+
+    /// Returns the state of `B` at `location` before its primary effect. See
+    /// `ResultsCursor::seek_before_primary_effect`.
+    pub fn state_before(&mut self, location: Location) -> &B::Domain {
+        self.cursor.seek_before_primary_effect(location);
+        self.cursor.get()
+    }
+
+    /// Returns the state of `B` at `location` after its primary effect. See
+    /// `ResultsCursor::seek_after_primary_effect`.
+    pub fn state_after(&mut self, location: Location) -> &B::Domain {
+        self.cursor.seek_after_primary_effect(location);
+        self.cursor.get()
+    }
+
+    /// Returns the analysis `B`, e.g. to apply its transfer functions to the state of the dependent
+    /// analysis. This does not affect the results of `B`.
+    pub fn analysis_mut(&mut self) -> &mut B {
+        self.cursor.mut_analysis()
+    }
+}
+
+impl<'tcx, B> Clone for Dependency<'_, '_, 'tcx, B>
+where
+    B: Analysis<'tcx> + CloneAnalysis,
+{
+    fn clone(&self) -> Self {
+        Dependency { cursor: self.cursor.new_cursor() }
+    }
+}
@@ -1,16 +1,27 @@
-use rustc_middle::mir::{
-    self, BasicBlock, CallReturnPlaces, Location, SwitchTargets, TerminatorEdges,
-};
+use rustc_middle::mir::{self, BasicBlock, CallReturnPlaces, Location, TerminatorEdges};
 use std::ops::RangeInclusive;
 
 use super::visitor::{ResultsVisitable, ResultsVisitor};
-use super::{Analysis, Effect, EffectIndex, GenKillAnalysis, GenKillSet, SwitchIntTarget};
+use super::{
+    edge_targets, is_imaginary_edge, Analysis, Effect, EffectIndex, FalseEdgeSemantics,
+    GenKill, GenKillAnalysis, SwitchIntTarget,
+};
+
+/// The direction of a dataflow analysis as a runtime value. See `Direction::KIND`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DirectionKind {
+    Forward,
+    Backward,
+}
 
 pub trait Direction {
     const IS_FORWARD: bool;
 
     const IS_BACKWARD: bool = !Self::IS_FORWARD;
 
+    const KIND: DirectionKind =
+        if Self::IS_FORWARD { DirectionKind::Forward } else { DirectionKind::Backward };
+
     /// Applies all effects between the given `EffectIndex`s.
     ///
     /// `effects.start()` must precede or equal `effects.end()` in this direction.
@@ -28,26 +39,30 @@ fn apply_effects_in_block<'mir, 'tcx, A>(
         state: &mut A::Domain,
         block: BasicBlock,
         block_data: &'mir mir::BasicBlockData<'tcx>,
-        statement_effect: Option<&dyn Fn(BasicBlock, &mut A::Domain)>,
+        statement_effect: Option<&dyn Fn(BasicBlock, &mut A::Domain) -> bool>,
+        block_cache: Option<&A::BlockCache>,
     ) -> TerminatorEdges<'mir, 'tcx>
     where
         A: Analysis<'tcx>;
 
     fn gen_kill_statement_effects_in_block<'tcx, A>(
         analysis: &mut A,
-        trans: &mut GenKillSet<A::Idx>,
+        trans: &mut impl GenKill<A::Idx>,
         block: BasicBlock,
         block_data: &mir::BasicBlockData<'tcx>,
     ) where
         A: GenKillAnalysis<'tcx>;
 
+    /// Visits `block` with `vis`, leaving the state at the exit of the block (in this direction)
+    /// in `state`. Returns the edges of its terminator, as `apply_effects_in_block` would.
     fn visit_results_in_block<'mir, 'tcx, F, R>(
         state: &mut F,
         block: BasicBlock,
         block_data: &'mir mir::BasicBlockData<'tcx>,
         results: &mut R,
         vis: &mut impl ResultsVisitor<'mir, 'tcx, R, FlowState = F>,
-    ) where
+    ) -> TerminatorEdges<'mir, 'tcx>
+    where
         R: ResultsVisitable<'tcx, FlowState = F>;
 
     fn join_state_into_successors_of<'tcx, A>(
@@ -56,12 +71,22 @@ fn join_state_into_successors_of<'tcx, A>(
         exit_state: &mut A::Domain,
         block: BasicBlock,
         edges: TerminatorEdges<'_, 'tcx>,
-        propagate: impl FnMut(BasicBlock, &A::Domain),
+        propagate: impl FnMut(&A, BasicBlock, &A::Domain),
     ) where
         A: Analysis<'tcx>;
 }
 
 /// Dataflow that runs from the exit of a block (the terminator), to its entry (the first statement).
+///
+/// Within a block, effects are applied in the following order:
+///
+/// 1. The "before" effect of the terminator, then its primary effect.
+/// 2. For each statement, from last to first, its "before" effect, then its primary effect.
+///
+/// Note that "before" refers to the order in which effects are applied, not to the order of
+/// execution. In a backward analysis, the "before" effect of a statement models something that
+/// happens immediately *after* that statement executes. The fixpoint iteration, the
+/// `ResultsCursor` and the `ResultsVisitor`s all observe effects in this order.
 pub struct Backward;
 
 impl Direction for Backward {
@@ -72,7 +97,8 @@ fn apply_effects_in_block<'mir, 'tcx, A>(
         state: &mut A::Domain,
         block: BasicBlock,
         block_data: &'mir mir::BasicBlockData<'tcx>,
-        statement_effect: Option<&dyn Fn(BasicBlock, &mut A::Domain)>,
+        statement_effect: Option<&dyn Fn(BasicBlock, &mut A::Domain) -> bool>,
+        block_cache: Option<&A::BlockCache>,
     ) -> TerminatorEdges<'mir, 'tcx>
     where
         A: Analysis<'tcx>,
@@ -80,14 +106,26 @@ fn apply_effects_in_block<'mir, 'tcx, A>(
         let terminator = block_data.terminator();
         let location = Location { block, statement_index: block_data.statements.len() };
         analysis.apply_before_terminator_effect(state, terminator, location);
-        let edges = analysis.apply_terminator_effect(state, terminator, location);
-        if let Some(statement_effect) = statement_effect {
-            statement_effect(block, state)
-        } else {
+        let edges = match block_cache {
+            Some(cache) => {
+                analysis.apply_terminator_effect_cached(cache, state, terminator, location)
+            }
+            None => analysis.apply_terminator_effect(state, terminator, location),
+        };
+        // A cached transfer function returns `false` for blocks it does not cover, and so does an
+        // analysis without a batched statement effect.
+        let applied = statement_effect.is_some_and(|cached| cached(block, state))
+            || analysis.apply_batch_statement_effect(state, block, block_data);
+        if !applied {
             for (statement_index, statement) in block_data.statements.iter().enumerate().rev() {
                 let location = Location { block, statement_index };
                 analysis.apply_before_statement_effect(state, statement, location);
-                analysis.apply_statement_effect(state, statement, location);
+                match block_cache {
+                    Some(cache) => {
+                        analysis.apply_statement_effect_cached(cache, state, statement, location)
+                    }
+                    None => analysis.apply_statement_effect(state, statement, location),
+                }
             }
         }
         edges
@@ -95,7 +133,7 @@ fn apply_effects_in_block<'mir, 'tcx, A>(
 
     fn gen_kill_statement_effects_in_block<'tcx, A>(
         analysis: &mut A,
-        trans: &mut GenKillSet<A::Idx>,
+        trans: &mut impl GenKill<A::Idx>,
         block: BasicBlock,
         block_data: &mir::BasicBlockData<'tcx>,
     ) where
@@ -191,7 +229,8 @@ fn visit_results_in_block<'mir, 'tcx, F, R>(
         block_data: &'mir mir::BasicBlockData<'tcx>,
         results: &mut R,
         vis: &mut impl ResultsVisitor<'mir, 'tcx, R, FlowState = F>,
-    ) where
+    ) -> TerminatorEdges<'mir, 'tcx>
+    where
         R: ResultsVisitable<'tcx, FlowState = F>,
     {
         results.reset_to_block_entry(state, block);
@@ -203,8 +242,11 @@ fn visit_results_in_block<'mir, 'tcx, F, R>(
         let term = block_data.terminator();
         results.reconstruct_before_terminator_effect(state, term, loc);
         vis.visit_terminator_before_primary_effect(results, state, term, loc);
-        results.reconstruct_terminator_effect(state, term, loc);
+        let edges = results.reconstruct_terminator_effect(state, term, loc);
         vis.visit_terminator_after_primary_effect(results, state, term, loc);
+        for target in edge_targets(edges) {
+            vis.visit_terminator_edge(results, state, block, target);
+        }
 
         for (statement_index, stmt) in block_data.statements.iter().enumerate().rev() {
             let loc = Location { block, statement_index };
@@ -215,6 +257,7 @@ fn visit_results_in_block<'mir, 'tcx, F, R>(
         }
 
         vis.visit_block_start(results, state, block_data, block);
+        edges
     }
 
     fn join_state_into_successors_of<'tcx, A>(
@@ -223,12 +266,21 @@ fn join_state_into_successors_of<'tcx, A>(
         exit_state: &mut A::Domain,
         bb: BasicBlock,
         _edges: TerminatorEdges<'_, 'tcx>,
-        mut propagate: impl FnMut(BasicBlock, &A::Domain),
+        mut propagate: impl FnMut(&A, BasicBlock, &A::Domain),
     ) where
         A: Analysis<'tcx>,
     {
+        let mut propagate = |analysis: &A, pred: BasicBlock, state: &A::Domain| {
+            if analysis.is_edge_feasible(state, body[pred].terminator(), bb, pred) {
+                propagate(analysis, pred, state);
+            }
+        };
+        let ignore_imaginary_edges = analysis.treat_false_edges() == FalseEdgeSemantics::Ignore;
+
         for pred in body.basic_blocks.predecessors()[bb].iter().copied() {
             match body[pred].terminator().kind {
+                _ if ignore_imaginary_edges && is_imaginary_edge(body[pred].terminator(), bb) => {}
+
                 // Apply terminator-specific edge effects.
                 //
                 // FIXME(ecstaticmorse): Avoid cloning the exit state unconditionally.
@@ -239,7 +291,10 @@ fn join_state_into_successors_of<'tcx, A>(
                         pred,
                         CallReturnPlaces::Call(destination),
                     );
-                    propagate(pred, &tmp);
+                    if A::MERGES_CALL_RETURN_STATE {
+                        analysis.merge_call_return_state(exit_state, &mut tmp);
+                    }
+                    propagate(analysis, pred, &tmp);
                 }
 
                 mir::TerminatorKind::InlineAsm {
@@ -251,73 +306,49 @@ fn join_state_into_successors_of<'tcx, A>(
                         pred,
                         CallReturnPlaces::InlineAsm(operands),
                     );
-                    propagate(pred, &tmp);
+                    propagate(analysis, pred, &tmp);
                 }
 
                 mir::TerminatorKind::Yield { resume, resume_arg, .. } if resume == bb => {
                     let mut tmp = exit_state.clone();
-                    analysis.apply_call_return_effect(
+                    analysis.apply_yield_resume_effect(&mut tmp, bb, resume_arg);
+                    propagate(analysis, pred, &tmp);
+                }
+
+                // Apply the unwind-specific effect if `bb` is the cleanup block of `pred`.
+                _ if body[pred].terminator().unwind() == Some(&mir::UnwindAction::Cleanup(bb)) => {
+                    let mut tmp = exit_state.clone();
+                    analysis.apply_terminator_unwind_effect(
                         &mut tmp,
-                        resume,
-                        CallReturnPlaces::Yield(resume_arg),
+                        body[pred].terminator(),
+                        pred,
                     );
-                    propagate(pred, &tmp);
+                    propagate(analysis, pred, &tmp);
                 }
 
                 mir::TerminatorKind::SwitchInt { targets: _, ref discr } => {
-                    let mut applier = BackwardSwitchIntEdgeEffectsApplier {
-                        body,
-                        pred,
-                        exit_state,
-                        bb,
-                        propagate: &mut propagate,
-                        effects_applied: false,
-                    };
-
-                    analysis.apply_switch_int_edge_effects(pred, discr, &mut applier);
-
-                    if !applier.effects_applied {
-                        propagate(pred, exit_state)
+                    if let Some(mut data) = analysis.get_switch_int_data(pred, discr) {
+                        let values = &body.basic_blocks.switch_sources()[&(bb, pred)];
+                        let targets =
+                            values.iter().map(|&value| SwitchIntTarget { value, target: bb });
+
+                        let mut tmp = None;
+                        for target in targets {
+                            let tmp = opt_clone_from_or_clone(&mut tmp, exit_state);
+                            analysis.apply_switch_int_edge_effect(&mut data, tmp, target);
+                            propagate(analysis, pred, tmp);
+                        }
+                    } else {
+                        propagate(analysis, pred, exit_state)
                     }
                 }
 
-                _ => propagate(pred, exit_state),
+                _ => propagate(analysis, pred, exit_state),
             }
         }
     }
 }
 
-struct BackwardSwitchIntEdgeEffectsApplier<'a, 'tcx, D, F> {
-    body: &'a mir::Body<'tcx>,
-    pred: BasicBlock,
-    exit_state: &'a mut D,
-    bb: BasicBlock,
-    propagate: &'a mut F,
-    effects_applied: bool,
-}
-
-impl<D, F> super::SwitchIntEdgeEffects<D> for BackwardSwitchIntEdgeEffectsApplier<'_, '_, D, F>
-where
-    D: Clone,
-    F: FnMut(BasicBlock, &D),
-{
-    fn apply(&mut self, mut apply_edge_effect: impl FnMut(&mut D, SwitchIntTarget)) {
-        assert!(!self.effects_applied);
-
-        let values = &self.body.basic_blocks.switch_sources()[&(self.bb, self.pred)];
-        let targets = values.iter().map(|&value| SwitchIntTarget { value, target: self.bb });
-
-        let mut tmp = None;
-        for target in targets {
-            let tmp = opt_clone_from_or_clone(&mut tmp, self.exit_state);
-            apply_edge_effect(tmp, target);
-            (self.propagate)(self.pred, tmp);
-        }
-
-        self.effects_applied = true;
-    }
-}
-
 /// Dataflow that runs from the entry of a block (the first statement), to its exit (terminator).
 pub struct Forward;
 
@@ -329,30 +360,43 @@ fn apply_effects_in_block<'mir, 'tcx, A>(
         state: &mut A::Domain,
         block: BasicBlock,
         block_data: &'mir mir::BasicBlockData<'tcx>,
-        statement_effect: Option<&dyn Fn(BasicBlock, &mut A::Domain)>,
+        statement_effect: Option<&dyn Fn(BasicBlock, &mut A::Domain) -> bool>,
+        block_cache: Option<&A::BlockCache>,
     ) -> TerminatorEdges<'mir, 'tcx>
     where
         A: Analysis<'tcx>,
     {
-        if let Some(statement_effect) = statement_effect {
-            statement_effect(block, state)
-        } else {
+        // A cached transfer function returns `false` for blocks it does not cover, and so does an
+        // analysis without a batched statement effect.
+        let applied = statement_effect.is_some_and(|cached| cached(block, state))
+            || analysis.apply_batch_statement_effect(state, block, block_data);
+        if !applied {
             for (statement_index, statement) in block_data.statements.iter().enumerate() {
                 let location = Location { block, statement_index };
                 analysis.apply_before_statement_effect(state, statement, location);
-                analysis.apply_statement_effect(state, statement, location);
+                match block_cache {
+                    Some(cache) => {
+                        analysis.apply_statement_effect_cached(cache, state, statement, location)
+                    }
+                    None => analysis.apply_statement_effect(state, statement, location),
+                }
             }
         }
 
         let terminator = block_data.terminator();
         let location = Location { block, statement_index: block_data.statements.len() };
         analysis.apply_before_terminator_effect(state, terminator, location);
-        analysis.apply_terminator_effect(state, terminator, location)
+        match block_cache {
+            Some(cache) => {
+                analysis.apply_terminator_effect_cached(cache, state, terminator, location)
+            }
+            None => analysis.apply_terminator_effect(state, terminator, location),
+        }
     }
 
     fn gen_kill_statement_effects_in_block<'tcx, A>(
         analysis: &mut A,
-        trans: &mut GenKillSet<A::Idx>,
+        trans: &mut impl GenKill<A::Idx>,
         block: BasicBlock,
         block_data: &mir::BasicBlockData<'tcx>,
     ) where
@@ -444,7 +488,8 @@ fn visit_results_in_block<'mir, 'tcx, F, R>(
         block_data: &'mir mir::BasicBlockData<'tcx>,
         results: &mut R,
         vis: &mut impl ResultsVisitor<'mir, 'tcx, R, FlowState = F>,
-    ) where
+    ) -> TerminatorEdges<'mir, 'tcx>
+    where
         R: ResultsVisitable<'tcx, FlowState = F>,
     {
         results.reset_to_block_entry(state, block);
@@ -463,59 +508,104 @@ fn visit_results_in_block<'mir, 'tcx, F, R>(
         let term = block_data.terminator();
         results.reconstruct_before_terminator_effect(state, term, loc);
         vis.visit_terminator_before_primary_effect(results, state, term, loc);
-        results.reconstruct_terminator_effect(state, term, loc);
+        let edges = results.reconstruct_terminator_effect(state, term, loc);
         vis.visit_terminator_after_primary_effect(results, state, term, loc);
+        for target in edge_targets(edges) {
+            if results.is_edge_feasible(state, term, block, target) {
+                vis.visit_terminator_edge(results, state, block, target);
+            }
+        }
 
         vis.visit_block_end(results, state, block_data, block);
+        edges
     }
 
     fn join_state_into_successors_of<'tcx, A>(
         analysis: &mut A,
-        _body: &mir::Body<'tcx>,
+        body: &mir::Body<'tcx>,
         exit_state: &mut A::Domain,
         bb: BasicBlock,
         edges: TerminatorEdges<'_, 'tcx>,
-        mut propagate: impl FnMut(BasicBlock, &A::Domain),
+        mut propagate: impl FnMut(&A, BasicBlock, &A::Domain),
     ) where
         A: Analysis<'tcx>,
     {
+        let terminator = body[bb].terminator();
+        let mut propagate = |analysis: &A, target: BasicBlock, state: &A::Domain| {
+            if analysis.is_edge_feasible(state, terminator, bb, target) {
+                propagate(analysis, target, state);
+            }
+        };
+        let is_unwind_edge =
+            |target| terminator.unwind() == Some(&mir::UnwindAction::Cleanup(target));
+        let ignore_imaginary_edges = analysis.treat_false_edges() == FalseEdgeSemantics::Ignore;
+
         match edges {
             TerminatorEdges::None => {}
-            TerminatorEdges::Single(target) => propagate(target, exit_state),
+            TerminatorEdges::Single(target) => propagate(analysis, target, exit_state),
             TerminatorEdges::Double(target, unwind) => {
-                propagate(target, exit_state);
-                propagate(unwind, exit_state);
+                propagate(analysis, target, exit_state);
+                if !(ignore_imaginary_edges && is_imaginary_edge(terminator, unwind)) {
+                    // `FalseEdge` terminators also have two successors, but neither is an unwind
+                    // edge.
+                    if is_unwind_edge(unwind) {
+                        analysis.apply_terminator_unwind_effect(exit_state, terminator, bb);
+                    }
+                    propagate(analysis, unwind, exit_state);
+                }
             }
             TerminatorEdges::AssignOnReturn { return_, cleanup, place } => {
                 // This must be done *first*, otherwise the unwind path will see the assignments.
+                //
+                // Note that the `cleanup` block of a `Yield` is the coroutine drop path, not an
+                // unwind edge.
                 if let Some(cleanup) = cleanup {
-                    propagate(cleanup, exit_state);
+                    if !is_unwind_edge(cleanup) {
+                        propagate(analysis, cleanup, exit_state);
+                    } else if return_.is_some() {
+                        let mut tmp = exit_state.clone();
+                        analysis.apply_terminator_unwind_effect(&mut tmp, terminator, bb);
+                        propagate(analysis, cleanup, &tmp);
+                    } else {
+                        analysis.apply_terminator_unwind_effect(exit_state, terminator, bb);
+                        propagate(analysis, cleanup, exit_state);
+                    }
                 }
                 if let Some(return_) = return_ {
-                    analysis.apply_call_return_effect(exit_state, bb, place);
-                    propagate(return_, exit_state);
+                    match place {
+                        CallReturnPlaces::Yield(resume_place) => {
+                            analysis.apply_yield_resume_effect(exit_state, return_, resume_place)
+                        }
+                        CallReturnPlaces::Call(_) if A::MERGES_CALL_RETURN_STATE => {
+                            let edge_state = exit_state.clone();
+                            analysis.apply_call_return_effect(exit_state, bb, place);
+                            analysis.merge_call_return_state(&edge_state, exit_state);
+                        }
+                        _ => analysis.apply_call_return_effect(exit_state, bb, place),
+                    }
+                    propagate(analysis, return_, exit_state);
                 }
             }
             TerminatorEdges::SwitchInt { targets, discr } => {
-                let mut applier = ForwardSwitchIntEdgeEffectsApplier {
-                    exit_state,
-                    targets,
-                    propagate,
-                    effects_applied: false,
-                };
-
-                analysis.apply_switch_int_edge_effects(bb, discr, &mut applier);
-
-                let ForwardSwitchIntEdgeEffectsApplier {
-                    exit_state,
-                    mut propagate,
-                    effects_applied,
-                    ..
-                } = applier;
-
-                if !effects_applied {
+                if let Some(mut data) = analysis.get_switch_int_data(bb, discr) {
+                    let mut tmp = None;
+                    for (value, target) in targets.iter() {
+                        let tmp = opt_clone_from_or_clone(&mut tmp, exit_state);
+                        let edge = SwitchIntTarget { value: Some(value), target };
+                        analysis.apply_switch_int_edge_effect(&mut data, tmp, edge);
+                        propagate(analysis, target, tmp);
+                    }
+
+                    // Once we get to the final, "otherwise" branch, there is no need to preserve
+                    // `exit_state`, so pass it directly to `apply_switch_int_edge_effect` to save
+                    // a clone of the dataflow state.
+                    let otherwise = targets.otherwise();
+                    let edge = SwitchIntTarget { value: None, target: otherwise };
+                    analysis.apply_switch_int_edge_effect(&mut data, exit_state, edge);
+                    propagate(analysis, otherwise, exit_state);
+                } else {
                     for target in targets.all_targets() {
-                        propagate(*target, exit_state);
+                        propagate(analysis, *target, exit_state);
                     }
                 }
             }
@@ -523,39 +613,6 @@ fn join_state_into_successors_of<'tcx, A>(
     }
 }
 
-struct ForwardSwitchIntEdgeEffectsApplier<'a, D, F> {
-    exit_state: &'a mut D,
-    targets: &'a SwitchTargets,
-    propagate: F,
-
-    effects_applied: bool,
-}
-
-impl<D, F> super::SwitchIntEdgeEffects<D> for ForwardSwitchIntEdgeEffectsApplier<'_, D, F>
-where
-    D: Clone,
-    F: FnMut(BasicBlock, &D),
-{
-    fn apply(&mut self, mut apply_edge_effect: impl FnMut(&mut D, SwitchIntTarget)) {
-        assert!(!self.effects_applied);
-
-        let mut tmp = None;
-        for (value, target) in self.targets.iter() {
-            let tmp = opt_clone_from_or_clone(&mut tmp, self.exit_state);
-            apply_edge_effect(tmp, SwitchIntTarget { value: Some(value), target });
-            (self.propagate)(target, tmp);
-        }
-
-        // Once we get to the final, "otherwise" branch, there is no need to preserve `exit_state`,
-        // so pass it directly to `apply_edge_effect` to save a clone of the dataflow state.
-        let otherwise = self.targets.otherwise();
-        apply_edge_effect(self.exit_state, SwitchIntTarget { value: None, target: otherwise });
-        (self.propagate)(otherwise, self.exit_state);
-
-        self.effects_applied = true;
-    }
-}
-
 /// An analogue of `Option::get_or_insert_with` that stores a clone of `val` into `opt`, but uses
 /// the more efficient `clone_from` if `opt` was `Some`.
 ///
@@ -1,35 +1,387 @@
 //! A solver for dataflow problems.
 
 use crate::errors::{
-    DuplicateValuesFor, PathMustEndInFilename, RequiresAnArgument, UnknownFormatter,
+    DidNotConverge, DuplicateValuesFor, InvalidBlockRange, InvalidFontSize, InvalidTrackedBit,
+    InvalidUncachedBlock, InvalidWrapWidth, PathMustEndInFilename, RequiresAnArgument,
+    StartBlockSeedLost, UnknownFormatter,
 };
 use crate::framework::BitSetExt;
 
 use std::borrow::Borrow;
-use std::ffi::OsString;
+use std::fmt;
+use std::hash::Hash;
+use std::iter;
 use std::marker::PhantomData;
-use std::path::PathBuf;
+use std::ops;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use rustc_ast as ast;
+use rustc_data_structures::captures::Captures;
+use rustc_data_structures::fx::{FxHashMap, FxIndexSet};
+use rustc_data_structures::sync::{self, DynSend, DynSync, FromDyn};
 use rustc_data_structures::work_queue::WorkQueue;
 use rustc_graphviz as dot;
 use rustc_hir::def_id::DefId;
+use rustc_index::bit_set::{BitMatrix, BitRelations, BitSet};
 use rustc_index::{Idx, IndexVec};
-use rustc_middle::mir::{self, traversal, BasicBlock};
-use rustc_middle::mir::{create_dump_file, dump_enabled};
+use rustc_middle::mir::graphviz::RustcMirAttrs;
+use rustc_middle::mir::{self, traversal, BasicBlock, TerminatorEdges};
+use rustc_middle::mir::{create_dump_file, dump_enabled, PassWhere};
 use rustc_middle::ty::print::with_no_trimmed_paths;
 use rustc_middle::ty::TyCtxt;
 use rustc_span::symbol::{sym, Symbol};
 
-use super::fmt::DebugWithContext;
+use super::fmt::{DebugWithAdapter, DebugWithContext};
 use super::graphviz;
 use super::{
-    visit_results, Analysis, AnalysisDomain, CloneAnalysis, Direction, GenKill, GenKillAnalysis,
-    GenKillSet, JoinSemiLattice, ResultsClonedCursor, ResultsCursor, ResultsRefCursor,
-    ResultsVisitor,
+    edge_targets, visit_results, Analysis, AnalysisDomain, CloneAnalysis, DenseBitSetResults,
+    Direction, GenKill, GenKillAnalysis, GenKillSet, JoinSemiLattice, ResultsClonedCursor,
+    ResultsCursor, ResultsRefCursor, ResultsVisitor,
 };
 
-pub type EntrySets<'tcx, A> = IndexVec<BasicBlock, <A as AnalysisDomain<'tcx>>::Domain>;
+/// The dataflow state on entry to each basic block of a body.
+///
+/// Entry sets are populated lazily: a block whose entry set was never written to shares a single
+/// `bottom_value` with all other such blocks. Most entry sets stay at bottom until late in the
+/// fixpoint iteration, and those of unreachable blocks stay there forever, so this avoids
+/// allocating a full domain per block up front.
+///
+/// After the fixpoint is reached, `compact` can additionally make blocks with equal entry sets share
+/// a single copy of it. Writing to such a block gives it its own copy again.
+///
+/// A zero-sized domain has a single value, so every entry set is `bottom` and nothing is stored per
+/// block. This makes the entry sets of such an analysis, e.g. a pure reachability analysis over
+/// `()`, free to create.
+///
+/// This can be indexed and iterated over like an `IndexVec`. Indexing mutably populates a block.
+pub struct EntrySets<'tcx, A>
+where
+    A: AnalysisDomain<'tcx>,
+{
+    /// The entry set of each block. This is empty if the domain is zero-sized.
+    sets: IndexVec<BasicBlock, EntrySet<A::Domain>>,
+    /// The number of blocks.
+    len: usize,
+    /// The entry set of every unpopulated block, i.e. the `bottom_value` of the analysis.
+    bottom: A::Domain,
+    /// The entry sets shared by several blocks (see `compact`).
+    shared: Vec<A::Domain>,
+}
+
+/// How the entry set of a single block is stored in `EntrySets`.
+#[derive(Clone)]
+enum EntrySet<D> {
+    /// The block is unpopulated, so its entry set is `bottom`.
+    Bottom,
+    /// The block has its own entry set.
+    Owned(D),
+    /// The entry set of the block is at this index in `shared`.
+    Shared(usize),
+}
+
+impl<D: Clone> EntrySet<D> {
+    /// Gives the block its own copy of its entry set, and returns it.
+    fn make_owned(&mut self, bottom: &D, shared: &[D]) -> &mut D {
+        let set = match self {
+            EntrySet::Owned(set) => return set,
+            EntrySet::Bottom => bottom.clone(),
+            EntrySet::Shared(index) => shared[*index].clone(),
+        };
+        *self = EntrySet::Owned(set);
+        let EntrySet::Owned(set) = self else { unreachable!() };
+        set
+    }
+}
+
+impl<'tcx, A> EntrySets<'tcx, A>
+where
+    A: AnalysisDomain<'tcx>,
+{
+    const DOMAIN_IS_ZST: bool = std::mem::size_of::<A::Domain>() == 0;
+
+    /// Creates the entry sets for `n` blocks, all of them `bottom` and unpopulated.
+    pub fn from_bottom_n(bottom: A::Domain, n: usize) -> Self {
+        let sets = if Self::DOMAIN_IS_ZST {
+            IndexVec::new()
+        } else {
+            IndexVec::from_elem_n(EntrySet::Bottom, n)
+        };
+        EntrySets { sets, len: n, bottom, shared: vec![] }
+    }
+
+    /// Creates entry sets in which every block is populated with its entry in `sets`.
+    ///
+    /// `bottom` must be the `bottom_value` of the analysis. It is what blocks are reset to by
+    /// `take`, and what code without a `TyCtxt` uses as the bottom value.
+    pub fn from_sets(sets: IndexVec<BasicBlock, A::Domain>, bottom: A::Domain) -> Self {
+        Self::from_parts(sets.into_iter().map(EntrySet::Owned).collect(), bottom, vec![])
+    }
+
+    /// Creates entry sets from the given per-block `sets`, dropping them if the domain is
+    /// zero-sized.
+    fn from_parts(
+        sets: IndexVec<BasicBlock, EntrySet<A::Domain>>,
+        bottom: A::Domain,
+        shared: Vec<A::Domain>,
+    ) -> Self {
+        let len = sets.len();
+        if Self::DOMAIN_IS_ZST {
+            return EntrySets { sets: IndexVec::new(), len, bottom, shared: vec![] };
+        }
+        EntrySets { sets, len, bottom, shared }
+    }
+
+    fn assert_in_bounds(&self, block: BasicBlock) {
+        assert!(block.index() < self.len, "{block:?} out of bounds for {} entry sets", self.len);
+    }
+
+    /// Returns the entry set of unpopulated blocks, i.e. the `bottom_value` of the analysis.
+    ///
+    /// This lets code without a `TyCtxt` get at the bottom value.
+    pub fn bottom(&self) -> &A::Domain {
+        &self.bottom
+    }
+
+    fn resolve<'a>(&'a self, set: &'a EntrySet<A::Domain>) -> &'a A::Domain {
+        match set {
+            EntrySet::Bottom => self.bottom(),
+            EntrySet::Owned(set) => set,
+            EntrySet::Shared(index) => &self.shared[*index],
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns the entry set of `block`, or `None` if `block` is not a block of the body.
+    pub fn get(&self, block: BasicBlock) -> Option<&A::Domain> {
+        if Self::DOMAIN_IS_ZST {
+            return (block.index() < self.len).then(|| self.bottom());
+        }
+        self.sets.get(block).map(|set| self.resolve(set))
+    }
+
+    /// Returns `true` if the entry set of `block` was ever written to, and thus is not stored as
+    /// the shared `bottom`.
+    pub fn is_populated(&self, block: BasicBlock) -> bool {
+        if Self::DOMAIN_IS_ZST {
+            self.assert_in_bounds(block);
+            return false;
+        }
+        !matches!(self.sets[block], EntrySet::Bottom)
+    }
+
+    /// Calls `f` on the entry set of `block`, which must return whether it changed it.
+    ///
+    /// Unlike indexing mutably, this leaves `block` unpopulated if it was and `f` did not change
+    /// it, so joining `bottom` into a block that is still at bottom never populates it.
+    pub fn update(&mut self, block: BasicBlock, f: impl FnOnce(&mut A::Domain) -> bool) -> bool {
+        if Self::DOMAIN_IS_ZST {
+            return f(&mut self[block]);
+        }
+        if let EntrySet::Owned(set) = &mut self.sets[block] {
+            return f(set);
+        }
+
+        let mut set = self[block].clone();
+        let changed = f(&mut set);
+        if changed {
+            self.sets[block] = EntrySet::Owned(set);
+        }
+        changed
+    }
+
+    /// Returns the join of the entry sets of `blocks`, i.e. the facts that may hold on entry to any
+    /// of them. This is `bottom` if `blocks` is empty.
+    pub fn joined_over(&self, blocks: impl IntoIterator<Item = BasicBlock>) -> A::Domain {
+        let mut joined = self.bottom().clone();
+        for block in blocks {
+            joined.join(&self[block]);
+        }
+        joined
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &A::Domain> + '_ {
+        let unstored = if Self::DOMAIN_IS_ZST { self.len } else { 0 };
+        let stored = self.sets.iter().map(move |set| self.resolve(set));
+        stored.chain(iter::repeat_with(|| self.bottom()).take(unstored))
+    }
+
+    pub fn iter_enumerated(&self) -> impl Iterator<Item = (BasicBlock, &A::Domain)> + '_ {
+        (0..self.len).map(BasicBlock::new).zip(self.iter())
+    }
+
+    /// Like `iter_enumerated`, but populates every block.
+    pub fn iter_enumerated_mut(
+        &mut self,
+    ) -> impl Iterator<Item = (BasicBlock, &mut A::Domain)> + '_ {
+        let unstored_len = if Self::DOMAIN_IS_ZST { self.len } else { 0 };
+        let EntrySets { sets, len: _, bottom, shared } = self;
+        let (bottom, shared) = (&*bottom, &*shared);
+        let stored = sets.iter_mut().map(move |set| set.make_owned(bottom, shared));
+        // Leaking a box of a zero-sized type neither allocates nor leaks any memory.
+        let unstored =
+            iter::repeat_with(move || Box::leak(Box::new(bottom.clone()))).take(unstored_len);
+        stored.chain(unstored).enumerate().map(|(index, set)| (BasicBlock::new(index), set))
+    }
+
+    /// Calls `widen` on the entry set of every populated block. Unpopulated blocks are at `bottom`,
+    /// which is never widened.
+    pub(super) fn widen_populated(&mut self, widen: &dyn Fn(&mut A::Domain)) {
+        let EntrySets { sets, len: _, bottom, shared } = self;
+        for set in sets.iter_mut() {
+            if !matches!(set, EntrySet::Bottom) {
+                widen(set.make_owned(bottom, shared));
+            }
+        }
+    }
+
+    /// Converts every entry set with `f`, keeping unpopulated blocks unpopulated.
+    pub fn map<B>(&self, f: impl Fn(&A::Domain) -> B::Domain) -> EntrySets<'tcx, B>
+    where
+        B: AnalysisDomain<'tcx>,
+    {
+        let sets = self.sets.iter().map(|set| match set {
+            EntrySet::Bottom => EntrySet::Bottom,
+            EntrySet::Owned(set) => EntrySet::Owned(f(set)),
+            EntrySet::Shared(index) => EntrySet::Shared(*index),
+        });
+        let sets = if Self::DOMAIN_IS_ZST {
+            IndexVec::from_elem_n(EntrySet::Bottom, self.len)
+        } else {
+            sets.collect()
+        };
+        EntrySets::from_parts(sets, f(&self.bottom), self.shared.iter().map(&f).collect())
+    }
+
+    /// Makes all blocks with equal entry sets share a single copy of it, and returns the average
+    /// number of blocks per stored entry set, e.g. `4.0` if every distinct entry set is shared by
+    /// four blocks.
+    ///
+    /// Blocks whose entry set equals `bottom` are unpopulated again. This is meant to be called
+    /// once the entry sets are final, since writing to a shared entry set copies it.
+    pub fn compact(&mut self) -> f64
+    where
+        A::Domain: Hash,
+    {
+        if Self::DOMAIN_IS_ZST {
+            return self.len.max(1) as f64;
+        }
+
+        let mut distinct = FxIndexSet::default();
+        let sets = std::mem::take(&mut self.sets);
+        let shared = std::mem::take(&mut self.shared);
+        self.sets = sets
+            .into_iter()
+            .map(|set| {
+                let set = match set {
+                    EntrySet::Bottom => return EntrySet::Bottom,
+                    EntrySet::Owned(set) => set,
+                    EntrySet::Shared(index) => shared[index].clone(),
+                };
+                if self.bottom == set {
+                    return EntrySet::Bottom;
+                }
+                EntrySet::Shared(distinct.insert_full(set).0)
+            })
+            .collect();
+        self.shared = distinct.into_iter().collect();
+
+        let has_bottom = self.sets.iter().any(|set| matches!(set, EntrySet::Bottom));
+        let stored = self.shared.len() + usize::from(has_bottom);
+        if stored == 0 {
+            1.0
+        } else {
+            self.len() as f64 / stored as f64
+        }
+    }
+
+    /// Formats the entry set of every block on its own line, with `DebugWithContext`.
+    pub fn render(&self, analysis: &A) -> String
+    where
+        A::Domain: DebugWithContext<A>,
+    {
+        self.iter_enumerated()
+            .map(|(block, set)| {
+                format!("{block:?}: {:?}\n", DebugWithAdapter { this: set, ctxt: analysis })
+            })
+            .collect()
+    }
+}
+
+impl<'tcx, A> ops::Index<BasicBlock> for EntrySets<'tcx, A>
+where
+    A: AnalysisDomain<'tcx>,
+{
+    type Output = A::Domain;
+
+    fn index(&self, block: BasicBlock) -> &A::Domain {
+        if Self::DOMAIN_IS_ZST {
+            self.assert_in_bounds(block);
+            return self.bottom();
+        }
+        self.resolve(&self.sets[block])
+    }
+}
+
+impl<'tcx, A> ops::IndexMut<BasicBlock> for EntrySets<'tcx, A>
+where
+    A: AnalysisDomain<'tcx>,
+{
+    fn index_mut(&mut self, block: BasicBlock) -> &mut A::Domain {
+        if Self::DOMAIN_IS_ZST {
+            self.assert_in_bounds(block);
+            return &mut self.bottom;
+        }
+        let EntrySets { sets, len: _, bottom, shared } = self;
+        sets[block].make_owned(bottom, shared)
+    }
+}
+
+impl<'tcx, A> Clone for EntrySets<'tcx, A>
+where
+    A: AnalysisDomain<'tcx>,
+{
+    fn clone(&self) -> Self {
+        EntrySets {
+            sets: self.sets.clone(),
+            len: self.len,
+            bottom: self.bottom.clone(),
+            shared: self.shared.clone(),
+        }
+    }
+
+    fn clone_from(&mut self, source: &Self) {
+        self.sets.clone_from(&source.sets);
+        self.len = source.len;
+        self.bottom.clone_from(&source.bottom);
+        self.shared.clone_from(&source.shared);
+    }
+}
+
+impl<'tcx, A> PartialEq for EntrySets<'tcx, A>
+where
+    A: AnalysisDomain<'tcx>,
+{
+    /// Compares the entry sets block by block, regardless of which blocks are populated.
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl<'tcx, A> Eq for EntrySets<'tcx, A> where A: AnalysisDomain<'tcx> {}
+
+impl<'tcx, A> fmt::Debug for EntrySets<'tcx, A>
+where
+    A: AnalysisDomain<'tcx>,
+    A::Domain: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
 
 /// A dataflow analysis that has converged to fixpoint.
 pub struct Results<'tcx, A, E = EntrySets<'tcx, A>>
@@ -38,9 +390,71 @@ pub struct Results<'tcx, A, E = EntrySets<'tcx, A>>
 {
     pub analysis: A,
     pub(super) entry_sets: E,
+    /// The cumulative transfer function of the statements of each block, if the `Engine` was
+    /// created with `new_gen_kill_batched`. Used by `ResultsCursor` to skip over whole blocks.
+    pub(super) block_trans: Option<BlockTrans<A::Domain>>,
+    /// How often each block was visited, if requested by `Engine::with_convergence_tracking`.
+    pub(super) convergence: Option<ConvergenceInfo>,
     pub(super) _marker: PhantomData<&'tcx ()>,
 }
 
+/// The number of times each basic block was visited while iterating to fixpoint, as recorded by
+/// `Engine::with_convergence_tracking`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConvergenceInfo {
+    iterations: IndexVec<BasicBlock, u32>,
+}
+
+impl ConvergenceInfo {
+    /// Returns the number of times the transfer function of `block` was applied before its entry
+    /// set stabilized, including the final visit with the converged entry set. This is zero for
+    /// blocks that were never visited, e.g. unreachable ones.
+    pub fn iterations_for(&self, block: BasicBlock) -> u32 {
+        self.iterations[block]
+    }
+
+    /// Returns the number of block visits needed to reach fixpoint, i.e. the sum of
+    /// `iterations_for` over all blocks.
+    pub fn total_iterations(&self) -> u64 {
+        self.iterations.iter().map(|&n| u64::from(n)).sum()
+    }
+}
+
+/// Applies the effects of all statements in a block, cached by `Engine::new_gen_kill_batched`.
+///
+/// This is an `Arc` rather than an `Lrc` so that `Results` remain `Send`, which is why only the
+/// transfer functions of thread-safe index types can be passed on to `Results`.
+pub(super) type BlockTrans<D> = Arc<dyn Fn(BasicBlock, &mut D) + Send + Sync + DynSend + DynSync>;
+
+/// Returns the widening function of `Engine::new_bounded`, which calls `widen` on entry sets with
+/// more than `max_elements` elements.
+pub(super) fn widen_above<D, T>(
+    max_elements: usize,
+    widen: impl Fn(&mut D) + 'static,
+) -> impl Fn(&mut D) + 'static
+where
+    D: BitSetExt<T>,
+{
+    move |state: &mut D| {
+        if state.count() > max_elements {
+            widen(state);
+        }
+    }
+}
+
+/// Wraps the cached transfer functions of a gen/kill problem into a `BlockTrans`.
+///
+/// This only needs `D` to implement `GenKill`, so it works for domains that aren't bitsets.
+pub(super) fn block_trans<D, T>(
+    trans_for_block: Arc<IndexVec<BasicBlock, GenKillSet<T>>>,
+) -> BlockTrans<D>
+where
+    D: GenKill<T>,
+    T: Idx + Send + Sync + DynSend + DynSync,
+{
+    Arc::new(move |bb: BasicBlock, state: &mut D| trans_for_block[bb].apply(state))
+}
+
 /// `Results` type with a cloned `Analysis` and borrowed entry sets.
 pub type ResultsCloned<'res, 'tcx, A> = Results<'tcx, A, &'res EntrySets<'tcx, A>>;
 
@@ -49,6 +463,14 @@ impl<'tcx, A, E> Results<'tcx, A, E>
     A: Analysis<'tcx>,
     E: Borrow<EntrySets<'tcx, A>>,
 {
+    /// Creates `Results` from the entry sets of a solved analysis, without any of the extra
+    /// information an `Engine` may record, such as cached block transfer functions.
+    ///
+    /// This is private to the framework, so that `Results` always hold the fixpoint of `A`.
+    pub(super) fn new(analysis: A, entry_sets: E) -> Self {
+        Results { analysis, entry_sets, block_trans: None, convergence: None, _marker: PhantomData }
+    }
+
     /// Creates a `ResultsCursor` that can inspect these `Results`.
     pub fn into_results_cursor<'mir>(
         self,
@@ -62,6 +484,153 @@ pub fn entry_set_for_block(&self, block: BasicBlock) -> &A::Domain {
         &self.entry_sets.borrow()[block]
     }
 
+    /// Returns how often each block was visited while iterating to fixpoint, if the `Engine` was
+    /// asked to record it with `with_convergence_tracking`.
+    pub fn convergence_info(&self) -> Option<&ConvergenceInfo> {
+        self.convergence.as_ref()
+    }
+
+    /// Returns the number of iterations `block` needed to converge, if recorded. See
+    /// `ConvergenceInfo::iterations_for`.
+    pub fn block_converged_in(&self, block: BasicBlock) -> Option<u32> {
+        self.convergence.as_ref().map(|convergence| convergence.iterations_for(block))
+    }
+
+    /// Returns the CFG successors of `block` along edges that the analysis considers feasible, in
+    /// the order of `Terminator::successors`.
+    ///
+    /// This recomputes the effects of `block` from its entry set in the same way as the fixpoint
+    /// iteration, so edges pruned by `apply_terminator_effect` or by `Analysis::is_edge_feasible`
+    /// during the solve are pruned here as well.
+    pub fn terminator_edges(
+        &mut self,
+        body: &mir::Body<'tcx>,
+        block: BasicBlock,
+    ) -> Vec<BasicBlock> {
+        let edges: Vec<_> = edge_targets(self.block_edges(body, block)).collect();
+        body[block]
+            .terminator()
+            .successors()
+            .filter(|&target| {
+                let (from, to) =
+                    if A::Direction::IS_FORWARD { (block, target) } else { (target, block) };
+                edges.contains(&target) && self.feasible_edge_state(body, from, to).is_some()
+            })
+            .collect()
+    }
+
+    /// Returns the edges of the terminator of `block` as computed by `apply_terminator_effect`,
+    /// starting from the entry set of `block`.
+    fn block_edges<'mir>(
+        &mut self,
+        body: &'mir mir::Body<'tcx>,
+        block: BasicBlock,
+    ) -> TerminatorEdges<'mir, 'tcx> {
+        let mut state = self.entry_set_for_block(block).clone();
+        A::Direction::apply_effects_in_block(
+            &mut self.analysis,
+            &mut state,
+            block,
+            &body[block],
+            None,
+            None,
+        )
+    }
+
+    /// Returns the first block in reverse postorder whose entry set satisfies `pred`, or `None` if
+    /// there is no such block.
+    ///
+    /// Unreachable blocks are never passed to `pred`.
+    pub fn find_first_block_where(
+        &self,
+        body: &mir::Body<'tcx>,
+        pred: impl Fn(BasicBlock, &A::Domain) -> bool,
+    ) -> Option<BasicBlock> {
+        body.basic_blocks
+            .reverse_postorder()
+            .iter()
+            .copied()
+            .find(|&bb| pred(bb, self.entry_set_for_block(bb)))
+    }
+
+    /// Returns the state propagated from each CFG predecessor of `bb` into `bb`, along with that
+    /// predecessor. See `edge_state`. Predecessors with no feasible edge to `bb` (see
+    /// `Analysis::is_edge_feasible`) are left out.
+    ///
+    /// For a forward analysis, joining these states reproduces the entry set of `bb`, since they
+    /// already include any edge-specific effects (e.g. `apply_call_return_effect`). This is useful
+    /// for tracking down bugs in `join` or in those edge effects.
+    pub fn predecessor_exit_states(
+        &mut self,
+        body: &mir::Body<'tcx>,
+        bb: BasicBlock,
+    ) -> Vec<(BasicBlock, A::Domain)> {
+        assert!(A::Direction::IS_FORWARD, "predecessor exit states require a forward analysis");
+
+        // A block with several edges to `bb` appears once per edge, but `edge_state` already
+        // joins over all of them.
+        let mut preds = body.basic_blocks.predecessors()[bb].to_vec();
+        preds.dedup();
+        preds
+            .into_iter()
+            .filter_map(|pred| Some((pred, self.feasible_edge_state(body, pred, bb)?)))
+            .collect()
+    }
+
+    /// Returns the state propagated from `from` to `to` along the edges between them, in dataflow
+    /// order. That is, `from` is a CFG predecessor of `to` for forward analyses, and a CFG successor
+    /// for backward ones.
+    ///
+    /// This is the state after all effects of `from`, with the edge-specific effects of the edges
+    /// to `to` applied (e.g. `apply_call_return_effect`), joined exactly as the fixpoint iteration
+    /// joins it into the entry set of `to` (see `Analysis::join_at`). If there
+    /// are several such edges, their states are joined. If the analysis considers none of them
+    /// feasible, this is `bottom_value`.
+    pub fn edge_state(
+        &mut self,
+        body: &mir::Body<'tcx>,
+        from: BasicBlock,
+        to: BasicBlock,
+    ) -> A::Domain {
+        self.feasible_edge_state(body, from, to)
+            .unwrap_or_else(|| self.entry_sets.borrow().bottom().clone())
+    }
+
+    /// Like `edge_state`, but returns `None` if the analysis considers none of the edges from
+    /// `from` to `to` feasible.
+    fn feasible_edge_state(
+        &mut self,
+        body: &mir::Body<'tcx>,
+        from: BasicBlock,
+        to: BasicBlock,
+    ) -> Option<A::Domain> {
+        let mut cursor = ResultsCursor::new(body, &mut *self);
+        if A::Direction::IS_FORWARD {
+            cursor.seek_to_block_end(from);
+        } else {
+            cursor.seek_to_block_start(from);
+        }
+        let mut exit_state = cursor.get().clone();
+
+        let edges = self.block_edges(body, from);
+        let bottom = self.entry_sets.borrow().bottom();
+        let mut edge_state = None;
+        A::Direction::join_state_into_successors_of(
+            &mut self.analysis,
+            body,
+            &mut exit_state,
+            from,
+            edges,
+            |analysis: &A, target, state: &A::Domain| {
+                if target == to {
+                    let edge_state = edge_state.get_or_insert_with(|| bottom.clone());
+                    analysis.join_at(from, to, edge_state, state);
+                }
+            },
+        );
+        edge_state
+    }
+
     pub fn visit_with<'mir>(
         &mut self,
         body: &'mir mir::Body<'tcx>,
@@ -79,6 +648,151 @@ pub fn visit_reachable_with<'mir>(
         let blocks = mir::traversal::reachable(body);
         visit_results(body, blocks.map(|(bb, _)| bb), self, vis)
     }
+
+    /// Like `visit_reachable_with`, but skips blocks whose entry set is `bottom_value`.
+    ///
+    /// This is cheaper for sparse analyses, where most blocks are never affected. Note that the
+    /// effects within a skipped block may still make its state non-bottom, so this is only
+    /// appropriate for visitors that don't care about such blocks.
+    pub fn visit_non_bottom_blocks<'mir>(
+        &mut self,
+        body: &'mir mir::Body<'tcx>,
+        vis: &mut impl ResultsVisitor<'mir, 'tcx, Self, FlowState = A::Domain>,
+    ) {
+        let bottom = self.entry_sets.borrow().bottom();
+        let blocks: Vec<_> = mir::traversal::reachable(body)
+            .map(|(bb, _)| bb)
+            .filter(|&bb| self.entry_set_for_block(bb) != bottom)
+            .collect();
+        visit_results(body, blocks, self, vis)
+    }
+
+    /// Returns the intersection of the entry sets of `a` and `b`, i.e. the facts that hold on entry
+    /// to both blocks.
+    pub fn meet_blocks(&self, a: BasicBlock, b: BasicBlock) -> A::Domain
+    where
+        A::Domain: BitRelations<A::Domain>,
+    {
+        let entry_sets = self.entry_sets.borrow();
+        let mut meet = entry_sets[a].clone();
+        meet.intersect(&entry_sets[b]);
+        meet
+    }
+
+    /// Returns the largest number of elements in any entry set, i.e. the greatest height in the
+    /// lattice reached by the fixpoint.
+    ///
+    /// Compared to the domain size, this shows how much of the lattice an analysis explores on a
+    /// given body.
+    pub fn max_height<T>(&self) -> usize
+    where
+        A::Domain: BitSetExt<T>,
+    {
+        self.entry_sets.borrow().iter().map(|entry_set| entry_set.count()).max().unwrap_or(0)
+    }
+
+    /// Returns `true` if the entry set of every basic block is empty.
+    ///
+    /// Consumers can use this to skip per-location work entirely if the analysis never set a
+    /// single bit. Iteration stops at the first non-empty entry set.
+    pub fn is_empty_everywhere<T>(&self) -> bool
+    where
+        A::Domain: BitSetExt<T>,
+    {
+        self.entry_sets.borrow().iter().all(|set| set.is_empty())
+    }
+}
+
+impl<'tcx, A, E> Results<'tcx, A, E>
+where
+    A: GenKillAnalysis<'tcx>,
+    A::Domain: BitSetExt<A::Idx>,
+    E: Borrow<EntrySets<'tcx, A>>,
+{
+    /// Returns the locations that may have set `bit` in the state immediately before the primary
+    /// effect at `loc`, sorted and without duplicates.
+    ///
+    /// Starting at `loc`, this walks backward through the CFG, following only paths along which
+    /// `bit` is set, until it finds the location whose effect set it. If `bit` was set by an
+    /// edge-specific effect (e.g., `call_return_effect`), the location of the terminator of the
+    /// source block of that edge is returned. Bits set by `initialize_start_block` have no such
+    /// location and are not reported.
+    ///
+    /// This is only meaningful for forward "maybe" analyses (those whose join is union), such as
+    /// `MaybeInitializedPlaces`.
+    pub fn explain_bit(
+        &mut self,
+        body: &mir::Body<'tcx>,
+        loc: mir::Location,
+        bit: usize,
+    ) -> Vec<mir::Location> {
+        assert!(A::Direction::IS_FORWARD, "`explain_bit` requires a forward analysis");
+
+        let bit = A::Idx::new(bit);
+        let mut cursor = ResultsCursor::new(body, self);
+        let mut gen_sites = vec![];
+
+        cursor.seek_before_primary_effect(loc);
+        if !cursor.contains(bit) {
+            return gen_sites;
+        }
+
+        // Returns whether `bit` is set in the state just before any effect at `loc` is applied.
+        let set_before = |cursor: &mut ResultsCursor<'_, 'tcx, A, _>, loc: mir::Location| {
+            match loc.statement_index.checked_sub(1) {
+                Some(prev) => {
+                    cursor.seek_after_primary_effect(mir::Location { statement_index: prev, ..loc })
+                }
+                None => cursor.seek_to_block_start(loc.block),
+            }
+            cursor.contains(bit)
+        };
+
+        // Each entry is a location after whose primary effect `bit` is known to be set, or `None`
+        // if `bit` is known to be set on entry to the block.
+        let mut worklist = vec![];
+        let mut visited_entries = BitSet::new_empty(body.basic_blocks.len());
+        if set_before(&mut cursor, loc) {
+            worklist.push((loc.block, loc.statement_index.checked_sub(1)));
+        } else {
+            gen_sites.push(loc);
+        }
+
+        while let Some((block, statement_index)) = worklist.pop() {
+            if let Some(statement_index) = statement_index {
+                let loc = mir::Location { block, statement_index };
+                if set_before(&mut cursor, loc) {
+                    worklist.push((block, statement_index.checked_sub(1)));
+                } else {
+                    gen_sites.push(loc);
+                }
+                continue;
+            }
+
+            if !visited_entries.insert(block) {
+                continue;
+            }
+
+            // The edge states include any edge-specific effects, so they tell whether `bit`
+            // reaches `block` from each predecessor at all.
+            for (pred, edge_state) in cursor.mut_results().predecessor_exit_states(body, block) {
+                if !edge_state.contains(bit) {
+                    continue;
+                }
+                let pred_terminator = body.terminator_loc(pred);
+                cursor.seek_after_primary_effect(pred_terminator);
+                if cursor.contains(bit) {
+                    worklist.push((pred, Some(pred_terminator.statement_index)));
+                } else {
+                    gen_sites.push(pred_terminator);
+                }
+            }
+        }
+
+        gen_sites.sort_unstable();
+        gen_sites.dedup();
+        gen_sites
+    }
 }
 impl<'tcx, A> Results<'tcx, A>
 where
@@ -91,6 +805,70 @@ pub fn as_results_cursor<'a, 'mir>(
     ) -> ResultsRefCursor<'a, 'mir, 'tcx, A> {
         ResultsCursor::new(body, self)
     }
+
+    /// Replaces the entry set of `block` with `new_state`, returning `true` if it changed.
+    ///
+    /// This does not propagate the new state to any other block, so it is up to the caller to keep
+    /// the results consistent, e.g. after a MIR transform that only affects `block`.
+    pub fn patch_entry_set(&mut self, block: BasicBlock, new_state: A::Domain) -> bool {
+        let entry_set = &mut self.entry_sets[block];
+        if *entry_set == new_state {
+            return false;
+        }
+
+        *entry_set = new_state;
+        true
+    }
+
+    /// Combines these results with `other`, which must be for the same body, by applying `join` to
+    /// the entry sets of each block. The analysis of `other` is dropped.
+    ///
+    /// This is not the lattice join of `A::Domain` unless `join` is. Since only the entry sets are
+    /// combined, a cursor over the merged results recomputes the states within each block with the
+    /// transfer functions of `self.analysis`.
+    pub fn merge_with(mut self, other: Self, join: impl Fn(&mut A::Domain, &A::Domain)) -> Self {
+        assert_eq!(self.entry_sets.len(), other.entry_sets.len());
+        for (block, entry_set) in self.entry_sets.iter_enumerated_mut() {
+            join(entry_set, &other.entry_sets[block]);
+        }
+        self
+    }
+
+    /// Splits these results into the analysis and the entry set of each block.
+    pub fn into_parts(self) -> (A, EntrySets<'tcx, A>) {
+        (self.analysis, self.entry_sets)
+    }
+
+    /// Makes blocks with equal entry sets share a single copy of it, to save memory once the
+    /// results are final. Returns the average number of blocks per stored entry set.
+    ///
+    /// See `EntrySets::compact`. All ways of inspecting the results, including cursors, see the
+    /// same states as before.
+    pub fn compact(&mut self) -> f64
+    where
+        A::Domain: Hash,
+    {
+        self.entry_sets.compact()
+    }
+
+    /// Renders these results as a graphviz DOT graph of `body` in the given `style`.
+    ///
+    /// Unlike `-Z dump-mir-dataflow`, this ignores the font and theme options of the session, so
+    /// it needs no `TyCtxt` and its output can be compared against a snapshot in tests.
+    pub fn to_graphviz_string(
+        &mut self,
+        body: &mir::Body<'tcx>,
+        style: graphviz::OutputStyle<A::Domain>,
+    ) -> String
+    where
+        A::Domain: DebugWithContext<A>,
+    {
+        let graphviz = graphviz::Formatter::new(body, self, style);
+        let mut buf = Vec::new();
+        with_no_trimmed_paths!(dot::render(&graphviz, &mut buf))
+            .expect("writing to a `Vec` cannot fail");
+        String::from_utf8(buf).unwrap()
+    }
 }
 impl<'tcx, A> Results<'tcx, A>
 where
@@ -101,6 +879,8 @@ pub fn clone_analysis(&self) -> ResultsCloned<'_, 'tcx, A> {
         Results {
             analysis: self.analysis.clone_analysis(),
             entry_sets: &self.entry_sets,
+            block_trans: self.block_trans.clone(),
+            convergence: self.convergence.clone(),
             _marker: PhantomData,
         }
     }
@@ -112,6 +892,34 @@ pub fn cloned_results_cursor<'mir>(
     ) -> ResultsClonedCursor<'_, 'mir, 'tcx, A> {
         self.clone_analysis().into_results_cursor(body)
     }
+
+    /// Solves the analysis over `body` twice, once starting from these entry sets and once from
+    /// scratch, and returns the number of blocks whose entry sets differ between the two.
+    ///
+    /// This validates a warm start against the cold path: after an edit to `body` that only makes
+    /// its fixpoint greater, e.g. one that the results were patched for with `patch_entry_set`,
+    /// resuming from the old results must reach the same fixpoint, so this should return zero.
+    /// `body` must have as many blocks as the body these results were computed for.
+    pub fn count_changed_on_reanalysis(&self, body: &mir::Body<'tcx>) -> usize {
+        assert_eq!(self.entry_sets.len(), body.basic_blocks.len());
+
+        let mut warm = self.entry_sets.clone();
+        let mut analysis = self.analysis.clone_analysis();
+        analysis.pre_iterate(body);
+        let options = FixpointOptions::default();
+        let converged = iterate_entry_sets_to_fixpoint(&mut analysis, body, &mut warm, options);
+        assert!(converged.is_ok());
+
+        let mut analysis = self.analysis.clone_analysis();
+        analysis.pre_iterate(body);
+        let mut cold = EntrySets::from_bottom_n(self.entry_sets.bottom().clone(), warm.len());
+        analysis.initialize_start_block(body, &mut cold[mir::START_BLOCK]);
+        let options = FixpointOptions::default();
+        let converged = iterate_entry_sets_to_fixpoint(&mut analysis, body, &mut cold, options);
+        assert!(converged.is_ok());
+
+        iter::zip(warm.iter(), cold.iter()).filter(|(warm, cold)| warm != cold).count()
+    }
 }
 impl<'res, 'tcx, A> Results<'tcx, A, &'res EntrySets<'tcx, A>>
 where
@@ -122,6 +930,8 @@ pub fn reclone_analysis(&self) -> Self {
         Results {
             analysis: self.analysis.clone_analysis(),
             entry_sets: self.entry_sets,
+            block_trans: self.block_trans.clone(),
+            convergence: self.convergence.clone(),
             _marker: PhantomData,
         }
     }
@@ -134,19 +944,55 @@ pub struct Engine<'a, 'tcx, A>
 {
     tcx: TyCtxt<'tcx>,
     body: &'a mir::Body<'tcx>,
-    entry_sets: IndexVec<BasicBlock, A::Domain>,
+    entry_sets: EntrySets<'tcx, A>,
     pass_name: Option<&'static str>,
+    phase: Option<&'static str>,
     analysis: A,
 
-    /// Cached, cumulative transfer functions for each block.
+    /// Where and how to write graphviz output, if requested by `dump_to` instead of by `rustc_mir`
+    /// attributes.
+    dump_to: Option<(PathBuf, graphviz::OutputStyle<A::Domain>)>,
+
+    /// Cached, cumulative transfer functions for each block. Returns `false` for blocks whose
+    /// transfer function was not computed (see `new_gen_kill_selective`).
     //
     // FIXME(ecstaticmorse): This boxed `Fn` trait object is invoked inside a tight loop for
     // gen/kill problems on cyclic CFGs. This is not ideal, but it doesn't seem to degrade
     // performance in practice. I've tried a few ways to avoid this, but they have downsides. See
     // the message for the commit that added this FIXME for more information.
-    apply_statement_trans_for_block: Option<Box<dyn Fn(BasicBlock, &mut A::Domain)>>,
+    apply_statement_trans_for_block: Option<ApplyStatementTrans<A::Domain>>,
+
+    /// The same transfer functions, passed on to the `Results` so that cursors can apply them.
+    block_trans: Option<BlockTrans<A::Domain>>,
+
+    /// Called on an entry set whenever it changes as the result of a join. Used to bound the size
+    /// of the domain (see `new_bounded`).
+    widen_entry_set: Option<Box<dyn Fn(&mut A::Domain)>>,
+
+    /// The number of times a block may be visited before the analysis is considered to diverge
+    /// (see `with_cycle_detection`).
+    visit_limit: Option<usize>,
+
+    /// Whether to record the number of visits to each block (see `with_convergence_tracking`).
+    track_convergence: bool,
+
+    /// Whether the state of each iteration reuses the allocation of the previous one (see
+    /// `with_state_reuse`).
+    reuse_state: bool,
+
+    /// Whether to warn if the seed of the `START_BLOCK` is lost while solving (see
+    /// `with_seed_check`).
+    check_seed: bool,
 }
 
+/// The number of times `Engine::with_cycle_detection` lets a single block be visited. Analyses
+/// with a finite-height domain need far fewer visits even on large bodies.
+const CYCLE_DETECTION_VISIT_LIMIT: usize = 10_000;
+
+/// Applies the cached transfer function of a block to a state, or returns `false` if there is none
+/// for that block.
+type ApplyStatementTrans<D> = Box<dyn Fn(BasicBlock, &mut D) -> bool>;
+
 impl<'a, 'tcx, A, D, T> Engine<'a, 'tcx, A>
 where
     A: GenKillAnalysis<'tcx, Idx = T, Domain = D>,
@@ -154,35 +1000,164 @@ impl<'a, 'tcx, A, D, T> Engine<'a, 'tcx, A>
     T: Idx,
 {
     /// Creates a new `Engine` to solve a gen-kill dataflow problem.
-    pub fn new_gen_kill(tcx: TyCtxt<'tcx>, body: &'a mir::Body<'tcx>, mut analysis: A) -> Self {
-        // If there are no back-edges in the control-flow graph, we only ever need to apply the
-        // transfer function for each block exactly once (assuming that we process blocks in RPO).
-        //
+    pub fn new_gen_kill(tcx: TyCtxt<'tcx>, body: &'a mir::Body<'tcx>, analysis: A) -> Self {
+        Self::new_gen_kill_with(tcx, body, analysis, |analysis, body, domain_size| {
+            gen_kill_trans_for_blocks(analysis, body, domain_size).into()
+        })
+        .0
+    }
+
+    /// Like `new_gen_kill`, but also passes the transfer functions of all blocks on to the
+    /// `Results`, so that a `ResultsCursor` applies the effects of every statement in a block at
+    /// once when it seeks across the whole block.
+    ///
+    /// A batched seek does not call the statement effects of the analysis, so this must not be
+    /// used if they have side effects that a cursor relies on, e.g. recording which statements
+    /// were visited. Bodies without loops have no cached transfer functions, so their seeks are
+    /// never batched.
+    pub fn new_gen_kill_batched(
+        tcx: TyCtxt<'tcx>,
+        body: &'a mir::Body<'tcx>,
+        analysis: A,
+    ) -> Self
+    where
+        T: Send + Sync + DynSend + DynSync,
+    {
+        let (mut engine, trans_for_block) =
+            Self::new_gen_kill_with(tcx, body, analysis, |analysis, body, domain_size| {
+                gen_kill_trans_for_blocks(analysis, body, domain_size).into()
+            });
+        engine.block_trans = trans_for_block.map(block_trans);
+        engine
+    }
+
+    /// Like `new_gen_kill`, but computes the transfer functions of all blocks in parallel (under
+    /// the parallel compiler).
+    ///
+    /// Each block is analyzed by its own copy of `analysis`, made with `CloneAnalysis`, so its
+    /// statement effects must not rely on changes they make to the analysis. Outside of the
+    /// parallel compiler, a single copy analyzes every block, as in `new_gen_kill`.
+    pub fn new_gen_kill_concurrent(
+        tcx: TyCtxt<'tcx>,
+        body: &'a mir::Body<'tcx>,
+        analysis: A,
+    ) -> Self
+    where
+        A: CloneAnalysis + DynSync,
+    {
+        Self::new_gen_kill_with(tcx, body, analysis, |analysis, body, domain_size| {
+            gen_kill_trans_for_blocks_concurrently(analysis, body, domain_size).into()
+        })
+        .0
+    }
+
+    /// Like `new_gen_kill`, but only computes the transfer functions of blocks that are the source
+    /// of a back edge in a depth-first search of the CFG, i.e. the predecessors of loop headers
+    /// that close a loop. The statement effects of all other blocks are applied one at a time
+    /// whenever they are visited.
+    ///
+    /// This saves the time and memory needed to compute transfer functions for blocks that are
+    /// only visited a few times, at the cost of visiting them more slowly. Since not every block
+    /// has a transfer function, cursors over the results never skip over whole blocks at once.
+    pub fn new_gen_kill_selective(
+        tcx: TyCtxt<'tcx>,
+        body: &'a mir::Body<'tcx>,
+        analysis: A,
+    ) -> Self {
+        Self::new_gen_kill_with(tcx, body, analysis, |analysis, body, domain_size| {
+            let blocks = back_edge_sources(body);
+            CachedBlockTrans::Selected(gen_kill_trans_for_selected_blocks(
+                analysis,
+                body,
+                domain_size,
+                &blocks,
+            ))
+        })
+        .0
+    }
+
+    /// Computes the transfer functions of a gen-kill problem with `trans_for_blocks`.
+    ///
+    /// Also returns the transfer functions if every block has one, for `new_gen_kill_batched`.
+    fn new_gen_kill_with(
+        tcx: TyCtxt<'tcx>,
+        body: &'a mir::Body<'tcx>,
+        mut analysis: A,
+        trans_for_blocks: impl FnOnce(&mut A, &mir::Body<'tcx>, usize) -> CachedBlockTrans<T>,
+    ) -> (Self, Option<Arc<IndexVec<BasicBlock, GenKillSet<T>>>>) {
+        // Any tables that the statement effects consult must exist before they are cached.
+        Analysis::pre_iterate(&mut analysis, body);
+        let domain_size = analysis.domain_size(body);
+
+        // A `domain_size` that disagrees with the `bottom_value` would otherwise cause an
+        // out-of-bounds panic when applying a block transfer function, far from its cause.
+        if cfg!(debug_assertions) {
+            let bottom_size = analysis.bottom_value(tcx, body).domain_size();
+            if let Some(len) = bottom_size.filter(|&len| len != domain_size) {
+                bug!(
+                    "`{}` has a `domain_size` of {domain_size}, but a `bottom_value` of size {len}",
+                    A::NAME,
+                );
+            }
+        }
+
+        // `#[rustc_mir(borrowck_uncached_block = "bbN")]` applies the effects of each statement in
+        // `bbN` one at a time instead. Comparing the results with and without it narrows down a
+        // bug in the coalesced transfer function of that block. It is looked up even if it ends up
+        // unused below, so that an invalid block is always reported.
+        let uncached_block = uncached_block(tcx, body);
+
+        // If there are no back-edges in the control-flow graph, we only ever need to apply the
+        // transfer function for each block exactly once (assuming that we process blocks in RPO).
+        //
         // In this case, there's no need to compute the block transfer functions ahead of time.
         if !body.basic_blocks.is_cfg_cyclic() {
-            return Self::new(tcx, body, analysis, None);
+            return (Self::new_prepared(tcx, body, analysis, None), None);
         }
 
         // Otherwise, compute and store the cumulative transfer function for each block.
+        let trans_for_block = trans_for_blocks(&mut analysis, body, domain_size);
 
-        let identity = GenKillSet::identity(analysis.domain_size(body));
-        let mut trans_for_block = IndexVec::from_elem(identity, &body.basic_blocks);
+        if let Some(block) = uncached_block {
+            let block_data = &body[block];
+            let mut statements: Vec<_> = block_data.statements.iter().enumerate().collect();
+            if A::Direction::IS_BACKWARD {
+                statements.reverse();
+            }
 
-        for (block, block_data) in body.basic_blocks.iter_enumerated() {
-            let trans = &mut trans_for_block[block];
-            A::Direction::gen_kill_statement_effects_in_block(
-                &mut analysis,
-                trans,
-                block,
-                block_data,
-            );
+            let mut trans_for_statement = vec![];
+            for (statement_index, statement) in statements {
+                let location = mir::Location { block, statement_index };
+                let mut before = GenKillSet::identity(domain_size);
+                analysis.before_statement_effect(&mut before, statement, location);
+                let mut primary = GenKillSet::identity(domain_size);
+                analysis.statement_effect(&mut primary, statement, location);
+                trans_for_statement.extend([before, primary]);
+            }
+
+            let apply_trans = Box::new(move |bb: BasicBlock, state: &mut A::Domain| {
+                if bb != block {
+                    return trans_for_block.apply(bb, state);
+                }
+                for trans in &trans_for_statement {
+                    trans.apply(state);
+                }
+                true
+            });
+            let engine = Self::new_prepared(tcx, body, analysis, Some(apply_trans as Box<_>));
+            return (engine, None);
         }
 
+        // Cursors can only skip over whole blocks if every block has a transfer function.
+        let batched = match &trans_for_block {
+            CachedBlockTrans::All(trans_for_block) => Some(Arc::clone(trans_for_block)),
+            CachedBlockTrans::Selected(_) => None,
+        };
         let apply_trans = Box::new(move |bb: BasicBlock, state: &mut A::Domain| {
-            trans_for_block[bb].apply(state);
+            trans_for_block.apply(bb, state)
         });
 
-        Self::new(tcx, body, analysis, Some(apply_trans as Box<_>))
+        (Self::new_prepared(tcx, body, analysis, Some(apply_trans as Box<_>)), batched)
     }
 }
 
@@ -200,22 +1175,95 @@ pub fn new_generic(tcx: TyCtxt<'tcx>, body: &'a mir::Body<'tcx>, analysis: A) ->
         Self::new(tcx, body, analysis, None)
     }
 
-    fn new(
+    /// Creates a new `Engine` to solve a dataflow problem whose domain may grow without bound.
+    ///
+    /// Whenever joining into the entry set of a block leaves it with more than `max_elements`
+    /// elements, `widen` is called on that entry set. `widen` should move the state up the lattice
+    /// to a value with no more than `max_elements` elements (e.g. by replacing precise facts
+    /// with a conservative summary), trading precision for termination.
+    ///
+    /// Entry sets populated before iterating, e.g. by `initialize_start_block` or `seed_blocks`,
+    /// are checked once up front.
+    pub fn new_bounded<T>(
         tcx: TyCtxt<'tcx>,
         body: &'a mir::Body<'tcx>,
         analysis: A,
-        apply_statement_trans_for_block: Option<Box<dyn Fn(BasicBlock, &mut A::Domain)>>,
+        max_elements: usize,
+        widen: impl Fn(&mut A::Domain) + 'static,
+    ) -> Self
+    where
+        A::Domain: BitSetExt<T>,
+    {
+        let mut engine = Self::new(tcx, body, analysis, None);
+        engine.widen_entry_set = Some(Box::new(widen_above(max_elements, widen)));
+        engine
+    }
+
+    /// Creates a new `Engine` whose entry sets start out at the results of a related analysis `B`.
+    ///
+    /// `convert` maps each entry set of `seed` to the domain of `A`, and the result is joined into
+    /// the initial entry set of that block. This can reduce the number of iterations needed to
+    /// reach fixpoint. The converted states must be no greater than the fixpoint of `A`; otherwise,
+    /// the results will be less precise than those of an engine created with `new_generic`.
+    pub fn new_with_seed_from<B>(
+        tcx: TyCtxt<'tcx>,
+        body: &'a mir::Body<'tcx>,
+        analysis: A,
+        seed: &Results<'tcx, B>,
+        convert: impl Fn(&B::Domain) -> A::Domain,
+    ) -> Self
+    where
+        B: Analysis<'tcx>,
+    {
+        Self::new_generic(tcx, body, analysis).seed_blocks(join_converted_seed(seed, convert))
+    }
+
+    fn new(
+        tcx: TyCtxt<'tcx>,
+        body: &'a mir::Body<'tcx>,
+        mut analysis: A,
+        apply_statement_trans_for_block: Option<ApplyStatementTrans<A::Domain>>,
     ) -> Self {
-        let mut entry_sets =
-            IndexVec::from_fn_n(|_| analysis.bottom_value(body), body.basic_blocks.len());
-        analysis.initialize_start_block(body, &mut entry_sets[mir::START_BLOCK]);
+        analysis.pre_iterate(body);
+        Self::new_prepared(tcx, body, analysis, apply_statement_trans_for_block)
+    }
 
-        if A::Direction::IS_BACKWARD && entry_sets[mir::START_BLOCK] != analysis.bottom_value(body)
-        {
-            bug!("`initialize_start_block` is not yet supported for backward dataflow analyses");
+    /// Like `new`, for an analysis whose `pre_iterate` was already called on `body`.
+    fn new_prepared(
+        tcx: TyCtxt<'tcx>,
+        body: &'a mir::Body<'tcx>,
+        analysis: A,
+        apply_statement_trans_for_block: Option<ApplyStatementTrans<A::Domain>>,
+    ) -> Self {
+        for (bb, block_data) in body.basic_blocks.iter_enumerated() {
+            if block_data.terminator.is_none() {
+                bug!(
+                    "cannot run `{}` on `{}`: {bb:?} has no terminator",
+                    A::NAME,
+                    tcx.def_path_str(body.source.def_id()),
+                );
+            }
         }
 
-        Engine { analysis, tcx, body, pass_name: None, entry_sets, apply_statement_trans_for_block }
+        let entry_sets =
+            initial_entry_sets(&analysis, body, analysis.bottom_value(tcx, body));
+
+        Engine {
+            analysis,
+            tcx,
+            body,
+            pass_name: None,
+            phase: None,
+            dump_to: None,
+            entry_sets,
+            apply_statement_trans_for_block,
+            block_trans: None,
+            widen_entry_set: None,
+            visit_limit: None,
+            track_convergence: false,
+            reuse_state: true,
+            check_seed: false,
+        }
     }
 
     /// Adds an identifier to the graphviz output for this particular run of a dataflow analysis.
@@ -227,6 +1275,83 @@ pub fn pass_name(mut self, name: &'static str) -> Self {
         self
     }
 
+    /// Identifies the compilation phase this run of the analysis belongs to, e.g. the computation
+    /// of NLL regions or the borrow check proper.
+    ///
+    /// The phase is appended to the pass name in the names of MIR dump files. If
+    /// `-Z dump-mir-dataflow-phases` is given, graphviz output is only written for runs whose
+    /// phase is listed there. Runs without a phase are then never written.
+    pub fn phase(mut self, phase: &'static str) -> Self {
+        self.phase = Some(phase);
+        self
+    }
+
+    /// Writes the results of `iterate_to_fixpoint` to `path` as graphviz, in the given `style`.
+    ///
+    /// This is meant for callers such as test harnesses that want a dump without annotating the
+    /// body with `rustc_mir` attributes. It takes precedence over those attributes, and neither
+    /// `-Z dump-mir-dataflow` nor `-Z dump-mir-dataflow-phases` is consulted.
+    pub fn dump_to(mut self, path: PathBuf, style: graphviz::OutputStyle<A::Domain>) -> Self {
+        self.dump_to = Some((path, style));
+        self
+    }
+
+    /// Aborts compilation with an error listing the blocks involved if the analysis does not
+    /// converge, instead of looping forever.
+    ///
+    /// Non-convergence is detected by counting how often each block is visited while iterating to
+    /// fixpoint. It usually means that the domain of the analysis has a cycle in its order, or
+    /// that its transfer functions are not monotonic.
+    pub fn with_cycle_detection(mut self) -> Self {
+        self.visit_limit = Some(CYCLE_DETECTION_VISIT_LIMIT);
+        self
+    }
+
+    /// Records how often each block is visited while iterating to fixpoint, so that it can be
+    /// retrieved with `Results::convergence_info` or `Results::block_converged_in`.
+    ///
+    /// This is cheap, since the visits are counted anyway.
+    pub fn with_convergence_tracking(mut self) -> Self {
+        self.track_convergence = true;
+        self
+    }
+
+    /// Selects whether each iteration of the fixpoint loop starts by copying the entry set of the
+    /// block into the state of the previous iteration, which is the default, or into a fresh
+    /// clone of it.
+    ///
+    /// Reusing the state saves an allocation per iteration, but `clone_from` may be slower than
+    /// `clone` for domains that are cheap to allocate, so this lets authors benchmark both.
+    pub fn with_state_reuse(mut self, reuse_state: bool) -> Self {
+        self.reuse_state = reuse_state;
+        self
+    }
+
+    /// Emits a warning if, after solving, none of the facts set by `initialize_start_block` hold
+    /// on entry to the `START_BLOCK`.
+    ///
+    /// The entry set of the `START_BLOCK` only grows by joins, so the seed can only be lost by
+    /// widening it (see `new_bounded`) or by overwriting it with `seed_blocks`. Both may be
+    /// intended, which is why this is opt-in, but a lost seed usually means that the analysis
+    /// disagrees with itself about what holds on entry to the body. Only `iterate_to_fixpoint`
+    /// and `solve_in_place` perform this check.
+    pub fn with_seed_check(mut self) -> Self {
+        self.check_seed = true;
+        self
+    }
+
+    /// Calls `f` on the entry set of every basic block, e.g. to seed blocks other than the
+    /// `START_BLOCK` with facts that are known to hold on entry to them.
+    ///
+    /// This runs after `initialize_start_block`. Every reachable block is visited at least once
+    /// during fixpoint iteration, so seeded blocks need no special treatment. Seeds are discarded
+    /// by `solve_in_place`, which resets all entry sets. This populates the entry set of every
+    /// block, even if `f` leaves it at bottom.
+    pub fn seed_blocks(mut self, f: impl Fn(BasicBlock, &mut A::Domain)) -> Self {
+        seed_entry_sets(&mut self.entry_sets, f);
+        self
+    }
+
     /// Computes the fixpoint for this dataflow problem and returns it.
     pub fn iterate_to_fixpoint(self) -> Results<'tcx, A>
     where
@@ -238,213 +1363,1132 @@ pub fn iterate_to_fixpoint(self) -> Results<'tcx, A>
             mut entry_sets,
             tcx,
             apply_statement_trans_for_block,
+            block_trans,
             pass_name,
-            ..
+            phase,
+            dump_to,
+            widen_entry_set,
+            visit_limit,
+            track_convergence,
+            reuse_state,
+            check_seed,
         } = self;
 
-        let mut dirty_queue: WorkQueue<BasicBlock> = WorkQueue::with_none(body.basic_blocks.len());
+        let options = FixpointOptions {
+            apply_statement_trans_for_block: apply_statement_trans_for_block.as_deref(),
+            widen_entry_set: widen_entry_set.as_deref(),
+            visit_limit,
+            reuse_state,
+        };
+        let convergence =
+            match iterate_entry_sets_to_fixpoint(&mut analysis, body, &mut entry_sets, options) {
+                Ok(info) => track_convergence.then_some(info),
+                Err(blocks) => report_divergence::<A>(tcx, body, &blocks),
+            };
 
-        if A::Direction::IS_FORWARD {
-            for (bb, _) in traversal::reverse_postorder(body) {
+        if check_seed {
+            check_start_block_seed(tcx, &analysis, body, &entry_sets[mir::START_BLOCK]);
+        }
+
+        let mut results =
+            Results { analysis, entry_sets, block_trans, convergence, _marker: PhantomData };
+        dump_results_if_requested(tcx, body, &mut results, dump_to, pass_name, phase);
+        results
+    }
+
+    /// Returns a mutable reference to the analysis, e.g. to reconfigure it before calling
+    /// `solve_in_place` again.
+    ///
+    /// Changes to the analysis may change its statement effects, so this discards the block
+    /// transfer functions cached by `new_gen_kill`. Later solves apply the effects of each
+    /// statement one at a time instead.
+    pub fn analysis_mut(&mut self) -> &mut A {
+        self.apply_statement_trans_for_block = None;
+        self.block_trans = None;
+        &mut self.analysis
+    }
+
+    /// Computes the fixpoint for this dataflow problem without consuming the `Engine`.
+    ///
+    /// The entry sets are reset before solving, so this can be called repeatedly, e.g. after
+    /// modifying the analysis via `analysis_mut`.
+    ///
+    /// Unlike `iterate_to_fixpoint`, this never writes graphviz output.
+    pub fn solve_in_place(&mut self) -> ResultsCloned<'_, 'tcx, A>
+    where
+        A: CloneAnalysis,
+    {
+        let body = self.body;
+        let bottom = self.analysis.bottom_value(self.tcx, body);
+        self.entry_sets = initial_entry_sets(&self.analysis, body, bottom);
+
+        let options = FixpointOptions {
+            apply_statement_trans_for_block: self.apply_statement_trans_for_block.as_deref(),
+            widen_entry_set: self.widen_entry_set.as_deref(),
+            visit_limit: self.visit_limit,
+            reuse_state: self.reuse_state,
+        };
+        let converged =
+            iterate_entry_sets_to_fixpoint(&mut self.analysis, body, &mut self.entry_sets, options);
+        let convergence = match converged {
+            Ok(info) => self.track_convergence.then_some(info),
+            Err(blocks) => report_divergence::<A>(self.tcx, body, &blocks),
+        };
+
+        if self.check_seed {
+            let entry_set = &self.entry_sets[mir::START_BLOCK];
+            check_start_block_seed(self.tcx, &self.analysis, body, entry_set);
+        }
+
+        Results {
+            analysis: self.analysis.clone_analysis(),
+            entry_sets: &self.entry_sets,
+            block_trans: self.block_trans.clone(),
+            convergence,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, 'tcx, A, T> Engine<'a, 'tcx, A>
+where
+    A: Analysis<'tcx, Domain = BitSet<T>>,
+    T: Idx,
+{
+    /// Like `iterate_to_fixpoint`, but stores the entry sets as the rows of a single `BitMatrix`
+    /// instead of as one `BitSet` per block, which saves an allocation per block and keeps the
+    /// entry sets close together in memory.
+    ///
+    /// This is not supported for engines created with `new_bounded`. All other settings of the
+    /// engine apply as in `iterate_to_fixpoint`. Graphviz output, if requested, is written from a
+    /// copy of the results with one `BitSet` per block.
+    pub fn iterate_to_fixpoint_dense(self) -> DenseBitSetResults<'tcx, A, T>
+    where
+        A::Domain: DebugWithContext<A>,
+    {
+        // No `..`, so that new settings of the engine cannot be silently ignored here.
+        let Engine {
+            analysis,
+            body,
+            entry_sets,
+            tcx,
+            apply_statement_trans_for_block,
+            block_trans,
+            pass_name,
+            phase,
+            dump_to,
+            widen_entry_set,
+            visit_limit,
+            track_convergence,
+            reuse_state,
+            check_seed,
+        } = self;
+        assert!(widen_entry_set.is_none(), "dense entry sets are not supported by bounded engines");
+
+        let options = FixpointOptions {
+            apply_statement_trans_for_block: apply_statement_trans_for_block.as_deref(),
+            widen_entry_set: None,
+            visit_limit,
+            reuse_state,
+        };
+        let mut results = DenseBitSetResults::from_entry_sets(analysis, &entry_sets);
+        let (analysis, rows) = results.analysis_and_entry_sets_mut();
+        let mut rows = DenseEntrySets::new(rows);
+        match iterate_entry_sets_to_fixpoint(analysis, body, &mut rows, options) {
+            Ok(info) => results.convergence = track_convergence.then_some(info),
+            Err(blocks) => report_divergence::<A>(tcx, body, &blocks),
+        }
+
+        if check_seed {
+            let entry_set = results.entry_set_for_block(mir::START_BLOCK);
+            check_start_block_seed(tcx, &results.analysis, body, &entry_set);
+        }
+        results.block_trans = block_trans;
+
+        if dump_to.is_some() || tcx.sess.opts.unstable_opts.dump_mir_dataflow {
+            let mut generic = results.into_results();
+            dump_results_if_requested(tcx, body, &mut generic, dump_to, pass_name, phase);
+            results = generic.into_dense();
+        }
+        results
+    }
+}
+
+/// Calls `f` on the entry set of every block. This is the core of `Engine::seed_blocks`.
+pub(super) fn seed_entry_sets<'tcx, A>(
+    entry_sets: &mut EntrySets<'tcx, A>,
+    f: impl Fn(BasicBlock, &mut A::Domain),
+) where
+    A: AnalysisDomain<'tcx>,
+{
+    for (block, entry_set) in entry_sets.iter_enumerated_mut() {
+        f(block, entry_set);
+    }
+}
+
+/// Returns the seed function of `Engine::new_with_seed_from`, which joins the converted entry set
+/// of each block of `seed` into the entry set of that block.
+pub(super) fn join_converted_seed<'a, 'tcx, A, B>(
+    seed: &'a Results<'tcx, B>,
+    convert: impl Fn(&B::Domain) -> A::Domain + 'a,
+) -> impl Fn(BasicBlock, &mut A::Domain) + 'a
+where
+    A: AnalysisDomain<'tcx>,
+    B: Analysis<'tcx>,
+{
+    move |block, entry_set| {
+        entry_set.join(&convert(seed.entry_set_for_block(block)));
+    }
+}
+
+/// Aborts compilation because `A` did not converge on `body`, where the entry sets of `blocks` kept
+/// changing.
+fn report_divergence<'tcx, A>(
+    tcx: TyCtxt<'tcx>,
+    body: &mir::Body<'tcx>,
+    blocks: &BitSet<BasicBlock>,
+) -> !
+where
+    A: Analysis<'tcx>,
+{
+    let blocks = blocks.iter().map(|bb| format!("{bb:?}")).collect::<Vec<_>>().join(", ");
+    tcx.sess.emit_fatal(DidNotConverge {
+        span: tcx.def_span(body.source.def_id()),
+        name: A::NAME,
+        blocks,
+    })
+}
+
+/// Emits a warning if `entry_set`, the entry set of the `START_BLOCK` after solving, has lost
+/// the seed set by `initialize_start_block` (see `Engine::with_seed_check`).
+fn check_start_block_seed<'tcx, A>(
+    tcx: TyCtxt<'tcx>,
+    analysis: &A,
+    body: &mir::Body<'tcx>,
+    entry_set: &A::Domain,
+) where
+    A: Analysis<'tcx>,
+{
+    let bottom = analysis.bottom_value(tcx, body);
+    let mut seed = bottom.clone();
+    analysis.initialize_start_block(body, &mut seed);
+
+    if start_block_seed_lost(analysis, body, entry_set, &seed, &bottom) {
+        tcx.sess.emit_warning(StartBlockSeedLost {
+            span: tcx.def_span(body.source.def_id()),
+            name: A::NAME,
+        });
+    }
+}
+
+/// Returns whether `entry_set` shares no facts with `seed`, unless `seed` is `bottom` and so has
+/// no facts to lose.
+///
+/// Facts are compared bit by bit for analyses that implement `Analysis::contains_bit`. For other
+/// analyses, the seed only counts as lost if `entry_set` is back at `bottom`.
+pub(super) fn start_block_seed_lost<'tcx, A>(
+    analysis: &A,
+    body: &mir::Body<'tcx>,
+    entry_set: &A::Domain,
+    seed: &A::Domain,
+    bottom: &A::Domain,
+) -> bool
+where
+    A: Analysis<'tcx>,
+{
+    if seed == bottom {
+        return false;
+    }
+    if analysis.contains_bit(body, seed, 0).is_none() {
+        return entry_set == bottom;
+    }
+
+    let mut bits = (0..).map_while(|bit| {
+        Some((
+            analysis.contains_bit(body, seed, bit)?,
+            analysis.contains_bit(body, entry_set, bit)?,
+        ))
+    });
+    !bits.any(|(seeded, kept)| seeded && kept)
+}
+
+/// Creates the entry sets of `body` for `analysis`: `bottom` for every block, except for the
+/// `START_BLOCK`, which is initialized with `initialize_start_block`.
+///
+/// The `bottom` kept for unpopulated blocks is what the `START_BLOCK` of a backward analysis is
+/// checked against, so that `bottom_value` is not computed a second time just for that check.
+pub(super) fn initial_entry_sets<'tcx, A>(
+    analysis: &A,
+    body: &mir::Body<'tcx>,
+    bottom: A::Domain,
+) -> EntrySets<'tcx, A>
+where
+    A: Analysis<'tcx>,
+{
+    let mut entry_sets = EntrySets::from_bottom_n(bottom, body.basic_blocks.len());
+    analysis.initialize_start_block(body, &mut entry_sets[mir::START_BLOCK]);
+
+    if A::Direction::IS_BACKWARD && entry_sets[mir::START_BLOCK] != *entry_sets.bottom() {
+        bug!("`initialize_start_block` is not yet supported for backward dataflow analyses");
+    }
+
+    entry_sets
+}
+
+/// How `iterate_entry_sets_to_fixpoint` iterates to fixpoint. The default is what `Engine::new`
+/// uses.
+pub(super) struct FixpointOptions<'a, D> {
+    /// The cumulative statement transfer function of each block, if cached (see
+    /// `Engine::new_gen_kill`).
+    pub apply_statement_trans_for_block: Option<&'a dyn Fn(BasicBlock, &mut D) -> bool>,
+    /// Applied to each entry set after it changed (see `Engine::new_bounded`).
+    pub widen_entry_set: Option<&'a dyn Fn(&mut D)>,
+    /// The number of visits to a single block after which iteration gives up (see
+    /// `Engine::with_cycle_detection`).
+    pub visit_limit: Option<usize>,
+    /// See `Engine::with_state_reuse`.
+    pub reuse_state: bool,
+}
+
+impl<D> Default for FixpointOptions<'_, D> {
+    fn default() -> Self {
+        FixpointOptions {
+            apply_statement_trans_for_block: None,
+            widen_entry_set: None,
+            visit_limit: None,
+            reuse_state: true,
+        }
+    }
+}
+
+/// The entry sets that `iterate_entry_sets_to_fixpoint` reads and joins into.
+pub(super) trait FixpointEntrySets<D> {
+    /// Returns a copy of the entry set of `block`.
+    fn entry_set(&self, block: BasicBlock) -> D;
+
+    /// Overwrites `state` with the entry set of `block`.
+    fn copy_entry_set_into(&self, block: BasicBlock, state: &mut D);
+
+    /// Calls `join` on the entry set of `block`, which must return whether it changed it, and
+    /// returns whether the entry set changed.
+    fn update_entry_set(&mut self, block: BasicBlock, join: impl FnOnce(&mut D) -> bool) -> bool;
+
+    /// Calls `widen` on every entry set that is not at bottom.
+    fn widen_populated(&mut self, widen: &dyn Fn(&mut D));
+}
+
+impl<'tcx, A> FixpointEntrySets<A::Domain> for EntrySets<'tcx, A>
+where
+    A: AnalysisDomain<'tcx>,
+{
+    fn entry_set(&self, block: BasicBlock) -> A::Domain {
+        self[block].clone()
+    }
+
+    fn copy_entry_set_into(&self, block: BasicBlock, state: &mut A::Domain) {
+        state.clone_from(&self[block]);
+    }
+
+    fn update_entry_set(
+        &mut self,
+        block: BasicBlock,
+        join: impl FnOnce(&mut A::Domain) -> bool,
+    ) -> bool {
+        self.update(block, join)
+    }
+
+    fn widen_populated(&mut self, widen: &dyn Fn(&mut A::Domain)) {
+        EntrySets::widen_populated(self, widen)
+    }
+}
+
+/// The rows of a `BitMatrix`, as the entry sets of `Engine::iterate_to_fixpoint_dense`.
+pub(super) struct DenseEntrySets<'a, T: Idx> {
+    rows: &'a mut BitMatrix<BasicBlock, T>,
+    /// A row can only be unioned with, so entry sets are joined into this first.
+    joined: BitSet<T>,
+}
+
+impl<'a, T: Idx> DenseEntrySets<'a, T> {
+    pub(super) fn new(rows: &'a mut BitMatrix<BasicBlock, T>) -> Self {
+        let joined = BitSet::new_empty(rows.num_columns());
+        DenseEntrySets { rows, joined }
+    }
+}
+
+impl<T: Idx> FixpointEntrySets<BitSet<T>> for DenseEntrySets<'_, T> {
+    fn entry_set(&self, block: BasicBlock) -> BitSet<T> {
+        let mut state = BitSet::new_empty(self.rows.num_columns());
+        self.rows.copy_row_into(block, &mut state);
+        state
+    }
+
+    fn copy_entry_set_into(&self, block: BasicBlock, state: &mut BitSet<T>) {
+        self.rows.copy_row_into(block, state);
+    }
+
+    fn update_entry_set(
+        &mut self,
+        block: BasicBlock,
+        join: impl FnOnce(&mut BitSet<T>) -> bool,
+    ) -> bool {
+        // Entry sets only grow, so the joined entry set can be unioned back into its row.
+        self.rows.copy_row_into(block, &mut self.joined);
+        join(&mut self.joined) && self.rows.union_row_with(&self.joined, block)
+    }
+
+    fn widen_populated(&mut self, _widen: &dyn Fn(&mut BitSet<T>)) {
+        bug!("dense entry sets cannot be widened");
+    }
+}
+
+/// Iterates `analysis` to fixpoint over `body`, starting from (and updating) `entry_sets`. This is
+/// the core of `Engine::iterate_to_fixpoint` and of the other drivers in this module.
+///
+/// Returns the number of visits to each block if the analysis converged. If a block was visited
+/// more than `options.visit_limit` times instead, this gives up and returns the blocks that were
+/// visited more than half the visit limit times, i.e. the blocks whose entry sets kept changing
+/// along with the one that exceeded it, and `entry_sets` are left in an intermediate state.
+pub(super) fn iterate_entry_sets_to_fixpoint<'tcx, A, E>(
+    analysis: &mut A,
+    body: &mir::Body<'tcx>,
+    entry_sets: &mut E,
+    options: FixpointOptions<'_, A::Domain>,
+) -> Result<ConvergenceInfo, BitSet<BasicBlock>>
+where
+    A: Analysis<'tcx>,
+    E: FixpointEntrySets<A::Domain>,
+{
+    let FixpointOptions {
+        apply_statement_trans_for_block,
+        widen_entry_set,
+        visit_limit,
+        reuse_state,
+    } = options;
+    if let Some(widen) = widen_entry_set {
+        entry_sets.widen_populated(widen);
+    }
+
+    let is_analyzed = |bb: BasicBlock| A::REQUIRES_CLEANUP_BLOCKS || !body[bb].is_cleanup;
+    let mut visits = IndexVec::<BasicBlock, u32>::from_elem_n(0, body.basic_blocks.len());
+    let mut dirty_queue: WorkQueue<BasicBlock> = WorkQueue::with_none(body.basic_blocks.len());
+
+    if A::Direction::IS_FORWARD {
+        for (bb, _) in traversal::reverse_postorder(body) {
+            if is_analyzed(bb) {
                 dirty_queue.insert(bb);
             }
-        } else {
-            // Reverse post-order on the reverse CFG may generate a better iteration order for
-            // backward dataflow analyses, but probably not enough to matter.
-            for (bb, _) in traversal::postorder(body) {
+        }
+    } else {
+        // Reverse post-order on the reverse CFG may generate a better iteration order for
+        // backward dataflow analyses, but probably not enough to matter.
+        for (bb, _) in traversal::postorder(body) {
+            if is_analyzed(bb) {
                 dirty_queue.insert(bb);
             }
         }
+    }
 
-        // `state` is not actually used between iterations;
-        // this is just an optimization to avoid reallocating
-        // every iteration.
-        let mut state = analysis.bottom_value(body);
-        while let Some(bb) = dirty_queue.pop() {
-            let bb_data = &body[bb];
+    let block_cache = analysis.prepare_block_cache(body);
+
+    // `state` is not actually used between iterations;
+    // reusing it (see `reuse_state`) is just an optimization to avoid reallocating
+    // every iteration. Any entry set will do as its initial value, since it is overwritten.
+    let mut state = entry_sets.entry_set(mir::START_BLOCK);
+    while let Some(bb) = dirty_queue.pop() {
+        let bb_data = &body[bb];
+
+        visits[bb] += 1;
+        if let Some(repeated) = exceeded_visit_limit(&visits, bb, visit_limit) {
+            return Err(repeated);
+        }
 
-            // Set the state to the entry state of the block.
-            // This is equivalent to `state = entry_sets[bb].clone()`,
-            // but it saves an allocation, thus improving compile times.
-            state.clone_from(&entry_sets[bb]);
+        // Set the state to the entry state of the block.
+        // `clone_from` is equivalent to `state = entry_sets[bb].clone()`,
+        // but it saves an allocation, thus improving compile times.
+        if reuse_state {
+            entry_sets.copy_entry_set_into(bb, &mut state);
+        } else {
+            state = entry_sets.entry_set(bb);
+        }
 
-            // Apply the block transfer function, using the cached one if it exists.
-            let edges = A::Direction::apply_effects_in_block(
-                &mut analysis,
+        // Apply the block transfer function, using the cached one if it exists, unless the
+        // analysis knows that it would not change the entry state.
+        let edges = if analysis.can_skip_block_given_predecessors(bb, body, &state) {
+            bb_data.terminator().edges()
+        } else {
+            A::Direction::apply_effects_in_block(
+                analysis,
                 &mut state,
                 bb,
                 bb_data,
-                apply_statement_trans_for_block.as_deref(),
-            );
+                apply_statement_trans_for_block,
+                Some(&block_cache),
+            )
+        };
 
-            A::Direction::join_state_into_successors_of(
-                &mut analysis,
-                body,
-                &mut state,
-                bb,
-                edges,
-                |target: BasicBlock, state: &A::Domain| {
-                    let set_changed = entry_sets[target].join(state);
-                    if set_changed {
-                        dirty_queue.insert(target);
+        A::Direction::join_state_into_successors_of(
+            analysis,
+            body,
+            &mut state,
+            bb,
+            edges,
+            |analysis: &A, target: BasicBlock, state: &A::Domain| {
+                if !is_analyzed(target) {
+                    return;
+                }
+
+                let set_changed = entry_sets.update_entry_set(target, |entry_set| {
+                    let changed = analysis.join_at(bb, target, entry_set, state);
+                    if let (true, Some(widen)) = (changed, widen_entry_set) {
+                        widen(entry_set);
                     }
-                },
-            );
+                    changed
+                });
+                if set_changed {
+                    dirty_queue.insert(target);
+                }
+            },
+        );
+    }
+
+    Ok(ConvergenceInfo { iterations: visits })
+}
+
+/// If `bb` has now been visited more than `visit_limit` times, returns the blocks that were visited
+/// more than `visit_limit / 2` times, i.e. the blocks whose entry sets kept changing along with it.
+fn exceeded_visit_limit(
+    visits: &IndexVec<BasicBlock, u32>,
+    bb: BasicBlock,
+    visit_limit: Option<usize>,
+) -> Option<BitSet<BasicBlock>> {
+    let visit_limit = visit_limit?;
+    if visits[bb] as usize <= visit_limit {
+        return None;
+    }
+
+    let mut repeated = BitSet::new_empty(visits.len());
+    for (block, &n) in visits.iter_enumerated() {
+        if n as usize > visit_limit / 2 {
+            repeated.insert(block);
         }
+    }
+    Some(repeated)
+}
 
-        let mut results = Results { analysis, entry_sets, _marker: PhantomData };
+/// Returns the reachable blocks of `body` with an outgoing back edge in a depth-first search from
+/// the `START_BLOCK`, i.e. those with a successor that does not come after them in reverse
+/// postorder. Each cycle in the CFG contains at least one of these blocks.
+pub(super) fn back_edge_sources(body: &mir::Body<'_>) -> BitSet<BasicBlock> {
+    let mut rpo_index = IndexVec::from_elem(None, &body.basic_blocks);
+    for (index, &bb) in body.basic_blocks.reverse_postorder().iter().enumerate() {
+        rpo_index[bb] = Some(index);
+    }
 
-        if tcx.sess.opts.unstable_opts.dump_mir_dataflow {
-            let res = write_graphviz_results(tcx, body, &mut results, pass_name);
-            if let Err(e) = res {
-                error!("Failed to write graphviz dataflow results: {}", e);
-            }
+    let mut sources = BitSet::new_empty(body.basic_blocks.len());
+    for (bb, index) in rpo_index.iter_enumerated() {
+        let Some(index) = *index else { continue };
+        if body[bb].terminator().successors().any(|succ| rpo_index[succ] <= Some(index)) {
+            sources.insert(bb);
         }
+    }
+    sources
+}
 
-        results
+/// The cumulative transfer functions of the statements of the blocks of a gen/kill problem, as
+/// cached by `Engine::new_gen_kill` and its variants.
+pub(super) enum CachedBlockTrans<T: Idx> {
+    /// The transfer function of every block.
+    All(Arc<IndexVec<BasicBlock, GenKillSet<T>>>),
+    /// The transfer functions of only some blocks (see `Engine::new_gen_kill_selective`).
+    Selected(FxHashMap<BasicBlock, GenKillSet<T>>),
+}
+
+impl<T: Idx> From<IndexVec<BasicBlock, GenKillSet<T>>> for CachedBlockTrans<T> {
+    fn from(trans_for_block: IndexVec<BasicBlock, GenKillSet<T>>) -> Self {
+        CachedBlockTrans::All(Arc::new(trans_for_block))
     }
 }
 
+impl<T: Idx> CachedBlockTrans<T> {
+    /// Applies the transfer function of `block` to `state`. Returns `false`, leaving `state`
+    /// unchanged, if the transfer function of `block` is not cached.
+    pub(super) fn apply(&self, block: BasicBlock, state: &mut impl GenKill<T>) -> bool {
+        let trans = match self {
+            CachedBlockTrans::All(trans_for_block) => &trans_for_block[block],
+            CachedBlockTrans::Selected(trans_for_block) => match trans_for_block.get(&block) {
+                Some(trans) => trans,
+                None => return false,
+            },
+        };
+        trans.apply(state);
+        true
+    }
+}
+
+/// Computes the cumulative transfer function of the statements of each of `blocks`.
+pub(super) fn gen_kill_trans_for_selected_blocks<'tcx, A>(
+    analysis: &mut A,
+    body: &mir::Body<'tcx>,
+    domain_size: usize,
+    blocks: &BitSet<BasicBlock>,
+) -> FxHashMap<BasicBlock, GenKillSet<A::Idx>>
+where
+    A: GenKillAnalysis<'tcx>,
+{
+    blocks
+        .iter()
+        .map(|block| {
+            let mut trans = GenKillSet::identity(domain_size);
+            analysis.batch_statement_effect(&mut trans, block, &body[block]);
+            (block, trans)
+        })
+        .collect()
+}
+
+/// Computes the cumulative transfer function of the statements of each block of `body`.
+pub(super) fn gen_kill_trans_for_blocks<'tcx, A>(
+    analysis: &mut A,
+    body: &mir::Body<'tcx>,
+    domain_size: usize,
+) -> IndexVec<BasicBlock, GenKillSet<A::Idx>>
+where
+    A: GenKillAnalysis<'tcx>,
+{
+    let identity = GenKillSet::identity(domain_size);
+    let mut trans_for_block = IndexVec::from_elem(identity, &body.basic_blocks);
+
+    for (block, block_data) in body.basic_blocks.iter_enumerated() {
+        // Terminator effects are never part of the cached transfer function, so that of a block
+        // without statements is the identity it was initialized with.
+        if block_data.statements.is_empty() {
+            continue;
+        }
+
+        let trans = &mut trans_for_block[block];
+        analysis.batch_statement_effect(trans, block, block_data);
+    }
+
+    trans_for_block
+}
+
+/// Like `gen_kill_trans_for_blocks`, but in parallel if the session allows it. See
+/// `Engine::new_gen_kill_concurrent`.
+pub(super) fn gen_kill_trans_for_blocks_concurrently<'tcx, A>(
+    analysis: &A,
+    body: &mir::Body<'tcx>,
+    domain_size: usize,
+) -> IndexVec<BasicBlock, GenKillSet<A::Idx>>
+where
+    A: GenKillAnalysis<'tcx> + CloneAnalysis + DynSync,
+{
+    if !sync::is_dyn_thread_safe() {
+        return gen_kill_trans_for_blocks(&mut analysis.clone_analysis(), body, domain_size);
+    }
+
+    let trans_for_block = |block: BasicBlock| {
+        let mut trans = GenKillSet::identity(domain_size);
+        let block_data = &body[block];
+        if !block_data.statements.is_empty() {
+            let mut analysis = analysis.clone_analysis();
+            analysis.batch_statement_effect(&mut trans, block, block_data);
+        }
+        // `FromDyn` lets the transfer functions cross threads, which is sound only in this mode.
+        FromDyn::from(trans)
+    };
+
+    let blocks: Vec<_> = body.basic_blocks.indices().collect();
+    let trans: Vec<_> = sync::par_map(blocks, trans_for_block);
+    trans.into_iter().map(FromDyn::into_inner).collect()
+}
+
 // Graphviz
 
+/// Returns whether the results of an analysis run in `phase` may be written, according to
+/// `-Z dump-mir-dataflow-phases`.
+fn dump_enabled_for_phase(tcx: TyCtxt<'_>, phase: Option<&str>) -> bool {
+    phase_selected(tcx.sess.opts.unstable_opts.dump_mir_dataflow_phases.as_deref(), phase)
+}
+
+/// Returns whether a run in `phase` is one of `phases`. Every run is selected if no `phases` were
+/// given, and runs without a phase only in that case.
+pub(super) fn phase_selected(phases: Option<&[String]>, phase: Option<&str>) -> bool {
+    match phases {
+        None => true,
+        Some(phases) => phase.is_some_and(|phase| phases.iter().any(|p| p == phase)),
+    }
+}
+
+/// Returns the part of a MIR dump file name that tells apart the runs of one analysis on a body:
+/// the pass name, followed by the phase if there is one.
+pub(super) fn dump_file_disambiguator(pass_name: Option<&str>, phase: Option<&str>) -> String {
+    let pass_name = pass_name.unwrap_or("-----");
+    match phase {
+        Some(phase) => format!("{pass_name}.{phase}"),
+        None => pass_name.to_owned(),
+    }
+}
+
+/// Writes graphviz output for `results` to the file given by `Engine::dump_to`, if any, or
+/// otherwise as requested by `-Z dump-mir-dataflow`. Errors are logged rather than reported.
+fn dump_results_if_requested<'tcx, A>(
+    tcx: TyCtxt<'tcx>,
+    body: &mir::Body<'tcx>,
+    results: &mut Results<'tcx, A>,
+    dump_to: Option<(PathBuf, graphviz::OutputStyle<A::Domain>)>,
+    pass_name: Option<&'static str>,
+    phase: Option<&'static str>,
+) where
+    A: Analysis<'tcx>,
+    A::Domain: DebugWithContext<A>,
+{
+    let res = if let Some((path, style)) = dump_to {
+        write_graphviz_results_to(tcx, body, results, &path, style)
+    } else if tcx.sess.opts.unstable_opts.dump_mir_dataflow {
+        write_graphviz_results(tcx, body, results, pass_name, phase)
+    } else {
+        return;
+    };
+    if let Err(e) = res {
+        error!("Failed to write graphviz dataflow results: {}", e);
+    }
+}
+
 /// Writes a DOT file containing the results of a dataflow analysis if the user requested it via
 /// `rustc_mir` attributes and `-Z dump-mir-dataflow`.
-fn write_graphviz_results<'tcx, A>(
+pub(super) fn write_graphviz_results<'tcx, A>(
     tcx: TyCtxt<'tcx>,
     body: &mir::Body<'tcx>,
     results: &mut Results<'tcx, A>,
     pass_name: Option<&'static str>,
+    phase: Option<&'static str>,
 ) -> std::io::Result<()>
 where
     A: Analysis<'tcx>,
     A::Domain: DebugWithContext<A>,
 {
-    use std::fs;
-    use std::io::{self, Write};
+    use std::io::Write;
+
+    if !dump_enabled_for_phase(tcx, phase) {
+        return Ok(());
+    }
 
     let def_id = body.source.def_id();
-    let Ok(attrs) = RustcMirAttrs::parse(tcx, def_id) else {
-        // Invalid `rustc_mir` attrs are reported in `RustcMirAttrs::parse`
+    let Ok(attrs) = rustc_mir_attrs(tcx, def_id) else {
+        // Invalid `rustc_mir` attrs are reported in `parse_rustc_mir_attrs`
+        return Ok(());
+    };
+
+    if attrs.formatter == Some(sym::graphml) {
+        return write_graphml_results(tcx, body, results, &attrs, pass_name, phase);
+    }
+
+    let Some(mut file) = dataflow_output_file::<A>(tcx, body, &attrs, pass_name, phase, ".dot")?
+    else {
+        return Ok(());
+    };
+
+    let style = match attrs.formatter {
+        Some(sym::two_phase) => graphviz::OutputStyle::BeforeAndAfter,
+        _ => graphviz::OutputStyle::AfterOnly,
+    };
+
+    let mut graphviz = graphviz::Formatter::new(body, results, style);
+    if let Some(wrap_width) = attrs.wrap_width {
+        graphviz = graphviz.wrap_width(wrap_width);
+    }
+    if let Some(font_size) = attrs.font_size {
+        graphviz = graphviz.with_font_size(font_size);
+    }
+    if let Some(bit) = attrs.track_bit {
+        graphviz = graphviz.track_bit(bit);
+    }
+    if let Some(range) = attrs.block_range {
+        graphviz = graphviz.block_range(range);
+    }
+    file.write_all(&render_graphviz(tcx, &graphviz)?)?;
+
+    Ok(())
+}
+
+/// Writes a DOT file containing the results of a dataflow analysis to `path`, as requested by
+/// `Engine::dump_to`.
+fn write_graphviz_results_to<'tcx, A>(
+    tcx: TyCtxt<'tcx>,
+    body: &mir::Body<'tcx>,
+    results: &mut Results<'tcx, A>,
+    path: &std::path::Path,
+    style: graphviz::OutputStyle<A::Domain>,
+) -> std::io::Result<()>
+where
+    A: Analysis<'tcx>,
+    A::Domain: DebugWithContext<A>,
+{
+    debug!("printing dataflow results for {:?} to {}", body.source.def_id(), path.display());
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let graphviz = graphviz::Formatter::new(body, results, style);
+    std::fs::write(path, render_graphviz(tcx, &graphviz)?)
+}
+
+/// Renders `graphviz` as DOT, with the font and theme selected on the command line.
+fn render_graphviz<'tcx, A>(
+    tcx: TyCtxt<'tcx>,
+    graphviz: &graphviz::Formatter<'_, '_, 'tcx, A>,
+) -> std::io::Result<Vec<u8>>
+where
+    A: Analysis<'tcx>,
+    A::Domain: DebugWithContext<A>,
+{
+    let mut buf = Vec::new();
+    let render_opts = graphviz_render_opts(
+        graphviz,
+        tcx.sess.opts.unstable_opts.graphviz_font.clone(),
+        tcx.sess.opts.unstable_opts.graphviz_dark_mode,
+    );
+    with_no_trimmed_paths!(dot::render_opts(graphviz, &mut buf, &render_opts)?);
+    Ok(buf)
+}
+
+/// The options to render `graphviz` with, given the font and theme selected on the command line.
+pub(super) fn graphviz_render_opts<'tcx, A>(
+    graphviz: &graphviz::Formatter<'_, '_, 'tcx, A>,
+    font: String,
+    dark_mode: bool,
+) -> Vec<dot::RenderOption>
+where
+    A: Analysis<'tcx>,
+{
+    let mut render_opts = vec![dot::RenderOption::Fontname(font)];
+    if dark_mode {
+        render_opts.push(dot::RenderOption::DarkTheme);
+    }
+    if let Some(font_size) = graphviz.font_size() {
+        render_opts.push(dot::RenderOption::FontSize(font_size));
+    }
+    render_opts
+}
+
+/// Writes the results as GraphML instead of graphviz, if `borrowck_graphviz_format = "graphml"`.
+///
+/// The same attributes select the output file and the blocks to write. See
+/// `graphviz::Formatter::write_graphml` for the format.
+fn write_graphml_results<'tcx, A>(
+    tcx: TyCtxt<'tcx>,
+    body: &mir::Body<'tcx>,
+    results: &mut Results<'tcx, A>,
+    attrs: &RustcMirAttrs,
+    pass_name: Option<&'static str>,
+    phase: Option<&'static str>,
+) -> std::io::Result<()>
+where
+    A: Analysis<'tcx>,
+    A::Domain: DebugWithContext<A>,
+{
+    use std::io::Write;
+
+    let Some(mut file) =
+        dataflow_output_file::<A>(tcx, body, attrs, pass_name, phase, ".graphml")?
+    else {
         return Ok(());
     };
 
-    let mut file = match attrs.output_path(A::NAME) {
+    let mut formatter = graphviz::Formatter::new(body, results, graphviz::OutputStyle::AfterOnly);
+    if let Some(bit) = attrs.track_bit {
+        formatter = formatter.track_bit(bit);
+    }
+    if let Some(range) = attrs.block_range.clone() {
+        formatter = formatter.block_range(range);
+    }
+
+    let mut buf = Vec::new();
+    with_no_trimmed_paths!(formatter.write_graphml(&mut buf)?);
+    file.write_all(&buf)
+}
+
+/// Opens the file that the results of `A` for `body` should be written to, either the path given
+/// by `borrowck_graphviz_postflow` or a MIR dump file with `extension`. Returns `None` if neither
+/// was requested.
+fn dataflow_output_file<'tcx, A>(
+    tcx: TyCtxt<'tcx>,
+    body: &mir::Body<'tcx>,
+    attrs: &RustcMirAttrs,
+    pass_name: Option<&'static str>,
+    phase: Option<&'static str>,
+    extension: &str,
+) -> std::io::Result<Option<std::io::BufWriter<std::fs::File>>>
+where
+    A: Analysis<'tcx>,
+{
+    use std::fs;
+    use std::io;
+
+    let def_id = body.source.def_id();
+    let file = match attrs.output_path(A::NAME) {
         Some(path) => {
             debug!("printing dataflow results for {:?} to {}", def_id, path.display());
             if let Some(parent) = path.parent() {
                 fs::create_dir_all(parent)?;
             }
-            io::BufWriter::new(fs::File::create(&path)?)
+            let file = io::BufWriter::new(fs::File::create(&path)?);
+            write_dump_manifest_entry(&path, A::NAME, pass_name)?;
+            file
         }
 
         None if dump_enabled(tcx, A::NAME, def_id) => {
-            create_dump_file(tcx, ".dot", false, A::NAME, &pass_name.unwrap_or("-----"), body)?
+            let disambiguator = dump_file_disambiguator(pass_name, phase);
+            create_dump_file(tcx, extension, false, A::NAME, &disambiguator, body)?
         }
 
-        _ => return Ok(()),
+        _ => return Ok(None),
     };
+    Ok(Some(file))
+}
 
-    let style = match attrs.formatter {
-        Some(sym::two_phase) => graphviz::OutputStyle::BeforeAndAfter,
-        _ => graphviz::OutputStyle::AfterOnly,
+/// Records the dataflow dump at `path` in the `index.json` manifest in the same directory, so that
+/// tools can find every dump written there without knowing how their file names are derived.
+///
+/// The manifest is a JSON array with one object per file, in the order they were first written:
+///
+/// ```json
+/// [
+///   {"file": "maybe_init_borrowck.dot", "analysis": "maybe_init", "pass": null, "block": null},
+///   ...
+/// ]
+/// ```
+///
+/// `block` is the basic block the file is restricted to, which is `null` for dumps of the whole
+/// body. Writing a file that is already listed replaces its entry.
+pub(super) fn write_dump_manifest_entry(
+    path: &Path,
+    analysis_name: &str,
+    pass_name: Option<&str>,
+) -> std::io::Result<()> {
+    use std::{fs, io};
+
+    let file_name = json_string(&path.file_name().unwrap().to_string_lossy());
+    let key = format!("{{\"file\": {file_name},");
+    let entry = format!(
+        "{key} \"analysis\": {}, \"pass\": {}, \"block\": null}}",
+        json_string(analysis_name),
+        pass_name.map_or_else(|| "null".to_owned(), json_string),
+    );
+
+    // Only this function writes the manifest, with one entry per line, so the existing entries can
+    // be kept without parsing them.
+    let manifest_path = path.with_file_name("index.json");
+    let mut entries: Vec<String> = match fs::read_to_string(&manifest_path) {
+        Ok(manifest) => manifest
+            .lines()
+            .map(|line| line.trim().trim_end_matches(','))
+            .filter(|line| line.starts_with('{'))
+            .map(str::to_owned)
+            .collect(),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => vec![],
+        Err(e) => return Err(e),
     };
+    match entries.iter_mut().find(|existing| existing.starts_with(&key)) {
+        Some(existing) => *existing = entry,
+        None => entries.push(entry),
+    }
 
-    let mut buf = Vec::new();
+    let mut manifest = String::from("[\n");
+    for (i, entry) in entries.iter().enumerate() {
+        let sep = if i + 1 == entries.len() { "" } else { "," };
+        manifest.push_str(&format!("  {entry}{sep}\n"));
+    }
+    manifest.push_str("]\n");
+    fs::write(&manifest_path, manifest)
+}
 
-    let graphviz = graphviz::Formatter::new(body, results, style);
-    let mut render_opts =
-        vec![dot::RenderOption::Fontname(tcx.sess.opts.unstable_opts.graphviz_font.clone())];
-    if tcx.sess.opts.unstable_opts.graphviz_dark_mode {
-        render_opts.push(dot::RenderOption::DarkTheme);
+/// Quotes `s` as a JSON string.
+fn json_string(s: &str) -> String {
+    let mut ret = String::with_capacity(s.len() + 2);
+    ret.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => ret.push_str("\\\""),
+            '\\' => ret.push_str("\\\\"),
+            '\n' => ret.push_str("\\n"),
+            c if c.is_control() => ret.push_str(&format!("\\u{:04x}", c as u32)),
+            c => ret.push(c),
+        }
+    }
+    ret.push('"');
+    ret
+}
+
+// MIR dump
+
+/// Wraps `extra_data`, the callback a pass hands to `dump_mir`, so that the MIR dump it writes also
+/// shows the entry set of each basic block as a comment right before the block:
+///
+/// ```text
+///     // dataflow(maybe_init): bb0 = {_1, _2}
+///     bb0: {
+/// ```
+///
+/// ```ignore (cross-crate-imports)
+/// dump_mir(tcx, false, "my_pass", &0, body, append_dataflow_to_mir_dump(&results, |_, _| Ok(())));
+/// ```
+pub fn append_dataflow_to_mir_dump<'a, 'tcx, A>(
+    results: &'a Results<'tcx, A>,
+    mut extra_data: impl FnMut(PassWhere, &mut dyn std::io::Write) -> std::io::Result<()> + 'a,
+) -> impl FnMut(PassWhere, &mut dyn std::io::Write) -> std::io::Result<()> + Captures<'tcx> + 'a
+where
+    A: Analysis<'tcx>,
+    A::Domain: DebugWithContext<A>,
+{
+    move |pass_where, w| {
+        write_entry_set_comment(results, &pass_where, w)?;
+        extra_data(pass_where, w)
     }
-    with_no_trimmed_paths!(dot::render_opts(&graphviz, &mut buf, &render_opts)?);
+}
 
-    file.write_all(&buf)?;
+/// Writes the entry set of `bb` as a MIR dump comment if `pass_where` is `BeforeBlock(bb)`. See
+/// `append_dataflow_to_mir_dump`.
+pub(super) fn write_entry_set_comment<'tcx, A>(
+    results: &Results<'tcx, A>,
+    pass_where: &PassWhere,
+    w: &mut dyn std::io::Write,
+) -> std::io::Result<()>
+where
+    A: Analysis<'tcx>,
+    A::Domain: DebugWithContext<A>,
+{
+    if let &PassWhere::BeforeBlock(bb) = pass_where {
+        let entry_set = results.entry_set_for_block(bb);
+        let entry_set = DebugWithAdapter { this: entry_set, ctxt: &results.analysis };
+        let entry_set = with_no_trimmed_paths!(format!("{:?} = {:?}", bb, entry_set));
+        writeln!(w, "    // dataflow({}): {entry_set}", A::NAME)?;
+    }
 
     Ok(())
 }
 
-#[derive(Default)]
-struct RustcMirAttrs {
-    basename_and_suffix: Option<PathBuf>,
-    formatter: Option<Symbol>,
+/// Returns the `#[rustc_mir]` attributes of `def_id`, or `Err` if they are malformed.
+///
+/// Almost no items have a `rustc_mir` attribute, so the `rustc_mir_attrs` query, which parses them
+/// and reports any errors in them once per item, only runs for those that do.
+fn rustc_mir_attrs(tcx: TyCtxt<'_>, def_id: DefId) -> Result<RustcMirAttrs, ()> {
+    if !tcx.has_attr(def_id, sym::rustc_mir) {
+        return Ok(RustcMirAttrs::default());
+    }
+    tcx.rustc_mir_attrs(def_id).clone()
 }
 
-impl RustcMirAttrs {
-    fn parse(tcx: TyCtxt<'_>, def_id: DefId) -> Result<Self, ()> {
-        let mut result = Ok(());
-        let mut ret = RustcMirAttrs::default();
+/// Returns the block given by `#[rustc_mir(borrowck_uncached_block = "bbN")]` for `body`, if any.
+/// Reports an error and returns `None` if it is not a block of `body`.
+fn uncached_block(tcx: TyCtxt<'_>, body: &mir::Body<'_>) -> Option<BasicBlock> {
+    let (block, span) = rustc_mir_attrs(tcx, body.source.def_id()).ok()?.uncached_block?;
+    if block.index() >= body.basic_blocks.len() {
+        tcx.sess.emit_err(InvalidUncachedBlock { span });
+        return None;
+    }
+    Some(block)
+}
 
-        let rustc_mir_attrs = tcx
-            .get_attrs(def_id, sym::rustc_mir)
-            .flat_map(|attr| attr.meta_item_list().into_iter().flat_map(|v| v.into_iter()));
+/// Provides the `rustc_mir_attrs` query.
+pub(crate) fn parse_rustc_mir_attrs(tcx: TyCtxt<'_>, def_id: DefId) -> Result<RustcMirAttrs, ()> {
+    let mut result = Ok(());
+    let mut ret = RustcMirAttrs::default();
 
-        for attr in rustc_mir_attrs {
-            let attr_result = if attr.has_name(sym::borrowck_graphviz_postflow) {
-                Self::set_field(&mut ret.basename_and_suffix, tcx, &attr, |s| {
-                    let path = PathBuf::from(s.to_string());
-                    match path.file_name() {
-                        Some(_) => Ok(path),
-                        None => {
-                            tcx.sess.emit_err(PathMustEndInFilename { span: attr.span() });
-                            Err(())
-                        }
-                    }
-                })
-            } else if attr.has_name(sym::borrowck_graphviz_format) {
-                Self::set_field(&mut ret.formatter, tcx, &attr, |s| match s {
-                    sym::gen_kill | sym::two_phase => Ok(s),
-                    _ => {
-                        tcx.sess.emit_err(UnknownFormatter { span: attr.span() });
+    let rustc_mir_attrs = tcx
+        .get_attrs(def_id, sym::rustc_mir)
+        .flat_map(|attr| attr.meta_item_list().into_iter().flat_map(|v| v.into_iter()));
+
+    for attr in rustc_mir_attrs {
+        let attr_result = if attr.has_name(sym::borrowck_graphviz_postflow) {
+            set_field(&mut ret.basename_and_suffix, tcx, &attr, |s| {
+                let path = PathBuf::from(s.to_string());
+                match path.file_name() {
+                    Some(_) => Ok(path),
+                    None => {
+                        tcx.sess.emit_err(PathMustEndInFilename { span: attr.span() });
                         Err(())
                     }
+                }
+            })
+        } else if attr.has_name(sym::borrowck_graphviz_format) {
+            set_field(&mut ret.formatter, tcx, &attr, |s| match s {
+                sym::gen_kill | sym::two_phase | sym::graphml => Ok(s),
+                _ => {
+                    tcx.sess.emit_err(UnknownFormatter { span: attr.span() });
+                    Err(())
+                }
+            })
+        } else if attr.has_name(sym::borrowck_graphviz_wrap) {
+            set_field(&mut ret.wrap_width, tcx, &attr, |s| match s.as_str().parse::<usize>() {
+                Ok(width) if width > 0 => Ok(width),
+                _ => {
+                    tcx.sess.emit_err(InvalidWrapWidth { span: attr.span() });
+                    Err(())
+                }
+            })
+        } else if attr.has_name(sym::borrowck_graphviz_font_size) {
+            set_field(&mut ret.font_size, tcx, &attr, |s| match s.as_str().parse::<u32>() {
+                Ok(pt) if pt > 0 => Ok(pt),
+                _ => {
+                    tcx.sess.emit_err(InvalidFontSize { span: attr.span() });
+                    Err(())
+                }
+            })
+        } else if attr.has_name(sym::borrowck_graphviz_track_bit) {
+            set_field(&mut ret.track_bit, tcx, &attr, |s| {
+                s.as_str().parse::<usize>().map_err(|_| {
+                    tcx.sess.emit_err(InvalidTrackedBit { span: attr.span() });
                 })
-            } else {
-                Ok(())
-            };
-
-            result = result.and(attr_result);
-        }
+            })
+        } else if attr.has_name(sym::borrowck_graphviz_blocks) {
+            set_field(&mut ret.block_range, tcx, &attr, |s| {
+                let parse_block = |s: &str| s.trim().parse::<u32>().ok().map(BasicBlock::from_u32);
+                s.as_str()
+                    .split_once('-')
+                    .and_then(|(start, end)| Some(parse_block(start)?..=parse_block(end)?))
+                    .filter(|range| !range.is_empty())
+                    .ok_or_else(|| {
+                        tcx.sess.emit_err(InvalidBlockRange { span: attr.span() });
+                    })
+            })
+        } else if attr.has_name(sym::borrowck_uncached_block) {
+            set_field(&mut ret.uncached_block, tcx, &attr, |s| {
+                s.as_str()
+                    .strip_prefix("bb")
+                    .and_then(|index| index.parse::<u32>().ok())
+                    .map(|index| (BasicBlock::from_u32(index), attr.span()))
+                    .ok_or_else(|| {
+                        tcx.sess.emit_err(InvalidUncachedBlock { span: attr.span() });
+                    })
+            })
+        } else {
+            Ok(())
+        };
 
-        result.map(|()| ret)
+        result = result.and(attr_result);
     }
 
-    fn set_field<T>(
-        field: &mut Option<T>,
-        tcx: TyCtxt<'_>,
-        attr: &ast::NestedMetaItem,
-        mapper: impl FnOnce(Symbol) -> Result<T, ()>,
-    ) -> Result<(), ()> {
-        if field.is_some() {
-            tcx.sess.emit_err(DuplicateValuesFor { span: attr.span(), name: attr.name_or_empty() });
+    result.map(|()| ret)
+}
 
-            return Err(());
-        }
+fn set_field<T>(
+    field: &mut Option<T>,
+    tcx: TyCtxt<'_>,
+    attr: &ast::NestedMetaItem,
+    mapper: impl FnOnce(Symbol) -> Result<T, ()>,
+) -> Result<(), ()> {
+    if field.is_some() {
+        tcx.sess.emit_err(DuplicateValuesFor { span: attr.span(), name: attr.name_or_empty() });
 
-        if let Some(s) = attr.value_str() {
-            *field = Some(mapper(s)?);
-            Ok(())
-        } else {
-            tcx.sess.emit_err(RequiresAnArgument { span: attr.span(), name: attr.name_or_empty() });
-            Err(())
-        }
+        return Err(());
     }
 
-    /// Returns the path where dataflow results should be written, or `None`
-    /// `borrowck_graphviz_postflow` was not specified.
-    ///
-    /// This performs the following transformation to the argument of `borrowck_graphviz_postflow`:
-    ///
-    /// "path/suffix.dot" -> "path/analysis_name_suffix.dot"
-    fn output_path(&self, analysis_name: &str) -> Option<PathBuf> {
-        let mut ret = self.basename_and_suffix.as_ref().cloned()?;
-        let suffix = ret.file_name().unwrap(); // Checked when parsing attrs
-
-        let mut file_name: OsString = analysis_name.into();
-        file_name.push("_");
-        file_name.push(suffix);
-        ret.set_file_name(file_name);
-
-        Some(ret)
+    if let Some(s) = attr.value_str() {
+        *field = Some(mapper(s)?);
+        Ok(())
+    } else {
+        tcx.sess.emit_err(RequiresAnArgument { span: attr.span(), name: attr.name_or_empty() });
+        Err(())
     }
 }
@@ -0,0 +1,202 @@
+//! Dataflow results with the type of the analysis erased.
+//!
+//! This is meant for drivers that handle the results of several analyses uniformly, e.g. for
+//! debugging, and therefore cannot be generic over each analysis. Everything here goes through a
+//! trait object and renders states as strings, so it should not be used in performance-sensitive
+//! code.
+
+use std::io;
+
+use rustc_middle::mir::{self, BasicBlock, Location, PassWhere};
+use rustc_middle::ty::TyCtxt;
+
+use super::engine::{write_entry_set_comment, write_graphviz_results};
+use super::fmt::{DebugWithAdapter, DebugWithContext};
+use super::{
+    visit_results, Analysis, CloneAnalysis, Direction, DirectionKind, Results, ResultsVisitor,
+};
+
+/// A dataflow analysis whose type has been erased, so that analyses with different domains can be
+/// stored in the same collection and run over each body in turn.
+///
+/// ```ignore (cross-crate-imports)
+/// let mut runners: Vec<Box<dyn ErasedAnalysisRunner<'tcx>>> =
+///     vec![Box::new(MaybeStorageLive::new(always_live)), Box::new(MaybeBorrowedLocals)];
+/// for runner in &mut runners {
+///     let results = runner.run(tcx, body);
+///     dump_mir(tcx, false, "my_pass", &0, body, |pass_where, w| {
+///         results.write_mir_dump_comment(pass_where, w)
+///     });
+/// }
+/// ```
+pub trait ErasedAnalysisRunner<'tcx> {
+    /// The name of the analysis (see `AnalysisDomain::NAME`).
+    fn name(&self) -> &'static str;
+
+    /// Computes the fixpoint of a fresh copy of the analysis over `body`.
+    ///
+    /// This is equivalent to `into_engine(tcx, body).iterate_to_fixpoint()`, including the
+    /// graphviz output requested via `-Z dump-mir-dataflow`.
+    fn run<'s>(
+        &'s mut self,
+        tcx: TyCtxt<'tcx>,
+        body: &mir::Body<'tcx>,
+    ) -> Box<dyn ErasedResults<'tcx> + 's>;
+}
+
+impl<'tcx, A> ErasedAnalysisRunner<'tcx> for A
+where
+    A: Analysis<'tcx> + CloneAnalysis,
+    A::Domain: DebugWithContext<A>,
+{
+    fn name(&self) -> &'static str {
+        A::NAME
+    }
+
+    fn run<'s>(
+        &'s mut self,
+        tcx: TyCtxt<'tcx>,
+        body: &mir::Body<'tcx>,
+    ) -> Box<dyn ErasedResults<'tcx> + 's> {
+        Box::new(self.clone_analysis().into_engine(tcx, body).iterate_to_fixpoint())
+    }
+}
+
+/// The results of a dataflow analysis whose type has been erased.
+pub trait ErasedResults<'tcx> {
+    /// The name of the analysis (see `AnalysisDomain::NAME`).
+    fn name(&self) -> &'static str;
+
+    /// The direction of the analysis.
+    fn direction(&self) -> DirectionKind;
+
+    /// Returns the entry set for `block`, formatted with `DebugWithContext`.
+    fn entry_state_rendered(&self, block: BasicBlock) -> String;
+
+    /// Calls `vis` with the formatted state after the primary effect of each statement and
+    /// terminator in `blocks`, in dataflow order. Prefer `visit_results_dyn` to calling this
+    /// directly.
+    fn visit_rendered(
+        &mut self,
+        body: &mir::Body<'tcx>,
+        blocks: &mut dyn Iterator<Item = BasicBlock>,
+        vis: &mut dyn FnMut(Location, String),
+    );
+
+    /// Writes a DOT file with these results if one was requested via `rustc_mir` attributes and
+    /// `-Z dump-mir-dataflow`. See `Engine::pass_name` for `pass_name` and `Engine::phase` for
+    /// `phase`.
+    fn write_graphviz(
+        &mut self,
+        tcx: TyCtxt<'tcx>,
+        body: &mir::Body<'tcx>,
+        pass_name: Option<&'static str>,
+        phase: Option<&'static str>,
+    ) -> io::Result<()>;
+
+    /// If `pass_where` is `PassWhere::BeforeBlock`, writes the entry set of that block to `w` as a
+    /// MIR dump comment. Call this from the `extra_data` callback of `dump_mir`, as
+    /// `append_dataflow_to_mir_dump` does.
+    fn write_mir_dump_comment(
+        &self,
+        pass_where: PassWhere,
+        w: &mut dyn io::Write,
+    ) -> io::Result<()>;
+}
+
+impl<'tcx, A> ErasedResults<'tcx> for Results<'tcx, A>
+where
+    A: Analysis<'tcx>,
+    A::Domain: DebugWithContext<A>,
+{
+    fn name(&self) -> &'static str {
+        A::NAME
+    }
+
+    fn direction(&self) -> DirectionKind {
+        A::Direction::KIND
+    }
+
+    fn entry_state_rendered(&self, block: BasicBlock) -> String {
+        let state = self.entry_set_for_block(block);
+        format!("{:?}", DebugWithAdapter { this: state, ctxt: &self.analysis })
+    }
+
+    fn visit_rendered(
+        &mut self,
+        body: &mir::Body<'tcx>,
+        blocks: &mut dyn Iterator<Item = BasicBlock>,
+        vis: &mut dyn FnMut(Location, String),
+    ) {
+        visit_results(body, blocks, self, &mut RenderingVisitor { vis })
+    }
+
+    fn write_graphviz(
+        &mut self,
+        tcx: TyCtxt<'tcx>,
+        body: &mir::Body<'tcx>,
+        pass_name: Option<&'static str>,
+        phase: Option<&'static str>,
+    ) -> io::Result<()> {
+        write_graphviz_results(tcx, body, self, pass_name, phase)
+    }
+
+    fn write_mir_dump_comment(
+        &self,
+        pass_where: PassWhere,
+        w: &mut dyn io::Write,
+    ) -> io::Result<()> {
+        write_entry_set_comment(self, &pass_where, w)
+    }
+}
+
+/// The runtime-dispatched counterpart of `visit_results`.
+///
+/// Calls `vis` with the formatted dataflow state after the primary effect of each statement and
+/// terminator in `blocks`.
+pub fn visit_results_dyn<'tcx>(
+    body: &mir::Body<'tcx>,
+    blocks: impl IntoIterator<Item = BasicBlock>,
+    results: &mut dyn ErasedResults<'tcx>,
+    mut vis: impl FnMut(Location, String),
+) {
+    results.visit_rendered(body, &mut blocks.into_iter(), &mut vis)
+}
+
+struct RenderingVisitor<'a> {
+    vis: &'a mut dyn FnMut(Location, String),
+}
+
+impl<'mir, 'tcx, A> ResultsVisitor<'mir, 'tcx, Results<'tcx, A>> for RenderingVisitor<'_>
+where
+    A: Analysis<'tcx>,
+    A::Domain: DebugWithContext<A>,
+{
+    type FlowState = A::Domain;
+
+    fn visit_statement_after_primary_effect(
+        &mut self,
+        results: &mut Results<'tcx, A>,
+        state: &Self::FlowState,
+        _statement: &'mir mir::Statement<'tcx>,
+        location: Location,
+    ) {
+        (self.vis)(
+            location,
+            format!("{:?}", DebugWithAdapter { this: state, ctxt: &results.analysis }),
+        );
+    }
+
+    fn visit_terminator_after_primary_effect(
+        &mut self,
+        results: &mut Results<'tcx, A>,
+        state: &Self::FlowState,
+        _terminator: &'mir mir::Terminator<'tcx>,
+        location: Location,
+    ) {
+        (self.vis)(
+            location,
+            format!("{:?}", DebugWithAdapter { this: state, ctxt: &results.analysis }),
+        );
+    }
+}
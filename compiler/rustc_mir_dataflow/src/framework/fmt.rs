@@ -2,8 +2,10 @@
 //! analysis.
 
 use super::lattice::MaybeReachable;
+use super::{Analysis, BitSetExt, GenKillAnalysis};
 use rustc_index::bit_set::{BitSet, ChunkedBitSet, HybridBitSet};
 use rustc_index::Idx;
+use rustc_middle::mir;
 use std::fmt;
 
 /// An extension to `fmt::Debug` for data that can be better printed with some auxiliary data `C`.
@@ -69,6 +71,57 @@ fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
     }
 }
 
+/// Describes `state` with `Analysis::explain_state`, or with its `DebugWithContext` formatting if
+/// the analysis does not explain it.
+pub fn explain_state<'tcx, A>(analysis: &A, state: &A::Domain, body: &mir::Body<'tcx>) -> String
+where
+    A: Analysis<'tcx>,
+    A::Domain: DebugWithContext<A>,
+{
+    analysis
+        .explain_state(state, body)
+        .unwrap_or_else(|| format!("{:?}", DebugWithAdapter { this: state, ctxt: analysis }))
+}
+
+/// Returns the difference between `new` and `old` as printed by `fmt_diff_with`, but as plain text
+/// without control characters: elements only in `new` are prefixed with `+`, and elements only in
+/// `old` with `-`, all on one line.
+pub(super) fn plain_diff<T, C>(new: &T, old: &T, ctxt: &C) -> String
+where
+    T: DebugWithContext<C>,
+{
+    let diff = format!("{:?}", DebugDiffWithAdapter { new, old, ctxt });
+    diff.replace('\u{001f}', "").replace('\t', " ")
+}
+
+/// Implements `fmt::Debug` for the state of a gen/kill analysis by printing each element of the
+/// state with `GenKillAnalysis::domain_element_name`.
+pub struct DebugWithElementNames<'a, 'mir, 'tcx, A>
+where
+    A: GenKillAnalysis<'tcx>,
+{
+    pub state: &'a A::Domain,
+    pub analysis: &'a A,
+    pub body: &'mir mir::Body<'tcx>,
+}
+
+impl<'tcx, A> fmt::Debug for DebugWithElementNames<'_, '_, 'tcx, A>
+where
+    A: GenKillAnalysis<'tcx>,
+    A::Domain: BitSetExt<A::Idx>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut set = f.debug_set();
+        for idx in (0..self.analysis.domain_size(self.body)).map(A::Idx::new) {
+            if self.state.contains(idx) {
+                let name = self.analysis.domain_element_name(idx, self.body);
+                set.entry(&format_args!("{name}"));
+            }
+        }
+        set.finish()
+    }
+}
+
 // Impls
 
 impl<T, C> DebugWithContext<C> for BitSet<T>
@@ -217,6 +270,20 @@ fn fmt_diff_with(&self, old: &Self, ctxt: &C, f: &mut fmt::Formatter<'_>) -> fmt
     }
 }
 
+impl<T, U, C> DebugWithContext<C> for (T, U)
+where
+    T: DebugWithContext<C>,
+    U: DebugWithContext<C>,
+{
+    fn fmt_with(&self, ctxt: &C, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "(")?;
+        self.0.fmt_with(ctxt, f)?;
+        write!(f, ", ")?;
+        self.1.fmt_with(ctxt, f)?;
+        write!(f, ")")
+    }
+}
+
 impl<C> DebugWithContext<C> for rustc_middle::mir::Local {}
 impl<C> DebugWithContext<C> for crate::move_paths::InitIndex {}
 
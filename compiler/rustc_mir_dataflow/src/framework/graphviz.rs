@@ -3,40 +3,68 @@
 use std::borrow::Cow;
 use std::cell::RefCell;
 use std::sync::OnceLock;
-use std::{io, ops, str};
+use std::{fmt, io, ops, str};
 
 use regex::Regex;
 use rustc_graphviz as dot;
 use rustc_index::bit_set::BitSet;
+use rustc_index::IndexVec;
 use rustc_middle::mir::graphviz_safe_def_name;
 use rustc_middle::mir::{self, BasicBlock, Body, Location};
 
-use super::fmt::{DebugDiffWithAdapter, DebugWithAdapter, DebugWithContext};
+use super::fmt::{explain_state, DebugDiffWithAdapter, DebugWithContext};
 use super::{Analysis, CallReturnPlaces, Direction, Results, ResultsRefCursor, ResultsVisitor};
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-pub enum OutputStyle {
+/// Renders the "STATE" column of a statement or terminator from the states before and after its
+/// primary effect, in dataflow order.
+pub type CustomStateRenderer<D> = Box<dyn Fn(&D, &D, &mut fmt::Formatter<'_>) -> fmt::Result>;
+
+pub enum OutputStyle<D> {
     AfterOnly,
     BeforeAndAfter,
+    /// Renders the "STATE" column of each statement and terminator with the given function instead
+    /// of as the difference between the states around it. Its output is escaped, but not wrapped.
+    Custom(CustomStateRenderer<D>),
 }
 
-impl OutputStyle {
+impl<D> OutputStyle<D> {
     fn num_state_columns(&self) -> usize {
         match self {
-            Self::AfterOnly => 1,
+            Self::AfterOnly | Self::Custom(_) => 1,
             Self::BeforeAndAfter => 2,
         }
     }
 }
 
+/// Calls a `CustomStateRenderer` with a `fmt::Formatter`.
+struct RenderCustom<'a, D> {
+    render: &'a CustomStateRenderer<D>,
+    before: &'a D,
+    after: &'a D,
+}
+
+impl<D> fmt::Display for RenderCustom<'_, D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (self.render)(self.before, self.after, f)
+    }
+}
+
 pub struct Formatter<'res, 'mir, 'tcx, A>
 where
     A: Analysis<'tcx>,
 {
     body: &'mir Body<'tcx>,
     results: RefCell<&'res mut Results<'tcx, A>>,
-    style: OutputStyle,
+    style: OutputStyle<A::Domain>,
+    wrap_width: Option<usize>,
+    font_size: Option<u32>,
+    tracked_bit: Option<usize>,
+    block_range: Option<ops::RangeInclusive<BasicBlock>>,
     reachable: BitSet<BasicBlock>,
+
+    /// The targets of the edges of each block that the analysis considers feasible, computed by
+    /// `is_feasible` the first time an edge of that block is rendered.
+    feasible_targets: RefCell<IndexVec<BasicBlock, Option<Vec<BasicBlock>>>>,
 }
 
 impl<'res, 'mir, 'tcx, A> Formatter<'res, 'mir, 'tcx, A>
@@ -46,13 +74,206 @@ impl<'res, 'mir, 'tcx, A> Formatter<'res, 'mir, 'tcx, A>
     pub fn new(
         body: &'mir Body<'tcx>,
         results: &'res mut Results<'tcx, A>,
-        style: OutputStyle,
+        style: OutputStyle<A::Domain>,
     ) -> Self {
         let reachable = mir::traversal::reachable_as_bitset(body);
-        Formatter { body, results: results.into(), style, reachable }
+        Formatter {
+            body,
+            results: results.into(),
+            style,
+            wrap_width: None,
+            font_size: None,
+            tracked_bit: None,
+            block_range: None,
+            reachable,
+            feasible_targets: RefCell::new(IndexVec::from_elem(None, &body.basic_blocks)),
+        }
+    }
+
+    /// Sets the column at which full dataflow states are wrapped onto a new line. By default, they
+    /// are not wrapped.
+    pub fn wrap_width(mut self, wrap_width: usize) -> Self {
+        self.wrap_width = Some(wrap_width);
+        self
+    }
+
+    /// Sets the size of all text in the rendered graph, in points.
+    ///
+    /// This is not part of the graph itself, so callers of `dot::render_opts` must pass
+    /// `font_size` along as a `dot::RenderOption::FontSize`. `-Z dump-mir-dataflow` sets it from
+    /// `#[rustc_mir(borrowck_graphviz_font_size = "...")]`.
+    pub fn with_font_size(mut self, pt: u32) -> Self {
+        self.font_size = Some(pt);
+        self
+    }
+
+    /// The font size set by `with_font_size`, if any.
+    pub fn font_size(&self) -> Option<u32> {
+        self.font_size
+    }
+
+    /// Renders only the blocks where the value of `bit` on entry differs from its value on entry
+    /// to at least one of their predecessors (in dataflow order), i.e. where that fact was
+    /// generated, killed or merged. Blocks with no such predecessors are rendered if `bit` is set
+    /// on entry.
+    ///
+    /// Analyses that do not implement `Analysis::contains_bit` are rendered in full.
+    pub fn track_bit(mut self, bit: usize) -> Self {
+        self.tracked_bit = Some(bit);
+        self
+    }
+
+    /// Renders only the blocks in `range`, along with the edges between them.
+    pub fn block_range(mut self, range: ops::RangeInclusive<BasicBlock>) -> Self {
+        self.block_range = Some(range);
+        self
+    }
+
+    /// Returns the set of blocks that should be rendered.
+    fn rendered_blocks(&self) -> BitSet<BasicBlock> {
+        let mut in_range = self.reachable.clone();
+        if let Some(range) = &self.block_range {
+            for bb in self.reachable.iter().filter(|bb| !range.contains(bb)) {
+                in_range.remove(bb);
+            }
+        }
+
+        let mut rendered = in_range.clone();
+        let Some(bit) = self.tracked_bit else {
+            return rendered;
+        };
+
+        let results = self.results.borrow();
+        let contains_bit =
+            |bb| results.analysis.contains_bit(self.body, results.entry_set_for_block(bb), bit);
+
+        for bb in in_range.iter() {
+            let Some(on_entry) = contains_bit(bb) else {
+                return in_range;
+            };
+
+            let preds: Vec<_> = if A::Direction::IS_FORWARD {
+                self.body.basic_blocks.predecessors()[bb].to_vec()
+            } else {
+                self.body[bb].terminator().successors().collect()
+            };
+            let changed = if preds.is_empty() {
+                on_entry
+            } else {
+                preds.into_iter().any(|pred| contains_bit(pred) != Some(on_entry))
+            };
+
+            if !changed {
+                rendered.remove(bb);
+            }
+        }
+
+        rendered
+    }
+
+    /// Returns `true` if the analysis considers the edge `e` feasible.
+    fn is_feasible(&self, e: &CfgEdge) -> bool {
+        let target = self.body[e.source].terminator().successors().nth(e.index).unwrap();
+        let mut feasible_targets = self.feasible_targets.borrow_mut();
+        let targets = feasible_targets[e.source]
+            .get_or_insert_with(|| self.results.borrow_mut().terminator_edges(self.body, e.source));
+        targets.contains(&target)
     }
 }
 
+impl<'tcx, A> Formatter<'_, '_, 'tcx, A>
+where
+    A: Analysis<'tcx>,
+    A::Domain: DebugWithContext<A>,
+{
+    /// Writes the rendered blocks and the edges between them as a GraphML document, for tools such
+    /// as Gephi or yEd that can lay out and filter large graphs interactively.
+    ///
+    /// Each node carries the state on entry to its block and the state after all of its effects, in
+    /// dataflow order. Each edge carries its successor label and whether the analysis considers it
+    /// feasible. The output style and wrap width only apply to graphviz output.
+    pub fn write_graphml(&self, w: &mut impl io::Write) -> io::Result<()> {
+        let rendered = self.rendered_blocks();
+
+        writeln!(w, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(w, r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#)?;
+        for (id, kind, ty) in [
+            ("label", "all", "string"),
+            ("entry_state", "node", "string"),
+            ("exit_state", "node", "string"),
+            ("feasible", "edge", "boolean"),
+        ] {
+            writeln!(w, r#"  <key id="{id}" for="{kind}" attr.name="{id}" attr.type="{ty}"/>"#)?;
+        }
+        let graph_id = dot::Labeller::graph_id(self);
+        writeln!(w, r#"  <graph id="{}" edgedefault="directed">"#, graph_id.as_slice())?;
+
+        for block in rendered.iter() {
+            let mut results = self.results.borrow_mut();
+            let mut cursor = results.as_results_cursor(self.body);
+            let state = |cursor: &ResultsRefCursor<'_, '_, 'tcx, A>| {
+                xml_escape(&explain_state(cursor.analysis(), cursor.get(), self.body))
+            };
+
+            cursor.seek_to_block_entry(block);
+            let entry_state = state(&cursor);
+            if A::Direction::IS_FORWARD {
+                cursor.seek_to_block_end(block);
+            } else {
+                cursor.seek_to_block_start(block);
+            }
+            let exit_state = state(&cursor);
+
+            writeln!(w, r#"    <node id="bb{}">"#, block.index())?;
+            writeln!(w, r#"      <data key="label">{block:?}</data>"#)?;
+            writeln!(w, r#"      <data key="entry_state">{entry_state}</data>"#)?;
+            writeln!(w, r#"      <data key="exit_state">{exit_state}</data>"#)?;
+            writeln!(w, "    </node>")?;
+        }
+
+        // Unlike graphviz, GraphML does not create the endpoints of edges implicitly, so edges to or
+        // from blocks that are not rendered are always dropped.
+        for edge in rendered.iter().flat_map(|bb| dataflow_successors(self.body, bb)) {
+            let target = dot::GraphWalk::target(self, &edge);
+            if !rendered.contains(target) {
+                continue;
+            }
+
+            let labels = self.body[edge.source].terminator().kind.fmt_successor_labels();
+            writeln!(
+                w,
+                r#"    <edge source="bb{}" target="bb{}">"#,
+                edge.source.index(),
+                target.index()
+            )?;
+            writeln!(w, r#"      <data key="label">{}</data>"#, xml_escape(&labels[edge.index]))?;
+            writeln!(w, r#"      <data key="feasible">{}</data>"#, self.is_feasible(&edge))?;
+            writeln!(w, "    </edge>")?;
+        }
+
+        writeln!(w, "  </graph>")?;
+        writeln!(w, "</graphml>")
+    }
+}
+
+/// Escapes `s` for use as XML character data or attribute value. Control characters other than
+/// newlines and tabs cannot be represented in XML 1.0, so they are dropped.
+fn xml_escape(s: &str) -> String {
+    let mut ret = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => ret.push_str("&amp;"),
+            '<' => ret.push_str("&lt;"),
+            '>' => ret.push_str("&gt;"),
+            '"' => ret.push_str("&quot;"),
+            '\n' | '\t' => ret.push(c),
+            c if c.is_control() => {}
+            c => ret.push(c),
+        }
+    }
+    ret
+}
+
 /// A pair of a basic block and an index into that basic blocks `successors`.
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
 pub struct CfgEdge {
@@ -91,7 +312,8 @@ fn node_label(&self, block: &Self::Node) -> dot::LabelText<'_> {
         let mut results = self.results.borrow_mut();
         let mut fmt = BlockFormatter {
             results: results.as_results_cursor(self.body),
-            style: self.style,
+            style: &self.style,
+            wrap_width: self.wrap_width,
             bg: Background::Light,
         };
 
@@ -107,6 +329,15 @@ fn edge_label(&self, e: &Self::Edge) -> dot::LabelText<'_> {
         let label = &self.body[e.source].terminator().kind.fmt_successor_labels()[e.index];
         dot::LabelText::label(label.clone())
     }
+
+    fn edge_style(&self, e: &Self::Edge) -> dot::Style {
+        // Dash out edges that the analysis considers infeasible.
+        if self.is_feasible(e) {
+            dot::Style::None
+        } else {
+            dot::Style::Dashed
+        }
+    }
 }
 
 impl<'mir, 'tcx, A> dot::GraphWalk<'mir> for Formatter<'_, 'mir, 'tcx, A>
@@ -117,21 +348,24 @@ impl<'mir, 'tcx, A> dot::GraphWalk<'mir> for Formatter<'_, 'mir, 'tcx, A>
     type Edge = CfgEdge;
 
     fn nodes(&self) -> dot::Nodes<'_, Self::Node> {
-        self.body
-            .basic_blocks
-            .indices()
-            .filter(|&idx| self.reachable.contains(idx))
-            .collect::<Vec<_>>()
-            .into()
+        self.rendered_blocks().iter().collect::<Vec<_>>().into()
     }
 
     fn edges(&self) -> dot::Edges<'_, Self::Edge> {
-        self.body
-            .basic_blocks
-            .indices()
-            .flat_map(|bb| dataflow_successors(self.body, bb))
-            .collect::<Vec<_>>()
-            .into()
+        let edges =
+            self.body.basic_blocks.indices().flat_map(|bb| dataflow_successors(self.body, bb));
+
+        // When tracking a single bit or a range of blocks, most blocks are hidden. Drop the edges
+        // between them, since graphviz would otherwise render their endpoints as empty nodes.
+        if self.tracked_bit.is_some() || self.block_range.is_some() {
+            let rendered = self.rendered_blocks();
+            edges
+                .filter(|e| rendered.contains(e.source) && rendered.contains(self.target(e)))
+                .collect::<Vec<_>>()
+                .into()
+        } else {
+            edges.collect::<Vec<_>>().into()
+        }
     }
 
     fn source(&self, edge: &Self::Edge) -> Self::Node {
@@ -149,7 +383,8 @@ struct BlockFormatter<'res, 'mir, 'tcx, A>
 {
     results: ResultsRefCursor<'res, 'mir, 'tcx, A>,
     bg: Background,
-    style: OutputStyle,
+    style: &'res OutputStyle<A::Domain>,
+    wrap_width: Option<usize>,
 }
 
 impl<'res, 'mir, 'tcx, A> BlockFormatter<'res, 'mir, 'tcx, A>
@@ -203,7 +438,9 @@ fn write_node_label(&mut self, w: &mut impl io::Write, block: BasicBlock) -> io:
 
         // A + B: Block header
         match self.style {
-            OutputStyle::AfterOnly => self.write_block_header_simple(w, block)?,
+            OutputStyle::AfterOnly | OutputStyle::Custom(_) => {
+                self.write_block_header_simple(w, block)?
+            }
             OutputStyle::BeforeAndAfter => {
                 self.write_block_header_with_state_columns(w, block, &["BEFORE", "AFTER"])?
             }
@@ -269,11 +506,7 @@ fn write_node_label(&mut self, w: &mut impl io::Write, block: BasicBlock) -> io:
                 self.write_row(w, "", "(on yield resume)", |this, w, fmt| {
                     let state_on_coroutine_drop = this.results.get().clone();
                     this.results.apply_custom_effect(|analysis, state| {
-                        analysis.apply_call_return_effect(
-                            state,
-                            resume,
-                            CallReturnPlaces::Yield(resume_arg),
-                        );
+                        analysis.apply_yield_resume_effect(state, resume, resume_arg);
                     });
 
                     write!(
@@ -485,51 +718,69 @@ fn write_row_with_full_state(
             let state = this.results.get();
             let analysis = this.results.analysis();
 
-            // FIXME: The full state vector can be quite long. It would be nice to split on commas
-            // and use some text wrapping algorithm.
             write!(
                 w,
                 r#"<td colspan="{colspan}" {fmt} align="left">{state}</td>"#,
                 colspan = this.style.num_state_columns(),
                 fmt = fmt,
-                state = dot::escape_html(&format!(
-                    "{:?}",
-                    DebugWithAdapter { this: state, ctxt: analysis }
-                )),
+                state = escape_and_wrap(
+                    &explain_state(analysis, state, this.results.body()),
+                    this.wrap_width,
+                ),
             )
         })
     }
 }
 
-struct StateDiffCollector<D> {
+struct StateDiffCollector<'a, D> {
     prev_state: D,
     before: Option<Vec<String>>,
     after: Vec<String>,
+    custom: Option<&'a CustomStateRenderer<D>>,
 }
 
-impl<D> StateDiffCollector<D> {
+impl<'a, D> StateDiffCollector<'a, D> {
     fn run<'tcx, A>(
         body: &mir::Body<'tcx>,
         block: BasicBlock,
         results: &mut Results<'tcx, A>,
-        style: OutputStyle,
+        style: &'a OutputStyle<D>,
     ) -> Self
     where
         A: Analysis<'tcx, Domain = D>,
         D: DebugWithContext<A>,
     {
         let mut collector = StateDiffCollector {
-            prev_state: results.analysis.bottom_value(body),
+            // This is overwritten at the start of the block, before it is read.
+            prev_state: results.entry_set_for_block(block).clone(),
             after: vec![],
-            before: (style == OutputStyle::BeforeAndAfter).then_some(vec![]),
+            before: matches!(style, OutputStyle::BeforeAndAfter).then_some(vec![]),
+            custom: match style {
+                OutputStyle::Custom(render) => Some(render),
+                _ => None,
+            },
         };
 
         results.visit_with(body, std::iter::once(block), &mut collector);
         collector
     }
+
+    /// Renders `state`, the state after a primary effect, for the "STATE" column.
+    fn render_after<C>(&self, state: &D, ctxt: &C) -> String
+    where
+        D: DebugWithContext<C>,
+    {
+        match self.custom {
+            Some(render) => {
+                let custom = RenderCustom { render, before: &self.prev_state, after: state };
+                dot::escape_html(&custom.to_string())
+            }
+            None => diff_pretty(state, &self.prev_state, ctxt),
+        }
+    }
 }
 
-impl<'tcx, A> ResultsVisitor<'_, 'tcx, Results<'tcx, A>> for StateDiffCollector<A::Domain>
+impl<'tcx, A> ResultsVisitor<'_, 'tcx, Results<'tcx, A>> for StateDiffCollector<'_, A::Domain>
 where
     A: Analysis<'tcx>,
     A::Domain: DebugWithContext<A>,
@@ -580,7 +831,7 @@ fn visit_statement_after_primary_effect(
         _statement: &mir::Statement<'tcx>,
         _location: Location,
     ) {
-        self.after.push(diff_pretty(state, &self.prev_state, &results.analysis));
+        self.after.push(self.render_after(state, &results.analysis));
         self.prev_state.clone_from(state)
     }
 
@@ -604,7 +855,7 @@ fn visit_terminator_after_primary_effect(
         _terminator: &mir::Terminator<'tcx>,
         _location: Location,
     ) {
-        self.after.push(diff_pretty(state, &self.prev_state, &results.analysis));
+        self.after.push(self.render_after(state, &results.analysis));
         self.prev_state.clone_from(state)
     }
 }
@@ -616,6 +867,35 @@ macro_rules! regex {
     }};
 }
 
+/// Escapes `s` for use in an HTML label, breaking it into left-aligned lines of at most `width`
+/// characters if given.
+///
+/// Lines are only broken after a `", "` separator, so a single element that is longer than `width`
+/// is never split.
+fn escape_and_wrap(s: &str, width: Option<usize>) -> String {
+    let Some(width) = width else {
+        return dot::escape_html(s);
+    };
+
+    let mut lines = vec![];
+    let mut line = String::new();
+    let mut line_width = 0;
+    for piece in s.split_inclusive(", ") {
+        let piece_width = piece.trim_end().chars().count();
+        if line_width > 0 && line_width + piece_width > width {
+            lines.push(dot::escape_html(line.trim_end()));
+            line.clear();
+            line_width = 0;
+        }
+
+        line.push_str(piece);
+        line_width += piece.chars().count();
+    }
+    lines.push(dot::escape_html(&line));
+
+    lines.join(r#"<br align="left"/>"#)
+}
+
 fn diff_pretty<T, C>(new: T, old: T, ctxt: &C) -> String
 where
     T: DebugWithContext<C>,
@@ -39,7 +39,7 @@
 //! [poset]: https://en.wikipedia.org/wiki/Partially_ordered_set
 
 use crate::framework::BitSetExt;
-use rustc_index::bit_set::{BitSet, ChunkedBitSet, HybridBitSet};
+use rustc_index::bit_set::{BitSet, ChunkedBitSet};
 use rustc_index::{Idx, IndexVec};
 use std::iter;
 
@@ -149,6 +149,94 @@ fn meet(&mut self, other: &Self) -> bool {
     }
 }
 
+/// Like `IndexVec`, a `Vec` of lattices is a lattice whose join is taken element-wise. Both
+/// operands must have the same length.
+impl<T: JoinSemiLattice> JoinSemiLattice for Vec<T> {
+    fn join(&mut self, other: &Self) -> bool {
+        assert_eq!(self.len(), other.len());
+
+        let mut changed = false;
+        for (a, b) in iter::zip(self, other) {
+            changed |= a.join(b);
+        }
+        changed
+    }
+}
+
+impl<T: MeetSemiLattice> MeetSemiLattice for Vec<T> {
+    fn meet(&mut self, other: &Self) -> bool {
+        assert_eq!(self.len(), other.len());
+
+        let mut changed = false;
+        for (a, b) in iter::zip(self, other) {
+            changed |= a.meet(b);
+        }
+        changed
+    }
+}
+
+/// A pair of lattices is a lattice whose join is taken component-wise:
+///
+///     (A₀, A₁) ∨ (B₀, B₁) = (A₀∨B₀, A₁∨B₁)
+///
+/// This lets an analysis track two related kinds of facts in one state, e.g. shared and mutable
+/// borrows in `MaybeBorrowedLocalsByKind`.
+impl<T: JoinSemiLattice, U: JoinSemiLattice> JoinSemiLattice for (T, U) {
+    fn join(&mut self, other: &Self) -> bool {
+        let changed = self.0.join(&other.0);
+        self.1.join(&other.1) | changed
+    }
+}
+
+impl<T: MeetSemiLattice, U: MeetSemiLattice> MeetSemiLattice for (T, U) {
+    fn meet(&mut self, other: &Self) -> bool {
+        let changed = self.0.meet(&other.0);
+        self.1.meet(&other.1) | changed
+    }
+}
+
+/// An `Option` extends a lattice with a new bottom element, `None`, which is less than every
+/// `Some`:
+///
+/// ```text
+///      Some(T::TOP)
+///          |
+///         ...
+///          |
+///     Some(T::BOTTOM)
+///          |
+///        None
+/// ```
+impl<T: JoinSemiLattice + Clone> JoinSemiLattice for Option<T> {
+    fn join(&mut self, other: &Self) -> bool {
+        match (&mut *self, other) {
+            (_, None) => false,
+            (None, Some(_)) => {
+                *self = other.clone();
+                true
+            }
+            (Some(this), Some(other)) => this.join(other),
+        }
+    }
+}
+
+impl<T: MeetSemiLattice> MeetSemiLattice for Option<T> {
+    fn meet(&mut self, other: &Self) -> bool {
+        match (&mut *self, other) {
+            (None, _) => false,
+            (Some(_), None) => {
+                *self = None;
+                true
+            }
+            (Some(this), Some(other)) => this.meet(other),
+        }
+    }
+}
+
+impl<T> HasBottom for Option<T> {
+    const BOTTOM: Self = None;
+}
+
 /// A `BitSet` represents the lattice formed by the powerset of all possible values of
 /// the index type `T` ordered by inclusion. Equivalently, it is a tuple of "two-point" lattices,
 /// one for each possible value of `T`.
@@ -191,12 +279,16 @@ fn contains(&self, elem: T) -> bool {
         self.0.contains(elem)
     }
 
-    fn union(&mut self, other: &HybridBitSet<T>) {
-        self.0.union(other);
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn count(&self) -> usize {
+        self.0.count()
     }
 
-    fn subtract(&mut self, other: &HybridBitSet<T>) {
-        self.0.subtract(other);
+    fn domain_size(&self) -> Option<usize> {
+        Some(self.0.domain_size())
     }
 }
 
@@ -316,17 +408,24 @@ fn contains(&self, elem: T) -> bool {
         self.contains(elem)
     }
 
-    fn union(&mut self, other: &HybridBitSet<T>) {
+    fn is_empty(&self) -> bool {
+        match self {
+            MaybeReachable::Unreachable => true,
+            MaybeReachable::Reachable(set) => set.is_empty(),
+        }
+    }
+
+    fn count(&self) -> usize {
         match self {
-            MaybeReachable::Unreachable => {}
-            MaybeReachable::Reachable(set) => set.union(other),
+            MaybeReachable::Unreachable => 0,
+            MaybeReachable::Reachable(set) => set.count(),
         }
     }
 
-    fn subtract(&mut self, other: &HybridBitSet<T>) {
+    fn domain_size(&self) -> Option<usize> {
         match self {
-            MaybeReachable::Unreachable => {}
-            MaybeReachable::Reachable(set) => set.subtract(other),
+            MaybeReachable::Unreachable => None,
+            MaybeReachable::Reachable(set) => set.domain_size(),
         }
     }
 }
@@ -0,0 +1,538 @@
+//! Adapters that present the results of an existing analysis over a different domain.
+
+use std::marker::PhantomData;
+
+use rustc_index::bit_set::{BitRelations, BitSet, ChunkedBitSet};
+use rustc_index::{Idx, IndexVec};
+use rustc_middle::mir::{self, BasicBlock, CallReturnPlaces, Location, TerminatorEdges};
+use rustc_middle::mir::Terminator;
+use rustc_middle::ty::TyCtxt;
+
+use super::{
+    Analysis, AnalysisDomain, FalseEdgeSemantics, GenKill, GenKillAnalysis, JoinSemiLattice,
+    Results, SwitchIntTarget,
+};
+
+/// Wraps an `Analysis` so that its dataflow state is a `D` instead of `A::Domain`.
+///
+/// `to` and `from` must be mutually inverse, and `to` must map the join of `A::Domain` to the join
+/// of `D`. The transfer functions of `inner` are then applied by translating the state with `from`,
+/// applying the effect, and translating it back with `to`. Since this happens for every effect,
+/// prefer `RenumberedBitsetAnalysis` to renumber the elements of a gen/kill analysis. The same
+/// goes for joins, which go through `Analysis::join_at` of `inner`.
+pub struct MapDomain<A, D, F, G> {
+    inner: A,
+    to: F,
+    from: G,
+    _marker: PhantomData<D>,
+}
+
+impl<'tcx, A, D, F, G> MapDomain<A, D, F, G>
+where
+    A: Analysis<'tcx>,
+    F: Fn(&A::Domain) -> D,
+    G: Fn(&D) -> A::Domain,
+{
+    pub fn new(inner: A, to: F, from: G) -> Self {
+        MapDomain { inner, to, from, _marker: PhantomData }
+    }
+
+    pub fn into_inner(self) -> A {
+        self.inner
+    }
+
+    /// Applies `effect` to `state` as a state of the inner analysis.
+    fn translated<R>(
+        &mut self,
+        state: &mut D,
+        effect: impl FnOnce(&mut A, &mut A::Domain) -> R,
+    ) -> R {
+        let mut inner_state = (self.from)(state);
+        let ret = effect(&mut self.inner, &mut inner_state);
+        *state = (self.to)(&inner_state);
+        ret
+    }
+}
+
+impl<'tcx, A, D, F, G> AnalysisDomain<'tcx> for MapDomain<A, D, F, G>
+where
+    A: Analysis<'tcx>,
+    D: Clone + JoinSemiLattice,
+    F: Fn(&A::Domain) -> D,
+    G: Fn(&D) -> A::Domain,
+{
+    type Domain = D;
+    type Direction = A::Direction;
+    type SwitchIntData = A::SwitchIntData;
+
+    const NAME: &'static str = A::NAME;
+    const REQUIRES_CLEANUP_BLOCKS: bool = A::REQUIRES_CLEANUP_BLOCKS;
+    const MERGES_CALL_RETURN_STATE: bool = A::MERGES_CALL_RETURN_STATE;
+
+    fn bottom_value(&self, tcx: TyCtxt<'tcx>, body: &mir::Body<'tcx>) -> Self::Domain {
+        (self.to)(&self.inner.bottom_value(tcx, body))
+    }
+
+    fn initialize_start_block(&self, body: &mir::Body<'tcx>, state: &mut Self::Domain) {
+        let mut inner_state = (self.from)(state);
+        self.inner.initialize_start_block(body, &mut inner_state);
+        *state = (self.to)(&inner_state);
+    }
+}
+
+impl<'tcx, A, D, F, G> Analysis<'tcx> for MapDomain<A, D, F, G>
+where
+    A: Analysis<'tcx>,
+    D: Clone + JoinSemiLattice,
+    F: Fn(&A::Domain) -> D,
+    G: Fn(&D) -> A::Domain,
+{
+    fn apply_statement_effect(
+        &mut self,
+        state: &mut Self::Domain,
+        statement: &mir::Statement<'tcx>,
+        location: Location,
+    ) {
+        self.translated(state, |inner, state| {
+            inner.apply_statement_effect(state, statement, location)
+        })
+    }
+
+    fn apply_before_statement_effect(
+        &mut self,
+        state: &mut Self::Domain,
+        statement: &mir::Statement<'tcx>,
+        location: Location,
+    ) {
+        self.translated(state, |inner, state| {
+            inner.apply_before_statement_effect(state, statement, location)
+        })
+    }
+
+    fn apply_batch_statement_effect(
+        &mut self,
+        state: &mut Self::Domain,
+        block: BasicBlock,
+        block_data: &mir::BasicBlockData<'tcx>,
+    ) -> bool {
+        self.translated(state, |inner, state| {
+            inner.apply_batch_statement_effect(state, block, block_data)
+        })
+    }
+
+    fn apply_terminator_effect<'mir>(
+        &mut self,
+        state: &mut Self::Domain,
+        terminator: &'mir Terminator<'tcx>,
+        location: Location,
+    ) -> TerminatorEdges<'mir, 'tcx> {
+        self.translated(state, |inner, state| {
+            inner.apply_terminator_effect(state, terminator, location)
+        })
+    }
+
+    fn apply_before_terminator_effect(
+        &mut self,
+        state: &mut Self::Domain,
+        terminator: &Terminator<'tcx>,
+        location: Location,
+    ) {
+        self.translated(state, |inner, state| {
+            inner.apply_before_terminator_effect(state, terminator, location)
+        })
+    }
+
+    fn apply_terminator_unwind_effect(
+        &mut self,
+        state: &mut Self::Domain,
+        terminator: &Terminator<'tcx>,
+        block: BasicBlock,
+    ) {
+        self.translated(state, |inner, state| {
+            inner.apply_terminator_unwind_effect(state, terminator, block)
+        })
+    }
+
+    fn apply_call_return_effect(
+        &mut self,
+        state: &mut Self::Domain,
+        block: BasicBlock,
+        return_places: CallReturnPlaces<'_, 'tcx>,
+    ) {
+        self.translated(state, |inner, state| {
+            inner.apply_call_return_effect(state, block, return_places)
+        })
+    }
+
+    fn apply_yield_resume_effect(
+        &mut self,
+        state: &mut Self::Domain,
+        resume_block: BasicBlock,
+        resume_place: mir::Place<'tcx>,
+    ) {
+        self.translated(state, |inner, state| {
+            inner.apply_yield_resume_effect(state, resume_block, resume_place)
+        })
+    }
+
+    fn get_switch_int_data(
+        &mut self,
+        block: BasicBlock,
+        discr: &mir::Operand<'tcx>,
+    ) -> Option<Self::SwitchIntData> {
+        self.inner.get_switch_int_data(block, discr)
+    }
+
+    fn apply_switch_int_edge_effect(
+        &mut self,
+        data: &mut Self::SwitchIntData,
+        state: &mut Self::Domain,
+        edge: SwitchIntTarget,
+    ) {
+        self.translated(state, |inner, state| inner.apply_switch_int_edge_effect(data, state, edge))
+    }
+
+    fn treat_false_edges(&self) -> FalseEdgeSemantics {
+        self.inner.treat_false_edges()
+    }
+
+    fn join_at(
+        &self,
+        source: BasicBlock,
+        bb: BasicBlock,
+        into: &mut Self::Domain,
+        from: &Self::Domain,
+    ) -> bool {
+        let mut inner_into = (self.from)(into);
+        let changed = self.inner.join_at(source, bb, &mut inner_into, &(self.from)(from));
+        if changed {
+            *into = (self.to)(&inner_into);
+        }
+        changed
+    }
+
+    fn merge_call_return_state(
+        &mut self,
+        edge_state: &Self::Domain,
+        return_state: &mut Self::Domain,
+    ) {
+        let edge_state = (self.from)(edge_state);
+        self.translated(return_state, |inner, return_state| {
+            inner.merge_call_return_state(&edge_state, return_state)
+        })
+    }
+
+    fn can_skip_block_given_predecessors(
+        &self,
+        block: BasicBlock,
+        body: &mir::Body<'tcx>,
+        entry: &Self::Domain,
+    ) -> bool {
+        self.inner.can_skip_block_given_predecessors(block, body, &(self.from)(entry))
+    }
+
+    fn explain_state(&self, state: &Self::Domain, body: &mir::Body<'tcx>) -> Option<String> {
+        self.inner.explain_state(&(self.from)(state), body)
+    }
+
+    fn is_edge_feasible(
+        &self,
+        state: &Self::Domain,
+        terminator: &Terminator<'tcx>,
+        source: BasicBlock,
+        target: BasicBlock,
+    ) -> bool {
+        self.inner.is_edge_feasible(&(self.from)(state), terminator, source, target)
+    }
+
+    fn pre_iterate(&mut self, body: &mir::Body<'tcx>) {
+        self.inner.pre_iterate(body)
+    }
+}
+
+/// The analysis of the `Results` returned by `Results::to_chunked`.
+pub type ChunkedRepr<A, T> = MapDomain<
+    A,
+    ChunkedBitSet<T>,
+    fn(&BitSet<T>) -> ChunkedBitSet<T>,
+    fn(&ChunkedBitSet<T>) -> BitSet<T>,
+>;
+
+/// The analysis of the `Results` returned by `Results::to_dense`.
+pub type DenseRepr<A, T> =
+    MapDomain<A, BitSet<T>, fn(&ChunkedBitSet<T>) -> BitSet<T>, fn(&BitSet<T>) -> ChunkedBitSet<T>>;
+
+fn to_chunked<T: Idx>(set: &BitSet<T>) -> ChunkedBitSet<T> {
+    let mut ret = ChunkedBitSet::new_empty(set.domain_size());
+    for elem in set.iter() {
+        ret.insert(elem);
+    }
+    ret
+}
+
+fn to_dense<T: Idx>(set: &ChunkedBitSet<T>) -> BitSet<T> {
+    let mut ret = BitSet::new_empty(set.domain_size());
+    ret.union(set);
+    ret
+}
+
+impl<'tcx, A, T> Results<'tcx, A>
+where
+    A: Analysis<'tcx, Domain = BitSet<T>>,
+    T: Idx,
+{
+    /// Converts every entry set to a `ChunkedBitSet`, without solving the analysis again.
+    ///
+    /// The analysis is wrapped in a `MapDomain`, so the returned `Results` can still be inspected
+    /// with a cursor or visited. Each transfer function applied to them converts the state back and
+    /// forth, however, so this is best suited to consumers that mostly read entry sets.
+    pub fn to_chunked(self) -> Results<'tcx, ChunkedRepr<A, T>> {
+        let Results { analysis, entry_sets, .. } = self;
+        Results::new(MapDomain::new(analysis, to_chunked, to_dense), entry_sets.map(to_chunked))
+    }
+}
+
+impl<'tcx, A, T> Results<'tcx, A>
+where
+    A: Analysis<'tcx, Domain = ChunkedBitSet<T>>,
+    T: Idx,
+{
+    /// Converts every entry set to a `BitSet`, without solving the analysis again.
+    ///
+    /// See `to_chunked` for how the returned `Results` apply transfer functions.
+    pub fn to_dense(self) -> Results<'tcx, DenseRepr<A, T>> {
+        let Results { analysis, entry_sets, .. } = self;
+        Results::new(MapDomain::new(analysis, to_dense, to_chunked), entry_sets.map(to_dense))
+    }
+}
+
+/// Wraps a gen/kill analysis over a `BitSet<I>` so that its elements are renumbered to `J`, e.g.
+/// to restrict an analysis over all locals to a dense numbering of the relevant ones.
+///
+/// Unlike `MapDomain`, this translates individual gen and kill operations through an `IndexVec`,
+/// and the result is again a `GenKillAnalysis` whose block transfer functions can be cached.
+/// Elements of `I` that have no counterpart in `J` are dropped: gens and kills of them are ignored,
+/// and they are never in the state passed to the `terminator_effect` of the inner analysis. The
+/// results are therefore only exact if those elements never affect the others.
+pub struct RenumberedBitsetAnalysis<A, I: Idx, J: Idx> {
+    inner: A,
+    to_new: IndexVec<I, Option<J>>,
+    to_old: IndexVec<J, I>,
+}
+
+impl<A, I: Idx, J: Idx> RenumberedBitsetAnalysis<A, I, J> {
+    /// Creates an analysis whose element `j` is the element `to_old[j]` of `inner`.
+    ///
+    /// Panics if an element of `inner` appears more than once in `to_old`.
+    pub fn new<'tcx>(inner: A, body: &mir::Body<'tcx>, to_old: IndexVec<J, I>) -> Self
+    where
+        A: GenKillAnalysis<'tcx, Idx = I>,
+    {
+        let mut to_new = IndexVec::from_elem_n(None, inner.domain_size(body));
+        for (new, &old) in to_old.iter_enumerated() {
+            assert!(to_new[old].replace(new).is_none(), "{old:?} is renumbered more than once");
+        }
+
+        RenumberedBitsetAnalysis { inner, to_new, to_old }
+    }
+
+    /// Returns the element of the inner analysis that `elem` corresponds to.
+    pub fn old_index(&self, elem: J) -> I {
+        self.to_old[elem]
+    }
+
+    /// Returns the element that `elem` of the inner analysis was renumbered to, if any.
+    pub fn new_index(&self, elem: I) -> Option<J> {
+        self.to_new[elem]
+    }
+
+    pub fn into_inner(self) -> A {
+        self.inner
+    }
+
+    fn to_inner_state(&self, state: &BitSet<J>) -> BitSet<I> {
+        let mut inner_state = BitSet::new_empty(self.to_new.len());
+        for elem in state.iter() {
+            inner_state.insert(self.to_old[elem]);
+        }
+        inner_state
+    }
+
+    fn from_inner_state(&self, state: &mut BitSet<J>, inner_state: &BitSet<I>) {
+        state.clear();
+        for elem in inner_state.iter().filter_map(|elem| self.to_new[elem]) {
+            state.insert(elem);
+        }
+    }
+}
+
+impl<'tcx, A, I, J> AnalysisDomain<'tcx> for RenumberedBitsetAnalysis<A, I, J>
+where
+    A: GenKillAnalysis<'tcx, Idx = I, Domain = BitSet<I>>,
+    I: Idx,
+    J: Idx,
+{
+    type Domain = BitSet<J>;
+    type Direction = A::Direction;
+    type SwitchIntData = A::SwitchIntData;
+
+    const NAME: &'static str = A::NAME;
+    const REQUIRES_CLEANUP_BLOCKS: bool = A::REQUIRES_CLEANUP_BLOCKS;
+    const MERGES_CALL_RETURN_STATE: bool = A::MERGES_CALL_RETURN_STATE;
+
+    fn bottom_value(&self, _: TyCtxt<'tcx>, _: &mir::Body<'tcx>) -> Self::Domain {
+        BitSet::new_empty(self.to_old.len())
+    }
+
+    fn initialize_start_block(&self, body: &mir::Body<'tcx>, state: &mut Self::Domain) {
+        let mut inner_state = self.to_inner_state(state);
+        self.inner.initialize_start_block(body, &mut inner_state);
+        self.from_inner_state(state, &inner_state);
+    }
+}
+
+impl<'tcx, A, I, J> GenKillAnalysis<'tcx> for RenumberedBitsetAnalysis<A, I, J>
+where
+    A: GenKillAnalysis<'tcx, Idx = I, Domain = BitSet<I>>,
+    I: Idx,
+    J: Idx,
+{
+    type Idx = J;
+
+    fn domain_size(&self, _: &mir::Body<'tcx>) -> usize {
+        self.to_old.len()
+    }
+
+    fn domain_element_name(&self, idx: Self::Idx, body: &mir::Body<'tcx>) -> String {
+        self.inner.domain_element_name(self.to_old[idx], body)
+    }
+
+    fn statement_effect(
+        &mut self,
+        trans: &mut impl GenKill<Self::Idx>,
+        statement: &mir::Statement<'tcx>,
+        location: Location,
+    ) {
+        let mut trans = RenumberedTrans { trans, to_new: &self.to_new };
+        self.inner.statement_effect(&mut trans, statement, location);
+    }
+
+    fn before_statement_effect(
+        &mut self,
+        trans: &mut impl GenKill<Self::Idx>,
+        statement: &mir::Statement<'tcx>,
+        location: Location,
+    ) {
+        let mut trans = RenumberedTrans { trans, to_new: &self.to_new };
+        self.inner.before_statement_effect(&mut trans, statement, location);
+    }
+
+    fn batch_statement_effect(
+        &mut self,
+        trans: &mut impl GenKill<Self::Idx>,
+        block: BasicBlock,
+        block_data: &mir::BasicBlockData<'tcx>,
+    ) {
+        let mut trans = RenumberedTrans { trans, to_new: &self.to_new };
+        self.inner.batch_statement_effect(&mut trans, block, block_data);
+    }
+
+    fn terminator_effect<'mir>(
+        &mut self,
+        trans: &mut Self::Domain,
+        terminator: &'mir Terminator<'tcx>,
+        location: Location,
+    ) -> TerminatorEdges<'mir, 'tcx> {
+        let mut inner_state = self.to_inner_state(trans);
+        let edges = self.inner.terminator_effect(&mut inner_state, terminator, location);
+        self.from_inner_state(trans, &inner_state);
+        edges
+    }
+
+    fn before_terminator_effect(
+        &mut self,
+        trans: &mut impl GenKill<Self::Idx>,
+        terminator: &Terminator<'tcx>,
+        location: Location,
+    ) {
+        let mut trans = RenumberedTrans { trans, to_new: &self.to_new };
+        self.inner.before_terminator_effect(&mut trans, terminator, location);
+    }
+
+    fn terminator_unwind_effect(
+        &mut self,
+        trans: &mut impl GenKill<Self::Idx>,
+        terminator: &Terminator<'tcx>,
+        block: BasicBlock,
+    ) {
+        let mut trans = RenumberedTrans { trans, to_new: &self.to_new };
+        self.inner.terminator_unwind_effect(&mut trans, terminator, block);
+    }
+
+    fn call_return_effect(
+        &mut self,
+        trans: &mut impl GenKill<Self::Idx>,
+        block: BasicBlock,
+        return_places: CallReturnPlaces<'_, 'tcx>,
+    ) {
+        let mut trans = RenumberedTrans { trans, to_new: &self.to_new };
+        self.inner.call_return_effect(&mut trans, block, return_places);
+    }
+
+    fn yield_resume_effect(
+        &mut self,
+        trans: &mut impl GenKill<Self::Idx>,
+        resume_block: BasicBlock,
+        resume_place: mir::Place<'tcx>,
+    ) {
+        let mut trans = RenumberedTrans { trans, to_new: &self.to_new };
+        self.inner.yield_resume_effect(&mut trans, resume_block, resume_place);
+    }
+
+    fn switch_int_data(
+        &mut self,
+        block: BasicBlock,
+        discr: &mir::Operand<'tcx>,
+    ) -> Option<Self::SwitchIntData> {
+        self.inner.switch_int_data(block, discr)
+    }
+
+    fn switch_int_edge_effect(
+        &mut self,
+        data: &mut Self::SwitchIntData,
+        trans: &mut impl GenKill<Self::Idx>,
+        edge: SwitchIntTarget,
+    ) {
+        let mut trans = RenumberedTrans { trans, to_new: &self.to_new };
+        self.inner.switch_int_edge_effect(data, &mut trans, edge);
+    }
+
+    fn pre_iterate(&mut self, body: &mir::Body<'tcx>) {
+        GenKillAnalysis::pre_iterate(&mut self.inner, body)
+    }
+}
+
+/// Forwards the gens and kills of the inner analysis of a `RenumberedBitsetAnalysis` to `trans`.
+struct RenumberedTrans<'a, T, I: Idx, J: Idx> {
+    trans: &'a mut T,
+    to_new: &'a IndexVec<I, Option<J>>,
+}
+
+impl<T, I, J> GenKill<I> for RenumberedTrans<'_, T, I, J>
+where
+    T: GenKill<J>,
+    I: Idx,
+    J: Idx,
+{
+    fn gen(&mut self, elem: I) {
+        if let Some(elem) = self.to_new[elem] {
+            self.trans.gen(elem);
+        }
+    }
+
+    fn kill(&mut self, elem: I) {
+        if let Some(elem) = self.to_new[elem] {
+            self.trans.kill(elem);
+        }
+    }
+}
@@ -37,26 +37,60 @@
 use rustc_middle::mir::{self, BasicBlock, CallReturnPlaces, Location, TerminatorEdges};
 use rustc_middle::ty::TyCtxt;
 
+use self::fmt::DebugWithElementNames;
+
+pub mod adhoc;
+mod bidirectional;
+mod cache;
+mod compressed;
+mod counting;
 mod cursor;
+mod dense;
+mod dependency;
 mod direction;
 mod engine;
+mod erased;
 pub mod fmt;
 pub mod graphviz;
 pub mod lattice;
+mod mapped;
+mod trace;
 mod visitor;
 
+pub use self::bidirectional::{BidirectionalEngine, CoupledAnalysis};
+pub use self::cache::DataflowCache;
+pub use self::compressed::CompressedResults;
+pub use self::counting::{CountingAnalysis, TransferCounts};
 pub use self::cursor::{AnalysisResults, ResultsClonedCursor, ResultsCursor, ResultsRefCursor};
-pub use self::direction::{Backward, Direction, Forward};
-pub use self::engine::{Engine, EntrySets, Results, ResultsCloned};
+pub use self::dense::DenseBitSetResults;
+pub use self::dependency::Dependency;
+pub use self::direction::{Backward, Direction, DirectionKind, Forward};
+pub(crate) use self::engine::parse_rustc_mir_attrs;
+pub use self::engine::{
+    append_dataflow_to_mir_dump, ConvergenceInfo, Engine, EntrySets, Results, ResultsCloned,
+};
+pub use self::erased::{visit_results_dyn, ErasedAnalysisRunner, ErasedResults};
 pub use self::lattice::{JoinSemiLattice, MaybeReachable};
-pub use self::visitor::{visit_results, ResultsVisitable, ResultsVisitor};
+pub use self::mapped::{ChunkedRepr, DenseRepr, MapDomain, RenumberedBitsetAnalysis};
+pub use self::trace::{TraceAnalysis, TraceGenKillAnalysis};
+pub use self::visitor::{visit_results, Chain, ResultsVisitable, ResultsVisitor};
 
-/// Analysis domains are all bitsets of various kinds. This trait holds
-/// operations needed by all of them.
+/// Analysis domains are all bitsets of various kinds. This trait holds the
+/// queries needed by all of them. Updating a domain with a gen/kill transfer
+/// function goes through `GenKill` instead, so other sets can implement this too.
 pub trait BitSetExt<T> {
     fn contains(&self, elem: T) -> bool;
-    fn union(&mut self, other: &HybridBitSet<T>);
-    fn subtract(&mut self, other: &HybridBitSet<T>);
+    fn is_empty(&self) -> bool;
+
+    /// Returns the number of elements in the set.
+    ///
+    /// Implementations should not iterate over the elements: `BitSet` counts the set bits of each
+    /// word, and `ChunkedBitSet` sums the counts it keeps for each chunk.
+    fn count(&self) -> usize;
+
+    /// Returns the number of elements in the domain of the set, or `None` if the set doesn't have
+    /// a domain of its own (e.g. `MaybeReachable::Unreachable`).
+    fn domain_size(&self) -> Option<usize>;
 }
 
 impl<T: Idx> BitSetExt<T> for BitSet<T> {
@@ -64,12 +98,16 @@ fn contains(&self, elem: T) -> bool {
         self.contains(elem)
     }
 
-    fn union(&mut self, other: &HybridBitSet<T>) {
-        self.union(other);
+    fn is_empty(&self) -> bool {
+        self.is_empty()
+    }
+
+    fn count(&self) -> usize {
+        self.count()
     }
 
-    fn subtract(&mut self, other: &HybridBitSet<T>) {
-        self.subtract(other);
+    fn domain_size(&self) -> Option<usize> {
+        Some(self.domain_size())
     }
 }
 
@@ -78,12 +116,16 @@ fn contains(&self, elem: T) -> bool {
         self.contains(elem)
     }
 
-    fn union(&mut self, other: &HybridBitSet<T>) {
-        self.union(other);
+    fn is_empty(&self) -> bool {
+        self.count() == 0
     }
 
-    fn subtract(&mut self, other: &HybridBitSet<T>) {
-        self.subtract(other);
+    fn count(&self) -> usize {
+        self.count()
+    }
+
+    fn domain_size(&self) -> Option<usize> {
+        Some(self.domain_size())
     }
 }
 
@@ -98,14 +140,34 @@ pub trait AnalysisDomain<'tcx> {
     /// The direction of this analysis. Either `Forward` or `Backward`.
     type Direction: Direction = Forward;
 
+    /// The data that `Analysis::get_switch_int_data` computes once for a `SwitchInt` terminator
+    /// and then hands to the effect on each of its edges.
+    type SwitchIntData = !;
+
     /// A descriptive name for this analysis. Used only for debugging.
     ///
     /// This name should be brief and contain no spaces, periods or other characters that are not
     /// suitable as part of a filename.
     const NAME: &'static str;
 
+    /// Whether this analysis needs dataflow state for cleanup (unwinding) blocks.
+    ///
+    /// If this is `false`, the engine never propagates state into cleanup blocks, so their entry
+    /// sets remain at `bottom_value`.
+    const REQUIRES_CLEANUP_BLOCKS: bool = true;
+
+    /// Whether the engine should call `Analysis::merge_call_return_state` on the return edge of
+    /// each `Call` terminator.
+    ///
+    /// This requires cloning the dataflow state on every such edge, so it is opt-in.
+    const MERGES_CALL_RETURN_STATE: bool = false;
+
     /// Returns the initial value of the dataflow state upon entry to each basic block.
-    fn bottom_value(&self, body: &mir::Body<'tcx>) -> Self::Domain;
+    ///
+    /// `tcx` can be used to intern parts of the domain or to allocate them in an arena. This is
+    /// only called where a `TyCtxt` is available, e.g. by the `Engine`. Code without one, such as
+    /// `ResultsCursor`, reuses the bottom value kept by the `EntrySets` instead.
+    fn bottom_value(&self, tcx: TyCtxt<'tcx>, body: &mir::Body<'tcx>) -> Self::Domain;
 
     /// Mutates the initial value of the dataflow state upon entry to the `START_BLOCK`.
     ///
@@ -135,6 +197,11 @@ pub trait AnalysisDomain<'tcx> {
 /// to the same point in the program at different points in time. The dataflow state at a given
 /// point in the program may or may not be greater than the state at any preceding point.
 pub trait Analysis<'tcx>: AnalysisDomain<'tcx> {
+    /// Data derived from the blocks of a body that the transfer functions would otherwise
+    /// recompute each time the engine visits a block, e.g. the locals written by each block. See
+    /// `prepare_block_cache`.
+    type BlockCache: Default = ();
+
     /// Updates the current dataflow state with the effect of evaluating a statement.
     fn apply_statement_effect(
         &mut self,
@@ -157,6 +224,23 @@ fn apply_before_statement_effect(
     ) {
     }
 
+    /// Updates the current dataflow state with the effects of every statement of `block_data`, in
+    /// the order given by `Self::Direction`, and returns `true`. Returns `false` without changing
+    /// `state` to have the engine apply the statements one at a time.
+    ///
+    /// The engine calls this while iterating to fixpoint, for blocks that it has no cached
+    /// transfer function for. Cursors and visitors still apply effects one statement at a time,
+    /// so the two must agree on the state at the end of the block. Gen/kill analyses implement
+    /// this with `GenKillAnalysis::batch_statement_effect`.
+    fn apply_batch_statement_effect(
+        &mut self,
+        _state: &mut Self::Domain,
+        _block: BasicBlock,
+        _block_data: &mir::BasicBlockData<'tcx>,
+    ) -> bool {
+        false
+    }
+
     /// Updates the current dataflow state with the effect of evaluating a terminator.
     ///
     /// The effect of a successful return from a `Call` terminator should **not** be accounted for
@@ -186,6 +270,20 @@ fn apply_before_terminator_effect(
 
     /* Edge-specific effects */
 
+    /// Updates the current dataflow state with an effect that occurs only along the unwind edge
+    /// of a terminator.
+    ///
+    /// The primary effect of the terminator (`apply_terminator_effect`) has already been applied
+    /// to `state`, so this only needs to account for the *difference* between the normal and the
+    /// unwind successors. `block` is the basic block that `terminator` belongs to.
+    fn apply_terminator_unwind_effect(
+        &mut self,
+        _state: &mut Self::Domain,
+        _terminator: &mir::Terminator<'tcx>,
+        _block: BasicBlock,
+    ) {
+    }
+
     /// Updates the current dataflow state with the effect of a successful return from a `Call`
     /// terminator.
     ///
@@ -198,25 +296,204 @@ fn apply_call_return_effect(
         return_places: CallReturnPlaces<'_, 'tcx>,
     );
 
-    /// Updates the current dataflow state with the effect of taking a particular branch in a
-    /// `SwitchInt` terminator.
+    /// Updates the current dataflow state with the effect of resuming a coroutine suspended at
+    /// a `Yield` terminator.
     ///
-    /// Unlike the other edge-specific effects, which are allowed to mutate `Self::Domain`
-    /// directly, overriders of this method must pass a callback to
-    /// `SwitchIntEdgeEffects::apply`. The callback will be run once for each outgoing edge and
-    /// will have access to the dataflow state that will be propagated along that edge.
+    /// This is applied only along the resume edge, never along the coroutine drop edge.
+    /// `resume_block` is the block that the coroutine resumes in, i.e. the `resume` target of the
+    /// `Yield`, and `resume_place` is the place that receives the resume argument.
     ///
-    /// This interface is somewhat more complex than the other visitor-like "effect" methods.
-    /// However, it is both more ergonomic—callers don't need to recompute or cache information
-    /// about a given `SwitchInt` terminator for each one of its edges—and more efficient—the
-    /// engine doesn't need to clone the exit state for a block unless
-    /// `SwitchIntEdgeEffects::apply` is actually called.
-    fn apply_switch_int_edge_effects(
+    /// By default, this defers to `apply_call_return_effect`, passing it `resume_block` and
+    /// `CallReturnPlaces::Yield`. Analyses that look at the `block` of `apply_call_return_effect`
+    /// must override this.
+    fn apply_yield_resume_effect(
+        &mut self,
+        state: &mut Self::Domain,
+        resume_block: BasicBlock,
+        resume_place: mir::Place<'tcx>,
+    ) {
+        self.apply_call_return_effect(state, resume_block, CallReturnPlaces::Yield(resume_place));
+    }
+
+    /// Computes the data that the edge-specific effects of the `SwitchInt` terminator of `block`
+    /// need, or returns `None` if this analysis has no such effects for it.
+    ///
+    /// If this returns `Some`, the engine calls `apply_switch_int_edge_effect` once for each
+    /// outgoing edge, with a copy of the exit state of `block`, and propagates that state along the
+    /// edge right away. Forward analyses see the edges in the order of `SwitchTargets::iter`,
+    /// followed by the "otherwise" edge. Computing the data once per terminator saves overriders
+    /// from recomputing information about it for each of its edges, and the engine doesn't need to
+    /// clone the exit state of a block unless this returns `Some`.
+    fn get_switch_int_data(
         &mut self,
         _block: BasicBlock,
         _discr: &mir::Operand<'tcx>,
-        _apply_edge_effects: &mut impl SwitchIntEdgeEffects<Self::Domain>,
+    ) -> Option<Self::SwitchIntData> {
+        None
+    }
+
+    /// Updates the current dataflow state with the effect of taking a particular branch in a
+    /// `SwitchInt` terminator. Only called if `get_switch_int_data` returned `Some`.
+    fn apply_switch_int_edge_effect(
+        &mut self,
+        _data: &mut Self::SwitchIntData,
+        _state: &mut Self::Domain,
+        _edge: SwitchIntTarget,
+    ) {
+        unreachable!();
+    }
+
+    /// Returns `true` if the engine may propagate `entry`, the entry set of `block`, to the
+    /// successors of `block` without applying its transfer function, e.g. because none of its
+    /// statements affect any of the facts in `entry`.
+    ///
+    /// This is only sound if applying the statement and terminator effects of `block` to `entry`
+    /// would leave it unchanged. Edge effects are still applied. Unlike the transfer functions,
+    /// this is called with the current entry set each time the engine visits `block`, so it can
+    /// depend on the facts that reach it.
+    fn can_skip_block_given_predecessors(
+        &self,
+        _block: BasicBlock,
+        _body: &mir::Body<'tcx>,
+        _entry: &Self::Domain,
+    ) -> bool {
+        false
+    }
+
+    /// Determines whether the engine propagates state along the imaginary edges of `FalseEdge`
+    /// and `FalseUnwind` terminators.
+    ///
+    /// Those edges exist only to keep borrowck conservative, so analyses whose results feed into
+    /// borrowck must keep the default of `FalseEdgeSemantics::Real`. `CleanupPostBorrowck` removes
+    /// these terminators, so this has no effect on analyses that run after it.
+    fn treat_false_edges(&self) -> FalseEdgeSemantics {
+        FalseEdgeSemantics::Real
+    }
+
+    /// Returns whether the edge from `source` to `target` is feasible, given the `state` that would
+    /// be propagated along it. If not, `state` is not joined into the entry set of `target`.
+    ///
+    /// Like in `join_at`, the edge is given in dataflow order.
+    /// `terminator` is the one the edge leaves in the CFG: that of `source` for forward analyses,
+    /// and that of `target` for backward ones. This complements the edges returned by
+    /// `apply_terminator_effect`, which cannot express an arbitrary subset of the targets of a
+    /// `SwitchInt`: an analysis that learns that some successors of a block are unreachable, e.g.
+    /// because its state pins down the value being switched on, can prune them individually here.
+    /// For forward analyses, pruned edges are not visited by
+    /// `ResultsVisitor::visit_terminator_edge` either.
+    fn is_edge_feasible(
+        &self,
+        _state: &Self::Domain,
+        _terminator: &mir::Terminator<'tcx>,
+        _source: BasicBlock,
+        _target: BasicBlock,
+    ) -> bool {
+        true
+    }
+
+    /// Joins `from`, the state propagated along the edge from `source` into `bb`, into `into`, the
+    /// entry set of `bb`, and returns whether it changed. The engine calls this for every edge it
+    /// propagates a state along. By default, this is `JoinSemiLattice::join`.
+    ///
+    /// The edge is given in dataflow order, so for backward analyses `source` is the CFG successor
+    /// and `bb` the CFG predecessor. Domains that record where their facts came from can use this
+    /// to capture the edge. It also allows a limited form of path sensitivity: at particular merge
+    /// points, an analysis may join differently, e.g. to keep the states from the arms of a
+    /// specific switch apart in a domain that can represent them. The entry set must still only
+    /// ever grow, or the engine may not terminate.
+    fn join_at(
+        &self,
+        _source: BasicBlock,
+        _bb: BasicBlock,
+        into: &mut Self::Domain,
+        from: &Self::Domain,
+    ) -> bool {
+        into.join(from)
+    }
+
+    /// Combines the state on the return edge of a `Call` terminator, `edge_state`, with the state
+    /// after `apply_call_return_effect` was applied to it, `return_state`. Only called if
+    /// `MERGES_CALL_RETURN_STATE` is `true`.
+    ///
+    /// `edge_state` is the state after the primary effect of the `Call`, not the state before the
+    /// call. An analysis whose return effect conservatively clobbers everything the callee might
+    /// have changed can use this to put back the facts that survive the call, e.g. those about
+    /// locals whose address was never taken. The return effect could do this itself only by
+    /// cloning the state before clobbering it, while backward analyses already have `edge_state`
+    /// at hand. By default, `return_state` is left unchanged.
+    fn merge_call_return_state(
+        &mut self,
+        _edge_state: &Self::Domain,
+        _return_state: &mut Self::Domain,
+    ) {
+    }
+
+    /// Returns whether the element with index `bit` is in `state`, or `None` if the domain of this
+    /// analysis is not a set of indices or `bit` is out of range.
+    ///
+    /// This is used to track a single element through the graphviz output (see
+    /// `borrowck_graphviz_track_bit`). Gen/kill analyses get an implementation for free.
+    fn contains_bit(
+        &self,
+        _body: &mir::Body<'tcx>,
+        _state: &Self::Domain,
+        _bit: usize,
+    ) -> Option<bool> {
+        None
+    }
+
+    /// Returns a succinct, human-readable description of `state`, for use in diagnostics.
+    ///
+    /// By default, this returns `None`, and `fmt::explain_state` falls back to the
+    /// `DebugWithContext` formatting of `state`, which lists every fact it contains. Analyses can
+    /// override this to explain the facts that matter to a user in prose instead, e.g. "`x` may be
+    /// uninitialized". Gen/kill analyses get an implementation for free.
+    fn explain_state(&self, _state: &Self::Domain, _body: &mir::Body<'tcx>) -> Option<String> {
+        None
+    }
+
+    /// Called once when an `Engine` is created for `body`, before any transfer function runs,
+    /// e.g. to build lookup tables for `body` that the transfer functions consult.
+    ///
+    /// Solving the same `Engine` again, e.g. with `Engine::solve_in_place`, does not call this
+    /// again.
+    fn pre_iterate(&mut self, _body: &mir::Body<'tcx>) {}
+
+    /// Computes the `BlockCache` for `body`. The engine calls this each time before iterating to
+    /// fixpoint, after `pre_iterate`, and passes the result to
+    /// `apply_statement_effect_cached` and `apply_terminator_effect_cached`.
+    ///
+    /// This is the counterpart for arbitrary analyses of the block transfer functions cached by
+    /// `Engine::new_gen_kill`.
+    fn prepare_block_cache(&mut self, _body: &mir::Body<'tcx>) -> Self::BlockCache {
+        Self::BlockCache::default()
+    }
+
+    /// Like `apply_statement_effect`, but with the `BlockCache` of the body.
+    ///
+    /// The engine calls this while iterating to fixpoint, but cursors and visitors call
+    /// `apply_statement_effect`, so the two must have the same effect. By default, this ignores
+    /// the cache.
+    fn apply_statement_effect_cached(
+        &mut self,
+        _cache: &Self::BlockCache,
+        state: &mut Self::Domain,
+        statement: &mir::Statement<'tcx>,
+        location: Location,
     ) {
+        self.apply_statement_effect(state, statement, location)
+    }
+
+    /// Like `apply_terminator_effect`, but with the `BlockCache` of the body. See
+    /// `apply_statement_effect_cached`.
+    fn apply_terminator_effect_cached<'mir>(
+        &mut self,
+        _cache: &Self::BlockCache,
+        state: &mut Self::Domain,
+        terminator: &'mir mir::Terminator<'tcx>,
+        location: Location,
+    ) -> TerminatorEdges<'mir, 'tcx> {
+        self.apply_terminator_effect(state, terminator, location)
     }
 
     /* Extension methods */
@@ -274,6 +551,26 @@ pub trait GenKillAnalysis<'tcx>: Analysis<'tcx> {
 
     fn domain_size(&self, body: &mir::Body<'tcx>) -> usize;
 
+    /// Returns a human-readable name for the domain element `idx`, e.g. the name of the local it
+    /// corresponds to. Used only for debugging.
+    ///
+    /// This is how graphviz output and `Analysis::explain_state` print the state. By default, this
+    /// is the `Debug` formatting of `idx`.
+    fn domain_element_name(&self, idx: Self::Idx, _body: &mir::Body<'tcx>) -> String {
+        format!("{idx:?}")
+    }
+
+    /// See `Analysis::explain_state`.
+    ///
+    /// By default, this lists the elements of `state` by their `domain_element_name`.
+    fn explain_gen_kill_state(&self, state: &Self::Domain, body: &mir::Body<'tcx>) -> String
+    where
+        Self: Sized,
+        Self::Domain: BitSetExt<Self::Idx>,
+    {
+        format!("{:?}", DebugWithElementNames { state, analysis: self, body })
+    }
+
     /// See `Analysis::apply_statement_effect`.
     fn statement_effect(
         &mut self,
@@ -291,6 +588,25 @@ fn before_statement_effect(
     ) {
     }
 
+    /// Applies the effects of every statement of `block_data` to `trans`, in the order given by
+    /// `Self::Direction`.
+    ///
+    /// This is called instead of `before_statement_effect` and `statement_effect` when computing
+    /// the cached transfer function of a block, and by `Analysis::apply_batch_statement_effect`
+    /// otherwise, so an analysis that needs to observe all the statements of a block before
+    /// deciding on their effects can override it. Cursors and visitors still apply effects one
+    /// statement at a time, so the two must agree on the state at the end of the block.
+    fn batch_statement_effect(
+        &mut self,
+        trans: &mut impl GenKill<Self::Idx>,
+        block: BasicBlock,
+        block_data: &mir::BasicBlockData<'tcx>,
+    ) where
+        Self: Sized,
+    {
+        Self::Direction::gen_kill_statement_effects_in_block(self, trans, block, block_data);
+    }
+
     /// See `Analysis::apply_terminator_effect`.
     fn terminator_effect<'mir>(
         &mut self,
@@ -310,6 +626,15 @@ fn before_terminator_effect(
 
     /* Edge-specific effects */
 
+    /// See `Analysis::apply_terminator_unwind_effect`.
+    fn terminator_unwind_effect(
+        &mut self,
+        _trans: &mut impl GenKill<Self::Idx>,
+        _terminator: &mir::Terminator<'tcx>,
+        _block: BasicBlock,
+    ) {
+    }
+
     /// See `Analysis::apply_call_return_effect`.
     fn call_return_effect(
         &mut self,
@@ -318,14 +643,40 @@ fn call_return_effect(
         return_places: CallReturnPlaces<'_, 'tcx>,
     );
 
-    /// See `Analysis::apply_switch_int_edge_effects`.
-    fn switch_int_edge_effects<G: GenKill<Self::Idx>>(
+    /// See `Analysis::apply_yield_resume_effect`.
+    fn yield_resume_effect(
+        &mut self,
+        trans: &mut impl GenKill<Self::Idx>,
+        resume_block: BasicBlock,
+        resume_place: mir::Place<'tcx>,
+    ) {
+        self.call_return_effect(trans, resume_block, CallReturnPlaces::Yield(resume_place));
+    }
+
+    /// See `Analysis::get_switch_int_data`.
+    fn switch_int_data(
         &mut self,
         _block: BasicBlock,
         _discr: &mir::Operand<'tcx>,
-        _edge_effects: &mut impl SwitchIntEdgeEffects<G>,
+    ) -> Option<Self::SwitchIntData> {
+        None
+    }
+
+    /// See `Analysis::apply_switch_int_edge_effect`.
+    fn switch_int_edge_effect(
+        &mut self,
+        _data: &mut Self::SwitchIntData,
+        _trans: &mut impl GenKill<Self::Idx>,
+        _edge: SwitchIntTarget,
     ) {
+        unreachable!();
     }
+
+    /// See `Analysis::pre_iterate`.
+    ///
+    /// `Engine::new_gen_kill` calls this before it caches the block transfer functions, so the
+    /// statement effects may consult what it sets up.
+    fn pre_iterate(&mut self, _body: &mir::Body<'tcx>) {}
 }
 
 impl<'tcx, A> Analysis<'tcx> for A
@@ -351,6 +702,16 @@ fn apply_before_statement_effect(
         self.before_statement_effect(state, statement, location);
     }
 
+    fn apply_batch_statement_effect(
+        &mut self,
+        state: &mut A::Domain,
+        block: BasicBlock,
+        block_data: &mir::BasicBlockData<'tcx>,
+    ) -> bool {
+        self.batch_statement_effect(state, block, block_data);
+        true
+    }
+
     fn apply_terminator_effect<'mir>(
         &mut self,
         state: &mut A::Domain,
@@ -371,6 +732,15 @@ fn apply_before_terminator_effect(
 
     /* Edge-specific effects */
 
+    fn apply_terminator_unwind_effect(
+        &mut self,
+        state: &mut A::Domain,
+        terminator: &mir::Terminator<'tcx>,
+        block: BasicBlock,
+    ) {
+        self.terminator_unwind_effect(state, terminator, block);
+    }
+
     fn apply_call_return_effect(
         &mut self,
         state: &mut A::Domain,
@@ -380,13 +750,42 @@ fn apply_call_return_effect(
         self.call_return_effect(state, block, return_places);
     }
 
-    fn apply_switch_int_edge_effects(
+    fn apply_yield_resume_effect(
+        &mut self,
+        state: &mut A::Domain,
+        resume_block: BasicBlock,
+        resume_place: mir::Place<'tcx>,
+    ) {
+        self.yield_resume_effect(state, resume_block, resume_place);
+    }
+
+    fn get_switch_int_data(
         &mut self,
         block: BasicBlock,
         discr: &mir::Operand<'tcx>,
-        edge_effects: &mut impl SwitchIntEdgeEffects<A::Domain>,
+    ) -> Option<A::SwitchIntData> {
+        self.switch_int_data(block, discr)
+    }
+
+    fn apply_switch_int_edge_effect(
+        &mut self,
+        data: &mut A::SwitchIntData,
+        state: &mut A::Domain,
+        edge: SwitchIntTarget,
     ) {
-        self.switch_int_edge_effects(block, discr, edge_effects);
+        self.switch_int_edge_effect(data, state, edge);
+    }
+
+    fn contains_bit(&self, body: &mir::Body<'tcx>, state: &A::Domain, bit: usize) -> Option<bool> {
+        (bit < self.domain_size(body)).then(|| state.contains(A::Idx::new(bit)))
+    }
+
+    fn explain_state(&self, state: &A::Domain, body: &mir::Body<'tcx>) -> Option<String> {
+        Some(self.explain_gen_kill_state(state, body))
+    }
+
+    fn pre_iterate(&mut self, body: &mir::Body<'tcx>) {
+        GenKillAnalysis::pre_iterate(self, body);
     }
 
     /* Extension methods */
@@ -432,6 +831,20 @@ fn kill_all(&mut self, elems: impl IntoIterator<Item = T>) {
             self.kill(elem);
         }
     }
+
+    /// Applies the cumulative transfer function `trans` to the state vector.
+    ///
+    /// By default, this calls `gen` and `kill` for each element of `trans`, so any domain that
+    /// implements `GenKill` can use the cached transfer functions of `Engine::new_gen_kill`. Bitsets
+    /// override this to update a word at a time.
+    fn apply_trans(&mut self, trans: &GenKillSet<T>)
+    where
+        T: Idx,
+    {
+        // `gen` and `kill` are disjoint, so the order in which they are applied doesn't matter.
+        self.gen_all(trans.gen.iter());
+        self.kill_all(trans.kill.iter());
+    }
 }
 
 /// Stores a transfer function for a gen/kill problem.
@@ -454,9 +867,8 @@ pub fn identity(universe: usize) -> Self {
         }
     }
 
-    pub fn apply(&self, state: &mut impl BitSetExt<T>) {
-        state.union(&self.gen);
-        state.subtract(&self.kill);
+    pub fn apply(&self, state: &mut impl GenKill<T>) {
+        state.apply_trans(self);
     }
 }
 
@@ -480,6 +892,11 @@ fn gen(&mut self, elem: T) {
     fn kill(&mut self, elem: T) {
         self.remove(elem);
     }
+
+    fn apply_trans(&mut self, trans: &GenKillSet<T>) {
+        self.union(&trans.gen);
+        self.subtract(&trans.kill);
+    }
 }
 
 impl<T: Idx> GenKill<T> for ChunkedBitSet<T> {
@@ -490,6 +907,11 @@ fn gen(&mut self, elem: T) {
     fn kill(&mut self, elem: T) {
         self.remove(elem);
     }
+
+    fn apply_trans(&mut self, trans: &GenKillSet<T>) {
+        self.union(&trans.gen);
+        self.subtract(&trans.kill);
+    }
 }
 
 impl<T, S: GenKill<T>> GenKill<T> for MaybeReachable<S> {
@@ -508,6 +930,17 @@ fn kill(&mut self, elem: T) {
             MaybeReachable::Reachable(set) => set.kill(elem),
         }
     }
+
+    fn apply_trans(&mut self, trans: &GenKillSet<T>)
+    where
+        T: Idx,
+    {
+        match self {
+            // If the state is not reachable, applying a transfer function does nothing.
+            MaybeReachable::Unreachable => {}
+            MaybeReachable::Reachable(set) => set.apply_trans(trans),
+        }
+    }
 }
 
 impl<T: Idx> GenKill<T> for lattice::Dual<BitSet<T>> {
@@ -518,6 +951,10 @@ fn gen(&mut self, elem: T) {
     fn kill(&mut self, elem: T) {
         self.0.remove(elem);
     }
+
+    fn apply_trans(&mut self, trans: &GenKillSet<T>) {
+        self.0.apply_trans(trans);
+    }
 }
 
 // NOTE: DO NOT CHANGE VARIANT ORDER. The derived `Ord` impls rely on the current order.
@@ -579,17 +1016,64 @@ fn precedes_in_backward_order(self, other: Self) -> bool {
     }
 }
 
+/// How the engine treats the imaginary edges of `FalseEdge` and `FalseUnwind` terminators.
+///
+/// See `Analysis::treat_false_edges`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FalseEdgeSemantics {
+    /// Imaginary edges are treated like any other edge.
+    Real,
+
+    /// Dataflow state is never propagated along imaginary edges.
+    Ignore,
+}
+
+/// Panics with the name of `block` and of `body` if `block` has no terminator yet.
+///
+/// `Engine::new` checks every block of a body up front. Cursors and visitors can be created from
+/// `Results` that were not computed by an `Engine`, so they check each block they enter.
+fn assert_has_terminator(body: &mir::Body<'_>, block: BasicBlock) {
+    if body[block].terminator.is_none() {
+        bug!("{block:?} of `{:?}` has no terminator", body.source.def_id());
+    }
+}
+
+/// Returns `true` if the edge from `terminator` to `target` only exists to make borrowck more
+/// conservative and cannot actually be taken.
+pub(crate) fn is_imaginary_edge(terminator: &mir::Terminator<'_>, target: BasicBlock) -> bool {
+    match terminator.kind {
+        mir::TerminatorKind::FalseEdge { real_target, imaginary_target } => {
+            imaginary_target == target && real_target != target
+        }
+        mir::TerminatorKind::FalseUnwind {
+            real_target,
+            unwind: mir::UnwindAction::Cleanup(unwind),
+        } => unwind == target && real_target != target,
+        _ => false,
+    }
+}
+
+/// Returns the target of each edge in `edges`, in the order in which the engine propagates
+/// dataflow state along them.
+pub(crate) fn edge_targets<'mir>(
+    edges: TerminatorEdges<'mir, '_>,
+) -> impl Iterator<Item = BasicBlock> + 'mir {
+    let (first, second, rest): (_, _, &[BasicBlock]) = match edges {
+        TerminatorEdges::None => (None, None, &[]),
+        TerminatorEdges::Single(target) => (Some(target), None, &[]),
+        TerminatorEdges::Double(target, unwind) => (Some(target), Some(unwind), &[]),
+        TerminatorEdges::AssignOnReturn { return_, cleanup, place: _ } => (cleanup, return_, &[]),
+        TerminatorEdges::SwitchInt { targets, discr: _ } => (None, None, targets.all_targets()),
+    };
+    first.into_iter().chain(second).chain(rest.iter().copied())
+}
+
 pub struct SwitchIntTarget {
     pub value: Option<u128>,
     pub target: BasicBlock,
 }
 
-/// A type that records the edge-specific effects for a `SwitchInt` terminator.
-pub trait SwitchIntEdgeEffects<D> {
-    /// Calls `apply_edge_effect` for each outgoing edge from a `SwitchInt` terminator and
-    /// records the results.
-    fn apply(&mut self, apply_edge_effect: impl FnMut(&mut D, SwitchIntTarget));
-}
-
+#[cfg(test)]
+mod testing;
 #[cfg(test)]
 mod tests;
@@ -0,0 +1,498 @@
+//! Utilities for the framework's tests and benchmarks.
+//!
+//! The main tool is `MockAnalysis`, whose state at every location is unique and can be computed
+//! directly (see `MockAnalysis::expected_state_at_target`). Combined with one of the body builders
+//! below, it can be used to check code that walks dataflow results, as `check_cursor` does for
+//! `ResultsCursor`.
+
+use std::iter;
+use std::marker::PhantomData;
+
+use rustc_index::bit_set::BitSet;
+use rustc_index::IndexVec;
+use rustc_middle::mir::{self, BasicBlock, Location};
+use rustc_middle::ty::TyCtxt;
+use rustc_span::DUMMY_SP;
+
+use super::{
+    Analysis, AnalysisDomain, CallReturnPlaces, CountingAnalysis, Direction, Effect, EffectIndex,
+    EntrySets, GenKillAnalysis, Results, TerminatorEdges, TraceAnalysis, TraceGenKillAnalysis,
+};
+
+/// Creates a `mir::Body` from a list of terminators, one per basic block. Each block contains
+/// `statements_per_block` `Nop` statements.
+pub fn body_from_terminators<'tcx>(
+    statements_per_block: usize,
+    terminators: impl IntoIterator<Item = mir::TerminatorKind<'tcx>>,
+) -> mir::Body<'tcx> {
+    let source_info = mir::SourceInfo::outermost(DUMMY_SP);
+    let nop = mir::Statement { source_info, kind: mir::StatementKind::Nop };
+
+    let blocks = terminators
+        .into_iter()
+        .map(|kind| mir::BasicBlockData {
+            statements: vec![nop.clone(); statements_per_block],
+            terminator: Some(mir::Terminator { source_info, kind }),
+            is_cleanup: false,
+        })
+        .collect();
+    mir::Body::new_cfg_only(blocks)
+}
+
+/// Returns a condition for a `SwitchInt` in one of the bodies below. Its value is irrelevant.
+pub fn dummy_condition<'tcx>() -> mir::Operand<'tcx> {
+    mir::Operand::Copy(mir::Place::from(mir::RETURN_PLACE))
+}
+
+/// Returns a `Call` terminator that returns to `target` and has no cleanup block. The callee, its
+/// arguments and its destination are irrelevant.
+pub fn call_returning_to<'tcx>(target: BasicBlock) -> mir::TerminatorKind<'tcx> {
+    let dummy_place = mir::Place::from(mir::RETURN_PLACE);
+    mir::TerminatorKind::Call {
+        func: mir::Operand::Copy(dummy_place),
+        args: vec![],
+        destination: dummy_place,
+        target: Some(target),
+        unwind: mir::UnwindAction::Continue,
+        call_source: mir::CallSource::Misc,
+        fn_span: DUMMY_SP,
+    }
+}
+
+/// Creates a `mir::Body` whose `num_blocks` basic blocks each jump to the next, with the last one
+/// returning.
+pub fn straight_line_body<'tcx>(num_blocks: usize, statements_per_block: usize) -> mir::Body<'tcx> {
+    assert!(num_blocks > 0);
+    let gotos =
+        (1..num_blocks).map(|i| mir::TerminatorKind::Goto { target: BasicBlock::from_usize(i) });
+    body_from_terminators(statements_per_block, gotos.chain([mir::TerminatorKind::Return]))
+}
+
+/// Creates a `mir::Body` in which `bb0` branches to `bb1` and `bb2`, which both jump to `bb3`.
+pub fn diamond_body<'tcx>(statements_per_block: usize) -> mir::Body<'tcx> {
+    let (bb1, bb2, bb3) =
+        (BasicBlock::from_usize(1), BasicBlock::from_usize(2), BasicBlock::from_usize(3));
+    body_from_terminators(
+        statements_per_block,
+        [
+            mir::TerminatorKind::if_(dummy_condition(), bb1, bb2),
+            mir::TerminatorKind::Goto { target: bb3 },
+            mir::TerminatorKind::Goto { target: bb3 },
+            mir::TerminatorKind::Return,
+        ],
+    )
+}
+
+/// Creates a `mir::Body` in which `bb0` jumps to the loop header `bb1`, which either jumps to itself
+/// or exits to `bb2`.
+pub fn loop_body<'tcx>(statements_per_block: usize) -> mir::Body<'tcx> {
+    let (bb1, bb2) = (BasicBlock::from_usize(1), BasicBlock::from_usize(2));
+    body_from_terminators(
+        statements_per_block,
+        [
+            mir::TerminatorKind::Goto { target: bb1 },
+            mir::TerminatorKind::if_(dummy_condition(), bb1, bb2),
+            mir::TerminatorKind::Return,
+        ],
+    )
+}
+
+/// Creates a `mir::Body` in which `bb0` jumps to the outer loop header `bb1`, which jumps to `bb2`.
+/// `bb2` either jumps to itself or to `bb3`, which either goes around the outer loop or exits to
+/// `bb4`.
+pub fn nested_loop_body<'tcx>(statements_per_block: usize) -> mir::Body<'tcx> {
+    let [bb1, bb2, bb3, bb4] = [1, 2, 3, 4].map(BasicBlock::from_usize);
+    body_from_terminators(
+        statements_per_block,
+        [
+            mir::TerminatorKind::Goto { target: bb1 },
+            mir::TerminatorKind::Goto { target: bb2 },
+            mir::TerminatorKind::if_(dummy_condition(), bb2, bb3),
+            mir::TerminatorKind::if_(dummy_condition(), bb1, bb4),
+            mir::TerminatorKind::Return,
+        ],
+    )
+}
+
+/// Creates a `mir::Body` with `depth` loops nested in one another.
+///
+/// `bb0` jumps to the outermost loop header `bb1`, and each loop header up to `bb{depth}` jumps to
+/// the next one. The innermost header either jumps to itself or to the latch of the loop around
+/// it, and each latch either goes around its own loop or on to the latch of the next loop outward.
+/// The outermost latch exits to the returning block. `loop_body` and `nested_loop_body` are the
+/// cases of `depth` 1 and 2.
+pub fn loop_nest_body<'tcx>(depth: usize, statements_per_block: usize) -> mir::Body<'tcx> {
+    assert!(depth > 0);
+    let header = BasicBlock::from_usize;
+    let latch = |i: usize| BasicBlock::from_usize(2 * depth - i);
+    let exit = BasicBlock::from_usize(2 * depth);
+
+    let entry = mir::TerminatorKind::Goto { target: header(1) };
+    let headers = (1..depth).map(|i| mir::TerminatorKind::Goto { target: header(i + 1) });
+    let latches = (1..=depth).rev().map(|i| {
+        let next = if i == 1 { exit } else { latch(i - 1) };
+        mir::TerminatorKind::if_(dummy_condition(), header(i), next)
+    });
+    let terminators = iter::once(entry).chain(headers).chain(latches);
+    body_from_terminators(statements_per_block, terminators.chain([mir::TerminatorKind::Return]))
+}
+
+/// Returns a `SwitchInt` terminator that branches to each of `targets`.
+fn switch_to<'tcx>(targets: &[BasicBlock]) -> mir::TerminatorKind<'tcx> {
+    let (&otherwise, values) = targets.split_last().unwrap();
+    let values = values.iter().enumerate().map(|(value, &target)| (value as u128, target));
+    mir::TerminatorKind::SwitchInt {
+        discr: dummy_condition(),
+        targets: mir::SwitchTargets::new(values, otherwise),
+    }
+}
+
+/// Creates a `mir::Body` in which `bb0` switches to `width` arms, which all jump to the returning
+/// block after them.
+pub fn wide_diamond_body<'tcx>(width: usize, statements_per_block: usize) -> mir::Body<'tcx> {
+    assert!(width > 1);
+    let arms = (1..=width).map(BasicBlock::from_usize).collect::<Vec<_>>();
+    let join = BasicBlock::from_usize(width + 1);
+    let terminators = iter::once(switch_to(&arms))
+        .chain(arms.iter().map(|_| mir::TerminatorKind::Goto { target: join }));
+    body_from_terminators(statements_per_block, terminators.chain([mir::TerminatorKind::Return]))
+}
+
+/// Creates a `mir::Body` with an irreducible cycle of `cycle_len` blocks.
+///
+/// `bb0` switches to every block of the cycle, so none of them dominates the others. Each block in
+/// the cycle either jumps to the next one, with the last one jumping back to `bb1`, or exits to the
+/// returning block after them.
+pub fn irreducible_body<'tcx>(cycle_len: usize, statements_per_block: usize) -> mir::Body<'tcx> {
+    assert!(cycle_len > 1);
+    let cycle = (1..=cycle_len).map(BasicBlock::from_usize).collect::<Vec<_>>();
+    let exit = BasicBlock::from_usize(cycle_len + 1);
+    let terminators = iter::once(switch_to(&cycle))
+        .chain((0..cycle_len).map(|i| {
+            mir::TerminatorKind::if_(dummy_condition(), cycle[(i + 1) % cycle_len], exit)
+        }));
+    body_from_terminators(statements_per_block, terminators.chain([mir::TerminatorKind::Return]))
+}
+
+/// Creates a `mir::Body` with a few disconnected basic blocks, some of which end in a `Call`.
+///
+/// Most blocks in this body are unreachable, which is useful for exercising cursors over blocks
+/// that would be skipped by a traversal.
+pub fn disconnected_body<'tcx>() -> mir::Body<'tcx> {
+    let source_info = mir::SourceInfo::outermost(DUMMY_SP);
+
+    let mut blocks = IndexVec::new();
+    let mut block = |n, kind| {
+        let nop = mir::Statement { source_info, kind: mir::StatementKind::Nop };
+
+        blocks.push(mir::BasicBlockData {
+            statements: std::iter::repeat(&nop).cloned().take(n).collect(),
+            terminator: Some(mir::Terminator { source_info, kind }),
+            is_cleanup: false,
+        })
+    };
+
+    block(4, mir::TerminatorKind::Return);
+    block(1, mir::TerminatorKind::Return);
+    block(2, call_returning_to(mir::START_BLOCK));
+    block(3, mir::TerminatorKind::Return);
+    block(0, mir::TerminatorKind::Return);
+    block(4, call_returning_to(mir::START_BLOCK));
+
+    mir::Body::new_cfg_only(blocks)
+}
+
+/// The bottom value of an analysis used in tests and benchmarks.
+///
+/// `AnalysisDomain::bottom_value` is passed a `TyCtxt`, which these cannot create. The analyses
+/// they use implement this instead, and forward `bottom_value` to it.
+pub trait TestBottom<'tcx>: AnalysisDomain<'tcx> {
+    fn test_bottom(&self, body: &mir::Body<'tcx>) -> Self::Domain;
+}
+
+impl<'tcx, A: TestBottom<'tcx>> TestBottom<'tcx> for CountingAnalysis<A> {
+    fn test_bottom(&self, body: &mir::Body<'tcx>) -> Self::Domain {
+        self.inner().test_bottom(body)
+    }
+}
+
+impl<'tcx, A: TestBottom<'tcx>> TestBottom<'tcx> for TraceAnalysis<A> {
+    fn test_bottom(&self, body: &mir::Body<'tcx>) -> Self::Domain {
+        self.inner().test_bottom(body)
+    }
+}
+
+impl<'tcx, A> TestBottom<'tcx> for TraceGenKillAnalysis<A>
+where
+    A: GenKillAnalysis<'tcx> + TestBottom<'tcx>,
+{
+    fn test_bottom(&self, body: &mir::Body<'tcx>) -> Self::Domain {
+        self.inner().test_bottom(body)
+    }
+}
+
+/// A dataflow analysis whose state is unique at every possible `SeekTarget`.
+///
+/// Uniqueness is achieved by having a *locally* unique effect before and after each statement and
+/// terminator (see `effect_at_target`) while ensuring that the entry set for each block is
+/// *globally* unique (see `mock_entry_set`).
+///
+/// For example, a `BasicBlock` with ID `2` and a `Call` terminator has the following state at each
+/// location ("+x" indicates that "x" is added to the state).
+///
+/// | Location               | Before            | After  |
+/// |------------------------|-------------------|--------|
+/// | (on_entry)             | {102}                     ||
+/// | statement 0            | +0                | +1     |
+/// | statement 1            | +2                | +3     |
+/// | `Call` terminator      | +4                | +5     |
+/// | (on unwind)            | {102,0,1,2,3,4,5}         ||
+///
+/// The `102` in the block's entry set is derived from the basic block index and ensures that the
+/// expected state is unique across all basic blocks. Remember, it is generated by
+/// `mock_entry_sets`, not from actually running `MockAnalysis` to fixpoint.
+pub struct MockAnalysis<'tcx, D> {
+    body: &'tcx mir::Body<'tcx>,
+    dir: PhantomData<D>,
+}
+
+// Not derived, since that would require `D: Copy`.
+impl<D> Clone for MockAnalysis<'_, D> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<D> Copy for MockAnalysis<'_, D> {}
+
+impl<'tcx, D: Direction> MockAnalysis<'tcx, D> {
+    pub fn new(body: &'tcx mir::Body<'tcx>) -> Self {
+        MockAnalysis { body, dir: PhantomData }
+    }
+}
+
+impl<'tcx, D: Direction> MockAnalysis<'tcx, D> {
+    const BASIC_BLOCK_OFFSET: usize = 100;
+
+    /// The entry set for each `BasicBlock` is the ID of that block offset by a fixed amount to
+    /// avoid colliding with the statement/terminator effects.
+    fn mock_entry_set(&self, bb: BasicBlock) -> BitSet<usize> {
+        let mut ret = self.test_bottom(self.body);
+        ret.insert(Self::BASIC_BLOCK_OFFSET + bb.index());
+        ret
+    }
+
+    pub fn mock_entry_sets(&self) -> EntrySets<'tcx, Self> {
+        let empty = self.test_bottom(self.body);
+        let mut ret = IndexVec::from_elem(empty.clone(), &self.body.basic_blocks);
+
+        for (bb, _) in self.body.basic_blocks.iter_enumerated() {
+            ret[bb] = self.mock_entry_set(bb);
+        }
+
+        EntrySets::from_sets(ret, empty)
+    }
+
+    /// Returns the index that should be added to the dataflow state at the given target.
+    fn effect(&self, loc: EffectIndex) -> usize {
+        let idx = match loc.effect {
+            Effect::Before => loc.statement_index * 2,
+            Effect::Primary => loc.statement_index * 2 + 1,
+        };
+
+        assert!(idx < Self::BASIC_BLOCK_OFFSET, "Too many statements in basic block");
+        idx
+    }
+
+    /// Returns the expected state at the given `SeekTarget`.
+    ///
+    /// This is the union of index of the target basic block, the index assigned to the
+    /// target statement or terminator, and the indices of all preceding statements in the target
+    /// basic block.
+    ///
+    /// For example, the expected state when calling
+    /// `seek_before_primary_effect(Location { block: 2, statement_index: 2 })`
+    /// would be `[102, 0, 1, 2, 3, 4]`.
+    pub fn expected_state_at_target(&self, target: SeekTarget) -> BitSet<usize> {
+        let block = target.block();
+        let mut ret = self.test_bottom(self.body);
+        ret.insert(Self::BASIC_BLOCK_OFFSET + block.index());
+
+        let target = match target {
+            SeekTarget::BlockEntry { .. } => return ret,
+            SeekTarget::Before(loc) => Effect::Before.at_index(loc.statement_index),
+            SeekTarget::After(loc) => Effect::Primary.at_index(loc.statement_index),
+        };
+
+        let mut pos = if D::IS_FORWARD {
+            Effect::Before.at_index(0)
+        } else {
+            Effect::Before.at_index(self.body[block].statements.len())
+        };
+
+        loop {
+            ret.insert(self.effect(pos));
+
+            if pos == target {
+                return ret;
+            }
+
+            if D::IS_FORWARD {
+                pos = pos.next_in_forward_order();
+            } else {
+                pos = pos.next_in_backward_order();
+            }
+        }
+    }
+}
+
+impl<'tcx, D: Direction> AnalysisDomain<'tcx> for MockAnalysis<'tcx, D> {
+    type Domain = BitSet<usize>;
+    type Direction = D;
+
+    const NAME: &'static str = "mock";
+
+    fn bottom_value(&self, _: TyCtxt<'tcx>, body: &mir::Body<'tcx>) -> Self::Domain {
+        self.test_bottom(body)
+    }
+
+    fn initialize_start_block(&self, _: &mir::Body<'tcx>, _: &mut Self::Domain) {
+        unreachable!("This is never called since `MockAnalysis` is never iterated to fixpoint");
+    }
+}
+
+impl<'tcx, D: Direction> TestBottom<'tcx> for MockAnalysis<'tcx, D> {
+    fn test_bottom(&self, body: &mir::Body<'tcx>) -> Self::Domain {
+        BitSet::new_empty(Self::BASIC_BLOCK_OFFSET + body.basic_blocks.len())
+    }
+}
+
+impl<'tcx, D: Direction> Analysis<'tcx> for MockAnalysis<'tcx, D> {
+    fn apply_statement_effect(
+        &mut self,
+        state: &mut Self::Domain,
+        _statement: &mir::Statement<'tcx>,
+        location: Location,
+    ) {
+        let idx = self.effect(Effect::Primary.at_index(location.statement_index));
+        assert!(state.insert(idx));
+    }
+
+    fn apply_before_statement_effect(
+        &mut self,
+        state: &mut Self::Domain,
+        _statement: &mir::Statement<'tcx>,
+        location: Location,
+    ) {
+        let idx = self.effect(Effect::Before.at_index(location.statement_index));
+        assert!(state.insert(idx));
+    }
+
+    fn apply_terminator_effect<'mir>(
+        &mut self,
+        state: &mut Self::Domain,
+        terminator: &'mir mir::Terminator<'tcx>,
+        location: Location,
+    ) -> TerminatorEdges<'mir, 'tcx> {
+        let idx = self.effect(Effect::Primary.at_index(location.statement_index));
+        assert!(state.insert(idx));
+        terminator.edges()
+    }
+
+    fn apply_before_terminator_effect(
+        &mut self,
+        state: &mut Self::Domain,
+        _terminator: &mir::Terminator<'tcx>,
+        location: Location,
+    ) {
+        let idx = self.effect(Effect::Before.at_index(location.statement_index));
+        assert!(state.insert(idx));
+    }
+
+    fn apply_call_return_effect(
+        &mut self,
+        _state: &mut Self::Domain,
+        _block: BasicBlock,
+        _return_places: CallReturnPlaces<'_, 'tcx>,
+    ) {
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SeekTarget {
+    BlockEntry(BasicBlock),
+    Before(Location),
+    After(Location),
+}
+
+impl SeekTarget {
+    pub fn block(&self) -> BasicBlock {
+        use SeekTarget::*;
+
+        match *self {
+            BlockEntry(block) => block,
+            Before(loc) | After(loc) => loc.block,
+        }
+    }
+
+    /// An iterator over all possible `SeekTarget`s in a given block in order, starting with
+    /// `BlockEntry`.
+    pub fn iter_in_block(body: &mir::Body<'_>, block: BasicBlock) -> impl Iterator<Item = Self> {
+        let statements_and_terminator = (0..=body[block].statements.len())
+            .flat_map(|i| (0..2).map(move |j| (i, j)))
+            .map(move |(i, kind)| {
+                let loc = Location { block, statement_index: i };
+                match kind {
+                    0 => SeekTarget::Before(loc),
+                    1 => SeekTarget::After(loc),
+                    _ => unreachable!(),
+                }
+            });
+
+        std::iter::once(SeekTarget::BlockEntry(block)).chain(statements_and_terminator)
+    }
+}
+
+/// Checks that a `ResultsCursor` over `analysis` holds the expected state after seeking to every
+/// `SeekTarget` in the body from every other `SeekTarget`.
+///
+/// Since the number of seeks is quadratic in the size of the body, this should only be used with
+/// small bodies.
+pub fn check_cursor<D: Direction>(analysis: MockAnalysis<'_, D>) {
+    let body = analysis.body;
+
+    let entry_sets = analysis.mock_entry_sets();
+    let mut cursor = Results::new(analysis, entry_sets).into_results_cursor(body);
+
+    cursor.allow_unreachable();
+
+    let every_target = || {
+        body.basic_blocks.iter_enumerated().flat_map(|(bb, _)| SeekTarget::iter_in_block(body, bb))
+    };
+
+    let mut seek_to_target = |targ| {
+        use SeekTarget::*;
+
+        match targ {
+            BlockEntry(block) => cursor.seek_to_block_entry(block),
+            Before(loc) => cursor.seek_before_primary_effect(loc),
+            After(loc) => cursor.seek_after_primary_effect(loc),
+        }
+
+        assert_eq!(cursor.get(), &cursor.analysis().expected_state_at_target(targ));
+    };
+
+    // Seek *to* every possible `SeekTarget` *from* every possible `SeekTarget`.
+    //
+    // By resetting the cursor to `from` each time it changes, we end up checking some edges twice.
+    // What we really want is an Eulerian cycle for the complete digraph over all possible
+    // `SeekTarget`s, but it's not worth spending the time to compute it.
+    for from in every_target() {
+        seek_to_target(from);
+
+        for to in every_target() {
+            seek_to_target(to);
+            seek_to_target(from);
+        }
+    }
+}
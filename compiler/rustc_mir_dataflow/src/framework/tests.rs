@@ -1,322 +1,3875 @@
 //! A test for the logic that updates the state in a `ResultsCursor` during seek.
 
+use std::cell::Cell;
+use std::collections::{BTreeMap, BTreeSet};
 use std::marker::PhantomData;
 
+use rustc_data_structures::fingerprint::Fingerprint;
+use rustc_data_structures::sync::{self, Lrc};
 use rustc_index::bit_set::BitSet;
-use rustc_index::IndexVec;
+use rustc_index::{Idx, IndexVec};
 use rustc_middle::mir::{self, BasicBlock, Location};
-use rustc_middle::ty;
+use rustc_middle::ty::TyCtxt;
 use rustc_span::DUMMY_SP;
 
+use super::engine::{
+    back_edge_sources, block_trans, dump_file_disambiguator, gen_kill_trans_for_blocks,
+    gen_kill_trans_for_blocks_concurrently, gen_kill_trans_for_selected_blocks,
+    graphviz_render_opts, initial_entry_sets, iterate_entry_sets_to_fixpoint, join_converted_seed,
+    phase_selected, seed_entry_sets, start_block_seed_lost, widen_above,
+    write_dump_manifest_entry, CachedBlockTrans, DenseEntrySets, FixpointOptions,
+};
+use super::fmt::DebugWithContext;
+use super::lattice::MeetSemiLattice;
+use super::testing::*;
 use super::*;
 
-/// Creates a `mir::Body` with a few disconnected basic blocks.
+#[test]
+fn backward_cursor() {
+    let body = &disconnected_body();
+    check_cursor(MockAnalysis::<Backward>::new(body));
+}
+
+#[test]
+fn forward_cursor() {
+    let body = &disconnected_body();
+    check_cursor(MockAnalysis::<Forward>::new(body));
+}
+
+#[test]
+fn cursor_on_common_cfg_shapes() {
+    for body in [
+        &straight_line_body(3, 2),
+        &diamond_body(2),
+        &loop_body(2),
+        &loop_nest_body(3, 1),
+        &wide_diamond_body(4, 1),
+        &irreducible_body(3, 1),
+    ] {
+        check_cursor(MockAnalysis::<Backward>::new(body));
+        check_cursor(MockAnalysis::<Forward>::new(body));
+    }
+}
+
+/// Creates a `mir::Body` for a coroutine that suspends once.
 ///
-/// This is the `Body` that will be used by the `MockAnalysis` below. The shape of its CFG is not
-/// important.
-fn mock_body<'tcx>() -> mir::Body<'tcx> {
-    let source_info = mir::SourceInfo::outermost(DUMMY_SP);
+/// `bb0` yields, resuming into `bb1` with the resume argument written to `_1`, or dropping into
+/// `bb2` if the coroutine is dropped while suspended.
+fn mock_yield_body<'tcx>() -> mir::Body<'tcx> {
+    body_from_terminators(
+        0,
+        [
+            mir::TerminatorKind::Yield {
+                value: dummy_condition(),
+                resume: BasicBlock::from_u32(1),
+                resume_arg: mir::Place::from(mir::Local::from_u32(1)),
+                drop: Some(BasicBlock::from_u32(2)),
+            },
+            mir::TerminatorKind::Return,
+            mir::TerminatorKind::CoroutineDrop,
+        ],
+    )
+}
 
-    let mut blocks = IndexVec::new();
-    let mut block = |n, kind| {
-        let nop = mir::Statement { source_info, kind: mir::StatementKind::Nop };
+/// An analysis that records which locals were written by resuming a coroutine.
+struct MockYieldAnalysis<D> {
+    dir: PhantomData<D>,
+}
 
-        blocks.push(mir::BasicBlockData {
-            statements: std::iter::repeat(&nop).cloned().take(n).collect(),
-            terminator: Some(mir::Terminator { source_info, kind }),
-            is_cleanup: false,
-        })
+// Not derived, since that would require `D: Copy`.
+impl<D> Clone for MockYieldAnalysis<D> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<D> Copy for MockYieldAnalysis<D> {}
+
+impl<'tcx, D: Direction> AnalysisDomain<'tcx> for MockYieldAnalysis<D> {
+    type Domain = BitSet<mir::Local>;
+    type Direction = D;
+
+    const NAME: &'static str = "mock_yield";
+
+    fn bottom_value(&self, _: TyCtxt<'tcx>, body: &mir::Body<'tcx>) -> Self::Domain {
+        self.test_bottom(body)
+    }
+
+    fn initialize_start_block(&self, _: &mir::Body<'tcx>, _: &mut Self::Domain) {}
+}
+
+impl<'tcx, D: Direction> TestBottom<'tcx> for MockYieldAnalysis<D> {
+    fn test_bottom(&self, _: &mir::Body<'tcx>) -> Self::Domain {
+        BitSet::new_empty(2)
+    }
+}
+
+impl<'tcx, D: Direction> Analysis<'tcx> for MockYieldAnalysis<D> {
+    fn apply_statement_effect(
+        &mut self,
+        _state: &mut Self::Domain,
+        _statement: &mir::Statement<'tcx>,
+        _location: Location,
+    ) {
+    }
+
+    fn apply_terminator_effect<'mir>(
+        &mut self,
+        _state: &mut Self::Domain,
+        terminator: &'mir mir::Terminator<'tcx>,
+        _location: Location,
+    ) -> TerminatorEdges<'mir, 'tcx> {
+        terminator.edges()
+    }
+
+    fn apply_call_return_effect(
+        &mut self,
+        _state: &mut Self::Domain,
+        _block: BasicBlock,
+        _return_places: CallReturnPlaces<'_, 'tcx>,
+    ) {
+        panic!("`Yield` terminators should use `apply_yield_resume_effect`");
+    }
+
+    fn apply_yield_resume_effect(
+        &mut self,
+        state: &mut Self::Domain,
+        resume_block: BasicBlock,
+        resume_place: mir::Place<'tcx>,
+    ) {
+        assert_eq!(resume_block, BasicBlock::from_u32(1));
+        state.insert(resume_place.local);
+    }
+}
+
+#[test]
+fn forward_yield_resume_edge() {
+    let body = &mock_yield_body();
+    let mut analysis = MockYieldAnalysis { dir: PhantomData::<Forward> };
+
+    let mut state = analysis.test_bottom(body);
+    let edges = body[mir::START_BLOCK].terminator().edges();
+    let mut propagated = vec![];
+    Forward::join_state_into_successors_of(
+        &mut analysis,
+        body,
+        &mut state,
+        mir::START_BLOCK,
+        edges,
+        |_, target, state: &BitSet<mir::Local>| propagated.push((target, state.clone())),
+    );
+
+    let resume_local = mir::Local::from_u32(1);
+    let [(drop, on_drop), (resume, on_resume)] = &propagated[..] else {
+        panic!("expected exactly two outgoing edges, got {propagated:?}");
     };
+    assert_eq!(*drop, BasicBlock::from_u32(2));
+    assert!(!on_drop.contains(resume_local));
+    assert_eq!(*resume, BasicBlock::from_u32(1));
+    assert!(on_resume.contains(resume_local));
+}
+
+#[test]
+fn backward_yield_resume_edge() {
+    let body = &mock_yield_body();
+    let mut analysis = MockYieldAnalysis { dir: PhantomData::<Backward> };
+
+    let resume_local = mir::Local::from_u32(1);
+    for (bb, expect_resume_effect) in
+        [(BasicBlock::from_u32(1), true), (BasicBlock::from_u32(2), false)]
+    {
+        let mut state = analysis.test_bottom(body);
+        let edges = body[bb].terminator().edges();
+        let mut propagated = vec![];
+        Backward::join_state_into_successors_of(
+            &mut analysis,
+            body,
+            &mut state,
+            bb,
+            edges,
+            |_, target, state: &BitSet<mir::Local>| propagated.push((target, state.clone())),
+        );
+
+        let [(pred, on_edge)] = &propagated[..] else {
+            panic!("expected exactly one incoming edge, got {propagated:?}");
+        };
+        assert_eq!(*pred, mir::START_BLOCK);
+        assert_eq!(on_edge.contains(resume_local), expect_resume_effect);
+    }
+}
+
+/// A gen/kill analysis in which the unwind edge of a terminator generates the index of its block,
+/// and the return edge of a call generates bit 2.
+struct MockUnwindEffect;
+
+impl<'tcx> AnalysisDomain<'tcx> for MockUnwindEffect {
+    type Domain = BitSet<usize>;
+
+    const NAME: &'static str = "mock_unwind_effect";
+
+    fn bottom_value(&self, _: TyCtxt<'tcx>, body: &mir::Body<'tcx>) -> Self::Domain {
+        self.test_bottom(body)
+    }
+
+    fn initialize_start_block(&self, _: &mir::Body<'tcx>, _: &mut Self::Domain) {}
+}
+
+impl<'tcx> TestBottom<'tcx> for MockUnwindEffect {
+    fn test_bottom(&self, _: &mir::Body<'tcx>) -> Self::Domain {
+        BitSet::new_empty(3)
+    }
+}
+
+impl<'tcx> GenKillAnalysis<'tcx> for MockUnwindEffect {
+    type Idx = usize;
+
+    fn domain_size(&self, _: &mir::Body<'tcx>) -> usize {
+        3
+    }
+
+    fn statement_effect(
+        &mut self,
+        _trans: &mut impl GenKill<Self::Idx>,
+        _statement: &mir::Statement<'tcx>,
+        _location: Location,
+    ) {
+    }
+
+    fn terminator_effect<'mir>(
+        &mut self,
+        _trans: &mut Self::Domain,
+        terminator: &'mir mir::Terminator<'tcx>,
+        _location: Location,
+    ) -> TerminatorEdges<'mir, 'tcx> {
+        terminator.edges()
+    }
+
+    fn terminator_unwind_effect(
+        &mut self,
+        trans: &mut impl GenKill<Self::Idx>,
+        _terminator: &mir::Terminator<'tcx>,
+        block: BasicBlock,
+    ) {
+        trans.gen(block.index());
+    }
+
+    fn call_return_effect(
+        &mut self,
+        trans: &mut impl GenKill<Self::Idx>,
+        _block: BasicBlock,
+        _return_places: CallReturnPlaces<'_, 'tcx>,
+    ) {
+        trans.gen(2);
+    }
+}
+
+/// Returns the elements of the states that `bb` propagates to each of its successors.
+fn propagated_elems<'tcx, A>(
+    analysis: &mut A,
+    body: &mir::Body<'tcx>,
+    bb: BasicBlock,
+) -> Vec<(BasicBlock, Vec<usize>)>
+where
+    A: Analysis<'tcx, Domain = BitSet<usize>> + TestBottom<'tcx>,
+{
+    let mut state = analysis.test_bottom(body);
+    let edges = body[bb].terminator().edges();
+    let mut propagated = vec![];
+    Forward::join_state_into_successors_of(
+        analysis,
+        body,
+        &mut state,
+        bb,
+        edges,
+        |_, target, state: &BitSet<usize>| propagated.push((target, state.iter().collect())),
+    );
+    propagated.sort();
+    propagated
+}
+
+/// Creates a `mir::Body` in which `bb0` has a `Double` edge, and `bb1` an `AssignOnReturn` edge
+/// with both a return and an unwind target. Both unwind to `bb3`.
+fn unwind_effect_body<'tcx>() -> mir::Body<'tcx> {
+    let [bb1, bb2, bb3] = [1, 2, 3].map(BasicBlock::from_u32);
+    let dummy_place = mir::Place::from(mir::RETURN_PLACE);
+    let unwind = mir::UnwindAction::Cleanup(bb3);
+
+    let mut body = body_from_terminators(
+        0,
+        [
+            mir::TerminatorKind::Drop { place: dummy_place, target: bb1, unwind, replace: false },
+            mir::TerminatorKind::Call {
+                func: mir::Operand::Copy(dummy_place),
+                args: vec![],
+                destination: dummy_place,
+                target: Some(bb2),
+                unwind,
+                call_source: mir::CallSource::Misc,
+                fn_span: DUMMY_SP,
+            },
+            mir::TerminatorKind::Return,
+            mir::TerminatorKind::UnwindResume,
+        ],
+    );
+    body.basic_blocks_mut()[bb3].is_cleanup = true;
+    body
+}
+
+#[test]
+fn unwind_effect() {
+    let body = &unwind_effect_body();
+    let [bb0, bb1, bb2, bb3] = [0, 1, 2, 3].map(BasicBlock::from_u32);
+
+    // The unwind effect of each terminator reaches the cleanup block, but not the other successor.
+    let analysis = &mut MockUnwindEffect;
+    assert_eq!(propagated_elems(analysis, body, bb0), [(bb1, vec![]), (bb3, vec![0])]);
+    assert_eq!(propagated_elems(analysis, body, bb1), [(bb2, vec![2]), (bb3, vec![1])]);
+}
+
+/// An analysis that considers only the `true` arm of every two-way `SwitchInt` feasible.
+struct PruneFalseArm;
+
+impl<'tcx> AnalysisDomain<'tcx> for PruneFalseArm {
+    type Domain = BitSet<usize>;
+
+    const NAME: &'static str = "prune_false_arm";
+
+    fn bottom_value(&self, _: TyCtxt<'tcx>, body: &mir::Body<'tcx>) -> Self::Domain {
+        self.test_bottom(body)
+    }
+
+    fn initialize_start_block(&self, _: &mir::Body<'tcx>, _: &mut Self::Domain) {}
+}
+
+impl<'tcx> TestBottom<'tcx> for PruneFalseArm {
+    fn test_bottom(&self, _: &mir::Body<'tcx>) -> Self::Domain {
+        BitSet::new_empty(0)
+    }
+}
+
+impl<'tcx> Analysis<'tcx> for PruneFalseArm {
+    fn apply_statement_effect(
+        &mut self,
+        _state: &mut Self::Domain,
+        _statement: &mir::Statement<'tcx>,
+        _location: Location,
+    ) {
+    }
+
+    fn apply_terminator_effect<'mir>(
+        &mut self,
+        _state: &mut Self::Domain,
+        terminator: &'mir mir::Terminator<'tcx>,
+        _location: Location,
+    ) -> TerminatorEdges<'mir, 'tcx> {
+        match terminator.edges() {
+            TerminatorEdges::SwitchInt { targets, .. } => {
+                TerminatorEdges::Single(targets.target_for_value(1))
+            }
+            edges => edges,
+        }
+    }
+
+    fn apply_call_return_effect(
+        &mut self,
+        _state: &mut Self::Domain,
+        _block: BasicBlock,
+        _return_places: CallReturnPlaces<'_, 'tcx>,
+    ) {
+    }
+}
+
+/// Records every edge passed to `visit_terminator_edge`.
+struct EdgeCollector(Vec<(BasicBlock, BasicBlock)>);
+
+impl<'mir, 'tcx, R> ResultsVisitor<'mir, 'tcx, R> for EdgeCollector {
+    type FlowState = BitSet<usize>;
+
+    fn visit_terminator_edge(
+        &mut self,
+        _results: &mut R,
+        _state: &Self::FlowState,
+        from: BasicBlock,
+        to: BasicBlock,
+    ) {
+        self.0.push((from, to));
+    }
+}
+
+#[test]
+fn pruned_edges_are_not_visited() {
+    let (bb1, bb2) = (BasicBlock::from_u32(1), BasicBlock::from_u32(2));
+    let body = &body_from_terminators(
+        0,
+        [
+            mir::TerminatorKind::if_(dummy_condition(), bb1, bb2),
+            mir::TerminatorKind::Return,
+            mir::TerminatorKind::Return,
+        ],
+    );
+
+    let analysis = PruneFalseArm;
+    let entry_sets =
+        EntrySets::from_bottom_n(analysis.test_bottom(body), body.basic_blocks.len());
+    let mut results = Results::new(analysis, entry_sets);
+
+    assert_eq!(results.terminator_edges(body, mir::START_BLOCK), [bb1]);
+
+    let mut vis = EdgeCollector(vec![]);
+    results.visit_with(body, [mir::START_BLOCK], &mut vis);
+    assert_eq!(vis.0, [(mir::START_BLOCK, bb1)]);
+}
+
+/// An analysis in which each block's terminator records that block, and that considers the edge
+/// from `bb0` to `bb2` infeasible.
+struct PruneSecondArm;
+
+impl<'tcx> AnalysisDomain<'tcx> for PruneSecondArm {
+    type Domain = BitSet<usize>;
+
+    const NAME: &'static str = "prune_second_arm";
+
+    fn bottom_value(&self, _: TyCtxt<'tcx>, body: &mir::Body<'tcx>) -> Self::Domain {
+        self.test_bottom(body)
+    }
+
+    fn initialize_start_block(&self, _: &mir::Body<'tcx>, _: &mut Self::Domain) {}
+}
+
+impl<'tcx> TestBottom<'tcx> for PruneSecondArm {
+    fn test_bottom(&self, body: &mir::Body<'tcx>) -> Self::Domain {
+        BitSet::new_empty(body.basic_blocks.len())
+    }
+}
+
+impl<'tcx> Analysis<'tcx> for PruneSecondArm {
+    fn apply_statement_effect(
+        &mut self,
+        _state: &mut Self::Domain,
+        _statement: &mir::Statement<'tcx>,
+        _location: Location,
+    ) {
+    }
+
+    fn apply_terminator_effect<'mir>(
+        &mut self,
+        state: &mut Self::Domain,
+        terminator: &'mir mir::Terminator<'tcx>,
+        location: Location,
+    ) -> TerminatorEdges<'mir, 'tcx> {
+        state.insert(location.block.index());
+        terminator.edges()
+    }
+
+    fn apply_call_return_effect(
+        &mut self,
+        _state: &mut Self::Domain,
+        _block: BasicBlock,
+        _return_places: CallReturnPlaces<'_, 'tcx>,
+    ) {
+    }
+
+    fn is_edge_feasible(
+        &self,
+        _state: &Self::Domain,
+        _terminator: &mir::Terminator<'tcx>,
+        source: BasicBlock,
+        target: BasicBlock,
+    ) -> bool {
+        (source.index(), target.index()) != (0, 2)
+    }
+}
+
+#[test]
+fn infeasible_edges_are_not_propagated() {
+    // bb0 switches to one of bb1..=bb3, all of which go to bb4.
+    let body = &wide_diamond_body(3, 0);
+    let entry_sets = solve(&mut PruneSecondArm, body);
+
+    let bb = BasicBlock::from_usize;
+    assert!(entry_sets[bb(1)].contains(0));
+    assert!(entry_sets[bb(2)].is_empty());
+    assert!(entry_sets[bb(3)].contains(0));
+
+    let mut results = Results::new(PruneSecondArm, entry_sets);
+    let mut vis = EdgeCollector(vec![]);
+    results.visit_with(body, [mir::START_BLOCK], &mut vis);
+    assert_eq!(vis.0, [(bb(0), bb(1)), (bb(0), bb(3))]);
+
+    // Inspecting the results prunes the same edges.
+    assert_eq!(results.terminator_edges(body, bb(0)), [bb(1), bb(3)]);
+    assert!(results.predecessor_exit_states(body, bb(2)).is_empty());
+    assert_eq!(results.predecessor_exit_states(body, bb(1)).len(), 1);
+
+    // `MapDomain` asks the analysis it wraps.
+    let mut analysis = MapDomain::new(PruneSecondArm, BitSet::clone, BitSet::clone);
+    let entry_sets =
+        solve_with_bottom(&mut analysis, body, |_, body| PruneSecondArm.test_bottom(body));
+    assert!(entry_sets[bb(2)].is_empty());
+    assert!(entry_sets[bb(3)].contains(0));
+}
+
+/// Creates a `mir::Body` with the shape of a `match` arm with a guard.
+///
+/// `bb0` falls through to the guard in `bb1`, with an imaginary edge to the next arm in `bb2`. If
+/// the guard fails, `bb1` continues to `bb2`; otherwise it enters the arm body in `bb3`.
+fn mock_match_guard_body<'tcx>() -> mir::Body<'tcx> {
+    let (bb1, bb2, bb3) =
+        (BasicBlock::from_u32(1), BasicBlock::from_u32(2), BasicBlock::from_u32(3));
+    body_from_terminators(
+        0,
+        [
+            mir::TerminatorKind::FalseEdge { real_target: bb1, imaginary_target: bb2 },
+            mir::TerminatorKind::if_(dummy_condition(), bb3, bb2),
+            mir::TerminatorKind::Return,
+            mir::TerminatorKind::Return,
+        ],
+    )
+}
+
+/// An analysis that treats imaginary edges according to `false_edges`.
+struct MockFalseEdgeAnalysis<D> {
+    false_edges: FalseEdgeSemantics,
+    dir: PhantomData<D>,
+}
+
+impl<'tcx, D: Direction> AnalysisDomain<'tcx> for MockFalseEdgeAnalysis<D> {
+    type Domain = BitSet<usize>;
+    type Direction = D;
+
+    const NAME: &'static str = "mock_false_edge";
+
+    fn bottom_value(&self, _: TyCtxt<'tcx>, body: &mir::Body<'tcx>) -> Self::Domain {
+        self.test_bottom(body)
+    }
+
+    fn initialize_start_block(&self, _: &mir::Body<'tcx>, _: &mut Self::Domain) {}
+}
+
+impl<'tcx, D: Direction> TestBottom<'tcx> for MockFalseEdgeAnalysis<D> {
+    fn test_bottom(&self, _: &mir::Body<'tcx>) -> Self::Domain {
+        BitSet::new_empty(0)
+    }
+}
+
+impl<'tcx, D: Direction> Analysis<'tcx> for MockFalseEdgeAnalysis<D> {
+    fn apply_statement_effect(
+        &mut self,
+        _state: &mut Self::Domain,
+        _statement: &mir::Statement<'tcx>,
+        _location: Location,
+    ) {
+    }
+
+    fn apply_terminator_effect<'mir>(
+        &mut self,
+        _state: &mut Self::Domain,
+        terminator: &'mir mir::Terminator<'tcx>,
+        _location: Location,
+    ) -> TerminatorEdges<'mir, 'tcx> {
+        terminator.edges()
+    }
+
+    fn apply_call_return_effect(
+        &mut self,
+        _state: &mut Self::Domain,
+        _block: BasicBlock,
+        _return_places: CallReturnPlaces<'_, 'tcx>,
+    ) {
+    }
+
+    fn treat_false_edges(&self) -> FalseEdgeSemantics {
+        self.false_edges
+    }
+}
+
+#[test]
+fn forward_false_edge_semantics() {
+    let body = &mock_match_guard_body();
+    let (bb1, bb2) = (BasicBlock::from_u32(1), BasicBlock::from_u32(2));
+
+    for (false_edges, expected) in
+        [(FalseEdgeSemantics::Real, &[bb1, bb2][..]), (FalseEdgeSemantics::Ignore, &[bb1][..])]
+    {
+        let mut analysis = MockFalseEdgeAnalysis { false_edges, dir: PhantomData::<Forward> };
+
+        let mut state = analysis.test_bottom(body);
+        let edges = body[mir::START_BLOCK].terminator().edges();
+        let mut propagated = vec![];
+        Forward::join_state_into_successors_of(
+            &mut analysis,
+            body,
+            &mut state,
+            mir::START_BLOCK,
+            edges,
+            |_, target, _: &BitSet<usize>| propagated.push(target),
+        );
+        assert_eq!(propagated, expected, "{false_edges:?}");
+    }
+}
+
+#[test]
+fn backward_false_edge_semantics() {
+    let body = &mock_match_guard_body();
+    let (bb1, bb2) = (BasicBlock::from_u32(1), BasicBlock::from_u32(2));
+
+    for (false_edges, expected) in [
+        (FalseEdgeSemantics::Real, &[mir::START_BLOCK, bb1][..]),
+        (FalseEdgeSemantics::Ignore, &[bb1][..]),
+    ] {
+        let mut analysis = MockFalseEdgeAnalysis { false_edges, dir: PhantomData::<Backward> };
+
+        let mut state = analysis.test_bottom(body);
+        let edges = body[bb2].terminator().edges();
+        let mut propagated = vec![];
+        Backward::join_state_into_successors_of(
+            &mut analysis,
+            body,
+            &mut state,
+            bb2,
+            edges,
+            |_, pred, _: &BitSet<usize>| propagated.push(pred),
+        );
+        propagated.sort();
+        assert_eq!(propagated, expected, "{false_edges:?}");
+    }
+}
+
+/// A toy "available expressions" analysis that inserts the single tracked expression at the end of
+/// `bb1` and `bb2` wherever `MockAnticipated` says it is anticipated.
+struct MockAvailable {
+    anticipated: IndexVec<BasicBlock, BitSet<usize>>,
+}
+
+/// A toy "anticipated expressions" analysis. The single tracked expression is computed in `bb3`,
+/// and both `bb1` and `bb2` clobber its operands. However, `bb1` recomputes it if it is available
+/// on entry to `bb3` according to `MockAvailable`.
+struct MockAnticipated {
+    available: IndexVec<BasicBlock, BitSet<usize>>,
+}
+
+impl<'tcx> AnalysisDomain<'tcx> for MockAvailable {
+    type Domain = BitSet<usize>;
+
+    const NAME: &'static str = "mock_available";
+
+    fn bottom_value(&self, _: TyCtxt<'tcx>, body: &mir::Body<'tcx>) -> Self::Domain {
+        self.test_bottom(body)
+    }
+
+    fn initialize_start_block(&self, _: &mir::Body<'tcx>, _: &mut Self::Domain) {}
+}
+
+impl<'tcx> TestBottom<'tcx> for MockAvailable {
+    fn test_bottom(&self, _: &mir::Body<'tcx>) -> Self::Domain {
+        BitSet::new_empty(1)
+    }
+}
+
+impl<'tcx> AnalysisDomain<'tcx> for MockAnticipated {
+    type Domain = BitSet<usize>;
+    type Direction = Backward;
+
+    const NAME: &'static str = "mock_anticipated";
+
+    fn bottom_value(&self, _: TyCtxt<'tcx>, body: &mir::Body<'tcx>) -> Self::Domain {
+        self.test_bottom(body)
+    }
+
+    fn initialize_start_block(&self, _: &mir::Body<'tcx>, _: &mut Self::Domain) {}
+}
+
+impl<'tcx> TestBottom<'tcx> for MockAnticipated {
+    fn test_bottom(&self, _: &mir::Body<'tcx>) -> Self::Domain {
+        BitSet::new_empty(1)
+    }
+}
+
+impl<'tcx> Analysis<'tcx> for MockAvailable {
+    fn apply_statement_effect(
+        &mut self,
+        _state: &mut Self::Domain,
+        _statement: &mir::Statement<'tcx>,
+        _location: Location,
+    ) {
+    }
+
+    fn apply_terminator_effect<'mir>(
+        &mut self,
+        state: &mut Self::Domain,
+        terminator: &'mir mir::Terminator<'tcx>,
+        location: Location,
+    ) -> TerminatorEdges<'mir, 'tcx> {
+        let block = location.block;
+        if [1, 2].contains(&block.index()) && self.anticipated[block].contains(0) {
+            state.insert(0);
+        }
+        terminator.edges()
+    }
+
+    fn apply_call_return_effect(
+        &mut self,
+        _state: &mut Self::Domain,
+        _block: BasicBlock,
+        _return_places: CallReturnPlaces<'_, 'tcx>,
+    ) {
+    }
+}
+
+impl<'tcx> Analysis<'tcx> for MockAnticipated {
+    fn apply_statement_effect(
+        &mut self,
+        _state: &mut Self::Domain,
+        _statement: &mir::Statement<'tcx>,
+        _location: Location,
+    ) {
+    }
+
+    fn apply_terminator_effect<'mir>(
+        &mut self,
+        state: &mut Self::Domain,
+        terminator: &'mir mir::Terminator<'tcx>,
+        location: Location,
+    ) -> TerminatorEdges<'mir, 'tcx> {
+        match location.block.index() {
+            1 => {
+                state.remove(0);
+                if self.available[BasicBlock::from_u32(3)].contains(0) {
+                    state.insert(0);
+                }
+            }
+            2 => {
+                state.remove(0);
+            }
+            3 => {
+                state.insert(0);
+            }
+            _ => {}
+        }
+        terminator.edges()
+    }
+
+    fn apply_call_return_effect(
+        &mut self,
+        _state: &mut Self::Domain,
+        _block: BasicBlock,
+        _return_places: CallReturnPlaces<'_, 'tcx>,
+    ) {
+    }
+}
+
+impl<'tcx> CoupledAnalysis<'tcx, MockAnticipated> for MockAvailable {
+    fn observe(&mut self, anticipated: &EntrySets<'tcx, MockAnticipated>) {
+        self.anticipated.clone_from(anticipated);
+    }
+}
+
+impl<'tcx> CoupledAnalysis<'tcx, MockAvailable> for MockAnticipated {
+    fn observe(&mut self, available: &EntrySets<'tcx, MockAvailable>) {
+        self.available.clone_from(available);
+    }
+}
+
+#[test]
+fn bidirectional_fixpoint() {
+    let (bb1, bb2, bb3) =
+        (BasicBlock::from_u32(1), BasicBlock::from_u32(2), BasicBlock::from_u32(3));
+    let body = &diamond_body(0);
+
+    let empty = || IndexVec::from_elem(BitSet::new_empty(1), &body.basic_blocks);
+    let forward = MockAvailable { anticipated: empty() };
+    let backward = MockAnticipated { available: empty() };
+    let (forward_bottom, backward_bottom) = (forward.test_bottom(body), backward.test_bottom(body));
+    let (available, anticipated) = BidirectionalEngine::with_bottom_values(
+        body,
+        forward,
+        backward,
+        forward_bottom,
+        backward_bottom,
+    )
+    .max_rounds(3)
+    .iterate_to_fixpoint();
+
+    // The expression is inserted at the end of `bb1` and `bb2`, so it is available in `bb3`.
+    // That in turn makes it anticipated at the start of `bb1`, and thus at the end of `bb0`.
+    let expected = |blocks_with_bit: &[BasicBlock]| -> IndexVec<BasicBlock, BitSet<usize>> {
+        let mut sets = empty();
+        for &bb in blocks_with_bit {
+            sets[bb].insert(0);
+        }
+        sets
+    };
+    let available: Vec<_> = available.entry_sets.iter().cloned().collect();
+    assert_eq!(available, expected(&[bb3]).raw);
+    let anticipated: Vec<_> = anticipated.entry_sets.iter().cloned().collect();
+    assert_eq!(anticipated, expected(&[mir::START_BLOCK, bb1, bb2]).raw);
+}
+
+/// A gen/kill analysis over a single bit that is generated at `bb0[0]` and `bb1[1]`, and killed at
+/// `bb1[0]`. The bit is named `x0`.
+///
+/// Its `bottom_value` must not be called, since the tests have no `TyCtxt` to pass to it. Any test
+/// using this analysis thus checks that the framework code it runs takes the bottom value from
+/// elsewhere.
+#[derive(Clone, Copy)]
+struct MockGenKill;
+
+impl<'tcx> AnalysisDomain<'tcx> for MockGenKill {
+    type Domain = BitSet<usize>;
+
+    const NAME: &'static str = "mock_gen_kill";
+
+    fn bottom_value(&self, _: TyCtxt<'tcx>, _: &mir::Body<'tcx>) -> Self::Domain {
+        unreachable!("`bottom_value` is only called where a `TyCtxt` is available")
+    }
+
+    fn initialize_start_block(&self, _: &mir::Body<'tcx>, _: &mut Self::Domain) {}
+}
+
+impl<'tcx> TestBottom<'tcx> for MockGenKill {
+    fn test_bottom(&self, _: &mir::Body<'tcx>) -> Self::Domain {
+        BitSet::new_empty(1)
+    }
+}
+
+impl<'tcx> GenKillAnalysis<'tcx> for MockGenKill {
+    type Idx = usize;
+
+    fn domain_size(&self, _: &mir::Body<'tcx>) -> usize {
+        1
+    }
+
+    fn domain_element_name(&self, idx: usize, _: &mir::Body<'tcx>) -> String {
+        format!("x{idx}")
+    }
+
+    fn statement_effect(
+        &mut self,
+        trans: &mut impl GenKill<Self::Idx>,
+        _statement: &mir::Statement<'tcx>,
+        location: Location,
+    ) {
+        match (location.block.index(), location.statement_index) {
+            (0, 0) | (1, 1) => trans.gen(0),
+            (1, 0) => trans.kill(0),
+            _ => {}
+        }
+    }
+
+    fn terminator_effect<'mir>(
+        &mut self,
+        _trans: &mut Self::Domain,
+        terminator: &'mir mir::Terminator<'tcx>,
+        _location: Location,
+    ) -> TerminatorEdges<'mir, 'tcx> {
+        terminator.edges()
+    }
+
+    fn call_return_effect(
+        &mut self,
+        _trans: &mut impl GenKill<Self::Idx>,
+        _block: BasicBlock,
+        _return_places: CallReturnPlaces<'_, 'tcx>,
+    ) {
+    }
+}
+
+/// A gen/kill analysis in which each block that ends in a `Goto` generates its own index. The
+/// blocks are looked up in a table built by `pre_iterate`.
+#[derive(Default)]
+struct GotoBlocks {
+    gotos: Option<BitSet<BasicBlock>>,
+    pre_iterate_calls: usize,
+}
+
+impl<'tcx> AnalysisDomain<'tcx> for GotoBlocks {
+    type Domain = BitSet<BasicBlock>;
+
+    const NAME: &'static str = "goto_blocks";
+
+    fn bottom_value(&self, _: TyCtxt<'tcx>, body: &mir::Body<'tcx>) -> Self::Domain {
+        self.test_bottom(body)
+    }
+
+    fn initialize_start_block(&self, _: &mir::Body<'tcx>, _: &mut Self::Domain) {}
+}
+
+impl<'tcx> TestBottom<'tcx> for GotoBlocks {
+    fn test_bottom(&self, body: &mir::Body<'tcx>) -> Self::Domain {
+        BitSet::new_empty(body.basic_blocks.len())
+    }
+}
+
+impl<'tcx> GenKillAnalysis<'tcx> for GotoBlocks {
+    type Idx = BasicBlock;
+
+    fn domain_size(&self, body: &mir::Body<'tcx>) -> usize {
+        body.basic_blocks.len()
+    }
+
+    fn statement_effect(
+        &mut self,
+        _trans: &mut impl GenKill<Self::Idx>,
+        _statement: &mir::Statement<'tcx>,
+        _location: Location,
+    ) {
+    }
+
+    fn terminator_effect<'mir>(
+        &mut self,
+        trans: &mut Self::Domain,
+        terminator: &'mir mir::Terminator<'tcx>,
+        location: Location,
+    ) -> TerminatorEdges<'mir, 'tcx> {
+        if self.gotos.as_ref().unwrap().contains(location.block) {
+            trans.gen(location.block);
+        }
+        terminator.edges()
+    }
+
+    fn call_return_effect(
+        &mut self,
+        _trans: &mut impl GenKill<Self::Idx>,
+        _block: BasicBlock,
+        _return_places: CallReturnPlaces<'_, 'tcx>,
+    ) {
+    }
+
+    fn pre_iterate(&mut self, body: &mir::Body<'tcx>) {
+        let mut gotos = BitSet::new_empty(body.basic_blocks.len());
+        for (bb, data) in body.basic_blocks.iter_enumerated() {
+            if let mir::TerminatorKind::Goto { .. } = data.terminator().kind {
+                gotos.insert(bb);
+            }
+        }
+        self.gotos = Some(gotos);
+        self.pre_iterate_calls += 1;
+    }
+}
+
+#[test]
+fn pre_iterate() {
+    let body = &diamond_body(1);
+    let (bb1, bb2, bb3) =
+        (BasicBlock::from_u32(1), BasicBlock::from_u32(2), BasicBlock::from_u32(3));
+
+    let mut analysis = GotoBlocks::default();
+    let entry_sets = solve(&mut analysis, body);
+    assert_eq!(analysis.pre_iterate_calls, 1);
+    assert_eq!(entry_sets[bb3].iter().collect::<Vec<_>>(), [bb1, bb2]);
+
+    // Wrappers forward the hook to the analysis they wrap.
+    let mut analysis = CountingAnalysis::new(GotoBlocks::default());
+    solve(&mut analysis, body);
+    assert_eq!(analysis.into_inner().pre_iterate_calls, 1);
+}
+
+#[test]
+fn meet_blocks() {
+    let body = &diamond_body(0);
+    let (bb1, bb2) = (BasicBlock::from_u32(1), BasicBlock::from_u32(2));
+
+    let mut entry_sets =
+        EntrySets::from_bottom_n(BitSet::new_empty(3), body.basic_blocks.len());
+    entry_sets[bb1].insert(0);
+    entry_sets[bb1].insert(1);
+    entry_sets[bb2].insert(1);
+    entry_sets[bb2].insert(2);
+    let results = Results::new(MockGenKill, entry_sets);
+    assert_eq!(results.meet_blocks(bb1, bb2).iter().collect::<Vec<_>>(), [1]);
+    assert_eq!(results.meet_blocks(bb1, mir::START_BLOCK).iter().count(), 0);
+
+    let results = results.to_chunked();
+    assert_eq!(results.meet_blocks(bb2, bb1).iter().collect::<Vec<_>>(), [1]);
+}
+
+#[test]
+fn max_height() {
+    let body = &diamond_body(0);
+    let (bb1, bb2, bb3) =
+        (BasicBlock::from_u32(1), BasicBlock::from_u32(2), BasicBlock::from_u32(3));
+
+    let mut entry_sets =
+        EntrySets::from_bottom_n(BitSet::new_empty(3), body.basic_blocks.len());
+    entry_sets[bb1].insert(0);
+    entry_sets[bb2].insert(1);
+    entry_sets[bb3].insert(0);
+    entry_sets[bb3].insert(1);
+    entry_sets[bb3].insert(2);
+    let results = Results::new(MockGenKill, entry_sets);
+    assert_eq!(results.max_height(), 3);
+}
+
+#[test]
+fn is_empty_everywhere() {
+    // `MockAnalysis` is not a gen/kill analysis, but its domain is a bitset.
+    let body = &diamond_body(0);
+    let analysis = MockAnalysis::<Forward>::new(body);
+
+    let bottom = EntrySets::from_bottom_n(analysis.test_bottom(body), body.basic_blocks.len());
+    assert!(Results::new(analysis, bottom).is_empty_everywhere());
+
+    let mut entry_sets =
+        EntrySets::from_bottom_n(analysis.test_bottom(body), body.basic_blocks.len());
+    entry_sets[BasicBlock::from_u32(3)].insert(0);
+    assert!(!Results::new(analysis, entry_sets).is_empty_everywhere());
+}
+
+/// An analysis with a zero-sized domain, e.g. a pure reachability analysis.
+struct MockZst;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct Reached;
+
+impl JoinSemiLattice for Reached {
+    fn join(&mut self, _: &Self) -> bool {
+        false
+    }
+}
+
+impl<'tcx> AnalysisDomain<'tcx> for MockZst {
+    type Domain = Reached;
+
+    const NAME: &'static str = "mock_zst";
+
+    fn bottom_value(&self, _: TyCtxt<'tcx>, _: &mir::Body<'tcx>) -> Self::Domain {
+        Reached
+    }
+
+    fn initialize_start_block(&self, _: &mir::Body<'tcx>, _: &mut Self::Domain) {}
+}
+
+#[test]
+fn zero_sized_entry_sets() {
+    let bb1 = BasicBlock::from_u32(1);
+    let mut entry_sets = EntrySets::<MockZst>::from_bottom_n(Reached, 3);
+    assert_eq!(entry_sets.len(), 3);
+    assert_eq!(entry_sets.get(bb1), Some(&Reached));
+    assert_eq!(entry_sets.get(BasicBlock::from_u32(3)), None);
+
+    // Writing to a block leaves it unpopulated, since there is nothing to store.
+    entry_sets[bb1] = Reached;
+    assert!(!entry_sets.update(bb1, |set| set.join(&Reached)));
+    assert!(!entry_sets.is_populated(bb1));
+
+    let blocks: Vec<_> = entry_sets.iter_enumerated_mut().map(|(block, _)| block).collect();
+    assert_eq!(blocks, (0..3).map(BasicBlock::new).collect::<Vec<_>>());
+    assert_eq!(entry_sets.compact(), 3.0);
+
+    let from_sets = EntrySets::<MockZst>::from_sets(IndexVec::from_elem_n(Reached, 3), Reached);
+    assert_eq!(from_sets, entry_sets);
+
+    let mapped = entry_sets.map::<MockUnwindEffect>(|_| BitSet::new_empty(3));
+    assert_eq!(mapped.len(), 3);
+    assert!(!mapped.is_populated(bb1));
+    assert_eq!(mapped[bb1], BitSet::new_empty(3));
+}
+
+#[test]
+fn explain_bit() {
+    let source_info = mir::SourceInfo::outermost(DUMMY_SP);
+    let (bb1, bb2, bb3) =
+        (BasicBlock::from_u32(1), BasicBlock::from_u32(2), BasicBlock::from_u32(3));
+
+    let mut blocks = IndexVec::new();
+    let mut block = |n, kind| {
+        let nop = mir::Statement { source_info, kind: mir::StatementKind::Nop };
+
+        blocks.push(mir::BasicBlockData {
+            statements: std::iter::repeat(&nop).cloned().take(n).collect(),
+            terminator: Some(mir::Terminator { source_info, kind }),
+            is_cleanup: false,
+        })
+    };
+    let cond = mir::Operand::Copy(mir::Place::from(mir::RETURN_PLACE));
+    block(1, mir::TerminatorKind::if_(cond, bb1, bb2));
+    block(2, mir::TerminatorKind::Goto { target: bb3 });
+    block(1, mir::TerminatorKind::Goto { target: bb3 });
+    block(1, mir::TerminatorKind::Return);
+    let body = &mir::Body::new_cfg_only(blocks);
+
+    // The bit is set on entry to every block except `bb0`.
+    let mut entry_sets =
+        EntrySets::from_sets(
+            IndexVec::from_elem(BitSet::new_filled(1), &body.basic_blocks),
+            BitSet::new_empty(1),
+        );
+    entry_sets[mir::START_BLOCK].clear();
+    let mut results = Results::new(MockGenKill, entry_sets);
+
+    // The gen at `bb0[0]` reaches `bb3` through `bb2`. Along the path through `bb1`, it is killed
+    // and the bit is generated again at `bb1[1]`.
+    let gen_sites = results.explain_bit(body, body.terminator_loc(bb3), 0);
+    assert_eq!(
+        gen_sites,
+        [mir::START_BLOCK.start_location(), Location { block: bb1, statement_index: 1 }]
+    );
+
+    // The bit is not set between the kill and the gen in `bb1`.
+    assert!(results.explain_bit(body, Location { block: bb1, statement_index: 1 }, 0).is_empty());
+}
+
+#[test]
+fn explain_state() {
+    let body = &diamond_body(0);
+    let (bb0, bb1) = (mir::START_BLOCK, BasicBlock::from_u32(1));
+
+    // Gen/kill analyses list their elements by `domain_element_name`.
+    let mut state = MockGenKill.test_bottom(body);
+    assert_eq!(fmt::explain_state(&MockGenKill, &state, body), "{}");
+    state.insert(0);
+    assert_eq!(fmt::explain_state(&MockGenKill, &state, body), "{x0}");
+    assert_eq!(MockGenKill.explain_gen_kill_state(&state, body), "{x0}");
+
+    // Wrappers explain the state as the analysis they wrap does.
+    let explained = Some("{x0}".to_owned());
+    assert_eq!(CountingAnalysis::new(MockGenKill).explain_state(&state, body), explained);
+    assert_eq!(TraceAnalysis::new(MockGenKill).explain_state(&state, body), explained);
+    let trace = TraceGenKillAnalysis::new(MockGenKill);
+    assert_eq!(trace.explain_state(&state, body), explained);
+    assert_eq!(trace.explain_gen_kill_state(&state, body), "{x0}");
+    let map = MapDomain::new(MockGenKill, BitSet::clone, BitSet::clone);
+    assert_eq!(map.explain_state(&state, body), explained);
+
+    // Other analyses fall back to the `DebugWithContext` formatting of their domain.
+    let state = Provenance(BTreeSet::from([(bb0, bb1)]));
+    assert_eq!(MockProvenanceAnalysis.explain_state(&state, body), None);
+    assert_eq!(
+        fmt::explain_state(&MockProvenanceAnalysis, &state, body),
+        "Provenance({(bb0, bb1)})"
+    );
+}
+
+#[test]
+fn start_block_seed() {
+    let body = &diamond_body(0);
+    let [bb0, bb1, bb2, _] = [0, 1, 2, 3].map(BasicBlock::from_u32);
+    let set = |blocks: &[BasicBlock]| {
+        let mut set = BitSet::new_empty(body.basic_blocks.len());
+        for &block in blocks {
+            set.insert(block);
+        }
+        set
+    };
+
+    // With `contains_bit`, the seed is kept as long as any of its bits are.
+    let analysis = GotoBlocks::default();
+    let (seed, bottom) = (set(&[bb0, bb1]), set(&[]));
+    assert!(!start_block_seed_lost(&analysis, body, &set(&[bb1, bb2]), &seed, &bottom));
+    assert!(start_block_seed_lost(&analysis, body, &set(&[bb2]), &seed, &bottom));
+    assert!(start_block_seed_lost(&analysis, body, &bottom, &seed, &bottom));
+
+    // There is nothing to lose without a seed.
+    assert!(!start_block_seed_lost(&analysis, body, &bottom, &bottom, &bottom));
+
+    // Without it, the seed is only lost if the entry set is back at bottom.
+    let seed = Provenance(BTreeSet::from([(bb0, bb1)]));
+    let bottom = Provenance(BTreeSet::new());
+    let other = Provenance(BTreeSet::from([(bb0, bb2)]));
+    assert!(!start_block_seed_lost(&MockProvenanceAnalysis, body, &other, &seed, &bottom));
+    assert!(start_block_seed_lost(&MockProvenanceAnalysis, body, &bottom, &seed, &bottom));
+}
+
+#[test]
+fn erased_results() {
+    let body = &mock_yield_body();
+    let resume_local = mir::Local::from_u32(1);
+
+    let forward = {
+        let analysis = MockYieldAnalysis { dir: PhantomData::<Forward> };
+        let mut entry_sets =
+            EntrySets::from_bottom_n(analysis.test_bottom(body), body.basic_blocks.len());
+        entry_sets[BasicBlock::from_u32(1)].insert(resume_local);
+        Results::new(analysis, entry_sets)
+    };
+    let backward = {
+        let analysis = MockYieldAnalysis { dir: PhantomData::<Backward> };
+        let entry_sets =
+            EntrySets::from_bottom_n(analysis.test_bottom(body), body.basic_blocks.len());
+        Results::new(analysis, entry_sets)
+    };
+
+    let mut all_results: Vec<Box<dyn ErasedResults<'_>>> =
+        vec![Box::new(forward), Box::new(backward)];
+
+    let directions: Vec<_> = all_results.iter().map(|results| results.direction()).collect();
+    assert_eq!(directions, [DirectionKind::Forward, DirectionKind::Backward]);
+
+    let entry_states: Vec<_> = all_results
+        .iter()
+        .map(|results| results.entry_state_rendered(BasicBlock::from_u32(1)))
+        .collect();
+    assert_eq!(entry_states, ["{_1}", "{}"]);
+
+    for results in &mut all_results {
+        let mut visited = vec![];
+        visit_results_dyn(body, [mir::START_BLOCK], &mut **results, |loc, state| {
+            visited.push((loc, state))
+        });
+        assert_eq!(visited, [(body.terminator_loc(mir::START_BLOCK), "{}".to_owned())]);
+    }
+}
+
+#[test]
+fn erased_analysis_runner() {
+    let body = &mock_yield_body();
+    let bb1 = BasicBlock::from_u32(1);
+
+    let runners: Vec<Box<dyn ErasedAnalysisRunner<'_>>> = vec![
+        Box::new(MockYieldAnalysis { dir: PhantomData::<Forward> }),
+        Box::new(MockProvenanceAnalysis),
+    ];
+
+    let names: Vec<_> = runners.iter().map(|runner| runner.name()).collect();
+    assert_eq!(names, ["mock_yield", "mock_provenance"]);
+
+    // `run` needs a `TyCtxt`, so the analyses are solved here and only their results erased.
+    let mut yield_analysis = MockYieldAnalysis { dir: PhantomData::<Forward> };
+    let yield_entry_sets = solve(&mut yield_analysis, body);
+    let provenance_entry_sets = solve(&mut MockProvenanceAnalysis, body);
+    let all_results: Vec<Box<dyn ErasedResults<'_>>> = vec![
+        Box::new(Results::new(yield_analysis, yield_entry_sets)),
+        Box::new(Results::new(MockProvenanceAnalysis, provenance_entry_sets)),
+    ];
+
+    let entry_states: Vec<_> =
+        all_results.iter().map(|results| results.entry_state_rendered(bb1)).collect();
+    assert_eq!(entry_states, ["{_1}", "Provenance({(bb0, bb1)})"]);
+}
+
+/// The set of CFG edges along which state reached a block.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Provenance(BTreeSet<(BasicBlock, BasicBlock)>);
+
+impl JoinSemiLattice for Provenance {
+    fn join(&mut self, other: &Self) -> bool {
+        let len = self.0.len();
+        self.0.extend(other.0.iter().copied());
+        self.0.len() != len
+    }
+}
+
+impl<C> DebugWithContext<C> for Provenance {}
+
+/// An analysis that records the edges along which state was propagated.
+#[derive(Clone, Copy)]
+struct MockProvenanceAnalysis;
+
+impl<'tcx> AnalysisDomain<'tcx> for MockProvenanceAnalysis {
+    type Domain = Provenance;
+
+    const NAME: &'static str = "mock_provenance";
+
+    fn bottom_value(&self, _: TyCtxt<'tcx>, body: &mir::Body<'tcx>) -> Self::Domain {
+        self.test_bottom(body)
+    }
+
+    fn initialize_start_block(&self, _: &mir::Body<'tcx>, _: &mut Self::Domain) {}
+}
+
+impl<'tcx> TestBottom<'tcx> for MockProvenanceAnalysis {
+    fn test_bottom(&self, _: &mir::Body<'tcx>) -> Self::Domain {
+        Provenance(BTreeSet::new())
+    }
+}
+
+impl<'tcx> Analysis<'tcx> for MockProvenanceAnalysis {
+    fn apply_statement_effect(
+        &mut self,
+        _state: &mut Self::Domain,
+        _statement: &mir::Statement<'tcx>,
+        _location: Location,
+    ) {
+    }
+
+    fn apply_terminator_effect<'mir>(
+        &mut self,
+        _state: &mut Self::Domain,
+        terminator: &'mir mir::Terminator<'tcx>,
+        _location: Location,
+    ) -> TerminatorEdges<'mir, 'tcx> {
+        terminator.edges()
+    }
+
+    fn apply_call_return_effect(
+        &mut self,
+        _state: &mut Self::Domain,
+        _block: BasicBlock,
+        _return_places: CallReturnPlaces<'_, 'tcx>,
+    ) {
+    }
+
+    fn join_at(
+        &self,
+        source: BasicBlock,
+        bb: BasicBlock,
+        into: &mut Self::Domain,
+        from: &Self::Domain,
+    ) -> bool {
+        let mut from = from.clone();
+        from.0.insert((source, bb));
+        into.join(&from)
+    }
+}
+
+#[test]
+fn entry_sets_helpers() {
+    let body = &diamond_body(0);
+    let (bb1, bb2, bb3) =
+        (BasicBlock::from_u32(1), BasicBlock::from_u32(2), BasicBlock::from_u32(3));
+    let analysis = MockProvenanceAnalysis;
+    let entry_sets = solve(&mut MockProvenanceAnalysis, body);
+
+    assert_eq!(entry_sets.get(bb3), Some(&entry_sets[bb3]));
+    assert_eq!(entry_sets.get(BasicBlock::from_u32(4)), None);
+    assert_eq!(entry_sets.iter_enumerated().count(), 4);
+
+    let mut expected = entry_sets[bb1].clone();
+    expected.join(&entry_sets[bb2]);
+    assert_eq!(entry_sets.joined_over([bb1, bb2]), expected);
+    assert_eq!(entry_sets.joined_over([bb3]), entry_sets[bb3]);
+    assert_eq!(entry_sets.joined_over([]), analysis.test_bottom(body));
+
+    let body = &straight_line_body(2, 0);
+    let entry_sets = solve(&mut MockProvenanceAnalysis, body);
+    assert_eq!(
+        entry_sets.render(&analysis),
+        "bb0: Provenance({})\nbb1: Provenance({(bb0, bb1)})\n"
+    );
+}
+
+#[test]
+fn bottom_value_is_only_called_with_tcx() {
+    let body = &diamond_body(1);
+    let (bb1, bb2, bb3) =
+        (BasicBlock::from_u32(1), BasicBlock::from_u32(2), BasicBlock::from_u32(3));
+    let entry_sets = solve(&mut MockGenKill, body);
+    assert_eq!(entry_sets.joined_over([]), BitSet::new_empty(1));
+
+    // Everything that needs a bottom value without a `TyCtxt` takes the one kept by the entry
+    // sets, or starts from a copy of an entry set that it overwrites.
+    let mut results = Results::new(MockGenKill, entry_sets);
+    assert!(!results.edge_state(body, bb1, bb3).contains(0));
+    assert!(results.edge_state(body, bb2, bb3).contains(0));
+    let exit_states: Vec<_> = results
+        .predecessor_exit_states(body, bb3)
+        .into_iter()
+        .map(|(pred, state)| (pred, state.contains(0)))
+        .collect();
+    assert_eq!(exit_states, [(bb1, false), (bb2, true)]);
+
+    let mut vis = BlockStartStates { project: BitSet::clone, states: vec![] };
+    results.visit_non_bottom_blocks(body, &mut vis);
+    let blocks: Vec<_> = vis.states.iter().map(|&(block, _)| block).collect();
+    assert_eq!(blocks, [bb1, bb2, bb3]);
+
+    results.to_graphviz_string(body, graphviz::OutputStyle::AfterOnly);
+
+    let mut cursor = results.into_results_cursor(body);
+    cursor.seek_to_block_end(bb1);
+    assert!(!cursor.contains(0));
+    cursor.seek_to_block_end(bb3);
+    assert!(cursor.contains(0));
+}
+
+#[test]
+fn cursor_diff_against() {
+    let body = &diamond_body(0);
+    let bb3 = BasicBlock::from_u32(3);
+    let entry_sets = solve(&mut MockProvenanceAnalysis, body);
+    let mut modified = entry_sets.clone();
+    modified[bb3] = Provenance(BTreeSet::new());
+
+    let results = Results::new(MockProvenanceAnalysis, entry_sets);
+    let other = Results::new(MockProvenanceAnalysis, modified);
+    let mut cursor = results.into_results_cursor(body);
+    let mut other = other.into_results_cursor(body);
+
+    cursor.seek_to_block_start(mir::START_BLOCK);
+    other.seek_to_block_start(mir::START_BLOCK);
+    assert_eq!(cursor.diff_against(&other), None);
+
+    cursor.seek_to_block_start(bb3);
+    other.seek_to_block_start(bb3);
+    let expected = "+Provenance({(bb0, bb1), (bb0, bb2), (bb1, bb3), (bb2, bb3)}) -Provenance({})";
+    assert_eq!(cursor.diff_against(&other).as_deref(), Some(expected));
+}
+
+/// An analysis in which each block inserts its own index, but whose entry set of `bb3` never takes
+/// in index 1.
+struct MockJoinAtAnalysis;
+
+impl<'tcx> AnalysisDomain<'tcx> for MockJoinAtAnalysis {
+    type Domain = BitSet<usize>;
+
+    const NAME: &'static str = "mock_join_at";
+
+    fn bottom_value(&self, _: TyCtxt<'tcx>, body: &mir::Body<'tcx>) -> Self::Domain {
+        self.test_bottom(body)
+    }
+
+    fn initialize_start_block(&self, _: &mir::Body<'tcx>, _: &mut Self::Domain) {}
+}
+
+impl<'tcx> TestBottom<'tcx> for MockJoinAtAnalysis {
+    fn test_bottom(&self, body: &mir::Body<'tcx>) -> Self::Domain {
+        BitSet::new_empty(body.basic_blocks.len())
+    }
+}
+
+impl<'tcx> Analysis<'tcx> for MockJoinAtAnalysis {
+    fn apply_statement_effect(
+        &mut self,
+        _state: &mut Self::Domain,
+        _statement: &mir::Statement<'tcx>,
+        _location: Location,
+    ) {
+    }
+
+    fn apply_terminator_effect<'mir>(
+        &mut self,
+        state: &mut Self::Domain,
+        terminator: &'mir mir::Terminator<'tcx>,
+        location: Location,
+    ) -> TerminatorEdges<'mir, 'tcx> {
+        state.insert(location.block.index());
+        terminator.edges()
+    }
+
+    fn apply_call_return_effect(
+        &mut self,
+        _state: &mut Self::Domain,
+        _block: BasicBlock,
+        _return_places: CallReturnPlaces<'_, 'tcx>,
+    ) {
+    }
+
+    fn join_at(
+        &self,
+        _source: BasicBlock,
+        bb: BasicBlock,
+        into: &mut Self::Domain,
+        from: &Self::Domain,
+    ) -> bool {
+        let mut from = from.clone();
+        if bb == BasicBlock::from_u32(3) {
+            from.remove(1);
+        }
+        into.join(&from)
+    }
+}
+
+#[test]
+fn join_at() {
+    let body = &diamond_body(0);
+    let bb3 = BasicBlock::from_u32(3);
+
+    let entry_sets = solve(&mut MockJoinAtAnalysis, body);
+    assert_eq!(entry_sets[bb3].iter().collect::<Vec<_>>(), [0, 2]);
+}
+
+#[test]
+fn edge_state() {
+    let body = &diamond_body(0);
+    let (bb0, bb1, bb2, bb3) = (
+        BasicBlock::from_u32(0),
+        BasicBlock::from_u32(1),
+        BasicBlock::from_u32(2),
+        BasicBlock::from_u32(3),
+    );
+    let entry_sets = solve(&mut MockProvenanceAnalysis, body);
+    let mut results = Results::new(MockProvenanceAnalysis, entry_sets);
+
+    let expected = Provenance([(bb0, bb1), (bb1, bb3)].into_iter().collect());
+    assert_eq!(results.edge_state(body, bb1, bb3), expected);
+
+    let mut joined = results.edge_state(body, bb1, bb3);
+    joined.join(&results.edge_state(body, bb2, bb3));
+    assert_eq!(joined, results.entry_sets[bb3]);
+
+    // The predecessor exit states are the edge states, including the provenance of each edge.
+    let mut joined = Provenance(BTreeSet::new());
+    for (_, state) in results.predecessor_exit_states(body, bb3) {
+        joined.join(&state);
+    }
+    assert_eq!(joined, results.entry_sets[bb3]);
+
+    // There is no edge from `bb1` to `bb2`.
+    assert_eq!(results.edge_state(body, bb1, bb2), Provenance(BTreeSet::new()));
+
+    // Entry sets in which every block is populated still have a bottom value to fall back to.
+    let populated = results.entry_sets.iter().cloned().collect();
+    let entry_sets = EntrySets::from_sets(populated, Provenance(BTreeSet::new()));
+    let mut results = Results::new(MockProvenanceAnalysis, entry_sets);
+    assert_eq!(results.edge_state(body, bb1, bb2), Provenance(BTreeSet::new()));
+}
+
+#[test]
+fn results_are_send() {
+    fn assert_send<T: Send>() {}
+
+    assert_send::<Results<'static, crate::impls::MaybeLiveLocals>>();
+    assert_send::<Results<'static, crate::impls::MaybeBorrowedLocals>>();
+}
+
+#[test]
+fn concurrent_gen_kill_trans() {
+    enable_dyn_thread_safe_mode();
+    for body in [&straight_line_body(3, 2), &diamond_body(2), &loop_body(2)] {
+        let sequential = gen_kill_trans_for_blocks(&mut MockGenKill, body, 1);
+        let concurrent = gen_kill_trans_for_blocks_concurrently(&MockGenKill, body, 1);
+        assert_eq!(sequential.len(), concurrent.len());
+
+        // `GenKillSet` has no notion of equality, so compare the effect of each transfer function.
+        for (sequential, concurrent) in std::iter::zip(sequential, concurrent) {
+            for mut state in [BitSet::new_empty(1), BitSet::new_filled(1)] {
+                let mut other = state.clone();
+                sequential.apply(&mut state);
+                concurrent.apply(&mut other);
+                assert_eq!(state, other);
+            }
+        }
+    }
+}
+
+/// A gen/kill domain that is not a bitset, and so relies on the default `GenKill::apply_trans`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct SortedSet(BTreeSet<usize>);
+
+impl GenKill<usize> for SortedSet {
+    fn gen(&mut self, elem: usize) {
+        self.0.insert(elem);
+    }
+
+    fn kill(&mut self, elem: usize) {
+        self.0.remove(&elem);
+    }
+}
+
+impl BitSetExt<usize> for SortedSet {
+    fn contains(&self, elem: usize) -> bool {
+        self.0.contains(&elem)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn count(&self) -> usize {
+        self.0.len()
+    }
+
+    fn domain_size(&self) -> Option<usize> {
+        None
+    }
+}
+
+impl JoinSemiLattice for SortedSet {
+    fn join(&mut self, other: &Self) -> bool {
+        let len = self.0.len();
+        self.0.extend(other.0.iter().copied());
+        self.0.len() != len
+    }
+}
+
+impl<C> DebugWithContext<C> for SortedSet {}
+
+/// `MockGenKill` over a `SortedSet`.
+struct MockSortedGenKill;
+
+impl<'tcx> AnalysisDomain<'tcx> for MockSortedGenKill {
+    type Domain = SortedSet;
+
+    const NAME: &'static str = "mock_sorted_gen_kill";
+
+    fn bottom_value(&self, _: TyCtxt<'tcx>, body: &mir::Body<'tcx>) -> Self::Domain {
+        self.test_bottom(body)
+    }
+
+    fn initialize_start_block(&self, _: &mir::Body<'tcx>, _: &mut Self::Domain) {}
+}
+
+impl<'tcx> TestBottom<'tcx> for MockSortedGenKill {
+    fn test_bottom(&self, _: &mir::Body<'tcx>) -> Self::Domain {
+        SortedSet::default()
+    }
+}
+
+impl<'tcx> GenKillAnalysis<'tcx> for MockSortedGenKill {
+    type Idx = usize;
+
+    fn domain_size(&self, _: &mir::Body<'tcx>) -> usize {
+        1
+    }
+
+    fn statement_effect(
+        &mut self,
+        trans: &mut impl GenKill<Self::Idx>,
+        statement: &mir::Statement<'tcx>,
+        location: Location,
+    ) {
+        MockGenKill.statement_effect(trans, statement, location);
+    }
+
+    fn terminator_effect<'mir>(
+        &mut self,
+        _trans: &mut Self::Domain,
+        terminator: &'mir mir::Terminator<'tcx>,
+        _location: Location,
+    ) -> TerminatorEdges<'mir, 'tcx> {
+        terminator.edges()
+    }
+
+    fn call_return_effect(
+        &mut self,
+        _trans: &mut impl GenKill<Self::Idx>,
+        _block: BasicBlock,
+        _return_places: CallReturnPlaces<'_, 'tcx>,
+    ) {
+    }
+}
+
+#[test]
+fn gen_kill_trans_for_other_domains() {
+    for body in [&straight_line_body(3, 2), &diamond_body(2), &loop_body(2)] {
+        let expected = solve(&mut MockGenKill, body);
+
+        let trans = gen_kill_trans_for_blocks(&mut MockSortedGenKill, body, 1);
+        let apply_trans = block_trans::<SortedSet, _>(std::sync::Arc::new(trans));
+        let mut entry_sets =
+            EntrySets::from_bottom_n(SortedSet::default(), body.basic_blocks.len());
+        let options = FixpointOptions {
+            apply_statement_trans_for_block: Some(&|bb: BasicBlock, state: &mut SortedSet| {
+                apply_trans(bb, state);
+                true
+            }),
+            ..FixpointOptions::default()
+        };
+        solve_from(&mut MockSortedGenKill, body, &mut entry_sets, options);
+
+        for (bb, entry_set) in entry_sets.iter_enumerated() {
+            let elems: Vec<_> = entry_set.0.iter().copied().collect();
+            assert_eq!(elems, expected[bb].iter().collect::<Vec<_>>(), "{bb:?}");
+        }
+    }
+}
+
+/// A gen/kill analysis in which each statement generates its own index and kills that of the
+/// previous one. Its batched effect only looks at the number of statements in a block.
+#[derive(Default)]
+struct MockBatchGenKill {
+    batches: usize,
+}
+
+impl<'tcx> AnalysisDomain<'tcx> for MockBatchGenKill {
+    type Domain = BitSet<usize>;
+
+    const NAME: &'static str = "mock_batch_gen_kill";
+
+    fn bottom_value(&self, _: TyCtxt<'tcx>, body: &mir::Body<'tcx>) -> Self::Domain {
+        self.test_bottom(body)
+    }
+
+    fn initialize_start_block(&self, _: &mir::Body<'tcx>, _: &mut Self::Domain) {}
+}
+
+impl<'tcx> TestBottom<'tcx> for MockBatchGenKill {
+    fn test_bottom(&self, _: &mir::Body<'tcx>) -> Self::Domain {
+        BitSet::new_empty(4)
+    }
+}
+
+impl<'tcx> GenKillAnalysis<'tcx> for MockBatchGenKill {
+    type Idx = usize;
+
+    fn domain_size(&self, _: &mir::Body<'tcx>) -> usize {
+        4
+    }
+
+    fn statement_effect(
+        &mut self,
+        trans: &mut impl GenKill<Self::Idx>,
+        _statement: &mir::Statement<'tcx>,
+        location: Location,
+    ) {
+        if let Some(previous) = location.statement_index.checked_sub(1) {
+            trans.kill(previous);
+        }
+        trans.gen(location.statement_index);
+    }
+
+    fn batch_statement_effect(
+        &mut self,
+        trans: &mut impl GenKill<Self::Idx>,
+        _block: BasicBlock,
+        block_data: &mir::BasicBlockData<'tcx>,
+    ) {
+        self.batches += 1;
+        let last = block_data.statements.len() - 1;
+        trans.kill_all(0..last);
+        trans.gen(last);
+    }
+
+    fn terminator_effect<'mir>(
+        &mut self,
+        _trans: &mut Self::Domain,
+        terminator: &'mir mir::Terminator<'tcx>,
+        _location: Location,
+    ) -> TerminatorEdges<'mir, 'tcx> {
+        terminator.edges()
+    }
+
+    fn call_return_effect(
+        &mut self,
+        _trans: &mut impl GenKill<Self::Idx>,
+        _block: BasicBlock,
+        _return_places: CallReturnPlaces<'_, 'tcx>,
+    ) {
+    }
+}
+
+#[test]
+fn batch_statement_effect() {
+    let body = &straight_line_body(3, 3);
+    let mut analysis = MockBatchGenKill::default();
+    let trans = gen_kill_trans_for_blocks(&mut analysis, body, 4);
+
+    assert_eq!(analysis.batches, 3);
+    for block in body.basic_blocks.indices() {
+        let mut state = BitSet::new_filled(4);
+        trans[block].apply(&mut state);
+        assert_eq!(state.iter().collect::<Vec<_>>(), [2, 3]);
+    }
+
+    // Without cached transfer functions, the engine applies the batched effect as well.
+    analysis.batches = 0;
+    let entry_sets = solve(&mut analysis, body);
+    assert_eq!(analysis.batches, 3);
+
+    // Cursors apply one statement at a time, and agree with the batched effect at the end.
+    let mut cursor = Results::new(analysis, entry_sets).into_results_cursor(body);
+    cursor.seek_to_block_end(BasicBlock::from_u32(2));
+    assert_eq!(cursor.get().iter().collect::<Vec<_>>(), [2]);
+}
+
+/// Iterates `analysis` to fixpoint over `body` with dense entry sets, and checks that the result
+/// is the same as with generic ones.
+fn check_dense_fixpoint<'tcx, A, T>(
+    mut analysis: A,
+    body: &mir::Body<'tcx>,
+    apply_statement_trans_for_block: Option<&dyn Fn(BasicBlock, &mut BitSet<T>) -> bool>,
+) where
+    A: Analysis<'tcx, Domain = BitSet<T>> + TestBottom<'tcx>,
+    T: Idx,
+{
+    let expected = solve(&mut analysis, body);
+
+    let bottom = analysis.test_bottom(body);
+    let entry_sets = EntrySets::from_bottom_n(bottom, body.basic_blocks.len());
+    let mut dense = Results::new(analysis, entry_sets).into_dense();
+    let (dense_analysis, rows) = dense.analysis_and_entry_sets_mut();
+    let mut rows = DenseEntrySets::new(rows);
+    let options = FixpointOptions { apply_statement_trans_for_block, ..FixpointOptions::default() };
+    let converged = iterate_entry_sets_to_fixpoint(dense_analysis, body, &mut rows, options);
+    assert!(converged.is_ok());
+
+    for (bb, expected) in expected.iter_enumerated() {
+        assert_eq!(&dense.entry_set_for_block(bb), expected, "{bb:?}");
+    }
+    assert_eq!(dense.into_results().entry_sets, expected);
+}
+
+#[test]
+fn dense_fixpoint() {
+    for body in [&straight_line_body(3, 2), &diamond_body(2), &loop_body(2)] {
+        check_dense_fixpoint(MockGenKill, body, None);
+
+        let trans = gen_kill_trans_for_blocks(&mut MockGenKill, body, 1);
+        check_dense_fixpoint(
+            MockGenKill,
+            body,
+            Some(&|bb: BasicBlock, state: &mut BitSet<usize>| {
+                trans[bb].apply(state);
+                true
+            }),
+        );
+    }
+
+    let body = &mock_yield_body();
+    check_dense_fixpoint(MockYieldAnalysis { dir: PhantomData::<Forward> }, body, None);
+    check_dense_fixpoint(MockYieldAnalysis { dir: PhantomData::<Backward> }, body, None);
+}
+
+#[test]
+fn dense_results_round_trip() {
+    let body = &loop_body(2);
+    let analysis = MockAnalysis::<Forward>::new(body);
+    let entry_sets = analysis.mock_entry_sets();
+    let results = Results::new(analysis, entry_sets.clone());
+
+    let dense = results.into_dense();
+    let mut state = BitSet::new_filled(dense.entry_set_for_block(mir::START_BLOCK).domain_size());
+    for (bb, entry_set) in entry_sets.iter_enumerated() {
+        dense.copy_entry_set_into(bb, &mut state);
+        assert_eq!(&state, entry_set);
+        assert!(dense.contains(bb, 100 + bb.index()));
+    }
+    assert_eq!(dense.into_results().entry_sets, entry_sets);
+}
+
+#[test]
+fn compressed_results_round_trip() {
+    let bodies = [
+        &straight_line_body(3, 2),
+        &straight_line_body(100, 1),
+        &diamond_body(2),
+        &loop_body(2),
+        &disconnected_body(),
+    ];
+    for body in bodies {
+        let analysis = MockAnalysis::<Forward>::new(body);
+        let entry_sets = analysis.mock_entry_sets();
+        let results = Results::new(analysis, entry_sets.clone());
+
+        let compressed = results.compress(body);
+        for (bb, entry_set) in entry_sets.iter_enumerated() {
+            assert_eq!(&compressed.entry_set_for_block(bb), entry_set);
+        }
+        assert_eq!(compressed.into_results().entry_sets, entry_sets);
+    }
+}
+
+/// Makes the parallel helpers of `rustc_data_structures::sync` run, as they do under
+/// `-Z threads` with more than one thread. The mode is global and can only be set once, so every
+/// test that depends on it sets the same value.
+fn enable_dyn_thread_safe_mode() {
+    sync::set_dyn_thread_safe_mode(true);
+}
+
+#[test]
+fn join_at_with_provenance() {
+    let body = &mock_match_guard_body();
+    let entry_sets = solve(&mut MockProvenanceAnalysis, body);
+
+    let recorded: BTreeSet<_> = entry_sets.iter().flat_map(|set| set.0.iter().copied()).collect();
+    let cfg_edges: BTreeSet<_> = body
+        .basic_blocks
+        .iter_enumerated()
+        .flat_map(|(bb, data)| data.terminator().successors().map(move |succ| (bb, succ)))
+        .collect();
+    assert_eq!(recorded, cfg_edges);
+}
+
+#[test]
+fn join_at_through_wrappers() {
+    let body = &straight_line_body(4, 0);
+    let expected: Vec<BTreeSet<_>> = body
+        .basic_blocks
+        .indices()
+        .map(|bb| (1..=bb.index()).map(|i| (BasicBlock::new(i - 1), BasicBlock::new(i))).collect())
+        .collect();
+
+    let entry_sets = solve(&mut CountingAnalysis::new(MockProvenanceAnalysis), body);
+    let recorded: Vec<_> = entry_sets.iter().map(|set| set.0.clone()).collect();
+    assert_eq!(recorded, expected);
+
+    let entry_sets = solve(&mut TraceAnalysis::new(MockProvenanceAnalysis), body);
+    let recorded: Vec<_> = entry_sets.iter().map(|set| set.0.clone()).collect();
+    assert_eq!(recorded, expected);
+
+    let mut analysis = MapDomain::new(MockProvenanceAnalysis, Provenance::clone, Provenance::clone);
+    let entry_sets =
+        solve_with_bottom(&mut analysis, body, |_, body| MockProvenanceAnalysis.test_bottom(body));
+    let recorded: Vec<_> = entry_sets.iter().map(|set| set.0.clone()).collect();
+    assert_eq!(recorded, expected);
+}
+
+/// Creates a `mir::Body` whose `bb1` has not been given a terminator yet.
+fn missing_terminator_body<'tcx>() -> mir::Body<'tcx> {
+    let source_info = mir::SourceInfo::outermost(DUMMY_SP);
+    let bb1 = BasicBlock::from_u32(1);
+
+    let mut blocks = IndexVec::new();
+    blocks.push(mir::BasicBlockData {
+        statements: vec![],
+        terminator: Some(mir::Terminator {
+            source_info,
+            kind: mir::TerminatorKind::Goto { target: bb1 },
+        }),
+        is_cleanup: false,
+    });
+    blocks.push(mir::BasicBlockData {
+        statements: vec![mir::Statement { source_info, kind: mir::StatementKind::Nop }],
+        terminator: None,
+        is_cleanup: false,
+    });
+    mir::Body::new_cfg_only(blocks)
+}
+
+// Bodies must be fully built before they are analyzed, even in tests. There is no lenient mode
+// that treats such blocks as having no successors.
+#[test]
+#[should_panic(expected = "invalid terminator state")]
+fn missing_terminator() {
+    solve(&mut MockProvenanceAnalysis, &missing_terminator_body());
+}
+
+#[test]
+#[should_panic(expected = "bb1 of `DefId(0:0")]
+fn missing_terminator_in_cursor() {
+    let body = &missing_terminator_body();
+    let entry_sets = EntrySets::from_bottom_n(PruneFalseArm.test_bottom(body), 2);
+    let mut cursor = Results::new(PruneFalseArm, entry_sets).into_results_cursor(body);
+    cursor.seek_to_block_end(BasicBlock::from_u32(1));
+}
+
+#[test]
+#[should_panic(expected = "has no terminator")]
+fn missing_terminator_in_visitor() {
+    let body = &missing_terminator_body();
+    let entry_sets = EntrySets::from_bottom_n(PruneFalseArm.test_bottom(body), 2);
+    Results::new(PruneFalseArm, entry_sets).visit_reachable_with(body, &mut EdgeCollector(vec![]));
+}
+
+#[test]
+fn patch_entry_set() {
+    let body = &disconnected_body();
+    let bb1 = BasicBlock::from_u32(1);
+    let analysis = MockGenKill;
+    let entry_sets =
+        EntrySets::from_bottom_n(analysis.test_bottom(body), body.basic_blocks.len());
+    let mut results = Results::new(analysis, entry_sets);
+
+    let mut state = results.entry_set_for_block(bb1).clone();
+    assert!(!results.patch_entry_set(bb1, state.clone()));
+
+    state.insert(0);
+    assert!(results.patch_entry_set(bb1, state.clone()));
+    assert_eq!(*results.entry_set_for_block(bb1), state);
+    assert!(results.entry_set_for_block(mir::START_BLOCK).is_empty());
+}
+
+#[test]
+fn dump_manifest() {
+    let dir = std::env::temp_dir().join(format!("dataflow-manifest-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let _ = std::fs::remove_file(dir.join("index.json"));
+
+    write_dump_manifest_entry(&dir.join("init_f.dot"), "init", None).unwrap();
+    write_dump_manifest_entry(&dir.join("live_f.dot"), "live", Some("opt")).unwrap();
+    // Writing a file again replaces its entry instead of adding another.
+    write_dump_manifest_entry(&dir.join("init_f.dot"), "init", Some("opt")).unwrap();
+
+    let manifest = std::fs::read_to_string(dir.join("index.json")).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    let expected = [
+        r#"["#,
+        r#"  {"file": "init_f.dot", "analysis": "init", "pass": "opt", "block": null},"#,
+        r#"  {"file": "live_f.dot", "analysis": "live", "pass": "opt", "block": null}"#,
+        r#"]"#,
+    ];
+    assert_eq!(manifest.lines().collect::<Vec<_>>(), expected);
+}
+
+#[test]
+fn dump_phases() {
+    let phases = ["nll".to_owned()];
+
+    assert!(phase_selected(None, None));
+    assert!(phase_selected(None, Some("borrowck")));
+    assert!(phase_selected(Some(&phases), Some("nll")));
+    assert!(!phase_selected(Some(&phases), Some("borrowck")));
+    assert!(!phase_selected(Some(&phases), None));
+
+    assert_eq!(dump_file_disambiguator(Some("borrowck"), Some("nll")), "borrowck.nll");
+    assert_eq!(dump_file_disambiguator(Some("borrowck"), None), "borrowck");
+    assert_eq!(dump_file_disambiguator(None, Some("nll")), "-----.nll");
+    assert_eq!(dump_file_disambiguator(None, None), "-----");
+}
+
+/// The sequence of effects applied by `EffectLogAnalysis`, in the order they were applied.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct EffectLog(Vec<(usize, Effect)>);
+
+impl JoinSemiLattice for EffectLog {
+    fn join(&mut self, _: &Self) -> bool {
+        unreachable!(
+            "This is never called since `EffectLogAnalysis` is never iterated to fixpoint"
+        )
+    }
+}
+
+/// A backward analysis whose state records every effect applied to it, so that "before" and
+/// "primary" effects are distinguishable and their order is observable.
+struct EffectLogAnalysis;
+
+impl<'tcx> AnalysisDomain<'tcx> for EffectLogAnalysis {
+    type Domain = EffectLog;
+    type Direction = Backward;
+
+    const NAME: &'static str = "mock_effect_log";
+
+    fn bottom_value(&self, _: TyCtxt<'tcx>, body: &mir::Body<'tcx>) -> Self::Domain {
+        self.test_bottom(body)
+    }
+
+    fn initialize_start_block(&self, _: &mir::Body<'tcx>, _: &mut Self::Domain) {}
+}
+
+impl<'tcx> TestBottom<'tcx> for EffectLogAnalysis {
+    fn test_bottom(&self, _: &mir::Body<'tcx>) -> Self::Domain {
+        EffectLog(vec![])
+    }
+}
+
+impl<'tcx> Analysis<'tcx> for EffectLogAnalysis {
+    fn apply_statement_effect(
+        &mut self,
+        state: &mut Self::Domain,
+        _statement: &mir::Statement<'tcx>,
+        location: Location,
+    ) {
+        state.0.push((location.statement_index, Effect::Primary));
+    }
+
+    fn apply_before_statement_effect(
+        &mut self,
+        state: &mut Self::Domain,
+        _statement: &mir::Statement<'tcx>,
+        location: Location,
+    ) {
+        state.0.push((location.statement_index, Effect::Before));
+    }
+
+    fn apply_terminator_effect<'mir>(
+        &mut self,
+        state: &mut Self::Domain,
+        terminator: &'mir mir::Terminator<'tcx>,
+        location: Location,
+    ) -> TerminatorEdges<'mir, 'tcx> {
+        state.0.push((location.statement_index, Effect::Primary));
+        terminator.edges()
+    }
+
+    fn apply_before_terminator_effect(
+        &mut self,
+        state: &mut Self::Domain,
+        _terminator: &mir::Terminator<'tcx>,
+        location: Location,
+    ) {
+        state.0.push((location.statement_index, Effect::Before));
+    }
+
+    fn apply_call_return_effect(
+        &mut self,
+        _state: &mut Self::Domain,
+        _block: BasicBlock,
+        _return_places: CallReturnPlaces<'_, 'tcx>,
+    ) {
+    }
+}
+
+#[test]
+fn backward_effect_order() {
+    let body = &disconnected_body();
+    let mut analysis = EffectLogAnalysis;
+
+    for (bb, block_data) in body.basic_blocks.iter_enumerated() {
+        // The order in which the fixpoint iteration applies the effects of `bb`.
+        let mut block_log = analysis.test_bottom(body);
+        Backward::apply_effects_in_block(&mut analysis, &mut block_log, bb, block_data, None, None);
+
+        let terminator_index = block_data.statements.len();
+        let expected: Vec<_> = (0..=terminator_index)
+            .rev()
+            .flat_map(|i| [(i, Effect::Before), (i, Effect::Primary)])
+            .collect();
+        assert_eq!(block_log.0, expected);
+
+        // Every seek through the cursor must observe a prefix of that order ending at the target.
+        let entry_sets =
+            EntrySets::from_bottom_n(analysis.test_bottom(body), body.basic_blocks.len());
+        let mut cursor = Results::new(EffectLogAnalysis, entry_sets).into_results_cursor(body);
+        cursor.allow_unreachable();
+
+        for target in SeekTarget::iter_in_block(body, bb) {
+            let len = match target {
+                SeekTarget::BlockEntry(block) => {
+                    cursor.seek_to_block_entry(block);
+                    0
+                }
+                SeekTarget::Before(loc) => {
+                    cursor.seek_before_primary_effect(loc);
+                    2 * (terminator_index - loc.statement_index) + 1
+                }
+                SeekTarget::After(loc) => {
+                    cursor.seek_after_primary_effect(loc);
+                    2 * (terminator_index - loc.statement_index) + 2
+                }
+            };
+            assert_eq!(cursor.get().0, block_log.0[..len], "{target:?}");
+        }
+
+        cursor.seek_to_block_start(bb);
+        assert_eq!(*cursor.get(), block_log);
+    }
+}
+
+#[test]
+fn adhoc_call_reachability() {
+    let (bb1, bb2, bb3) =
+        (BasicBlock::from_u32(1), BasicBlock::from_u32(2), BasicBlock::from_u32(3));
+    let body = &body_from_terminators(
+        1,
+        [
+            mir::TerminatorKind::if_(dummy_condition(), bb1, bb2),
+            call_returning_to(bb3),
+            mir::TerminatorKind::Goto { target: bb3 },
+            mir::TerminatorKind::Return,
+        ],
+    );
+
+    // Bit 0 is set in every block from which a call is reachable.
+    let mut analysis = adhoc::backward_bitset_analysis::<usize>(
+        1,
+        |_, _, _| {},
+        |trans, terminator, _| {
+            if let mir::TerminatorKind::Call { .. } = terminator.kind {
+                trans.gen(0);
+            }
+        },
+    );
+    let entry_sets = solve_with_bottom(&mut analysis, body, |_, _| BitSet::new_empty(1));
+
+    let mut cursor = Results::new(analysis, entry_sets).into_results_cursor(body);
+    let reaches_call: Vec<_> = body
+        .basic_blocks
+        .indices()
+        .filter(|&bb| {
+            cursor.seek_to_block_start(bb);
+            cursor.get().contains(0)
+        })
+        .collect();
+    assert_eq!(reaches_call, [mir::START_BLOCK, bb1]);
+}
+
+#[test]
+fn bounded_widening() {
+    let body = &straight_line_body(2, 0);
+    let bb1 = BasicBlock::from_u32(1);
+    let widen: &dyn Fn(&mut BitSet<usize>) = &widen_above::<_, usize>(1, BitSet::insert_all);
+
+    for (seed, expected) in [(&[0, 1][..], 3), (&[0][..], 1)] {
+        let mut analysis = adhoc::forward_bitset_analysis::<usize>(3, |_, _, _| {}, |_, _, _| {});
+        let mut entry_sets = initial_entry_sets(&analysis, body, BitSet::new_empty(3));
+        for &elem in seed {
+            entry_sets[mir::START_BLOCK].insert(elem);
+        }
+        let options =
+            FixpointOptions { widen_entry_set: Some(widen), ..FixpointOptions::default() };
+        solve_from(&mut analysis, body, &mut entry_sets, options);
+
+        // The seed is widened up front if it is too large, although no join ever changes it.
+        // `bb1` only receives the widened state.
+        assert_eq!(entry_sets[mir::START_BLOCK].count(), expected, "{seed:?}");
+        assert_eq!(entry_sets[bb1].count(), expected, "{seed:?}");
+    }
+}
+
+/// A gen/kill analysis that generates bit 0 at every terminator. `CLEANUP` is its
+/// `REQUIRES_CLEANUP_BLOCKS`.
+struct MockCleanupBlocks<const CLEANUP: bool>;
+
+impl<'tcx, const CLEANUP: bool> AnalysisDomain<'tcx> for MockCleanupBlocks<CLEANUP> {
+    type Domain = BitSet<usize>;
+
+    const NAME: &'static str = "mock_cleanup_blocks";
+    const REQUIRES_CLEANUP_BLOCKS: bool = CLEANUP;
+
+    fn bottom_value(&self, _: TyCtxt<'tcx>, body: &mir::Body<'tcx>) -> Self::Domain {
+        self.test_bottom(body)
+    }
+
+    fn initialize_start_block(&self, _: &mir::Body<'tcx>, _: &mut Self::Domain) {}
+}
+
+impl<'tcx, const CLEANUP: bool> TestBottom<'tcx> for MockCleanupBlocks<CLEANUP> {
+    fn test_bottom(&self, _: &mir::Body<'tcx>) -> Self::Domain {
+        BitSet::new_empty(1)
+    }
+}
+
+impl<'tcx, const CLEANUP: bool> GenKillAnalysis<'tcx> for MockCleanupBlocks<CLEANUP> {
+    type Idx = usize;
+
+    fn domain_size(&self, _: &mir::Body<'tcx>) -> usize {
+        1
+    }
+
+    fn statement_effect(
+        &mut self,
+        _trans: &mut impl GenKill<Self::Idx>,
+        _statement: &mir::Statement<'tcx>,
+        _location: Location,
+    ) {
+    }
+
+    fn terminator_effect<'mir>(
+        &mut self,
+        trans: &mut Self::Domain,
+        terminator: &'mir mir::Terminator<'tcx>,
+        _location: Location,
+    ) -> TerminatorEdges<'mir, 'tcx> {
+        trans.gen(0);
+        terminator.edges()
+    }
+
+    fn call_return_effect(
+        &mut self,
+        _trans: &mut impl GenKill<Self::Idx>,
+        _block: BasicBlock,
+        _return_places: CallReturnPlaces<'_, 'tcx>,
+    ) {
+    }
+}
+
+#[test]
+fn skip_cleanup_blocks() {
+    let body = &unwind_effect_body();
+    let [bb1, bb2, bb3] = [1, 2, 3].map(BasicBlock::from_u32);
+
+    // `bb3` is the cleanup block that `bb0` and `bb1` unwind to.
+    let entry_sets = solve(&mut MockCleanupBlocks::<true>, body);
+    assert!(entry_sets[bb3].contains(0));
+
+    // Without `REQUIRES_CLEANUP_BLOCKS`, nothing is joined into it, so it stays unpopulated.
+    let entry_sets = solve(&mut MockCleanupBlocks::<false>, body);
+    assert!(entry_sets[bb1].contains(0));
+    assert!(entry_sets[bb2].contains(0));
+    assert!(!entry_sets.is_populated(bb3));
+}
+
+#[test]
+fn visit_non_bottom_blocks() {
+    let body = &straight_line_body(3, 2);
+    let entry_sets = solve(&mut MockGenKill, body);
+    let mut results = Results::new(MockGenKill, entry_sets);
+
+    // The bit is generated in `bb0`, so only the entry set of `bb0` is at bottom.
+    let mut vis = BlockStartStates { project: BitSet::clone, states: vec![] };
+    results.visit_non_bottom_blocks(body, &mut vis);
+    let blocks: Vec<_> = vis.states.iter().map(|&(block, _)| block).collect();
+    assert_eq!(blocks, [BasicBlock::from_u32(1), BasicBlock::from_u32(2)]);
+}
+
+#[test]
+fn seed_blocks() {
+    let body = &straight_line_body(3, 0);
+    let (bb1, bb2) = (BasicBlock::from_u32(1), BasicBlock::from_u32(2));
+
+    // A fact seeded on entry to `bb1` is kept, and propagated to `bb2`, but not back to `bb0`.
+    let mut analysis = adhoc::forward_bitset_analysis::<usize>(1, |_, _, _| {}, |_, _, _| {});
+    let mut entry_sets = initial_entry_sets(&analysis, body, BitSet::new_empty(1));
+    seed_entry_sets(&mut entry_sets, |block, entry_set| {
+        if block == bb1 {
+            entry_set.insert(0);
+        }
+    });
+    solve_from(&mut analysis, body, &mut entry_sets, FixpointOptions::default());
+    assert!(!entry_sets[mir::START_BLOCK].contains(0));
+    assert!(entry_sets[bb1].contains(0));
+    assert!(entry_sets[bb2].contains(0));
+}
+
+#[test]
+fn seed_from_results() {
+    let body = &loop_body(2);
+    let fixpoint = Results::new(MockGenKill, solve(&mut MockGenKill, body));
+
+    // Seeding with the fixpoint itself reaches the same fixpoint, visiting each block once.
+    let mut entry_sets = initial_entry_sets(&MockGenKill, body, MockGenKill.test_bottom(body));
+    seed_entry_sets(&mut entry_sets, join_converted_seed(&fixpoint, BitSet::clone));
+    let convergence = iterate_entry_sets_to_fixpoint(
+        &mut MockGenKill,
+        body,
+        &mut entry_sets,
+        FixpointOptions::default(),
+    )
+    .unwrap();
+    assert_eq!(entry_sets, fixpoint.entry_sets);
+    assert_eq!(convergence.total_iterations(), body.basic_blocks.len() as u64);
+}
+
+#[test]
+fn graphviz_track_bit() {
+    use rustc_graphviz::GraphWalk;
+
+    let body = &diamond_body(1);
+    let (bb1, bb3) = (BasicBlock::from_u32(1), BasicBlock::from_u32(3));
+
+    // The bit is set along `bb0 -> bb1 -> bb3`, but not `bb0 -> bb2 -> bb3`.
+    let mut entry_sets =
+        EntrySets::from_bottom_n(BitSet::new_empty(1), body.basic_blocks.len());
+    entry_sets[bb1].insert(0);
+    entry_sets[bb3].insert(0);
+    let mut results = Results::new(MockGenKill, entry_sets);
+
+    let formatter = graphviz::Formatter::new(body, &mut results, graphviz::OutputStyle::AfterOnly);
+    assert_eq!(formatter.nodes().len(), 4);
+
+    let formatter = formatter.track_bit(0);
+    assert_eq!(*formatter.nodes(), [bb1, bb3]);
+    let edges: Vec<_> =
+        formatter.edges().iter().map(|e| (formatter.source(e), formatter.target(e))).collect();
+    assert_eq!(edges, [(bb1, bb3)]);
+}
+
+#[test]
+fn graphviz_block_range() {
+    use rustc_graphviz::GraphWalk;
+
+    let body = &diamond_body(1);
+    let (bb1, bb2, bb3) =
+        (BasicBlock::from_u32(1), BasicBlock::from_u32(2), BasicBlock::from_u32(3));
+
+    let entry_sets = EntrySets::from_bottom_n(BitSet::new_empty(1), body.basic_blocks.len());
+    let mut results = Results::new(MockGenKill, entry_sets);
+
+    let formatter = graphviz::Formatter::new(body, &mut results, graphviz::OutputStyle::AfterOnly)
+        .block_range(bb1..=bb3);
+    assert_eq!(*formatter.nodes(), [bb1, bb2, bb3]);
+    let edges: Vec<_> =
+        formatter.edges().iter().map(|e| (formatter.source(e), formatter.target(e))).collect();
+    assert_eq!(edges, [(bb1, bb3), (bb2, bb3)]);
+}
+
+#[test]
+fn graphviz_string() {
+    let body = &straight_line_body(2, 0);
+    let entry_sets = solve(&mut MockGenKill, body);
+    let mut results = Results::new(MockGenKill, entry_sets);
+
+    let dot = results.to_graphviz_string(body, graphviz::OutputStyle::AfterOnly);
+    assert!(dot.starts_with("digraph graph_for_def_id_"));
+    assert!(dot.ends_with("}\n"));
+    assert!(dot.contains("    bb_0[label="));
+    assert!(dot.contains("    bb_1[label="));
+    assert!(dot.contains("    bb_0 -> bb_1[label="));
+    assert_eq!(dot.matches(" -> ").count(), 1);
+
+    // Full states are printed with `domain_element_name`, diffs with `DebugWithContext`.
+    assert!(dot.contains(r#"align="left">{x0}</td>"#));
+    assert!(dot.contains(r#"<font color="darkgreen">+0</font>"#));
+}
+
+#[test]
+fn graphviz_wrap_width() {
+    use rustc_graphviz::Labeller;
+
+    let body = &straight_line_body(1, 0);
+    let analysis = adhoc::forward_bitset_analysis::<usize>(40, |_, _, _| {}, |_, _, _| {});
+    let mut entry_sets = initial_entry_sets(&analysis, body, BitSet::new_empty(40));
+    entry_sets[mir::START_BLOCK].insert_all();
+    let mut results = Results::new(analysis, entry_sets);
+    let full_state = format!("{:?}", (0..40).collect::<BTreeSet<_>>());
+
+    // States are not wrapped by default.
+    let label = graphviz::Formatter::new(body, &mut results, graphviz::OutputStyle::AfterOnly)
+        .node_label(&mir::START_BLOCK)
+        .to_dot_string();
+    assert!(label.contains(&full_state));
+    assert!(!label.contains(r#"<br align="left"/>"#));
+
+    // With a wrap width, they are broken after a separator, into lines no wider than it.
+    let label = graphviz::Formatter::new(body, &mut results, graphviz::OutputStyle::AfterOnly)
+        .wrap_width(20)
+        .node_label(&mir::START_BLOCK)
+        .to_dot_string();
+    assert!(!label.contains(&full_state));
+    assert!(label.contains(r#"{0, 1, 2, 3, 4, 5,<br align="left"/>6, 7, 8, 9, 10, 11,"#));
+}
+
+#[test]
+fn graphviz_font_size() {
+    let body = &straight_line_body(1, 0);
+    let entry_sets = solve(&mut MockGenKill, body);
+    let mut results = Results::new(MockGenKill, entry_sets);
+    let render = |graphviz: &graphviz::Formatter<'_, '_, '_, MockGenKill>| {
+        let opts = graphviz_render_opts(graphviz, "Courier".to_owned(), false);
+        let mut buf = Vec::new();
+        rustc_graphviz::render_opts(graphviz, &mut buf, &opts).unwrap();
+        String::from_utf8(buf).unwrap()
+    };
+
+    // The font size is left to graphviz by default.
+    let graphviz = graphviz::Formatter::new(body, &mut results, graphviz::OutputStyle::AfterOnly);
+    let dot = render(&graphviz);
+    assert!(dot.contains(r#"graph[fontname="Courier"];"#));
+    assert!(!dot.contains("fontsize"));
+
+    let graphviz = graphviz::Formatter::new(body, &mut results, graphviz::OutputStyle::AfterOnly)
+        .with_font_size(8);
+    let dot = render(&graphviz);
+    assert!(dot.contains(r#"graph[fontname="Courier" fontsize=8];"#));
+    assert!(dot.contains(r#"node[fontname="Courier" fontsize=8];"#));
+}
+
+#[test]
+fn dataflow_in_mir_dump() {
+    let body = &straight_line_body(2, 1);
+    let entry_sets = solve(&mut MockGenKill, body);
+    let results = Results::new(MockGenKill, entry_sets);
+
+    // The comments are added to what the pass itself writes.
+    let mut extra_data = append_dataflow_to_mir_dump(&results, |pass_where, w| match pass_where {
+        mir::PassWhere::BeforeCFG => writeln!(w, "// pass"),
+        _ => Ok(()),
+    });
+    let mut dump = Vec::new();
+    for pass_where in [
+        mir::PassWhere::BeforeCFG,
+        mir::PassWhere::BeforeBlock(mir::START_BLOCK),
+        mir::PassWhere::BeforeBlock(BasicBlock::from_u32(1)),
+        mir::PassWhere::AfterCFG,
+    ] {
+        extra_data(pass_where, &mut dump).unwrap();
+    }
+    assert_eq!(
+        String::from_utf8(dump).unwrap(),
+        "// pass\n    // dataflow(mock_gen_kill): bb0 = {}\n    // dataflow(mock_gen_kill): bb1 = {0}\n"
+    );
+}
+
+#[test]
+fn graphviz_custom_style() {
+    use rustc_graphviz::Labeller;
+
+    let body = &straight_line_body(1, 2);
+    let entry_sets = solve(&mut MockGenKill, body);
+    let mut results = Results::new(MockGenKill, entry_sets);
+
+    let style = graphviz::OutputStyle::Custom(Box::new(
+        |before: &BitSet<usize>, after: &BitSet<usize>, f: &mut std::fmt::Formatter<'_>| {
+            write!(f, "<{} {}>", before.count(), after.count())
+        },
+    ));
+    let formatter = graphviz::Formatter::new(body, &mut results, style);
+    let label = formatter.node_label(&mir::START_BLOCK).to_dot_string();
+
+    // `bb0[0]` sets the only bit, which `bb0[1]` and the terminator leave unchanged.
+    let rendered: Vec<_> = label.match_indices("&lt;").map(|(i, _)| &label[i..i + 11]).collect();
+    assert_eq!(rendered, ["&lt;0 1&gt;", "&lt;1 1&gt;", "&lt;1 1&gt;"]);
+}
+
+#[test]
+fn graphml() {
+    let body = &straight_line_body(2, 0);
+    let entry_sets = solve(&mut MockProvenanceAnalysis, body);
+    let mut results = Results::new(MockProvenanceAnalysis, entry_sets);
+
+    let formatter = graphviz::Formatter::new(body, &mut results, graphviz::OutputStyle::AfterOnly);
+    let mut out = vec![];
+    formatter.write_graphml(&mut out).unwrap();
+    let out = String::from_utf8(out).unwrap();
+
+    assert!(out.starts_with("<?xml"));
+    assert!(out.ends_with("  </graph>\n</graphml>\n"));
+    let expected_node = r#"    <node id="bb1">
+      <data key="label">bb1</data>
+      <data key="entry_state">Provenance({(bb0, bb1)})</data>
+      <data key="exit_state">Provenance({(bb0, bb1)})</data>
+    </node>
+"#;
+    assert!(out.contains(expected_node));
+    assert_eq!(out.matches("<node ").count(), 2);
+    assert!(out.contains(r#"<edge source="bb0" target="bb1">"#));
+    assert_eq!(out.matches("<edge ").count(), 1);
+    assert!(out.contains(r#"<data key="feasible">true</data>"#));
+}
+
+/// An analysis in which calls clobber every bit, but bit 0 survives them.
+struct MockCallSurvivorAnalysis<D> {
+    dir: PhantomData<D>,
+}
+
+impl<'tcx, D: Direction> AnalysisDomain<'tcx> for MockCallSurvivorAnalysis<D> {
+    type Domain = BitSet<usize>;
+    type Direction = D;
+
+    const NAME: &'static str = "mock_call_survivor";
+
+    const MERGES_CALL_RETURN_STATE: bool = true;
+
+    fn bottom_value(&self, _: TyCtxt<'tcx>, body: &mir::Body<'tcx>) -> Self::Domain {
+        self.test_bottom(body)
+    }
+
+    fn initialize_start_block(&self, _: &mir::Body<'tcx>, _: &mut Self::Domain) {}
+}
+
+impl<'tcx, D: Direction> TestBottom<'tcx> for MockCallSurvivorAnalysis<D> {
+    fn test_bottom(&self, _: &mir::Body<'tcx>) -> Self::Domain {
+        BitSet::new_empty(2)
+    }
+}
+
+impl<'tcx, D: Direction> Analysis<'tcx> for MockCallSurvivorAnalysis<D> {
+    fn apply_statement_effect(
+        &mut self,
+        _state: &mut Self::Domain,
+        _statement: &mir::Statement<'tcx>,
+        _location: Location,
+    ) {
+    }
+
+    fn apply_terminator_effect<'mir>(
+        &mut self,
+        state: &mut Self::Domain,
+        terminator: &'mir mir::Terminator<'tcx>,
+        _location: Location,
+    ) -> TerminatorEdges<'mir, 'tcx> {
+        state.insert_all();
+        terminator.edges()
+    }
+
+    fn apply_call_return_effect(
+        &mut self,
+        state: &mut Self::Domain,
+        _block: BasicBlock,
+        _return_places: CallReturnPlaces<'_, 'tcx>,
+    ) {
+        state.clear();
+    }
+
+    fn merge_call_return_state(
+        &mut self,
+        edge_state: &Self::Domain,
+        return_state: &mut Self::Domain,
+    ) {
+        if edge_state.contains(0) {
+            return_state.insert(0);
+        }
+    }
+}
+
+#[test]
+fn merge_call_return_state() {
+    let bb1 = BasicBlock::from_u32(1);
+    let body = &body_from_terminators(0, [call_returning_to(bb1), mir::TerminatorKind::Return]);
+
+    // In both directions, only bit 0 makes it across the return edge of the call.
+    let mut expected = BitSet::new_empty(2);
+    expected.insert(0);
+
+    let mut analysis = MockCallSurvivorAnalysis { dir: PhantomData::<Forward> };
+    let mut entry_sets =
+        EntrySets::from_bottom_n(analysis.test_bottom(body), body.basic_blocks.len());
+    solve_from(&mut analysis, body, &mut entry_sets, FixpointOptions::default());
+    assert_eq!(entry_sets[bb1], expected);
+
+    let mut analysis = MockCallSurvivorAnalysis { dir: PhantomData::<Backward> };
+    let mut entry_sets =
+        EntrySets::from_bottom_n(analysis.test_bottom(body), body.basic_blocks.len());
+    solve_from(&mut analysis, body, &mut entry_sets, FixpointOptions::default());
+    assert_eq!(entry_sets[mir::START_BLOCK], expected);
+}
+
+#[test]
+fn counting_analysis() {
+    let body = &diamond_body(2);
+    let mut analysis = CountingAnalysis::new(MockGenKill);
+    let mut entry_sets =
+        EntrySets::from_bottom_n(analysis.test_bottom(body), body.basic_blocks.len());
+    solve_from(&mut analysis, body, &mut entry_sets, FixpointOptions::default());
+
+    // The CFG is acyclic, so every block is visited exactly once.
+    let expected = TransferCounts { statement_effects: 8, terminator_effects: 4, edge_effects: 0 };
+    assert_eq!(analysis.counts(), expected);
+
+    analysis.reset_counts();
+    let mut cursor = Results::new(analysis, entry_sets).into_results_cursor(body);
+    cursor.seek_after_primary_effect(Location { block: mir::START_BLOCK, statement_index: 1 });
+    assert_eq!(cursor.analysis().counts().statement_effects, 2);
+}
+
+/// An analysis whose only effect is to set bit 0 in each statement of `bb0`, so that any other
+/// block can be skipped.
+struct MockSkipping;
+
+impl<'tcx> AnalysisDomain<'tcx> for MockSkipping {
+    type Domain = BitSet<usize>;
+
+    const NAME: &'static str = "mock_skipping";
+
+    fn bottom_value(&self, _: TyCtxt<'tcx>, body: &mir::Body<'tcx>) -> Self::Domain {
+        self.test_bottom(body)
+    }
+
+    fn initialize_start_block(&self, _: &mir::Body<'tcx>, _: &mut Self::Domain) {}
+}
+
+impl<'tcx> TestBottom<'tcx> for MockSkipping {
+    fn test_bottom(&self, _: &mir::Body<'tcx>) -> Self::Domain {
+        BitSet::new_empty(1)
+    }
+}
+
+impl<'tcx> Analysis<'tcx> for MockSkipping {
+    fn apply_statement_effect(
+        &mut self,
+        state: &mut Self::Domain,
+        _statement: &mir::Statement<'tcx>,
+        location: Location,
+    ) {
+        if location.block == mir::START_BLOCK {
+            state.insert(0);
+        }
+    }
+
+    fn apply_terminator_effect<'mir>(
+        &mut self,
+        _state: &mut Self::Domain,
+        terminator: &'mir mir::Terminator<'tcx>,
+        _location: Location,
+    ) -> TerminatorEdges<'mir, 'tcx> {
+        terminator.edges()
+    }
+
+    fn apply_call_return_effect(
+        &mut self,
+        _state: &mut Self::Domain,
+        _block: BasicBlock,
+        _return_places: CallReturnPlaces<'_, 'tcx>,
+    ) {
+    }
+
+    fn can_skip_block_given_predecessors(
+        &self,
+        block: BasicBlock,
+        _body: &mir::Body<'tcx>,
+        _entry: &Self::Domain,
+    ) -> bool {
+        block != mir::START_BLOCK
+    }
+}
+
+#[test]
+fn can_skip_block_given_predecessors() {
+    let body = &straight_line_body(3, 2);
+    let mut analysis = CountingAnalysis::new(MockSkipping);
+    let entry_sets = solve(&mut analysis, body);
+
+    let expected = TransferCounts { statement_effects: 2, terminator_effects: 1, edge_effects: 0 };
+    assert_eq!(analysis.counts(), expected);
+    let with_bit: Vec<_> = entry_sets.iter().map(|set| set.contains(0)).collect();
+    assert_eq!(with_bit, [false, true, true]);
+}
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = Cell::new(0);
+}
+
+/// A `BitSet` that counts how often it is allocated, i.e. created or cloned, in `ALLOCATIONS`.
+#[derive(Debug, PartialEq, Eq)]
+struct CountedBitSet(BitSet<usize>);
+
+impl CountedBitSet {
+    fn new(set: BitSet<usize>) -> Self {
+        ALLOCATIONS.with(|n| n.set(n.get() + 1));
+        CountedBitSet(set)
+    }
+
+    fn new_empty() -> Self {
+        CountedBitSet::new(BitSet::new_empty(1))
+    }
+}
+
+impl Clone for CountedBitSet {
+    fn clone(&self) -> Self {
+        CountedBitSet::new(self.0.clone())
+    }
+
+    fn clone_from(&mut self, source: &Self) {
+        self.0.clone_from(&source.0);
+    }
+}
+
+impl JoinSemiLattice for CountedBitSet {
+    fn join(&mut self, other: &Self) -> bool {
+        self.0.join(&other.0)
+    }
+}
+
+/// An analysis that sets bit 0 at the terminator of every block, over a `CountedBitSet`.
+struct MockCountedAllocations;
+
+impl<'tcx> AnalysisDomain<'tcx> for MockCountedAllocations {
+    type Domain = CountedBitSet;
+
+    const NAME: &'static str = "mock_counted_allocations";
+
+    fn bottom_value(&self, _: TyCtxt<'tcx>, body: &mir::Body<'tcx>) -> Self::Domain {
+        self.test_bottom(body)
+    }
+
+    fn initialize_start_block(&self, _: &mir::Body<'tcx>, _: &mut Self::Domain) {}
+}
+
+impl<'tcx> TestBottom<'tcx> for MockCountedAllocations {
+    fn test_bottom(&self, _: &mir::Body<'tcx>) -> Self::Domain {
+        CountedBitSet::new_empty()
+    }
+}
+
+impl<'tcx> Analysis<'tcx> for MockCountedAllocations {
+    fn apply_statement_effect(
+        &mut self,
+        _state: &mut Self::Domain,
+        _statement: &mir::Statement<'tcx>,
+        _location: Location,
+    ) {
+    }
+
+    fn apply_terminator_effect<'mir>(
+        &mut self,
+        state: &mut Self::Domain,
+        terminator: &'mir mir::Terminator<'tcx>,
+        _location: Location,
+    ) -> TerminatorEdges<'mir, 'tcx> {
+        state.0.insert(0);
+        terminator.edges()
+    }
+
+    fn apply_call_return_effect(
+        &mut self,
+        _state: &mut Self::Domain,
+        _block: BasicBlock,
+        _return_places: CallReturnPlaces<'_, 'tcx>,
+    ) {
+    }
+}
+
+#[test]
+fn bottom_entry_sets_are_not_allocated() {
+    let bb1 = BasicBlock::from_u32(1);
+
+    // `bb0` jumps to `bb1`, which returns. The remaining blocks are unreachable.
+    let body_with_unreachable_blocks = |unreachable: usize| {
+        let goto = mir::TerminatorKind::Goto { target: bb1 };
+        let returns = std::iter::repeat(mir::TerminatorKind::Return).take(unreachable + 1);
+        body_from_terminators(0, std::iter::once(goto).chain(returns))
+    };
+
+    let mut allocations = vec![];
+    for unreachable in [0, 10] {
+        let body = &body_with_unreachable_blocks(unreachable);
+        ALLOCATIONS.with(|n| n.set(0));
+        let entry_sets = solve(&mut MockCountedAllocations, body);
+        allocations.push(ALLOCATIONS.with(|n| n.get()));
+
+        // Only `bb1` ever receives a state other than bottom.
+        for (block, entry_set) in entry_sets.iter_enumerated() {
+            assert_eq!(entry_sets.is_populated(block), block == bb1);
+            assert_eq!(entry_set.0.contains(0), block == bb1);
+        }
+    }
+
+    // Unreachable blocks never allocate, so they do not change the number of allocations.
+    assert_eq!(allocations[0], allocations[1]);
+}
+
+/// A backward analysis without any effects, over a `CountedBitSet`.
+struct MockBackwardCountedAllocations;
+
+impl<'tcx> AnalysisDomain<'tcx> for MockBackwardCountedAllocations {
+    type Domain = CountedBitSet;
+    type Direction = Backward;
+
+    const NAME: &'static str = "mock_backward_counted_allocations";
+
+    fn bottom_value(&self, _: TyCtxt<'tcx>, body: &mir::Body<'tcx>) -> Self::Domain {
+        self.test_bottom(body)
+    }
+
+    fn initialize_start_block(&self, _: &mir::Body<'tcx>, _: &mut Self::Domain) {}
+}
+
+impl<'tcx> TestBottom<'tcx> for MockBackwardCountedAllocations {
+    fn test_bottom(&self, _: &mir::Body<'tcx>) -> Self::Domain {
+        CountedBitSet::new_empty()
+    }
+}
+
+impl<'tcx> Analysis<'tcx> for MockBackwardCountedAllocations {
+    fn apply_statement_effect(
+        &mut self,
+        _state: &mut Self::Domain,
+        _statement: &mir::Statement<'tcx>,
+        _location: Location,
+    ) {
+    }
+
+    fn apply_terminator_effect<'mir>(
+        &mut self,
+        _state: &mut Self::Domain,
+        terminator: &'mir mir::Terminator<'tcx>,
+        _location: Location,
+    ) -> TerminatorEdges<'mir, 'tcx> {
+        terminator.edges()
+    }
+
+    fn apply_call_return_effect(
+        &mut self,
+        _state: &mut Self::Domain,
+        _block: BasicBlock,
+        _return_places: CallReturnPlaces<'_, 'tcx>,
+    ) {
+    }
+}
+
+#[test]
+fn initial_entry_sets_reuse_bottom() {
+    let body = &diamond_body(0);
+    let analysis = MockBackwardCountedAllocations;
+    let bottom = analysis.test_bottom(body);
+
+    // Only the `START_BLOCK` gets its own copy of `bottom`. Checking that `initialize_start_block`
+    // left it at bottom does not compute `bottom_value` again.
+    ALLOCATIONS.with(|n| n.set(0));
+    let entry_sets = initial_entry_sets(&analysis, body, bottom);
+    assert_eq!(ALLOCATIONS.with(|n| n.get()), 1);
+    assert!(entry_sets.is_populated(mir::START_BLOCK));
+    assert!(entry_sets.iter().all(|set| set.0.is_empty()));
+}
+
+/// A `tracing` subscriber that records the events emitted by `TraceAnalysis` and
+/// `TraceGenKillAnalysis` as `effect@location diff`.
+#[derive(Clone, Default)]
+struct TraceRecorder {
+    events: std::sync::Arc<std::sync::Mutex<Vec<String>>>,
+}
+
+#[derive(Default)]
+struct TraceFields {
+    location: String,
+    effect: String,
+    diff: String,
+}
+
+impl tracing::field::Visit for TraceFields {
+    fn record_str(&mut self, field: &tracing::field::Field, value: &str) {
+        match field.name() {
+            "effect" => self.effect = value.to_string(),
+            "diff" => self.diff = value.to_string(),
+            _ => {}
+        }
+    }
+
+    fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "location" {
+            self.location = format!("{value:?}");
+        }
+    }
+}
+
+impl tracing::Subscriber for TraceRecorder {
+    fn enabled(&self, _: &tracing::Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, _: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+        tracing::span::Id::from_u64(1)
+    }
+
+    fn record(&self, _: &tracing::span::Id, _: &tracing::span::Record<'_>) {}
+
+    fn record_follows_from(&self, _: &tracing::span::Id, _: &tracing::span::Id) {}
+
+    fn event(&self, event: &tracing::Event<'_>) {
+        if event.metadata().target() != "rustc_mir_dataflow::framework::trace" {
+            return;
+        }
+
+        let mut fields = TraceFields::default();
+        event.record(&mut fields);
+        let TraceFields { location, effect, diff } = fields;
+        self.events
+            .lock()
+            .unwrap()
+            .push(format!("{effect}@{location} {diff}").trim_end().to_string());
+    }
+
+    fn enter(&self, _: &tracing::span::Id) {}
+
+    fn exit(&self, _: &tracing::span::Id) {}
+}
+
+#[test]
+fn trace_analysis() {
+    // `trace!` may be compiled out entirely.
+    if tracing::level_filters::STATIC_MAX_LEVEL < tracing::level_filters::LevelFilter::TRACE {
+        return;
+    }
+
+    let recorder = TraceRecorder::default();
+    let events = || std::mem::take(&mut *recorder.events.lock().unwrap());
+
+    let body = &straight_line_body(2, 2);
+    let mut analysis =
+        TraceGenKillAnalysis::new(MockGenKill).with_filter(|location| location.block.index() == 1);
+    tracing::subscriber::with_default(recorder.clone(), || {
+        let mut state = analysis.test_bottom(body);
+        for (block, data) in body.basic_blocks.iter_enumerated() {
+            Forward::apply_effects_in_block(&mut analysis, &mut state, block, data, None, None);
+        }
+        assert!(state.contains(0));
+    });
+    assert_eq!(
+        events(),
+        [
+            "before_statement@bb1[0]",
+            "statement@bb1[0] -0",
+            "before_statement@bb1[1]",
+            "statement@bb1[1] +0",
+            "before_terminator@bb1[2]",
+            "terminator@bb1[2]",
+        ]
+    );
+
+    let mut analysis = TraceAnalysis::new(MockYieldAnalysis { dir: PhantomData::<Forward> });
+    tracing::subscriber::with_default(recorder.clone(), || {
+        let mut state = analysis.test_bottom(body);
+        let resume_place = mir::Place::from(mir::Local::from_u32(1));
+        analysis.apply_yield_resume_effect(&mut state, BasicBlock::from_u32(1), resume_place);
+    });
+    assert_eq!(events(), ["yield_resume@bb1[0] +_1"]);
+}
+
+/// Reverses the order of the elements of the domain of `set`. This is its own inverse.
+fn reversed(set: &BitSet<usize>) -> BitSet<usize> {
+    let last = set.domain_size() - 1;
+    let mut ret = BitSet::new_empty(set.domain_size());
+    for elem in set.iter() {
+        ret.insert(last - elem);
+    }
+    ret
+}
+
+#[test]
+fn map_domain() {
+    let body = &diamond_body(2);
+    let mock = MockAnalysis::<Forward>::new(body);
+    let entry_sets = mock.mock_entry_sets().map(reversed);
+    let analysis = MapDomain::new(MockAnalysis::<Forward>::new(body), reversed, reversed);
+
+    let mut cursor = Results::new(analysis, entry_sets).into_results_cursor(body);
+    cursor.allow_unreachable();
+
+    for (bb, _) in body.basic_blocks.iter_enumerated() {
+        for target in SeekTarget::iter_in_block(body, bb) {
+            match target {
+                SeekTarget::BlockEntry(block) => cursor.seek_to_block_entry(block),
+                SeekTarget::Before(loc) => cursor.seek_before_primary_effect(loc),
+                SeekTarget::After(loc) => cursor.seek_after_primary_effect(loc),
+            }
+            assert_eq!(*cursor.get(), reversed(&mock.expected_state_at_target(target)));
+        }
+    }
+}
+
+/// Iterates `analysis` to fixpoint over `body` and returns its entry sets.
+fn solve<'tcx, A: Analysis<'tcx> + TestBottom<'tcx>>(
+    analysis: &mut A,
+    body: &mir::Body<'tcx>,
+) -> EntrySets<'tcx, A> {
+    solve_with_bottom(analysis, body, A::test_bottom)
+}
+
+/// Like `solve`, for analyses that do not implement `TestBottom`, such as `adhoc` ones.
+fn solve_with_bottom<'tcx, A: Analysis<'tcx>>(
+    analysis: &mut A,
+    body: &mir::Body<'tcx>,
+    bottom: impl FnOnce(&A, &mir::Body<'tcx>) -> A::Domain,
+) -> EntrySets<'tcx, A> {
+    analysis.pre_iterate(body);
+    let mut entry_sets = EntrySets::from_bottom_n(bottom(analysis, body), body.basic_blocks.len());
+    solve_from(analysis, body, &mut entry_sets, FixpointOptions::default());
+    entry_sets
+}
+
+/// Iterates `analysis` to fixpoint over `body`, starting from (and updating) `entry_sets`.
+fn solve_from<'tcx, A: Analysis<'tcx>>(
+    analysis: &mut A,
+    body: &mir::Body<'tcx>,
+    entry_sets: &mut EntrySets<'tcx, A>,
+    options: FixpointOptions<'_, A::Domain>,
+) {
+    let converged = iterate_entry_sets_to_fixpoint(analysis, body, entry_sets, options);
+    assert!(converged.is_ok());
+}
+
+#[test]
+fn convert_domain_repr() {
+    let body = &loop_body(2);
+    let entry_sets = solve(&mut MockGenKill, body);
+    let dense = Results::new(MockGenKill, entry_sets);
+    let expected = dense.entry_sets.clone();
+
+    let chunked = dense.to_chunked();
+    let mut cursor = chunked.into_results_cursor(body);
+    for (block, expected) in expected.iter_enumerated() {
+        cursor.seek_to_block_entry(block);
+        assert_eq!(cursor.get().iter().collect::<Vec<_>>(), expected.iter().collect::<Vec<_>>());
+    }
+
+    // Effects are applied through the conversion, so states within blocks agree as well.
+    let loc = Location { block: BasicBlock::from_usize(1), statement_index: 0 };
+    cursor.seek_after_primary_effect(loc);
+    assert!(cursor.get().is_empty());
+
+    let dense = cursor.into_results().to_dense();
+    assert_eq!(dense.entry_sets.iter().collect::<Vec<_>>(), expected.iter().collect::<Vec<_>>());
+}
+
+#[test]
+fn dataflow_cache() {
+    let mut body = loop_body(2);
+    let fingerprint = Fingerprint::new(0, 0);
+    let mut cache = DataflowCache::new();
+    let solve_mock = |body: &mir::Body<'_>| {
+        let entry_sets = solve(&mut MockGenKill, body);
+        Results::new(MockGenKill, entry_sets)
+    };
+
+    let first = cache.get_or_compute(&body, fingerprint, || solve_mock(&body));
+    let second = cache.get_or_compute(&body, fingerprint, || solve_mock(&body));
+    assert!(Lrc::ptr_eq(&first, &second));
+    assert_eq!(cache.computations(), 1);
+
+    // A different configuration of the analysis is cached separately.
+    let other = cache.get_or_compute(&body, Fingerprint::new(0, 1), || solve_mock(&body));
+    assert!(!Lrc::ptr_eq(&first, &other));
+    assert_eq!(cache.computations(), 2);
+
+    // So is a promoted, which shares the `DefId` of its parent.
+    let mut promoted = body.clone();
+    promoted.source.promoted = Some(mir::Promoted::from_u32(0));
+    let from_promoted = cache.get_or_compute(&promoted, fingerprint, || solve_mock(&promoted));
+    assert!(!Lrc::ptr_eq(&first, &from_promoted));
+    assert_eq!(cache.computations(), 3);
+
+    // Passes that modify the body bump its `pass_count`, which invalidates the cached results.
+    for data in body.basic_blocks_mut().iter_mut() {
+        data.statements.clear();
+    }
+    body.pass_count += 1;
+    let third = cache.get_or_compute(&body, fingerprint, || solve_mock(&body));
+    assert!(!Lrc::ptr_eq(&first, &third));
+    assert_eq!(cache.computations(), 4);
+    assert_ne!(first.entry_sets, third.entry_sets);
+
+    let fourth = cache.get_or_compute(&body, fingerprint, || solve_mock(&body));
+    assert!(Lrc::ptr_eq(&third, &fourth));
+    assert_eq!(cache.computations(), 4);
+
+    // A phase change restarts `pass_count`, but the phase still tells the versions apart.
+    body.phase = mir::MirPhase::Runtime(mir::RuntimePhase::Initial);
+    body.pass_count = 0;
+    let fifth = cache.get_or_compute(&body, fingerprint, || solve_mock(&body));
+    assert!(!Lrc::ptr_eq(&third, &fifth));
+    assert_eq!(cache.computations(), 5);
+}
+
+#[test]
+fn renumbered_bitset_analysis() {
+    let body = &diamond_body(2);
+
+    // Each block generates the bit with its own index and kills that of its successor in index
+    // order. No bit affects another, so dropping bit 2 doesn't change the others.
+    let analysis = || {
+        adhoc::forward_bitset_analysis::<usize>(
+            4,
+            |trans, _, location| match location.statement_index {
+                0 => trans.gen(location.block.index()),
+                _ => trans.kill((location.block.index() + 1) % 4),
+            },
+            |_, _, _| {},
+        )
+    };
+    let expected = solve_with_bottom(&mut analysis(), body, |_, _| BitSet::new_empty(4));
+
+    let to_old = IndexVec::<usize, usize>::from_raw(vec![3, 1, 0]);
+    let mut renumbered = RenumberedBitsetAnalysis::new(analysis(), body, to_old);
+    let actual = solve_with_bottom(&mut renumbered, body, |_, _| BitSet::new_empty(3));
+
+    assert_eq!(renumbered.new_index(2), None);
+    for (bb, actual) in actual.iter_enumerated() {
+        let actual: BTreeSet<_> = actual.iter().map(|elem| renumbered.old_index(elem)).collect();
+        let expected: BTreeSet<_> = expected[bb].iter().filter(|&elem| elem != 2).collect();
+        assert_eq!(actual, expected);
+    }
+}
+
+#[test]
+fn statement_states() {
+    fn check<'tcx, D: Direction>(body: &'tcx mir::Body<'tcx>) {
+        let analysis = MockAnalysis::<D>::new(body);
+        let entry_sets = analysis.mock_entry_sets();
+        let mut cursor = Results::new(analysis, entry_sets).into_results_cursor(body);
+        cursor.allow_unreachable();
+
+        let block = BasicBlock::from_u32(1);
+        let states: Vec<_> = cursor.statement_states(block).collect();
+        let mut indices: Vec<_> =
+            states.iter().map(|&(statement_index, _)| statement_index).collect();
+        if D::IS_BACKWARD {
+            indices.reverse();
+        }
+        assert_eq!(indices, [0, 1, 2]);
+
+        for (statement_index, state) in states {
+            let target = SeekTarget::After(Location { block, statement_index });
+            assert_eq!(state, cursor.analysis().expected_state_at_target(target));
+        }
+    }
+
+    let body = &diamond_body(3);
+    check::<Forward>(body);
+    check::<Backward>(body);
+}
+
+/// An analysis that checks the state of a `MockAnalysis` it depends on before and after each of its
+/// own effects.
+struct DependencyProbe<'res, 'tcx, B: Direction, D> {
+    dependency: Dependency<'res, 'tcx, 'tcx, MockAnalysis<'tcx, B>>,
+    expected: MockAnalysis<'tcx, B>,
+    checked: usize,
+    dir: PhantomData<D>,
+}
+
+impl<B: Direction, D> DependencyProbe<'_, '_, B, D> {
+    fn check(&mut self, target: SeekTarget) {
+        let state = match target {
+            SeekTarget::BlockEntry(block) => self.dependency.entry_state(block),
+            SeekTarget::Before(loc) => self.dependency.state_before(loc),
+            SeekTarget::After(loc) => self.dependency.state_after(loc),
+        };
+        assert_eq!(*state, self.expected.expected_state_at_target(target));
+        self.checked += 1;
+    }
+}
 
-    let dummy_place = mir::Place { local: mir::RETURN_PLACE, projection: ty::List::empty() };
+impl<'tcx, B: Direction, D: Direction> AnalysisDomain<'tcx> for DependencyProbe<'_, 'tcx, B, D> {
+    type Domain = BitSet<usize>;
+    type Direction = D;
 
-    block(4, mir::TerminatorKind::Return);
-    block(1, mir::TerminatorKind::Return);
-    block(
-        2,
-        mir::TerminatorKind::Call {
-            func: mir::Operand::Copy(dummy_place.clone()),
-            args: vec![],
-            destination: dummy_place.clone(),
-            target: Some(mir::START_BLOCK),
-            unwind: mir::UnwindAction::Continue,
-            call_source: mir::CallSource::Misc,
-            fn_span: DUMMY_SP,
-        },
-    );
-    block(3, mir::TerminatorKind::Return);
-    block(0, mir::TerminatorKind::Return);
-    block(
-        4,
-        mir::TerminatorKind::Call {
-            func: mir::Operand::Copy(dummy_place.clone()),
-            args: vec![],
-            destination: dummy_place.clone(),
-            target: Some(mir::START_BLOCK),
-            unwind: mir::UnwindAction::Continue,
-            call_source: mir::CallSource::Misc,
-            fn_span: DUMMY_SP,
-        },
-    );
+    const NAME: &'static str = "mock_dependency_probe";
 
-    mir::Body::new_cfg_only(blocks)
+    fn bottom_value(&self, _: TyCtxt<'tcx>, body: &mir::Body<'tcx>) -> Self::Domain {
+        self.test_bottom(body)
+    }
+
+    fn initialize_start_block(&self, _: &mir::Body<'tcx>, _: &mut Self::Domain) {}
 }
 
-/// A dataflow analysis whose state is unique at every possible `SeekTarget`.
-///
-/// Uniqueness is achieved by having a *locally* unique effect before and after each statement and
-/// terminator (see `effect_at_target`) while ensuring that the entry set for each block is
-/// *globally* unique (see `mock_entry_set`).
-///
-/// For example, a `BasicBlock` with ID `2` and a `Call` terminator has the following state at each
-/// location ("+x" indicates that "x" is added to the state).
-///
-/// | Location               | Before            | After  |
-/// |------------------------|-------------------|--------|
-/// | (on_entry)             | {102}                     ||
-/// | statement 0            | +0                | +1     |
-/// | statement 1            | +2                | +3     |
-/// | `Call` terminator      | +4                | +5     |
-/// | (on unwind)            | {102,0,1,2,3,4,5}         ||
-///
-/// The `102` in the block's entry set is derived from the basic block index and ensures that the
-/// expected state is unique across all basic blocks. Remember, it is generated by
-/// `mock_entry_sets`, not from actually running `MockAnalysis` to fixpoint.
-struct MockAnalysis<'tcx, D> {
-    body: &'tcx mir::Body<'tcx>,
-    dir: PhantomData<D>,
+impl<'tcx, B: Direction, D: Direction> TestBottom<'tcx> for DependencyProbe<'_, 'tcx, B, D> {
+    fn test_bottom(&self, _: &mir::Body<'tcx>) -> Self::Domain {
+        BitSet::new_empty(0)
+    }
 }
 
-impl<D: Direction> MockAnalysis<'_, D> {
-    const BASIC_BLOCK_OFFSET: usize = 100;
+impl<'tcx, B: Direction, D: Direction> Analysis<'tcx> for DependencyProbe<'_, 'tcx, B, D> {
+    fn apply_statement_effect(
+        &mut self,
+        _state: &mut Self::Domain,
+        _statement: &mir::Statement<'tcx>,
+        location: Location,
+    ) {
+        self.check(SeekTarget::After(location));
+    }
 
-    /// The entry set for each `BasicBlock` is the ID of that block offset by a fixed amount to
-    /// avoid colliding with the statement/terminator effects.
-    fn mock_entry_set(&self, bb: BasicBlock) -> BitSet<usize> {
-        let mut ret = self.bottom_value(self.body);
-        ret.insert(Self::BASIC_BLOCK_OFFSET + bb.index());
-        ret
+    fn apply_before_statement_effect(
+        &mut self,
+        _state: &mut Self::Domain,
+        _statement: &mir::Statement<'tcx>,
+        location: Location,
+    ) {
+        self.check(SeekTarget::Before(location));
     }
 
-    fn mock_entry_sets(&self) -> IndexVec<BasicBlock, BitSet<usize>> {
-        let empty = self.bottom_value(self.body);
-        let mut ret = IndexVec::from_elem(empty, &self.body.basic_blocks);
+    fn apply_terminator_effect<'mir>(
+        &mut self,
+        _state: &mut Self::Domain,
+        terminator: &'mir mir::Terminator<'tcx>,
+        location: Location,
+    ) -> TerminatorEdges<'mir, 'tcx> {
+        self.check(SeekTarget::After(location));
+        terminator.edges()
+    }
 
-        for (bb, _) in self.body.basic_blocks.iter_enumerated() {
-            ret[bb] = self.mock_entry_set(bb);
-        }
+    fn apply_before_terminator_effect(
+        &mut self,
+        _state: &mut Self::Domain,
+        _terminator: &mir::Terminator<'tcx>,
+        location: Location,
+    ) {
+        self.check(SeekTarget::Before(location));
+    }
 
-        ret
+    fn apply_call_return_effect(
+        &mut self,
+        _state: &mut Self::Domain,
+        _block: BasicBlock,
+        _return_places: CallReturnPlaces<'_, 'tcx>,
+    ) {
     }
+}
 
-    /// Returns the index that should be added to the dataflow state at the given target.
-    fn effect(&self, loc: EffectIndex) -> usize {
-        let idx = match loc.effect {
-            Effect::Before => loc.statement_index * 2,
-            Effect::Primary => loc.statement_index * 2 + 1,
-        };
+#[test]
+fn dependency() {
+    fn check<'tcx, B: Direction, D: Direction>(body: &'tcx mir::Body<'tcx>) {
+        let analysis = MockAnalysis::<B>::new(body);
+        let entry_sets = analysis.mock_entry_sets();
+        let results = Results::new(analysis, entry_sets);
 
-        assert!(idx < Self::BASIC_BLOCK_OFFSET, "Too many statements in basic block");
-        idx
-    }
-
-    /// Returns the expected state at the given `SeekTarget`.
-    ///
-    /// This is the union of index of the target basic block, the index assigned to the
-    /// target statement or terminator, and the indices of all preceding statements in the target
-    /// basic block.
-    ///
-    /// For example, the expected state when calling
-    /// `seek_before_primary_effect(Location { block: 2, statement_index: 2 })`
-    /// would be `[102, 0, 1, 2, 3, 4]`.
-    fn expected_state_at_target(&self, target: SeekTarget) -> BitSet<usize> {
-        let block = target.block();
-        let mut ret = self.bottom_value(self.body);
-        ret.insert(Self::BASIC_BLOCK_OFFSET + block.index());
-
-        let target = match target {
-            SeekTarget::BlockEntry { .. } => return ret,
-            SeekTarget::Before(loc) => Effect::Before.at_index(loc.statement_index),
-            SeekTarget::After(loc) => Effect::Primary.at_index(loc.statement_index),
+        let mut probe = DependencyProbe {
+            dependency: Dependency::new(&results, body),
+            expected: analysis,
+            checked: 0,
+            dir: PhantomData::<D>,
         };
+        solve(&mut probe, body);
 
-        let mut pos = if D::IS_FORWARD {
-            Effect::Before.at_index(0)
-        } else {
-            Effect::Before.at_index(self.body[block].statements.len())
-        };
+        // Every block is visited once, since the state of the probe never changes. Each of its two
+        // statements and its terminator has two effects.
+        assert_eq!(probe.checked, body.basic_blocks.len() * 3 * 2);
 
-        loop {
-            ret.insert(self.effect(pos));
+        for (bb, _) in body.basic_blocks.iter_enumerated() {
+            probe.check(SeekTarget::BlockEntry(bb));
+        }
+    }
 
-            if pos == target {
-                return ret;
-            }
+    let body = &diamond_body(2);
+    check::<Forward, Forward>(body);
+    check::<Forward, Backward>(body);
+    check::<Backward, Forward>(body);
+    check::<Backward, Backward>(body);
+}
 
-            if D::IS_FORWARD {
-                pos = pos.next_in_forward_order();
-            } else {
-                pos = pos.next_in_backward_order();
-            }
+#[test]
+fn merge_with() {
+    let body = &disconnected_body();
+    let bb1 = BasicBlock::from_u32(1);
+    let results = |bits: &[(BasicBlock, usize)]| {
+        let analysis = MockGenKill;
+        let mut entry_sets =
+            EntrySets::from_bottom_n(BitSet::new_empty(2), body.basic_blocks.len());
+        for &(bb, bit) in bits {
+            entry_sets[bb].insert(bit);
         }
+        Results::new(analysis, entry_sets)
+    };
+
+    let a = results(&[(mir::START_BLOCK, 0), (bb1, 0)]);
+    let b = results(&[(bb1, 1)]);
+    let merged = a.merge_with(b, |entry_set, other| {
+        entry_set.union(other);
+    });
+    assert_eq!(merged.entry_set_for_block(mir::START_BLOCK).iter().collect::<Vec<_>>(), [0]);
+    assert_eq!(merged.entry_set_for_block(bb1).iter().collect::<Vec<_>>(), [0, 1]);
+}
+
+#[test]
+fn into_parts() {
+    let body = &disconnected_body();
+    let bb1 = BasicBlock::from_u32(1);
+    let analysis = MockGenKill;
+    let mut entry_sets =
+        EntrySets::from_bottom_n(BitSet::new_empty(2), body.basic_blocks.len());
+    entry_sets[bb1].insert(1);
+    let results = Results::new(analysis, entry_sets.clone());
+
+    let (MockGenKill, parts) = results.into_parts();
+    assert_eq!(parts, entry_sets);
+}
+
+/// Counts the calls to `visit_statement_after_primary_effect` and
+/// `visit_terminator_after_primary_effect` at each location.
+#[derive(Default)]
+struct LocationCounter(BTreeMap<Location, usize>);
+
+impl<'mir, 'tcx, R> ResultsVisitor<'mir, 'tcx, R> for LocationCounter {
+    type FlowState = BitSet<usize>;
+
+    fn visit_statement_after_primary_effect(
+        &mut self,
+        _results: &mut R,
+        _state: &Self::FlowState,
+        _statement: &'mir mir::Statement<'tcx>,
+        location: Location,
+    ) {
+        *self.0.entry(location).or_default() += 1;
+    }
+
+    fn visit_terminator_after_primary_effect(
+        &mut self,
+        _results: &mut R,
+        _state: &Self::FlowState,
+        _terminator: &'mir mir::Terminator<'tcx>,
+        location: Location,
+    ) {
+        *self.0.entry(location).or_default() += 1;
+    }
+}
+
+#[test]
+fn chained_visitors() {
+    let body = &diamond_body(2);
+    let analysis = MockGenKill;
+    let entry_sets =
+        EntrySets::from_bottom_n(analysis.test_bottom(body), body.basic_blocks.len());
+    let mut results = Results::new(analysis, entry_sets);
+
+    let mut vis = Chain(LocationCounter::default(), LocationCounter::default());
+    results.visit_reachable_with(body, &mut vis);
+
+    let every_location: BTreeMap<_, _> = body
+        .basic_blocks
+        .iter_enumerated()
+        .flat_map(|(block, data)| {
+            (0..=data.statements.len())
+                .map(move |statement_index| Location { block, statement_index })
+        })
+        .map(|location| (location, 1))
+        .collect();
+    assert_eq!(vis.0 .0, every_location);
+    assert_eq!(vis.1 .0, every_location);
+}
+
+#[test]
+fn std_lattices() {
+    let mut option = None;
+    assert!(!option.join(&None));
+    assert!(option.join(&Some(false)));
+    assert_eq!(option, Some(false));
+    assert!(!option.join(&None));
+    assert!(option.join(&Some(true)));
+    assert_eq!(option, Some(true));
+    assert!(option.meet(&None));
+    assert_eq!(option, None);
+
+    let mut vec = vec![false, true, false];
+    assert!(!vec.join(&vec![false, false, false]));
+    assert!(vec.join(&vec![true, false, false]));
+    assert_eq!(vec, [true, true, false]);
+    assert!(vec.meet(&vec![true, false, true]));
+    assert_eq!(vec, [true, false, false]);
+}
+
+#[test]
+#[should_panic]
+fn std_lattices_vec_length_mismatch() {
+    vec![false].join(&vec![false, true]);
+}
+
+/// Records a projection of the state at the start of each visited block.
+struct BlockStartStates<D, T> {
+    project: fn(&D) -> T,
+    states: Vec<(BasicBlock, T)>,
+}
+
+impl<'mir, 'tcx, R, D, T> ResultsVisitor<'mir, 'tcx, R> for BlockStartStates<D, T> {
+    type FlowState = D;
+
+    fn visit_block_start(
+        &mut self,
+        _results: &mut R,
+        state: &Self::FlowState,
+        _block_data: &'mir mir::BasicBlockData<'tcx>,
+        block: BasicBlock,
+    ) {
+        self.states.push((block, (self.project)(state)));
     }
 }
 
-impl<'tcx, D: Direction> AnalysisDomain<'tcx> for MockAnalysis<'tcx, D> {
+/// An analysis whose statement effect inserts the number of statements in the block. The
+/// uncached effect looks that number up in the body each time, which is counted in `recomputed`,
+/// while the cached one reads it from the `BlockCache`.
+struct MockBlockCache<'a, 'tcx> {
+    body: &'a mir::Body<'tcx>,
+    prepared: usize,
+    recomputed: usize,
+}
+
+impl<'tcx> AnalysisDomain<'tcx> for MockBlockCache<'_, 'tcx> {
     type Domain = BitSet<usize>;
-    type Direction = D;
 
-    const NAME: &'static str = "mock";
+    const NAME: &'static str = "mock_block_cache";
 
-    fn bottom_value(&self, body: &mir::Body<'tcx>) -> Self::Domain {
-        BitSet::new_empty(Self::BASIC_BLOCK_OFFSET + body.basic_blocks.len())
+    fn bottom_value(&self, _: TyCtxt<'tcx>, body: &mir::Body<'tcx>) -> Self::Domain {
+        self.test_bottom(body)
     }
 
-    fn initialize_start_block(&self, _: &mir::Body<'tcx>, _: &mut Self::Domain) {
-        unimplemented!("This is never called since `MockAnalysis` is never iterated to fixpoint");
+    fn initialize_start_block(&self, _: &mir::Body<'tcx>, _: &mut Self::Domain) {}
+}
+
+impl<'tcx> TestBottom<'tcx> for MockBlockCache<'_, 'tcx> {
+    fn test_bottom(&self, _: &mir::Body<'tcx>) -> Self::Domain {
+        BitSet::new_empty(8)
     }
 }
 
-impl<'tcx, D: Direction> Analysis<'tcx> for MockAnalysis<'tcx, D> {
+impl<'tcx> Analysis<'tcx> for MockBlockCache<'_, 'tcx> {
+    type BlockCache = IndexVec<BasicBlock, usize>;
+
     fn apply_statement_effect(
         &mut self,
         state: &mut Self::Domain,
         _statement: &mir::Statement<'tcx>,
         location: Location,
     ) {
-        let idx = self.effect(Effect::Primary.at_index(location.statement_index));
-        assert!(state.insert(idx));
+        self.recomputed += 1;
+        state.insert(self.body[location.block].statements.len());
     }
 
-    fn apply_before_statement_effect(
+    fn apply_terminator_effect<'mir>(
+        &mut self,
+        _state: &mut Self::Domain,
+        terminator: &'mir mir::Terminator<'tcx>,
+        _location: Location,
+    ) -> TerminatorEdges<'mir, 'tcx> {
+        terminator.edges()
+    }
+
+    fn apply_call_return_effect(
+        &mut self,
+        _state: &mut Self::Domain,
+        _block: BasicBlock,
+        _return_places: CallReturnPlaces<'_, 'tcx>,
+    ) {
+    }
+
+    fn prepare_block_cache(&mut self, body: &mir::Body<'tcx>) -> Self::BlockCache {
+        self.prepared += 1;
+        body.basic_blocks.iter().map(|data| data.statements.len()).collect()
+    }
+
+    fn apply_statement_effect_cached(
         &mut self,
+        cache: &Self::BlockCache,
         state: &mut Self::Domain,
         _statement: &mir::Statement<'tcx>,
         location: Location,
     ) {
-        let idx = self.effect(Effect::Before.at_index(location.statement_index));
-        assert!(state.insert(idx));
+        state.insert(cache[location.block]);
+    }
+}
+
+#[test]
+fn block_cache() {
+    let body = &loop_body(2);
+    let mut analysis = MockBlockCache { body, prepared: 0, recomputed: 0 };
+    let entry_sets = solve(&mut analysis, body);
+    assert_eq!((analysis.prepared, analysis.recomputed), (1, 0));
+    for (block, entry_set) in entry_sets.iter_enumerated() {
+        assert_eq!(entry_set.contains(2), block != mir::START_BLOCK);
+    }
+
+    // Cursors use the uncached statement effects, which must agree with the cached ones.
+    let results = Results::new(analysis, entry_sets);
+    let mut cursor = ResultsCursor::new(body, results);
+    for block in body.basic_blocks.indices() {
+        cursor.seek_to_block_end(block);
+        assert!(cursor.get().contains(2));
+    }
+    assert_eq!(cursor.results().analysis.prepared, 1);
+    assert!(cursor.results().analysis.recomputed > 0);
+}
+
+/// A counter that is incremented by every terminator, so that it never converges in a loop.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Unbounded(usize);
+
+impl JoinSemiLattice for Unbounded {
+    fn join(&mut self, other: &Self) -> bool {
+        if other.0 > self.0 {
+            self.0 = other.0;
+            return true;
+        }
+        false
+    }
+}
+
+struct MockDivergent;
+
+impl<'tcx> AnalysisDomain<'tcx> for MockDivergent {
+    type Domain = Unbounded;
+
+    const NAME: &'static str = "mock_divergent";
+
+    fn bottom_value(&self, _: TyCtxt<'tcx>, body: &mir::Body<'tcx>) -> Self::Domain {
+        self.test_bottom(body)
+    }
+
+    fn initialize_start_block(&self, _: &mir::Body<'tcx>, _: &mut Self::Domain) {}
+}
+
+impl<'tcx> TestBottom<'tcx> for MockDivergent {
+    fn test_bottom(&self, _: &mir::Body<'tcx>) -> Self::Domain {
+        Unbounded(0)
+    }
+}
+
+impl<'tcx> Analysis<'tcx> for MockDivergent {
+    fn apply_statement_effect(
+        &mut self,
+        _state: &mut Self::Domain,
+        _statement: &mir::Statement<'tcx>,
+        _location: Location,
+    ) {
     }
 
     fn apply_terminator_effect<'mir>(
         &mut self,
         state: &mut Self::Domain,
         terminator: &'mir mir::Terminator<'tcx>,
-        location: Location,
+        _location: Location,
     ) -> TerminatorEdges<'mir, 'tcx> {
-        let idx = self.effect(Effect::Primary.at_index(location.statement_index));
-        assert!(state.insert(idx));
+        state.0 += 1;
         terminator.edges()
     }
 
-    fn apply_before_terminator_effect(
+    fn apply_call_return_effect(
         &mut self,
-        state: &mut Self::Domain,
-        _terminator: &mir::Terminator<'tcx>,
+        _state: &mut Self::Domain,
+        _block: BasicBlock,
+        _return_places: CallReturnPlaces<'_, 'tcx>,
+    ) {
+    }
+}
+
+#[test]
+fn visit_limit() {
+    let body = &loop_body(0);
+
+    // Analyses that converge are unaffected by the limit.
+    let expected = solve(&mut MockGenKill, body);
+    let mut entry_sets =
+        EntrySets::from_bottom_n(MockGenKill.test_bottom(body), body.basic_blocks.len());
+    let options = FixpointOptions { visit_limit: Some(10), ..FixpointOptions::default() };
+    solve_from(&mut MockGenKill, body, &mut entry_sets, options);
+    assert_eq!(entry_sets, expected);
+
+    // The loop header and the exit are updated whenever the loop goes around once more.
+    let mut entry_sets =
+        EntrySets::from_bottom_n(MockDivergent.test_bottom(body), body.basic_blocks.len());
+    let diverged = iterate_entry_sets_to_fixpoint(
+        &mut MockDivergent,
+        body,
+        &mut entry_sets,
+        FixpointOptions { visit_limit: Some(10), ..FixpointOptions::default() },
+    );
+    let blocks = diverged.unwrap_err();
+    assert_eq!(
+        blocks.iter().collect::<Vec<_>>(),
+        [BasicBlock::from_u32(1), BasicBlock::from_u32(2)]
+    );
+}
+
+#[test]
+fn convergence_info() {
+    let body = &nested_loop_body(0);
+    let mut analysis = GotoBlocks::default();
+    let mut entry_sets =
+        EntrySets::from_bottom_n(analysis.test_bottom(body), body.basic_blocks.len());
+    let convergence = iterate_entry_sets_to_fixpoint(
+        &mut analysis,
+        body,
+        &mut entry_sets,
+        FixpointOptions::default(),
+    )
+    .unwrap();
+
+    // Only the outer loop header is visited again, once the back edge from `bb3` adds `bb1` to its
+    // own entry set.
+    let iterations =
+        body.basic_blocks.indices().map(|bb| convergence.iterations_for(bb)).collect::<Vec<_>>();
+    assert_eq!(iterations, [1, 2, 1, 1, 1]);
+    assert_eq!(convergence.total_iterations(), 6);
+
+    let bb1 = BasicBlock::from_u32(1);
+    let mut results = Results::new(analysis, entry_sets);
+    results.convergence = Some(convergence);
+    assert_eq!(results.block_converged_in(bb1), Some(2));
+
+    results.convergence = None;
+    assert_eq!(results.block_converged_in(bb1), None);
+}
+
+#[test]
+fn state_reuse() {
+    for body in [&loop_body(2), &nested_loop_body(2), &irreducible_body(3, 1)] {
+        let expected = solve(&mut GotoBlocks::default(), body);
+        let mut entry_sets = EntrySets::from_bottom_n(
+            GotoBlocks::default().test_bottom(body),
+            body.basic_blocks.len(),
+        );
+        let options = FixpointOptions { reuse_state: false, ..FixpointOptions::default() };
+        solve_from(&mut GotoBlocks::default(), body, &mut entry_sets, options);
+        assert_eq!(entry_sets, expected);
+    }
+}
+
+#[test]
+fn count_changed_on_reanalysis() {
+    let body = &straight_line_body(3, 2);
+    let entry_sets = solve(&mut MockGenKill, body);
+    let mut results = Results::new(MockGenKill, entry_sets);
+    assert_eq!(results.count_changed_on_reanalysis(body), 0);
+
+    // A warm start from an entry set above the fixpoint never comes back down. `bb0` has no
+    // predecessors, so its entry set stays as patched.
+    let mut filled = BitSet::new_empty(1);
+    filled.insert(0);
+    assert!(results.patch_entry_set(mir::START_BLOCK, filled));
+    assert_eq!(results.count_changed_on_reanalysis(body), 1);
+}
+
+/// A gen/kill analysis over three bits in which the "before" and primary effects of each statement
+/// touch different bits, so that applying them in the wrong order changes the state. Counts the
+/// primary statement effects it applies.
+struct MockBeforeEffects<D: Direction> {
+    statement_effects: usize,
+    dir: PhantomData<D>,
+}
+
+impl<D: Direction> MockBeforeEffects<D> {
+    fn new() -> Self {
+        MockBeforeEffects { statement_effects: 0, dir: PhantomData }
+    }
+}
+
+impl<'tcx, D: Direction> AnalysisDomain<'tcx> for MockBeforeEffects<D> {
+    type Domain = BitSet<usize>;
+    type Direction = D;
+
+    const NAME: &'static str = "mock_before_effects";
+
+    fn bottom_value(&self, _: TyCtxt<'tcx>, body: &mir::Body<'tcx>) -> Self::Domain {
+        self.test_bottom(body)
+    }
+
+    fn initialize_start_block(&self, _: &mir::Body<'tcx>, state: &mut Self::Domain) {
+        state.insert(2);
+    }
+}
+
+impl<'tcx, D: Direction> TestBottom<'tcx> for MockBeforeEffects<D> {
+    fn test_bottom(&self, _: &mir::Body<'tcx>) -> Self::Domain {
+        BitSet::new_empty(3)
+    }
+}
+
+impl<'tcx, D: Direction> GenKillAnalysis<'tcx> for MockBeforeEffects<D> {
+    type Idx = usize;
+
+    fn domain_size(&self, _: &mir::Body<'tcx>) -> usize {
+        3
+    }
+
+    fn before_statement_effect(
+        &mut self,
+        trans: &mut impl GenKill<Self::Idx>,
+        _statement: &mir::Statement<'tcx>,
         location: Location,
     ) {
-        let idx = self.effect(Effect::Before.at_index(location.statement_index));
-        assert!(state.insert(idx));
+        trans.gen((location.block.index() + location.statement_index) % 3);
     }
 
-    fn apply_call_return_effect(
+    fn statement_effect(
         &mut self,
-        _state: &mut Self::Domain,
+        trans: &mut impl GenKill<Self::Idx>,
+        _statement: &mir::Statement<'tcx>,
+        location: Location,
+    ) {
+        self.statement_effects += 1;
+        trans.kill((location.block.index() + 2 * location.statement_index + 1) % 3);
+    }
+
+    fn terminator_effect<'mir>(
+        &mut self,
+        trans: &mut Self::Domain,
+        terminator: &'mir mir::Terminator<'tcx>,
+        location: Location,
+    ) -> TerminatorEdges<'mir, 'tcx> {
+        trans.gen(location.block.index() % 3);
+        terminator.edges()
+    }
+
+    fn call_return_effect(
+        &mut self,
+        _trans: &mut impl GenKill<Self::Idx>,
         _block: BasicBlock,
         _return_places: CallReturnPlaces<'_, 'tcx>,
     ) {
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-enum SeekTarget {
-    BlockEntry(BasicBlock),
-    Before(Location),
-    After(Location),
-}
+/// Checks that a cursor that skips over whole blocks with the cached transfer functions reaches
+/// the same states as one that applies the effects of each statement in turn.
+fn check_batched_seeks<D: Direction>(body: &mir::Body<'_>) {
+    let entry_sets = solve(&mut MockBeforeEffects::<D>::new(), body);
+    let trans = gen_kill_trans_for_blocks(&mut MockBeforeEffects::<D>::new(), body, 3);
+    let mut batched = Results::new(MockBeforeEffects::<D>::new(), entry_sets.clone());
+    batched.block_trans = Some(block_trans(std::sync::Arc::new(trans)));
+    let mut batched = batched.into_results_cursor(body);
+    let mut stepwise =
+        Results::new(MockBeforeEffects::<D>::new(), entry_sets).into_results_cursor(body);
 
-impl SeekTarget {
-    fn block(&self) -> BasicBlock {
-        use SeekTarget::*;
+    for (block, block_data) in body.basic_blocks.iter_enumerated() {
+        // Seeking across every statement of a block never replays them one at a time.
+        if D::IS_FORWARD {
+            batched.seek_to_block_end(block);
+            stepwise.seek_to_block_end(block);
+        } else {
+            batched.seek_to_block_start(block);
+            stepwise.seek_to_block_start(block);
+        }
+        assert_eq!(batched.get(), stepwise.get());
+        assert_eq!(batched.analysis().statement_effects, 0);
 
-        match *self {
-            BlockEntry(block) => block,
-            Before(loc) | After(loc) => loc.block,
+        // Seeks that stop within the block fall back to applying each effect.
+        for statement_index in 0..=block_data.statements.len() {
+            let location = Location { block, statement_index };
+            batched.seek_before_primary_effect(location);
+            stepwise.seek_before_primary_effect(location);
+            assert_eq!(batched.get(), stepwise.get());
+            batched.seek_after_primary_effect(location);
+            stepwise.seek_after_primary_effect(location);
+            assert_eq!(batched.get(), stepwise.get());
         }
-    }
 
-    /// An iterator over all possible `SeekTarget`s in a given block in order, starting with
-    /// `BlockEntry`.
-    fn iter_in_block(body: &mir::Body<'_>, block: BasicBlock) -> impl Iterator<Item = Self> {
-        let statements_and_terminator = (0..=body[block].statements.len())
-            .flat_map(|i| (0..2).map(move |j| (i, j)))
-            .map(move |(i, kind)| {
-                let loc = Location { block, statement_index: i };
-                match kind {
-                    0 => SeekTarget::Before(loc),
-                    1 => SeekTarget::After(loc),
-                    _ => unreachable!(),
-                }
-            });
+        // Seeking from within the block to its end is not batched either.
+        batched.seek_to_block_end(block);
+        stepwise.seek_to_block_end(block);
+        assert_eq!(batched.get(), stepwise.get());
+        batched.seek_to_block_start(block);
+        stepwise.seek_to_block_start(block);
+        assert_eq!(batched.get(), stepwise.get());
+        batched.mut_analysis().statement_effects = 0;
+    }
+}
 
-        std::iter::once(SeekTarget::BlockEntry(block)).chain(statements_and_terminator)
+#[test]
+fn batched_seeks() {
+    for body in [&straight_line_body(3, 2), &diamond_body(3), &loop_body(2), &loop_body(0)] {
+        check_batched_seeks::<Forward>(body);
+        check_batched_seeks::<Backward>(body);
     }
 }
 
-fn test_cursor<D: Direction>(analysis: MockAnalysis<'_, D>) {
-    let body = analysis.body;
+#[test]
+fn back_edge_sources_of_common_cfg_shapes() {
+    let sources = |body: &mir::Body<'_>| back_edge_sources(body).iter().collect::<Vec<_>>();
 
-    let mut cursor =
-        Results { entry_sets: analysis.mock_entry_sets(), analysis, _marker: PhantomData }
-            .into_results_cursor(body);
+    assert_eq!(sources(&straight_line_body(3, 0)), []);
+    assert_eq!(sources(&diamond_body(0)), []);
+    assert_eq!(sources(&loop_body(0)), [BasicBlock::from_u32(1)]);
+    assert_eq!(sources(&nested_loop_body(0)), [BasicBlock::from_u32(2), BasicBlock::from_u32(3)]);
+    assert_eq!(sources(&loop_nest_body(3, 0)), [3, 4, 5].map(BasicBlock::from_u32));
+    assert_eq!(sources(&wide_diamond_body(5, 0)), []);
 
-    cursor.allow_unreachable();
+    // Which edge of an irreducible cycle is a back edge depends on the traversal order.
+    assert_eq!(sources(&irreducible_body(3, 0)).len(), 1);
+}
+
+fn check_selectively_cached_fixpoint<D: Direction>(body: &mir::Body<'_>) {
+    let expected = solve(&mut MockBeforeEffects::<D>::new(), body);
 
-    let every_target = || {
-        body.basic_blocks.iter_enumerated().flat_map(|(bb, _)| SeekTarget::iter_in_block(body, bb))
+    // Only the blocks that close a loop have a transfer function; the statement effects of the
+    // others are applied one at a time.
+    let cached_blocks = back_edge_sources(body);
+    let trans = gen_kill_trans_for_selected_blocks(
+        &mut MockBeforeEffects::<D>::new(),
+        body,
+        3,
+        &cached_blocks,
+    );
+    assert_eq!(trans.len(), cached_blocks.count());
+    let trans = CachedBlockTrans::Selected(trans);
+    let apply_trans = |bb: BasicBlock, state: &mut BitSet<usize>| trans.apply(bb, state);
+
+    let mut analysis = MockBeforeEffects::<D>::new();
+    let mut entry_sets =
+        EntrySets::from_bottom_n(analysis.test_bottom(body), body.basic_blocks.len());
+    let options = FixpointOptions {
+        apply_statement_trans_for_block: Some(&apply_trans),
+        ..FixpointOptions::default()
     };
+    solve_from(&mut analysis, body, &mut entry_sets, options);
+    assert_eq!(entry_sets, expected);
+    assert!(analysis.statement_effects > 0);
+}
 
-    let mut seek_to_target = |targ| {
-        use SeekTarget::*;
+#[test]
+fn selectively_cached_fixpoint() {
+    for body in
+        [&loop_body(2), &nested_loop_body(2), &loop_nest_body(3, 2), &irreducible_body(3, 2)]
+    {
+        check_selectively_cached_fixpoint::<Forward>(body);
+        check_selectively_cached_fixpoint::<Backward>(body);
+    }
+}
 
-        match targ {
-            BlockEntry(block) => cursor.seek_to_block_entry(block),
-            Before(loc) => cursor.seek_before_primary_effect(loc),
-            After(loc) => cursor.seek_after_primary_effect(loc),
-        }
+#[test]
+fn compact_entry_sets() {
+    let body = &straight_line_body(20, 2);
+    let entry_sets = solve(&mut MockGenKill, body);
+    let mut expected = Results::new(MockGenKill, entry_sets.clone()).into_results_cursor(body);
+    let mut results = Results::new(MockGenKill, entry_sets);
 
-        assert_eq!(cursor.get(), &cursor.analysis().expected_state_at_target(targ));
-    };
+    // `bb0` is at bottom, and the bit is set on entry to every other block, so the entry sets of
+    // all twenty blocks are stored twice.
+    assert_eq!(results.compact(), 10.0);
+    let [bb1, bb2, bb19] = [1, 2, 19].map(BasicBlock::from_usize);
+    assert!(!results.entry_sets.is_populated(mir::START_BLOCK));
+    assert!(std::ptr::eq(&results.entry_sets[bb1], &results.entry_sets[bb19]));
+    assert_eq!(results.compact(), 10.0);
 
-    // Seek *to* every possible `SeekTarget` *from* every possible `SeekTarget`.
-    //
-    // By resetting the cursor to `from` each time it changes, we end up checking some edges twice.
-    // What we really want is an Eulerian cycle for the complete digraph over all possible
-    // `SeekTarget`s, but it's not worth spending the time to compute it.
-    for from in every_target() {
-        seek_to_target(from);
+    // Writing to a shared entry set gives the block its own copy.
+    let mut entry_sets = results.entry_sets.clone();
+    entry_sets[bb1].remove(0);
+    assert!(!entry_sets[bb1].contains(0));
+    assert!(entry_sets[bb2].contains(0));
 
-        for to in every_target() {
-            dbg!(from);
-            dbg!(to);
-            seek_to_target(to);
-            seek_to_target(from);
+    let mut cursor = results.into_results_cursor(body);
+    for (block, block_data) in body.basic_blocks.iter_enumerated() {
+        for statement_index in 0..=block_data.statements.len() {
+            let location = Location { block, statement_index };
+            cursor.seek_after_primary_effect(location);
+            expected.seek_after_primary_effect(location);
+            assert_eq!(cursor.get(), expected.get());
         }
     }
 }
 
 #[test]
-fn backward_cursor() {
-    let body = mock_body();
-    let body = &body;
-    let analysis = MockAnalysis { body, dir: PhantomData::<Backward> };
-    test_cursor(analysis)
-}
+fn monotone_seeks_are_incremental() {
+    let body = &straight_line_body(2, 4);
+    let entry_sets = solve(&mut MockBeforeEffects::<Forward>::new(), body);
+    let analysis = MockBeforeEffects::<Forward>::new();
+    let mut cursor = Results::new(analysis, entry_sets).into_results_cursor(body);
 
-#[test]
-fn forward_cursor() {
-    let body = mock_body();
-    let body = &body;
-    let analysis = MockAnalysis { body, dir: PhantomData::<Forward> };
-    test_cursor(analysis)
+    // Each seek to a later statement only applies the effects since the previous one.
+    for block in body.basic_blocks.indices() {
+        for statement_index in 0..4 {
+            cursor.seek_after_primary_effect(Location { block, statement_index });
+        }
+    }
+    assert_eq!(cursor.analysis().statement_effects, 8);
+
+    // Seeking back within a block starts over from its entry.
+    cursor
+        .seek_after_primary_effect(Location { block: BasicBlock::from_u32(1), statement_index: 1 });
+    assert_eq!(cursor.analysis().statement_effects, 10);
 }
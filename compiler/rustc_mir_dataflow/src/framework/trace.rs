@@ -0,0 +1,564 @@
+//! `Analysis` wrappers that emit a `trace!` event for each transfer function they apply.
+
+use rustc_index::Idx;
+use rustc_middle::mir::{self, BasicBlock, CallReturnPlaces, Location, TerminatorEdges};
+use rustc_middle::ty::TyCtxt;
+use tracing::Level;
+
+use super::fmt::{plain_diff, DebugWithContext};
+use super::{
+    Analysis, AnalysisDomain, BitSetExt, FalseEdgeSemantics, GenKill, GenKillAnalysis,
+    SwitchIntTarget,
+};
+
+/// Returns whether the effect at `location` should be traced.
+fn is_traced(filter: Option<fn(Location) -> bool>, location: Location) -> bool {
+    tracing::enabled!(Level::TRACE) && filter.map_or(true, |filter| filter(location))
+}
+
+fn trace_effect(analysis: &'static str, location: Location, effect: &'static str, diff: &str) {
+    trace!(analysis, ?location, effect, diff, "applied dataflow effect");
+}
+
+fn trace_state_change<'tcx, A>(
+    analysis: &A,
+    location: Location,
+    effect: &'static str,
+    old: &A::Domain,
+    new: &A::Domain,
+) where
+    A: AnalysisDomain<'tcx>,
+    A::Domain: DebugWithContext<A>,
+{
+    trace_effect(A::NAME, location, effect, &plain_diff(new, old, analysis));
+}
+
+/// Wraps an `Analysis` and emits a `trace!` event for each effect it applies, with the location
+/// and kind of the effect and the resulting change to the state.
+///
+/// ```ignore (cross-crate-imports)
+/// let filter = |location: Location| location.block == BasicBlock::from_u32(4);
+/// let results = Engine::new_generic(tcx, body, TraceAnalysis::new(analysis).with_filter(filter))
+///     .iterate_to_fixpoint();
+/// ```
+///
+/// In the change to the state, elements that were added are prefixed with `+` and elements that
+/// were removed with `-`. Edge effects, which have no location of their own, are attributed to the
+/// start of the block whose terminator they belong to.
+///
+/// Like `CountingAnalysis`, this always implements `Analysis` directly, so wrapping a
+/// `GenKillAnalysis` disables the caching of block transfer functions in the `Engine`. Use
+/// `TraceGenKillAnalysis` to keep it.
+#[derive(Clone)]
+pub struct TraceAnalysis<A> {
+    inner: A,
+    filter: Option<fn(Location) -> bool>,
+}
+
+impl<A> TraceAnalysis<A> {
+    pub fn new(inner: A) -> Self {
+        TraceAnalysis { inner, filter: None }
+    }
+
+    /// Only traces the effects at locations for which `filter` returns `true`.
+    pub fn with_filter(mut self, filter: fn(Location) -> bool) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    pub fn inner(&self) -> &A {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> A {
+        self.inner
+    }
+}
+
+impl<'tcx, A> TraceAnalysis<A>
+where
+    A: Analysis<'tcx>,
+    A::Domain: DebugWithContext<A>,
+{
+    fn traced<R>(
+        &mut self,
+        state: &mut A::Domain,
+        location: Location,
+        effect: &'static str,
+        apply: impl FnOnce(&mut A, &mut A::Domain) -> R,
+    ) -> R {
+        if !is_traced(self.filter, location) {
+            return apply(&mut self.inner, state);
+        }
+
+        let old = state.clone();
+        let ret = apply(&mut self.inner, state);
+        trace_state_change(&self.inner, location, effect, &old, state);
+        ret
+    }
+}
+
+impl<'tcx, A> AnalysisDomain<'tcx> for TraceAnalysis<A>
+where
+    A: AnalysisDomain<'tcx>,
+{
+    type Domain = A::Domain;
+    type Direction = A::Direction;
+    type SwitchIntData = A::SwitchIntData;
+
+    const NAME: &'static str = A::NAME;
+    const REQUIRES_CLEANUP_BLOCKS: bool = A::REQUIRES_CLEANUP_BLOCKS;
+    const MERGES_CALL_RETURN_STATE: bool = A::MERGES_CALL_RETURN_STATE;
+
+    fn bottom_value(&self, tcx: TyCtxt<'tcx>, body: &mir::Body<'tcx>) -> Self::Domain {
+        self.inner.bottom_value(tcx, body)
+    }
+
+    fn initialize_start_block(&self, body: &mir::Body<'tcx>, state: &mut Self::Domain) {
+        self.inner.initialize_start_block(body, state)
+    }
+}
+
+impl<'tcx, A> Analysis<'tcx> for TraceAnalysis<A>
+where
+    A: Analysis<'tcx>,
+    A::Domain: DebugWithContext<A>,
+{
+    type BlockCache = A::BlockCache;
+
+    fn apply_statement_effect(
+        &mut self,
+        state: &mut Self::Domain,
+        statement: &mir::Statement<'tcx>,
+        location: Location,
+    ) {
+        self.traced(state, location, "statement", |inner, state| {
+            inner.apply_statement_effect(state, statement, location)
+        })
+    }
+
+    fn apply_before_statement_effect(
+        &mut self,
+        state: &mut Self::Domain,
+        statement: &mir::Statement<'tcx>,
+        location: Location,
+    ) {
+        self.traced(state, location, "before_statement", |inner, state| {
+            inner.apply_before_statement_effect(state, statement, location)
+        })
+    }
+
+    fn apply_terminator_effect<'mir>(
+        &mut self,
+        state: &mut Self::Domain,
+        terminator: &'mir mir::Terminator<'tcx>,
+        location: Location,
+    ) -> TerminatorEdges<'mir, 'tcx> {
+        self.traced(state, location, "terminator", |inner, state| {
+            inner.apply_terminator_effect(state, terminator, location)
+        })
+    }
+
+    fn apply_before_terminator_effect(
+        &mut self,
+        state: &mut Self::Domain,
+        terminator: &mir::Terminator<'tcx>,
+        location: Location,
+    ) {
+        self.traced(state, location, "before_terminator", |inner, state| {
+            inner.apply_before_terminator_effect(state, terminator, location)
+        })
+    }
+
+    fn apply_terminator_unwind_effect(
+        &mut self,
+        state: &mut Self::Domain,
+        terminator: &mir::Terminator<'tcx>,
+        block: BasicBlock,
+    ) {
+        self.traced(state, block.start_location(), "terminator_unwind", |inner, state| {
+            inner.apply_terminator_unwind_effect(state, terminator, block)
+        })
+    }
+
+    fn apply_call_return_effect(
+        &mut self,
+        state: &mut Self::Domain,
+        block: BasicBlock,
+        return_places: CallReturnPlaces<'_, 'tcx>,
+    ) {
+        self.traced(state, block.start_location(), "call_return", |inner, state| {
+            inner.apply_call_return_effect(state, block, return_places)
+        })
+    }
+
+    fn apply_yield_resume_effect(
+        &mut self,
+        state: &mut Self::Domain,
+        resume_block: BasicBlock,
+        resume_place: mir::Place<'tcx>,
+    ) {
+        self.traced(state, resume_block.start_location(), "yield_resume", |inner, state| {
+            inner.apply_yield_resume_effect(state, resume_block, resume_place)
+        })
+    }
+
+    fn get_switch_int_data(
+        &mut self,
+        block: BasicBlock,
+        discr: &mir::Operand<'tcx>,
+    ) -> Option<Self::SwitchIntData> {
+        self.inner.get_switch_int_data(block, discr)
+    }
+
+    fn apply_switch_int_edge_effect(
+        &mut self,
+        data: &mut Self::SwitchIntData,
+        state: &mut Self::Domain,
+        edge: SwitchIntTarget,
+    ) {
+        self.traced(state, edge.target.start_location(), "switch_int_edge", |inner, state| {
+            inner.apply_switch_int_edge_effect(data, state, edge)
+        })
+    }
+
+    fn treat_false_edges(&self) -> FalseEdgeSemantics {
+        self.inner.treat_false_edges()
+    }
+
+    fn can_skip_block_given_predecessors(
+        &self,
+        block: BasicBlock,
+        body: &mir::Body<'tcx>,
+        entry: &Self::Domain,
+    ) -> bool {
+        self.inner.can_skip_block_given_predecessors(block, body, entry)
+    }
+
+    fn is_edge_feasible(
+        &self,
+        state: &Self::Domain,
+        terminator: &mir::Terminator<'tcx>,
+        source: BasicBlock,
+        target: BasicBlock,
+    ) -> bool {
+        self.inner.is_edge_feasible(state, terminator, source, target)
+    }
+
+    fn join_at(
+        &self,
+        source: BasicBlock,
+        bb: BasicBlock,
+        into: &mut Self::Domain,
+        from: &Self::Domain,
+    ) -> bool {
+        self.inner.join_at(source, bb, into, from)
+    }
+
+    fn merge_call_return_state(
+        &mut self,
+        edge_state: &Self::Domain,
+        return_state: &mut Self::Domain,
+    ) {
+        self.inner.merge_call_return_state(edge_state, return_state)
+    }
+
+    fn contains_bit(
+        &self,
+        body: &mir::Body<'tcx>,
+        state: &Self::Domain,
+        bit: usize,
+    ) -> Option<bool> {
+        self.inner.contains_bit(body, state, bit)
+    }
+
+    fn explain_state(&self, state: &Self::Domain, body: &mir::Body<'tcx>) -> Option<String> {
+        self.inner.explain_state(state, body)
+    }
+
+    fn pre_iterate(&mut self, body: &mir::Body<'tcx>) {
+        self.inner.pre_iterate(body)
+    }
+
+    fn prepare_block_cache(&mut self, body: &mir::Body<'tcx>) -> Self::BlockCache {
+        self.inner.prepare_block_cache(body)
+    }
+
+    fn apply_statement_effect_cached(
+        &mut self,
+        cache: &Self::BlockCache,
+        state: &mut Self::Domain,
+        statement: &mir::Statement<'tcx>,
+        location: Location,
+    ) {
+        self.traced(state, location, "statement", |inner, state| {
+            inner.apply_statement_effect_cached(cache, state, statement, location)
+        })
+    }
+
+    fn apply_terminator_effect_cached<'mir>(
+        &mut self,
+        cache: &Self::BlockCache,
+        state: &mut Self::Domain,
+        terminator: &'mir mir::Terminator<'tcx>,
+        location: Location,
+    ) -> TerminatorEdges<'mir, 'tcx> {
+        self.traced(state, location, "terminator", |inner, state| {
+            inner.apply_terminator_effect_cached(cache, state, terminator, location)
+        })
+    }
+}
+
+/// Wraps a `GenKillAnalysis` and emits a `trace!` event for each effect it applies, like
+/// `TraceAnalysis`. Since gen/kill effects do not see the state, the events list the elements that
+/// were generated (prefixed with `+`) and killed (prefixed with `-`), in order.
+///
+/// Unlike `TraceAnalysis`, this is again a `GenKillAnalysis`, so the `Engine` still caches the
+/// transfer function of each block if the CFG has cycles. Beware that the statement effects of
+/// each such block are then traced only once, when its transfer function is computed, and not on
+/// every visit during the fixpoint iteration.
+#[derive(Clone)]
+pub struct TraceGenKillAnalysis<A> {
+    inner: A,
+    filter: Option<fn(Location) -> bool>,
+}
+
+impl<A> TraceGenKillAnalysis<A> {
+    pub fn new(inner: A) -> Self {
+        TraceGenKillAnalysis { inner, filter: None }
+    }
+
+    /// Only traces the effects at locations for which `filter` returns `true`.
+    pub fn with_filter(mut self, filter: fn(Location) -> bool) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    pub fn inner(&self) -> &A {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> A {
+        self.inner
+    }
+}
+
+impl<'tcx, A> TraceGenKillAnalysis<A>
+where
+    A: GenKillAnalysis<'tcx>,
+{
+    fn traced<T: GenKill<A::Idx>>(
+        &mut self,
+        trans: &mut T,
+        location: Location,
+        effect: &'static str,
+        apply: impl FnOnce(&mut A, &mut TracedTrans<'_, T>),
+    ) {
+        if !is_traced(self.filter, location) {
+            return apply(&mut self.inner, &mut TracedTrans { trans, ops: None });
+        }
+
+        let mut trans = TracedTrans { trans, ops: Some(vec![]) };
+        apply(&mut self.inner, &mut trans);
+        trace_effect(A::NAME, location, effect, &trans.ops.unwrap_or_default().join(", "));
+    }
+}
+
+impl<'tcx, A> AnalysisDomain<'tcx> for TraceGenKillAnalysis<A>
+where
+    A: GenKillAnalysis<'tcx>,
+{
+    type Domain = A::Domain;
+    type Direction = A::Direction;
+    type SwitchIntData = A::SwitchIntData;
+
+    const NAME: &'static str = A::NAME;
+    const REQUIRES_CLEANUP_BLOCKS: bool = A::REQUIRES_CLEANUP_BLOCKS;
+    const MERGES_CALL_RETURN_STATE: bool = A::MERGES_CALL_RETURN_STATE;
+
+    fn bottom_value(&self, tcx: TyCtxt<'tcx>, body: &mir::Body<'tcx>) -> Self::Domain {
+        self.inner.bottom_value(tcx, body)
+    }
+
+    fn initialize_start_block(&self, body: &mir::Body<'tcx>, state: &mut Self::Domain) {
+        self.inner.initialize_start_block(body, state)
+    }
+}
+
+impl<'tcx, A> GenKillAnalysis<'tcx> for TraceGenKillAnalysis<A>
+where
+    A: GenKillAnalysis<'tcx>,
+    A::Domain: BitSetExt<A::Idx>,
+{
+    type Idx = A::Idx;
+
+    fn domain_size(&self, body: &mir::Body<'tcx>) -> usize {
+        self.inner.domain_size(body)
+    }
+
+    fn domain_element_name(&self, idx: Self::Idx, body: &mir::Body<'tcx>) -> String {
+        self.inner.domain_element_name(idx, body)
+    }
+
+    fn explain_gen_kill_state(&self, state: &Self::Domain, body: &mir::Body<'tcx>) -> String
+    where
+        Self: Sized,
+        Self::Domain: BitSetExt<Self::Idx>,
+    {
+        self.inner.explain_gen_kill_state(state, body)
+    }
+
+    fn statement_effect(
+        &mut self,
+        trans: &mut impl GenKill<Self::Idx>,
+        statement: &mir::Statement<'tcx>,
+        location: Location,
+    ) {
+        self.traced(trans, location, "statement", |inner, trans| {
+            inner.statement_effect(trans, statement, location)
+        })
+    }
+
+    fn before_statement_effect(
+        &mut self,
+        trans: &mut impl GenKill<Self::Idx>,
+        statement: &mir::Statement<'tcx>,
+        location: Location,
+    ) {
+        self.traced(trans, location, "before_statement", |inner, trans| {
+            inner.before_statement_effect(trans, statement, location)
+        })
+    }
+
+    fn batch_statement_effect(
+        &mut self,
+        trans: &mut impl GenKill<Self::Idx>,
+        block: BasicBlock,
+        block_data: &mir::BasicBlockData<'tcx>,
+    ) {
+        self.traced(trans, block.start_location(), "batch_statement", |inner, trans| {
+            inner.batch_statement_effect(trans, block, block_data)
+        })
+    }
+
+    fn terminator_effect<'mir>(
+        &mut self,
+        trans: &mut Self::Domain,
+        terminator: &'mir mir::Terminator<'tcx>,
+        location: Location,
+    ) -> TerminatorEdges<'mir, 'tcx> {
+        if !is_traced(self.filter, location) {
+            return self.inner.terminator_effect(trans, terminator, location);
+        }
+
+        let old = trans.clone();
+        let edges = self.inner.terminator_effect(trans, terminator, location);
+
+        // Describe the change to the state like the gens and kills of the other effects.
+        let size = trans.domain_size().or(old.domain_size()).unwrap_or(0);
+        let ops: Vec<_> = (0..size)
+            .map(A::Idx::new)
+            .filter_map(|elem| match (old.contains(elem), trans.contains(elem)) {
+                (false, true) => Some(format!("+{elem:?}")),
+                (true, false) => Some(format!("-{elem:?}")),
+                _ => None,
+            })
+            .collect();
+        trace_effect(A::NAME, location, "terminator", &ops.join(", "));
+        edges
+    }
+
+    fn before_terminator_effect(
+        &mut self,
+        trans: &mut impl GenKill<Self::Idx>,
+        terminator: &mir::Terminator<'tcx>,
+        location: Location,
+    ) {
+        self.traced(trans, location, "before_terminator", |inner, trans| {
+            inner.before_terminator_effect(trans, terminator, location)
+        })
+    }
+
+    fn terminator_unwind_effect(
+        &mut self,
+        trans: &mut impl GenKill<Self::Idx>,
+        terminator: &mir::Terminator<'tcx>,
+        block: BasicBlock,
+    ) {
+        self.traced(trans, block.start_location(), "terminator_unwind", |inner, trans| {
+            inner.terminator_unwind_effect(trans, terminator, block)
+        })
+    }
+
+    fn call_return_effect(
+        &mut self,
+        trans: &mut impl GenKill<Self::Idx>,
+        block: BasicBlock,
+        return_places: CallReturnPlaces<'_, 'tcx>,
+    ) {
+        self.traced(trans, block.start_location(), "call_return", |inner, trans| {
+            inner.call_return_effect(trans, block, return_places)
+        })
+    }
+
+    fn yield_resume_effect(
+        &mut self,
+        trans: &mut impl GenKill<Self::Idx>,
+        resume_block: BasicBlock,
+        resume_place: mir::Place<'tcx>,
+    ) {
+        self.traced(trans, resume_block.start_location(), "yield_resume", |inner, trans| {
+            inner.yield_resume_effect(trans, resume_block, resume_place)
+        })
+    }
+
+    fn switch_int_data(
+        &mut self,
+        block: BasicBlock,
+        discr: &mir::Operand<'tcx>,
+    ) -> Option<Self::SwitchIntData> {
+        self.inner.switch_int_data(block, discr)
+    }
+
+    fn switch_int_edge_effect(
+        &mut self,
+        data: &mut Self::SwitchIntData,
+        trans: &mut impl GenKill<Self::Idx>,
+        edge: SwitchIntTarget,
+    ) {
+        self.traced(trans, edge.target.start_location(), "switch_int_edge", |inner, trans| {
+            inner.switch_int_edge_effect(data, trans, edge)
+        })
+    }
+
+    fn pre_iterate(&mut self, body: &mir::Body<'tcx>) {
+        GenKillAnalysis::pre_iterate(&mut self.inner, body)
+    }
+}
+
+/// Forwards the gens and kills of the inner analysis of a `TraceGenKillAnalysis` to `trans`, and
+/// records them in `ops` if the effect is traced.
+struct TracedTrans<'a, T> {
+    trans: &'a mut T,
+    ops: Option<Vec<String>>,
+}
+
+impl<T, I> GenKill<I> for TracedTrans<'_, T>
+where
+    T: GenKill<I>,
+    I: Idx,
+{
+    fn gen(&mut self, elem: I) {
+        if let Some(ops) = &mut self.ops {
+            ops.push(format!("+{elem:?}"));
+        }
+        self.trans.gen(elem);
+    }
+
+    fn kill(&mut self, elem: I) {
+        if let Some(ops) = &mut self.ops {
+            ops.push(format!("-{elem:?}"));
+        }
+        self.trans.kill(elem);
+    }
+}
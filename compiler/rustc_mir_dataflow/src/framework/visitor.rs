@@ -1,8 +1,8 @@
 use std::borrow::Borrow;
 
-use rustc_middle::mir::{self, BasicBlock, Location};
+use rustc_middle::mir::{self, BasicBlock, Location, TerminatorEdges};
 
-use super::{Analysis, Direction, EntrySets, Results};
+use super::{assert_has_terminator, Analysis, Direction, EntrySets, Results};
 
 /// Calls the corresponding method in `ResultsVisitor` for every location in a `mir::Body` with the
 /// dataflow state at that location.
@@ -22,6 +22,7 @@ pub fn visit_results<'mir, 'tcx, F, R>(
     for block in blocks {
         #[cfg(debug_assertions)]
         assert!(reachable_blocks.contains(block));
+        assert_has_terminator(body, block);
 
         let block_data = &body[block];
         R::Direction::visit_results_in_block(&mut state, block, block_data, results, vis);
@@ -88,6 +89,21 @@ fn visit_terminator_after_primary_effect(
     ) {
     }
 
+    /// Called once for each outgoing edge of a terminator that the analysis considers feasible,
+    /// with both the `before_terminator_effect` and the `terminator_effect` of that terminator
+    /// applied to `state`.
+    ///
+    /// Edges pruned by the analysis's `apply_terminator_effect` are not visited. Edge-specific
+    /// effects (e.g. `call_return_effect`) will *not* be applied to `state`.
+    fn visit_terminator_edge(
+        &mut self,
+        _results: &mut R,
+        _state: &Self::FlowState,
+        _from: BasicBlock,
+        _to: BasicBlock,
+    ) {
+    }
+
     fn visit_block_end(
         &mut self,
         _results: &mut R,
@@ -98,6 +114,98 @@ fn visit_block_end(
     }
 }
 
+/// A `ResultsVisitor` that forwards every callback to `self.0` and then to `self.1`, so that a
+/// single traversal of the results can feed several independent visitors.
+///
+/// Both visitors must have the same `FlowState`, since they observe the same state. To combine more
+/// than two visitors, nest them, e.g. `Chain(a, Chain(b, c))`.
+pub struct Chain<V1, V2>(pub V1, pub V2);
+
+impl<'mir, 'tcx, R, F, V1, V2> ResultsVisitor<'mir, 'tcx, R> for Chain<V1, V2>
+where
+    V1: ResultsVisitor<'mir, 'tcx, R, FlowState = F>,
+    V2: ResultsVisitor<'mir, 'tcx, R, FlowState = F>,
+{
+    type FlowState = F;
+
+    fn visit_block_start(
+        &mut self,
+        results: &mut R,
+        state: &Self::FlowState,
+        block_data: &'mir mir::BasicBlockData<'tcx>,
+        block: BasicBlock,
+    ) {
+        self.0.visit_block_start(results, state, block_data, block);
+        self.1.visit_block_start(results, state, block_data, block);
+    }
+
+    fn visit_statement_before_primary_effect(
+        &mut self,
+        results: &mut R,
+        state: &Self::FlowState,
+        statement: &'mir mir::Statement<'tcx>,
+        location: Location,
+    ) {
+        self.0.visit_statement_before_primary_effect(results, state, statement, location);
+        self.1.visit_statement_before_primary_effect(results, state, statement, location);
+    }
+
+    fn visit_statement_after_primary_effect(
+        &mut self,
+        results: &mut R,
+        state: &Self::FlowState,
+        statement: &'mir mir::Statement<'tcx>,
+        location: Location,
+    ) {
+        self.0.visit_statement_after_primary_effect(results, state, statement, location);
+        self.1.visit_statement_after_primary_effect(results, state, statement, location);
+    }
+
+    fn visit_terminator_before_primary_effect(
+        &mut self,
+        results: &mut R,
+        state: &Self::FlowState,
+        terminator: &'mir mir::Terminator<'tcx>,
+        location: Location,
+    ) {
+        self.0.visit_terminator_before_primary_effect(results, state, terminator, location);
+        self.1.visit_terminator_before_primary_effect(results, state, terminator, location);
+    }
+
+    fn visit_terminator_after_primary_effect(
+        &mut self,
+        results: &mut R,
+        state: &Self::FlowState,
+        terminator: &'mir mir::Terminator<'tcx>,
+        location: Location,
+    ) {
+        self.0.visit_terminator_after_primary_effect(results, state, terminator, location);
+        self.1.visit_terminator_after_primary_effect(results, state, terminator, location);
+    }
+
+    fn visit_terminator_edge(
+        &mut self,
+        results: &mut R,
+        state: &Self::FlowState,
+        from: BasicBlock,
+        to: BasicBlock,
+    ) {
+        self.0.visit_terminator_edge(results, state, from, to);
+        self.1.visit_terminator_edge(results, state, from, to);
+    }
+
+    fn visit_block_end(
+        &mut self,
+        results: &mut R,
+        state: &Self::FlowState,
+        block_data: &'mir mir::BasicBlockData<'tcx>,
+        block: BasicBlock,
+    ) {
+        self.0.visit_block_end(results, state, block_data, block);
+        self.1.visit_block_end(results, state, block_data, block);
+    }
+}
+
 /// Things that can be visited by a `ResultsVisitor`.
 ///
 /// This trait exists so that we can visit the results of multiple dataflow analyses simultaneously.
@@ -135,12 +243,25 @@ fn reconstruct_before_terminator_effect(
         location: Location,
     );
 
-    fn reconstruct_terminator_effect(
+    /// Returns the outgoing edges of `terminator` that are feasible according to these results.
+    fn reconstruct_terminator_effect<'mir>(
         &mut self,
         state: &mut Self::FlowState,
-        terminator: &mir::Terminator<'tcx>,
+        terminator: &'mir mir::Terminator<'tcx>,
         location: Location,
-    );
+    ) -> TerminatorEdges<'mir, 'tcx>;
+
+    /// Returns whether the edge from `from` to `to` is feasible given the `state` after
+    /// `terminator`, the terminator of `from`. See `Analysis::is_edge_feasible`.
+    fn is_edge_feasible(
+        &self,
+        _state: &Self::FlowState,
+        _terminator: &mir::Terminator<'tcx>,
+        _from: BasicBlock,
+        _to: BasicBlock,
+    ) -> bool {
+        true
+    }
 }
 
 impl<'tcx, A, E> ResultsVisitable<'tcx> for Results<'tcx, A, E>
@@ -152,8 +273,9 @@ impl<'tcx, A, E> ResultsVisitable<'tcx> for Results<'tcx, A, E>
 
     type Direction = A::Direction;
 
-    fn new_flow_state(&self, body: &mir::Body<'tcx>) -> Self::FlowState {
-        self.analysis.bottom_value(body)
+    fn new_flow_state(&self, _body: &mir::Body<'tcx>) -> Self::FlowState {
+        // This is overwritten by `reset_to_block_entry` before it is read.
+        self.entry_set_for_block(mir::START_BLOCK).clone()
     }
 
     fn reset_to_block_entry(&self, state: &mut Self::FlowState, block: BasicBlock) {
@@ -187,12 +309,22 @@ fn reconstruct_before_terminator_effect(
         self.analysis.apply_before_terminator_effect(state, term, loc);
     }
 
-    fn reconstruct_terminator_effect(
+    fn reconstruct_terminator_effect<'mir>(
         &mut self,
         state: &mut Self::FlowState,
-        term: &mir::Terminator<'tcx>,
+        term: &'mir mir::Terminator<'tcx>,
         loc: Location,
-    ) {
-        self.analysis.apply_terminator_effect(state, term, loc);
+    ) -> TerminatorEdges<'mir, 'tcx> {
+        self.analysis.apply_terminator_effect(state, term, loc)
+    }
+
+    fn is_edge_feasible(
+        &self,
+        state: &Self::FlowState,
+        terminator: &mir::Terminator<'tcx>,
+        from: BasicBlock,
+        to: BasicBlock,
+    ) -> bool {
+        self.analysis.is_edge_feasible(state, terminator, from, to)
     }
 }
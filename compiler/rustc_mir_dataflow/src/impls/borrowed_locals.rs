@@ -1,8 +1,9 @@
-use rustc_index::bit_set::BitSet;
-use rustc_middle::mir::visit::Visitor;
+use rustc_index::bit_set::{BitSet, SparseBitMatrix};
+use rustc_middle::mir::visit::{PlaceContext, Visitor};
 use rustc_middle::mir::*;
+use rustc_middle::ty::TyCtxt;
 
-use crate::{AnalysisDomain, GenKill, GenKillAnalysis};
+use crate::{Analysis, AnalysisDomain, GenKill, GenKillAnalysis, Results, ResultsCursor};
 
 /// A dataflow analysis that tracks whether a pointer or reference could possibly exist that points
 /// to a given local. This analysis ignores fake borrows, so it should not be used by
@@ -24,7 +25,7 @@ impl<'tcx> AnalysisDomain<'tcx> for MaybeBorrowedLocals {
     type Domain = BitSet<Local>;
     const NAME: &'static str = "maybe_borrowed_locals";
 
-    fn bottom_value(&self, body: &Body<'tcx>) -> Self::Domain {
+    fn bottom_value(&self, _: TyCtxt<'tcx>, body: &Body<'tcx>) -> Self::Domain {
         // bottom = unborrowed
         BitSet::new_empty(body.local_decls().len())
     }
@@ -153,6 +154,185 @@ fn visit_terminator(&mut self, terminator: &Terminator<'tcx>, location: Location
     }
 }
 
+/// Like `MaybeBorrowedLocals`, but tracks shared and mutable borrows separately.
+///
+/// The state is a pair of sets of locals: those that may be borrowed by a shared reference, and
+/// those that may be borrowed mutably. A local may be in both. Raw pointers count as mutable
+/// borrows, whatever their mutability, since a `*const` can be cast to a `*mut`. Two-phase borrows
+/// count as shared borrows until they are activated by the first use of the reference, and `Drop`
+/// terminators count as mutable borrows, as in `MaybeBorrowedLocals`.
+pub struct MaybeBorrowedLocalsByKind {
+    /// For each local holding a two-phase borrow, the locals it may borrow. A temporary can be
+    /// assigned different two-phase borrows in different places, and since this is computed once
+    /// for the whole body, using it activates all of them.
+    two_phase_borrows: SparseBitMatrix<Local, Local>,
+}
+
+impl MaybeBorrowedLocalsByKind {
+    pub fn new(body: &Body<'_>) -> Self {
+        let mut two_phase_borrows = SparseBitMatrix::new(body.local_decls.len());
+        for statement in body.basic_blocks.iter().flat_map(|block_data| &block_data.statements) {
+            let StatementKind::Assign(box (place, Rvalue::Ref(_, kind, borrowed_place))) =
+                &statement.kind
+            else {
+                continue;
+            };
+            if let Some(local) = place.as_local() {
+                if kind.allows_two_phase_borrow() && !borrowed_place.is_indirect() {
+                    two_phase_borrows.insert(local, borrowed_place.local);
+                }
+            }
+        }
+        MaybeBorrowedLocalsByKind { two_phase_borrows }
+    }
+
+    /// Returns the locals that may be borrowed by a shared reference in `state`.
+    pub fn shared_borrowed(state: &(BitSet<Local>, BitSet<Local>)) -> &BitSet<Local> {
+        &state.0
+    }
+
+    /// Returns the locals that may be borrowed mutably in `state`.
+    pub fn mut_borrowed(state: &(BitSet<Local>, BitSet<Local>)) -> &BitSet<Local> {
+        &state.1
+    }
+}
+
+impl<'tcx> AnalysisDomain<'tcx> for MaybeBorrowedLocalsByKind {
+    /// The shared and the mutable borrows, in that order.
+    type Domain = (BitSet<Local>, BitSet<Local>);
+    const NAME: &'static str = "maybe_borrowed_locals_by_kind";
+
+    fn bottom_value(&self, _: TyCtxt<'tcx>, body: &Body<'tcx>) -> Self::Domain {
+        // bottom = unborrowed
+        let unborrowed = BitSet::new_empty(body.local_decls().len());
+        (unborrowed.clone(), unborrowed)
+    }
+
+    fn initialize_start_block(&self, _: &Body<'tcx>, _: &mut Self::Domain) {
+        // No locals are aliased on function entry
+    }
+}
+
+impl<'tcx> Analysis<'tcx> for MaybeBorrowedLocalsByKind {
+    fn apply_statement_effect(
+        &mut self,
+        state: &mut Self::Domain,
+        statement: &Statement<'tcx>,
+        location: Location,
+    ) {
+        ByKindTransferFunction { state, two_phase_borrows: &self.two_phase_borrows }
+            .visit_statement(statement, location);
+    }
+
+    fn apply_terminator_effect<'mir>(
+        &mut self,
+        state: &mut Self::Domain,
+        terminator: &'mir Terminator<'tcx>,
+        location: Location,
+    ) -> TerminatorEdges<'mir, 'tcx> {
+        ByKindTransferFunction { state, two_phase_borrows: &self.two_phase_borrows }
+            .visit_terminator(terminator, location);
+        terminator.edges()
+    }
+
+    fn apply_call_return_effect(
+        &mut self,
+        _state: &mut Self::Domain,
+        _block: BasicBlock,
+        _return_places: CallReturnPlaces<'_, 'tcx>,
+    ) {
+    }
+}
+
+impl<'tcx> Results<'tcx, MaybeBorrowedLocalsByKind> {
+    /// Returns the locals that may be borrowed by a shared reference on entry to `block`.
+    pub fn shared_borrowed_on_entry(&self, block: BasicBlock) -> &BitSet<Local> {
+        MaybeBorrowedLocalsByKind::shared_borrowed(self.entry_set_for_block(block))
+    }
+
+    /// Returns the locals that may be borrowed mutably on entry to `block`.
+    pub fn mut_borrowed_on_entry(&self, block: BasicBlock) -> &BitSet<Local> {
+        MaybeBorrowedLocalsByKind::mut_borrowed(self.entry_set_for_block(block))
+    }
+}
+
+impl<'mir, 'tcx, R> ResultsCursor<'mir, 'tcx, MaybeBorrowedLocalsByKind, R> {
+    /// Returns the locals that may be borrowed by a shared reference at the current location.
+    pub fn shared_borrowed(&self) -> &BitSet<Local> {
+        MaybeBorrowedLocalsByKind::shared_borrowed(self.get())
+    }
+
+    /// Returns the locals that may be borrowed mutably at the current location.
+    pub fn mut_borrowed(&self) -> &BitSet<Local> {
+        MaybeBorrowedLocalsByKind::mut_borrowed(self.get())
+    }
+}
+
+/// A `Visitor` that defines the transfer function for `MaybeBorrowedLocalsByKind`.
+struct ByKindTransferFunction<'a> {
+    state: &'a mut (BitSet<Local>, BitSet<Local>),
+    two_phase_borrows: &'a SparseBitMatrix<Local, Local>,
+}
+
+impl<'tcx> Visitor<'tcx> for ByKindTransferFunction<'_> {
+    fn visit_statement(&mut self, stmt: &Statement<'tcx>, location: Location) {
+        self.super_statement(stmt, location);
+
+        // When we reach a `StorageDead` statement, we can assume that any pointers to this memory
+        // are now invalid.
+        if let StatementKind::StorageDead(local) = stmt.kind {
+            self.state.0.remove(local);
+            self.state.1.remove(local);
+        }
+    }
+
+    fn visit_local(&mut self, local: Local, context: PlaceContext, _location: Location) {
+        // Any use of a two-phase borrow other than the assignment that reserves it activates it.
+        let Some(borrowed) = self.two_phase_borrows.row(local) else { return };
+        if context.is_use() && !context.is_place_assignment() {
+            self.state.1.union(borrowed);
+        }
+    }
+
+    fn visit_rvalue(&mut self, rvalue: &Rvalue<'tcx>, location: Location) {
+        self.super_rvalue(rvalue, location);
+
+        let (kind, borrowed_place) = match rvalue {
+            Rvalue::Ref(_, kind, borrowed_place) => (*kind, borrowed_place),
+            Rvalue::AddressOf(_, borrowed_place) => {
+                (BorrowKind::Mut { kind: MutBorrowKind::Default }, borrowed_place)
+            }
+            _ => return,
+        };
+        if borrowed_place.is_indirect() {
+            return;
+        }
+
+        match kind {
+            // A two-phase borrow is only a reservation until `visit_local` sees it activated.
+            BorrowKind::Shared | BorrowKind::Mut { kind: MutBorrowKind::TwoPhaseBorrow } => {
+                self.state.0.insert(borrowed_place.local);
+            }
+            BorrowKind::Mut { kind: MutBorrowKind::Default | MutBorrowKind::ClosureCapture } => {
+                self.state.1.insert(borrowed_place.local);
+            }
+            // We ignore fake borrows, like `MaybeBorrowedLocals`.
+            BorrowKind::Fake => {}
+        }
+    }
+
+    fn visit_terminator(&mut self, terminator: &Terminator<'tcx>, location: Location) {
+        self.super_terminator(terminator, location);
+
+        // Drop glue may create a `*mut` to the dropped local; see `MaybeBorrowedLocals`.
+        if let TerminatorKind::Drop { place: dropped_place, .. } = terminator.kind {
+            if !dropped_place.is_indirect() {
+                self.state.1.insert(dropped_place.local);
+            }
+        }
+    }
+}
+
 /// The set of locals that are borrowed at some point in the MIR body.
 pub fn borrowed_locals(body: &Body<'_>) -> BitSet<Local> {
     struct Borrowed(BitSet<Local>);
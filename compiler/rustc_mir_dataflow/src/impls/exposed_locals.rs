@@ -0,0 +1,240 @@
+use rustc_index::bit_set::{BitSet, SparseBitMatrix};
+use rustc_middle::mir::visit::{PlaceContext, Visitor};
+use rustc_middle::mir::*;
+use rustc_middle::ty::TyCtxt;
+
+use crate::{AnalysisDomain, GenKill, GenKillAnalysis};
+
+/// A dataflow analysis that tracks whether the address of a local may have been exposed, i.e.
+/// whether a pointer to it, or one derived from it through reborrows, casts and copies, may have
+/// escaped to code that this body cannot see.
+///
+/// Unlike `MaybeBorrowedLocals`, merely borrowing a local does not expose it. A local is exposed
+/// when a pointer that may point to it is:
+///
+/// - passed to a call, yielded or used by inline assembly,
+/// - stored through a pointer, including by `copy_nonoverlapping`, or returned,
+/// - cast to an integer, or
+/// - dropped along with the local, since drop glue may leak `&mut self`.
+///
+/// Which pointers may point to which locals is approximated flow-insensitively by `new`, so a local
+/// holding a pointer to another is assumed to do so throughout the body. Like borrows in
+/// `MaybeBorrowedLocals`, the exposure of a local ends at its `StorageDead`.
+pub struct AddressExposedLocals {
+    /// For each local, the locals that a pointer stored in it (or in any of its fields) may point
+    /// to, directly or by following the pointers stored in those locals in turn.
+    reachable: SparseBitMatrix<Local, Local>,
+}
+
+impl AddressExposedLocals {
+    pub fn new(body: &Body<'_>) -> Self {
+        let mut points_to = SparseBitMatrix::new(body.local_decls.len());
+
+        // Pairs of locals where the first holds pointers derived from those held by the second.
+        let mut derivations = vec![];
+        // Pairs of locals holding the `dst` and `src` pointers of a `copy_nonoverlapping`.
+        let mut copies = vec![];
+        for (block, block_data) in body.basic_blocks.iter_enumerated() {
+            for (statement_index, statement) in block_data.statements.iter().enumerate() {
+                if let StatementKind::Intrinsic(box NonDivergingIntrinsic::CopyNonOverlapping(
+                    CopyNonOverlapping { src, dst, .. },
+                )) = &statement.kind
+                {
+                    if let (Some(src), Some(dst)) = (src.place(), dst.place()) {
+                        copies.push((dst.local, src.local));
+                    }
+                    continue;
+                }
+                let StatementKind::Assign(box (place, rvalue)) = &statement.kind else { continue };
+                if !holds_derived_pointers(place) {
+                    continue;
+                }
+
+                if let Rvalue::Ref(_, _, borrowed_place) | Rvalue::AddressOf(_, borrowed_place) =
+                    rvalue
+                {
+                    if !borrowed_place.is_indirect() {
+                        points_to.insert(place.local, borrowed_place.local);
+                    }
+                }
+
+                let location = Location { block, statement_index };
+                MentionedLocals(&mut |local| derivations.push((place.local, local)))
+                    .visit_rvalue(rvalue, location);
+            }
+        }
+
+        // Propagate the pointees along derivations until they no longer change. Most bodies derive
+        // pointers along short chains, so this converges after a few rounds.
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &(derived, base) in &derivations {
+                if derived != base {
+                    changed |= points_to.union_rows(base, derived);
+                }
+            }
+
+            // A copy stores the pointers held by the locals `src` may point to in the locals `dst`
+            // may point to, as if by `*dst = *src`.
+            for &(dst, src) in &copies {
+                let sources: Vec<_> = points_to.iter(src).collect();
+                let targets: Vec<_> = points_to.iter(dst).collect();
+                for &target in &targets {
+                    for &source in &sources {
+                        if target != source {
+                            changed |= points_to.union_rows(source, target);
+                        }
+                    }
+                }
+            }
+        }
+
+        // Exposing a local exposes everything reachable from it, so compute that once per local
+        // rather than every time an operand is exposed.
+        let mut reachable = SparseBitMatrix::new(body.local_decls.len());
+        let mut worklist = vec![];
+        for local in points_to.rows() {
+            worklist.extend(points_to.iter(local));
+            while let Some(pointee) = worklist.pop() {
+                if reachable.insert(local, pointee) {
+                    worklist.extend(points_to.iter(pointee));
+                }
+            }
+        }
+
+        AddressExposedLocals { reachable }
+    }
+
+    /// Exposes every local that a pointer held by `local` may point to, and, since the callee may
+    /// follow pointers stored in those locals in turn, every local reachable from them.
+    fn expose_pointees(&self, trans: &mut impl GenKill<Local>, local: Local) {
+        trans.gen_all(self.reachable.iter(local));
+    }
+
+    /// Exposes every local that a pointer held by a local mentioned in `operand` may point to.
+    fn expose_operand(&self, trans: &mut impl GenKill<Local>, operand: &Operand<'_>) {
+        if let Some(place) = operand.place() {
+            self.expose_pointees(trans, place.local);
+        }
+    }
+}
+
+/// Whether assigning to `place` stores pointers in a local that this analysis keeps track of, as
+/// opposed to memory behind a pointer or the return value.
+fn holds_derived_pointers(place: &Place<'_>) -> bool {
+    !place.is_indirect() && place.local != RETURN_PLACE
+}
+
+impl<'tcx> AnalysisDomain<'tcx> for AddressExposedLocals {
+    type Domain = BitSet<Local>;
+    const NAME: &'static str = "address_exposed_locals";
+
+    fn bottom_value(&self, _: TyCtxt<'tcx>, body: &Body<'tcx>) -> Self::Domain {
+        // bottom = unexposed
+        BitSet::new_empty(body.local_decls.len())
+    }
+
+    fn initialize_start_block(&self, _: &Body<'tcx>, _: &mut Self::Domain) {
+        // No locals are exposed on function entry
+    }
+}
+
+impl<'tcx> GenKillAnalysis<'tcx> for AddressExposedLocals {
+    type Idx = Local;
+
+    fn domain_size(&self, body: &Body<'tcx>) -> usize {
+        body.local_decls.len()
+    }
+
+    fn statement_effect(
+        &mut self,
+        trans: &mut impl GenKill<Self::Idx>,
+        statement: &Statement<'tcx>,
+        location: Location,
+    ) {
+        match &statement.kind {
+            StatementKind::Assign(box (place, rvalue)) => {
+                if let Rvalue::Cast(CastKind::PointerExposeAddress, operand, _) = rvalue {
+                    self.expose_operand(trans, operand);
+                }
+
+                // Pointers stored anywhere but in a local escape our view of them.
+                if !holds_derived_pointers(place) {
+                    if let Rvalue::Ref(_, _, borrowed_place)
+                    | Rvalue::AddressOf(_, borrowed_place) = rvalue
+                    {
+                        if !borrowed_place.is_indirect() {
+                            trans.gen(borrowed_place.local);
+                        }
+                    }
+                    MentionedLocals(&mut |local| self.expose_pointees(trans, local))
+                        .visit_rvalue(rvalue, location);
+                }
+            }
+
+            // Like an assignment through `dst`, a copy may store the pointers behind `src` where
+            // we cannot follow them.
+            StatementKind::Intrinsic(box NonDivergingIntrinsic::CopyNonOverlapping(
+                CopyNonOverlapping { src, .. },
+            )) => self.expose_operand(trans, src),
+
+            // When we reach a `StorageDead` statement, we can assume that any pointers to this
+            // memory are now invalid.
+            StatementKind::StorageDead(local) => trans.kill(*local),
+
+            _ => {}
+        }
+    }
+
+    fn terminator_effect<'mir>(
+        &mut self,
+        trans: &mut Self::Domain,
+        terminator: &'mir Terminator<'tcx>,
+        location: Location,
+    ) -> TerminatorEdges<'mir, 'tcx> {
+        match &terminator.kind {
+            TerminatorKind::Call { func, args, .. } => {
+                self.expose_operand(trans, func);
+                for arg in args {
+                    self.expose_operand(trans, arg);
+                }
+            }
+
+            TerminatorKind::Yield { value, .. } => self.expose_operand(trans, value),
+
+            TerminatorKind::InlineAsm { .. } => {
+                MentionedLocals(&mut |local| self.expose_pointees(trans, local))
+                    .visit_terminator(terminator, location);
+            }
+
+            // See `MaybeBorrowedLocals` for why drops may expose the dropped local.
+            TerminatorKind::Drop { place, .. } => {
+                if !place.is_indirect() {
+                    trans.gen(place.local);
+                }
+                self.expose_pointees(trans, place.local);
+            }
+
+            _ => {}
+        }
+        terminator.edges()
+    }
+
+    fn call_return_effect(
+        &mut self,
+        _trans: &mut impl GenKill<Self::Idx>,
+        _block: BasicBlock,
+        _return_places: CallReturnPlaces<'_, 'tcx>,
+    ) {
+    }
+}
+
+/// A `Visitor` that calls a closure on every local that is mentioned, e.g. by an `Rvalue`.
+struct MentionedLocals<'a>(&'a mut dyn FnMut(Local));
+
+impl<'tcx> Visitor<'tcx> for MentionedLocals<'_> {
+    fn visit_local(&mut self, local: Local, _: PlaceContext, _: Location) {
+        (self.0)(local);
+    }
+}
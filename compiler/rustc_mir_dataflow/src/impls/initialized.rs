@@ -2,11 +2,12 @@
 use rustc_index::Idx;
 use rustc_middle::mir::{self, Body, CallReturnPlaces, Location, TerminatorEdges};
 use rustc_middle::ty::{self, TyCtxt};
+use rustc_target::abi::VariantIdx;
 
 use crate::drop_flag_effects_for_function_entry;
 use crate::drop_flag_effects_for_location;
 use crate::elaborate_drops::DropFlagState;
-use crate::framework::SwitchIntEdgeEffects;
+use crate::framework::SwitchIntTarget;
 use crate::move_paths::{HasMoveData, InitIndex, InitKind, LookupResult, MoveData, MovePathIndex};
 use crate::on_lookup_result_bits;
 use crate::MoveDataParamEnv;
@@ -309,9 +310,11 @@ fn update_bits(
 
 impl<'tcx> AnalysisDomain<'tcx> for MaybeInitializedPlaces<'_, 'tcx> {
     type Domain = MaybeReachable<ChunkedBitSet<MovePathIndex>>;
+    type SwitchIntData = MaybePlacesSwitchIntData<'tcx>;
+
     const NAME: &'static str = "maybe_init";
 
-    fn bottom_value(&self, _: &mir::Body<'tcx>) -> Self::Domain {
+    fn bottom_value(&self, _: TyCtxt<'tcx>, _: &mir::Body<'tcx>) -> Self::Domain {
         // bottom = uninitialized
         MaybeReachable::Unreachable
     }
@@ -333,6 +336,10 @@ fn domain_size(&self, _: &Body<'tcx>) -> usize {
         self.move_data().move_paths.len()
     }
 
+    fn domain_element_name(&self, idx: Self::Idx, _: &Body<'tcx>) -> String {
+        self.move_data().move_paths[idx].to_string()
+    }
+
     fn statement_effect(
         &mut self,
         trans: &mut impl GenKill<Self::Idx>,
@@ -398,57 +405,67 @@ fn call_return_effect(
         });
     }
 
-    fn switch_int_edge_effects<G: GenKill<Self::Idx>>(
+    fn yield_resume_effect(
+        &mut self,
+        trans: &mut impl GenKill<Self::Idx>,
+        _resume_block: mir::BasicBlock,
+        resume_place: mir::Place<'tcx>,
+    ) {
+        // when a coroutine is resumed, the resume argument is written to
+        // `resume_place`, so set the bits for it to 1 (initialized).
+        on_lookup_result_bits(
+            self.tcx,
+            self.body,
+            self.move_data(),
+            self.move_data().rev_lookup.find(resume_place.as_ref()),
+            |mpi| {
+                trans.gen(mpi);
+            },
+        );
+    }
+
+    fn switch_int_data(
         &mut self,
         block: mir::BasicBlock,
         discr: &mir::Operand<'tcx>,
-        edge_effects: &mut impl SwitchIntEdgeEffects<G>,
-    ) {
+    ) -> Option<Self::SwitchIntData> {
         if !self.tcx.sess.opts.unstable_opts.precise_enum_drop_elaboration {
-            return;
+            return None;
         }
 
-        let enum_ = discr.place().and_then(|discr| {
-            switch_on_enum_discriminant(self.tcx, self.body, &self.body[block], discr)
-        });
+        MaybePlacesSwitchIntData::new(self.tcx, self.body, block, discr)
+    }
 
-        let Some((enum_place, enum_def)) = enum_ else {
+    fn switch_int_edge_effect(
+        &mut self,
+        data: &mut Self::SwitchIntData,
+        trans: &mut impl GenKill<Self::Idx>,
+        edge: SwitchIntTarget,
+    ) {
+        let Some(value) = edge.value else {
             return;
         };
 
-        let mut discriminants = enum_def.discriminants(self.tcx);
-        edge_effects.apply(|trans, edge| {
-            let Some(value) = edge.value else {
-                return;
-            };
-
-            // MIR building adds discriminants to the `values` array in the same order as they
-            // are yielded by `AdtDef::discriminants`. We rely on this to match each
-            // discriminant in `values` to its corresponding variant in linear time.
-            let (variant, _) = discriminants
-                .find(|&(_, discr)| discr.val == value)
-                .expect("Order of `AdtDef::discriminants` differed from `SwitchInt::values`");
-
-            // Kill all move paths that correspond to variants we know to be inactive along this
-            // particular outgoing edge of a `SwitchInt`.
-            drop_flag_effects::on_all_inactive_variants(
-                self.tcx,
-                self.body,
-                self.move_data(),
-                enum_place,
-                variant,
-                |mpi| trans.kill(mpi),
-            );
-        });
+        // Kill all move paths that correspond to variants we know to be inactive along this
+        // particular outgoing edge of a `SwitchInt`.
+        drop_flag_effects::on_all_inactive_variants(
+            self.tcx,
+            self.body,
+            self.move_data(),
+            data.enum_place,
+            data.next_variant(value),
+            |mpi| trans.kill(mpi),
+        );
     }
 }
 
 impl<'tcx> AnalysisDomain<'tcx> for MaybeUninitializedPlaces<'_, 'tcx> {
     type Domain = ChunkedBitSet<MovePathIndex>;
+    type SwitchIntData = MaybePlacesSwitchIntData<'tcx>;
 
     const NAME: &'static str = "maybe_uninit";
 
-    fn bottom_value(&self, _: &mir::Body<'tcx>) -> Self::Domain {
+    fn bottom_value(&self, _: TyCtxt<'tcx>, _: &mir::Body<'tcx>) -> Self::Domain {
         // bottom = initialized (start_block_effect counters this at outset)
         ChunkedBitSet::new_empty(self.move_data().move_paths.len())
     }
@@ -472,6 +489,10 @@ fn domain_size(&self, _: &Body<'tcx>) -> usize {
         self.move_data().move_paths.len()
     }
 
+    fn domain_element_name(&self, idx: Self::Idx, _: &Body<'tcx>) -> String {
+        self.move_data().move_paths[idx].to_string()
+    }
+
     fn statement_effect(
         &mut self,
         trans: &mut impl GenKill<Self::Idx>,
@@ -525,52 +546,61 @@ fn call_return_effect(
         });
     }
 
-    fn switch_int_edge_effects<G: GenKill<Self::Idx>>(
+    fn yield_resume_effect(
+        &mut self,
+        trans: &mut impl GenKill<Self::Idx>,
+        _resume_block: mir::BasicBlock,
+        resume_place: mir::Place<'tcx>,
+    ) {
+        // when a coroutine is resumed, the resume argument is written to
+        // `resume_place`, so set the bits for it to 0 (initialized).
+        on_lookup_result_bits(
+            self.tcx,
+            self.body,
+            self.move_data(),
+            self.move_data().rev_lookup.find(resume_place.as_ref()),
+            |mpi| {
+                trans.kill(mpi);
+            },
+        );
+    }
+
+    fn switch_int_data(
         &mut self,
         block: mir::BasicBlock,
         discr: &mir::Operand<'tcx>,
-        edge_effects: &mut impl SwitchIntEdgeEffects<G>,
-    ) {
+    ) -> Option<Self::SwitchIntData> {
         if !self.tcx.sess.opts.unstable_opts.precise_enum_drop_elaboration {
-            return;
+            return None;
         }
 
         if !self.mark_inactive_variants_as_uninit {
-            return;
+            return None;
         }
 
-        let enum_ = discr.place().and_then(|discr| {
-            switch_on_enum_discriminant(self.tcx, self.body, &self.body[block], discr)
-        });
+        MaybePlacesSwitchIntData::new(self.tcx, self.body, block, discr)
+    }
 
-        let Some((enum_place, enum_def)) = enum_ else {
+    fn switch_int_edge_effect(
+        &mut self,
+        data: &mut Self::SwitchIntData,
+        trans: &mut impl GenKill<Self::Idx>,
+        edge: SwitchIntTarget,
+    ) {
+        let Some(value) = edge.value else {
             return;
         };
 
-        let mut discriminants = enum_def.discriminants(self.tcx);
-        edge_effects.apply(|trans, edge| {
-            let Some(value) = edge.value else {
-                return;
-            };
-
-            // MIR building adds discriminants to the `values` array in the same order as they
-            // are yielded by `AdtDef::discriminants`. We rely on this to match each
-            // discriminant in `values` to its corresponding variant in linear time.
-            let (variant, _) = discriminants
-                .find(|&(_, discr)| discr.val == value)
-                .expect("Order of `AdtDef::discriminants` differed from `SwitchInt::values`");
-
-            // Mark all move paths that correspond to variants other than this one as maybe
-            // uninitialized (in reality, they are *definitely* uninitialized).
-            drop_flag_effects::on_all_inactive_variants(
-                self.tcx,
-                self.body,
-                self.move_data(),
-                enum_place,
-                variant,
-                |mpi| trans.gen(mpi),
-            );
-        });
+        // Mark all move paths that correspond to variants other than this one as maybe
+        // uninitialized (in reality, they are *definitely* uninitialized).
+        drop_flag_effects::on_all_inactive_variants(
+            self.tcx,
+            self.body,
+            self.move_data(),
+            data.enum_place,
+            data.next_variant(value),
+            |mpi| trans.gen(mpi),
+        );
     }
 }
 
@@ -580,7 +610,7 @@ impl<'a, 'tcx> AnalysisDomain<'tcx> for DefinitelyInitializedPlaces<'a, 'tcx> {
 
     const NAME: &'static str = "definite_init";
 
-    fn bottom_value(&self, _: &mir::Body<'tcx>) -> Self::Domain {
+    fn bottom_value(&self, _: TyCtxt<'tcx>, _: &mir::Body<'tcx>) -> Self::Domain {
         // bottom = initialized (start_block_effect counters this at outset)
         lattice::Dual(BitSet::new_filled(self.move_data().move_paths.len()))
     }
@@ -603,6 +633,10 @@ fn domain_size(&self, _: &Body<'tcx>) -> usize {
         self.move_data().move_paths.len()
     }
 
+    fn domain_element_name(&self, idx: Self::Idx, _: &Body<'tcx>) -> String {
+        self.move_data().move_paths[idx].to_string()
+    }
+
     fn statement_effect(
         &mut self,
         trans: &mut impl GenKill<Self::Idx>,
@@ -646,6 +680,25 @@ fn call_return_effect(
             );
         });
     }
+
+    fn yield_resume_effect(
+        &mut self,
+        trans: &mut impl GenKill<Self::Idx>,
+        _resume_block: mir::BasicBlock,
+        resume_place: mir::Place<'tcx>,
+    ) {
+        // when a coroutine is resumed, the resume argument is written to
+        // `resume_place`, so set the bits for it to 1 (initialized).
+        on_lookup_result_bits(
+            self.tcx,
+            self.body,
+            self.move_data(),
+            self.move_data().rev_lookup.find(resume_place.as_ref()),
+            |mpi| {
+                trans.gen(mpi);
+            },
+        );
+    }
 }
 
 impl<'tcx> AnalysisDomain<'tcx> for EverInitializedPlaces<'_, 'tcx> {
@@ -653,7 +706,7 @@ impl<'tcx> AnalysisDomain<'tcx> for EverInitializedPlaces<'_, 'tcx> {
 
     const NAME: &'static str = "ever_init";
 
-    fn bottom_value(&self, _: &mir::Body<'tcx>) -> Self::Domain {
+    fn bottom_value(&self, _: TyCtxt<'tcx>, _: &mir::Body<'tcx>) -> Self::Domain {
         // bottom = no initialized variables by default
         ChunkedBitSet::new_empty(self.move_data().inits.len())
     }
@@ -737,6 +790,16 @@ fn call_return_effect(
             trans.gen(*init_index);
         }
     }
+
+    fn yield_resume_effect(
+        &mut self,
+        _trans: &mut impl GenKill<Self::Idx>,
+        _resume_block: mir::BasicBlock,
+        _resume_place: mir::Place<'tcx>,
+    ) {
+        // The resume place is initialized by an `InitKind::Deep` init at the `Yield` itself,
+        // which `terminator_effect` already applied.
+    }
 }
 
 /// Inspect a `SwitchInt`-terminated basic block to see if the condition of that `SwitchInt` is
@@ -752,6 +815,43 @@ fn call_return_effect(
 ///
 /// If the basic block matches this pattern, this function returns the place corresponding to the
 /// enum (`_1` in the example above) as well as the `AdtDef` of that enum.
+/// The `SwitchInt` data of `MaybeInitializedPlaces` and `MaybeUninitializedPlaces`: the enum
+/// whose discriminant a `SwitchInt` terminator switches on, and its variants.
+pub struct MaybePlacesSwitchIntData<'tcx> {
+    enum_place: mir::Place<'tcx>,
+    discriminants: Vec<(VariantIdx, u128)>,
+    index: usize,
+}
+
+impl<'tcx> MaybePlacesSwitchIntData<'tcx> {
+    fn new(
+        tcx: TyCtxt<'tcx>,
+        body: &Body<'tcx>,
+        block: mir::BasicBlock,
+        discr: &mir::Operand<'tcx>,
+    ) -> Option<Self> {
+        let discr = discr.place()?;
+        let (enum_place, enum_def) = switch_on_enum_discriminant(tcx, body, &body[block], discr)?;
+        let discriminants =
+            enum_def.discriminants(tcx).map(|(variant, discr)| (variant, discr.val)).collect();
+        Some(MaybePlacesSwitchIntData { enum_place, discriminants, index: 0 })
+    }
+
+    /// Returns the variant with the discriminant `value`.
+    ///
+    /// MIR building adds discriminants to the `values` array in the same order as they are yielded
+    /// by `AdtDef::discriminants`, and the engine applies the edge effects in that order. We rely
+    /// on this to match each discriminant in `values` to its corresponding variant in linear time.
+    fn next_variant(&mut self, value: u128) -> VariantIdx {
+        let offset = self.discriminants[self.index..]
+            .iter()
+            .position(|&(_, discr)| discr == value)
+            .expect("Order of `AdtDef::discriminants` differed from `SwitchInt::values`");
+        self.index += offset + 1;
+        self.discriminants[self.index - 1].0
+    }
+}
+
 fn switch_on_enum_discriminant<'mir, 'tcx>(
     tcx: TyCtxt<'tcx>,
     body: &'mir mir::Body<'tcx>,
@@ -1,9 +1,16 @@
+use std::fmt;
+
 use rustc_index::bit_set::{BitSet, ChunkedBitSet};
+use rustc_index::{Idx, IndexVec};
 use rustc_middle::mir::visit::{MutatingUseContext, NonMutatingUseContext, PlaceContext, Visitor};
 use rustc_middle::mir::{
-    self, CallReturnPlaces, Local, Location, Place, StatementKind, TerminatorEdges,
+    self, CallReturnPlaces, Local, Location, Place, PlaceRef, ProjectionElem, StatementKind,
+    TerminatorEdges,
 };
+use rustc_middle::ty::{self, TyCtxt};
+use rustc_target::abi::FieldIdx;
 
+use crate::fmt::DebugWithContext;
 use crate::{Analysis, AnalysisDomain, Backward, GenKill, GenKillAnalysis};
 
 /// A [live-variable dataflow analysis][liveness].
@@ -32,7 +39,7 @@ impl<'tcx> AnalysisDomain<'tcx> for MaybeLiveLocals {
 
     const NAME: &'static str = "liveness";
 
-    fn bottom_value(&self, body: &mir::Body<'tcx>) -> Self::Domain {
+    fn bottom_value(&self, _: TyCtxt<'tcx>, body: &mir::Body<'tcx>) -> Self::Domain {
         // bottom = not live
         ChunkedBitSet::new_empty(body.local_decls.len())
     }
@@ -74,19 +81,24 @@ fn call_return_effect(
         _block: mir::BasicBlock,
         return_places: CallReturnPlaces<'_, 'tcx>,
     ) {
-        if let CallReturnPlaces::Yield(resume_place) = return_places {
-            YieldResumeEffect(trans).visit_place(
-                &resume_place,
-                PlaceContext::MutatingUse(MutatingUseContext::Yield),
-                Location::START,
-            )
-        } else {
-            return_places.for_each(|place| {
-                if let Some(local) = place.as_local() {
-                    trans.kill(local);
-                }
-            });
-        }
+        return_places.for_each(|place| {
+            if let Some(local) = place.as_local() {
+                trans.kill(local);
+            }
+        });
+    }
+
+    fn yield_resume_effect(
+        &mut self,
+        trans: &mut impl GenKill<Self::Idx>,
+        _resume_block: mir::BasicBlock,
+        resume_place: mir::Place<'tcx>,
+    ) {
+        YieldResumeEffect(trans).visit_place(
+            &resume_place,
+            PlaceContext::MutatingUse(MutatingUseContext::Yield),
+            Location::START,
+        )
     }
 }
 
@@ -99,7 +111,7 @@ impl<'tcx, T> Visitor<'tcx> for TransferFunction<'_, T>
     fn visit_place(&mut self, place: &mir::Place<'tcx>, context: PlaceContext, location: Location) {
         if let PlaceContext::MutatingUse(MutatingUseContext::Yield) = context {
             // The resume place is evaluated and assigned to only after coroutine resumes, so its
-            // effect is handled separately in `call_resume_effect`.
+            // effect is handled separately in `yield_resume_effect`.
             return;
         }
 
@@ -239,7 +251,7 @@ impl<'a, 'tcx> AnalysisDomain<'tcx> for MaybeTransitiveLiveLocals<'a> {
 
     const NAME: &'static str = "transitive liveness";
 
-    fn bottom_value(&self, body: &mir::Body<'tcx>) -> Self::Domain {
+    fn bottom_value(&self, _: TyCtxt<'tcx>, body: &mir::Body<'tcx>) -> Self::Domain {
         // bottom = not live
         ChunkedBitSet::new_empty(body.local_decls.len())
     }
@@ -301,18 +313,300 @@ fn apply_call_return_effect(
         _block: mir::BasicBlock,
         return_places: CallReturnPlaces<'_, 'tcx>,
     ) {
-        if let CallReturnPlaces::Yield(resume_place) = return_places {
-            YieldResumeEffect(trans).visit_place(
-                &resume_place,
-                PlaceContext::MutatingUse(MutatingUseContext::Yield),
-                Location::START,
-            )
+        return_places.for_each(|place| {
+            if let Some(local) = place.as_local() {
+                trans.remove(local);
+            }
+        });
+    }
+
+    fn apply_yield_resume_effect(
+        &mut self,
+        trans: &mut Self::Domain,
+        _resume_block: mir::BasicBlock,
+        resume_place: mir::Place<'tcx>,
+    ) {
+        YieldResumeEffect(trans).visit_place(
+            &resume_place,
+            PlaceContext::MutatingUse(MutatingUseContext::Yield),
+            Location::START,
+        )
+    }
+}
+
+rustc_index::newtype_index! {
+    /// Identifies a place tracked by `MaybeLivePlaces`.
+    #[debug_format = "tp{}"]
+    pub struct TrackedPlaceIndex {}
+}
+
+/// The largest number of fields a local may have for `MaybeLivePlaces` to track them separately.
+/// The fields of larger locals are only tracked as part of the local.
+const MAX_TRACKED_FIELDS: usize = 16;
+
+/// A place tracked by `MaybeLivePlaces`: either a whole local, or one of its fields.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct TrackedPlace {
+    pub local: Local,
+    pub field: Option<FieldIdx>,
+}
+
+impl fmt::Debug for TrackedPlace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.field {
+            Some(field) => write!(f, "{:?}.{}", self.local, field.index()),
+            None => write!(f, "{:?}", self.local),
+        }
+    }
+}
+
+/// A field-sensitive [live-variable dataflow analysis][liveness].
+///
+/// Like `MaybeLiveLocals`, but each field of a local of tuple or struct type is tracked separately,
+/// unless the local has more than `MAX_TRACKED_FIELDS` fields. An assignment to a tracked field
+/// kills only that field, even if the local is later read as a whole, and an assignment to a whole
+/// local kills the local and all of its fields. A read through a projection makes the tracked
+/// place it is part of live, i.e. the field it goes through if that is tracked, and the whole local
+/// otherwise.
+///
+/// The bit of a local is set if the local may be read as a whole or through a projection that is
+/// not tracked, so a field is live if its own bit or the bit of its local is set. Use `is_live` to
+/// query the results.
+///
+/// All of the caveats of `MaybeLiveLocals` regarding references apply.
+///
+/// [liveness]: https://en.wikipedia.org/wiki/Live_variable_analysis
+#[derive(Clone)]
+pub struct MaybeLivePlaces {
+    places: IndexVec<TrackedPlaceIndex, TrackedPlace>,
+
+    /// The index of each local. The indices of its tracked fields, if any, directly follow it.
+    locals: IndexVec<Local, TrackedPlaceIndex>,
+}
+
+impl MaybeLivePlaces {
+    pub fn new(body: &mir::Body<'_>) -> Self {
+        let mut places = IndexVec::new();
+        let locals = body
+            .local_decls
+            .iter_enumerated()
+            .map(|(local, decl)| {
+                let index = places.push(TrackedPlace { local, field: None });
+                let num_fields = match decl.ty.kind() {
+                    ty::Tuple(tys) => tys.len(),
+                    ty::Adt(def, _) if def.is_struct() => def.non_enum_variant().fields.len(),
+                    _ => 0,
+                };
+                if num_fields <= MAX_TRACKED_FIELDS {
+                    for field in (0..num_fields).map(FieldIdx::from_usize) {
+                        places.push(TrackedPlace { local, field: Some(field) });
+                    }
+                }
+                index
+            })
+            .collect();
+
+        MaybeLivePlaces { places, locals }
+    }
+
+    /// Returns the tracked place that `place` refers to exactly, if any.
+    pub fn tracked_place(&self, place: PlaceRef<'_>) -> Option<TrackedPlaceIndex> {
+        match place.projection {
+            [] => Some(self.locals[place.local]),
+            [ProjectionElem::Field(field, _)] => self.tracked_field(place.local, *field),
+            _ => None,
+        }
+    }
+
+    /// Returns whether any part of `place` may be live in `state`.
+    ///
+    /// A field is live if it or its local may be read, and a local is live if it or any of its
+    /// fields may be read.
+    pub fn is_live(
+        &self,
+        state: &ChunkedBitSet<TrackedPlaceIndex>,
+        place: TrackedPlaceIndex,
+    ) -> bool {
+        let TrackedPlace { local, field } = self.places[place];
+        match field {
+            Some(_) => state.contains(place) || state.contains(self.locals[local]),
+            None => self.places_of(local).any(|place| state.contains(place)),
+        }
+    }
+
+    /// Returns the tracked places of `local`: the local itself, followed by its fields.
+    fn places_of(&self, local: Local) -> impl Iterator<Item = TrackedPlaceIndex> {
+        let start = self.locals[local];
+        let end = self.locals.get(local.plus(1)).copied().unwrap_or(self.places.next_index());
+        (start.index()..end.index()).map(TrackedPlaceIndex::new)
+    }
+
+    fn tracked_field(&self, local: Local, field: FieldIdx) -> Option<TrackedPlaceIndex> {
+        self.places_of(local).nth(field.index() + 1)
+    }
+
+    /// Returns the tracked place that `place` is part of.
+    fn covering_place(&self, place: PlaceRef<'_>) -> TrackedPlaceIndex {
+        let field = match place.projection {
+            [ProjectionElem::Field(field, _), ..] => self.tracked_field(place.local, *field),
+            _ => None,
+        };
+        field.unwrap_or(self.locals[place.local])
+    }
+
+    /// Kills the tracked place that `place` refers to exactly, along with its fields if it is a
+    /// local. Assignments to places that are not tracked exactly have no effect.
+    ///
+    /// A set local bit makes all of its fields live, so killing a field replaces the local bit with
+    /// the bits of the other fields.
+    fn kill(&self, trans: &mut impl GenKill<TrackedPlaceIndex>, place: PlaceRef<'_>) {
+        match place.projection {
+            [] => trans.kill_all(self.places_of(place.local)),
+            _ => {
+                if let Some(field) = self.tracked_place(place) {
+                    let local = self.locals[place.local];
+                    let siblings = self
+                        .places_of(place.local)
+                        .filter(|&other| other != local && other != field);
+                    trans.kill(local);
+                    trans.gen_all(siblings);
+                    trans.kill(field);
+                }
+            }
+        }
+    }
+}
+
+impl<'tcx> AnalysisDomain<'tcx> for MaybeLivePlaces {
+    type Domain = ChunkedBitSet<TrackedPlaceIndex>;
+    type Direction = Backward;
+
+    const NAME: &'static str = "place_liveness";
+
+    fn bottom_value(&self, _: TyCtxt<'tcx>, _: &mir::Body<'tcx>) -> Self::Domain {
+        // bottom = not live
+        ChunkedBitSet::new_empty(self.places.len())
+    }
+
+    fn initialize_start_block(&self, _: &mir::Body<'tcx>, _: &mut Self::Domain) {
+        // No places are live until we observe a use
+    }
+}
+
+impl<'tcx> GenKillAnalysis<'tcx> for MaybeLivePlaces {
+    type Idx = TrackedPlaceIndex;
+
+    fn domain_size(&self, _: &mir::Body<'tcx>) -> usize {
+        self.places.len()
+    }
+
+    fn domain_element_name(&self, idx: Self::Idx, _body: &mir::Body<'tcx>) -> String {
+        format!("{:?}", self.places[idx])
+    }
+
+    fn statement_effect(
+        &mut self,
+        trans: &mut impl GenKill<Self::Idx>,
+        statement: &mir::Statement<'tcx>,
+        location: Location,
+    ) {
+        PlacesTransferFunction { analysis: self, trans }.visit_statement(statement, location);
+    }
+
+    fn terminator_effect<'mir>(
+        &mut self,
+        trans: &mut Self::Domain,
+        terminator: &'mir mir::Terminator<'tcx>,
+        location: Location,
+    ) -> TerminatorEdges<'mir, 'tcx> {
+        PlacesTransferFunction { analysis: self, trans }.visit_terminator(terminator, location);
+        terminator.edges()
+    }
+
+    fn call_return_effect(
+        &mut self,
+        trans: &mut impl GenKill<Self::Idx>,
+        _block: mir::BasicBlock,
+        return_places: CallReturnPlaces<'_, 'tcx>,
+    ) {
+        return_places.for_each(|place| {
+            if !place.is_indirect() {
+                self.kill(trans, place.as_ref());
+            }
+        });
+    }
+
+    fn yield_resume_effect(
+        &mut self,
+        trans: &mut impl GenKill<Self::Idx>,
+        _resume_block: mir::BasicBlock,
+        resume_place: mir::Place<'tcx>,
+    ) {
+        // See `YieldResumeEffect`.
+        if resume_place.is_indirect() {
+            trans.gen(self.covering_place(resume_place.as_ref()));
         } else {
-            return_places.for_each(|place| {
-                if let Some(local) = place.as_local() {
-                    trans.remove(local);
+            self.kill(trans, resume_place.as_ref());
+        }
+        for elem in resume_place.projection {
+            if let ProjectionElem::Index(local) = elem {
+                trans.gen(self.locals[local]);
+            }
+        }
+    }
+}
+
+impl DebugWithContext<MaybeLivePlaces> for TrackedPlaceIndex {
+    fn fmt_with(&self, ctxt: &MaybeLivePlaces, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", ctxt.places[*self])
+    }
+}
+
+/// The transfer function of `MaybeLivePlaces`. See `TransferFunction` for the field-insensitive
+/// version.
+struct PlacesTransferFunction<'a, T> {
+    analysis: &'a MaybeLivePlaces,
+    trans: &'a mut T,
+}
+
+impl<'tcx, T> Visitor<'tcx> for PlacesTransferFunction<'_, T>
+where
+    T: GenKill<TrackedPlaceIndex>,
+{
+    fn visit_place(&mut self, place: &mir::Place<'tcx>, context: PlaceContext, location: Location) {
+        match context {
+            // The resume place is handled in `yield_resume_effect`, like in `TransferFunction`.
+            PlaceContext::MutatingUse(MutatingUseContext::Yield) => return,
+
+            // Assignments to the return place of a terminator only happen if it returns
+            // successfully, so they are handled in `call_return_effect` as well. Derefs in the
+            // return place are still unconditionally uses, however.
+            PlaceContext::MutatingUse(MutatingUseContext::Call | MutatingUseContext::AsmOutput)
+                if !place.is_indirect() => {}
+
+            // `DefUse` only considers assignments to whole locals to be defs. An assignment to a
+            // field is a def of that field as well, if it is tracked.
+            _ if !place.is_indirect()
+                && DefUse::for_place(place.local.into(), context) == Some(DefUse::Def) =>
+            {
+                self.analysis.kill(self.trans, place.as_ref());
+            }
+
+            _ => {
+                if DefUse::for_place(*place, context) == Some(DefUse::Use) {
+                    self.trans.gen(self.analysis.covering_place(place.as_ref()));
                 }
-            });
+            }
+        }
+
+        self.visit_projection(place.as_ref(), context, location);
+    }
+
+    fn visit_local(&mut self, local: Local, context: PlaceContext, _: Location) {
+        match DefUse::for_place(local.into(), context) {
+            Some(DefUse::Def) => self.analysis.kill(self.trans, local.into()),
+            Some(DefUse::Use) => self.trans.gen(self.analysis.locals[local]),
+            None => {}
         }
     }
 }
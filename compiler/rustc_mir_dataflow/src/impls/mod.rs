@@ -3,17 +3,20 @@
 //! zero-sized structure.
 
 mod borrowed_locals;
+mod exposed_locals;
 mod initialized;
 mod liveness;
 mod storage_liveness;
 
 pub use self::borrowed_locals::borrowed_locals;
-pub use self::borrowed_locals::MaybeBorrowedLocals;
+pub use self::borrowed_locals::{MaybeBorrowedLocals, MaybeBorrowedLocalsByKind};
+pub use self::exposed_locals::AddressExposedLocals;
 pub use self::initialized::{
     DefinitelyInitializedPlaces, EverInitializedPlaces, MaybeInitializedPlaces,
     MaybeUninitializedPlaces,
 };
 pub use self::liveness::MaybeLiveLocals;
+pub use self::liveness::{MaybeLivePlaces, TrackedPlace, TrackedPlaceIndex};
 pub use self::liveness::MaybeTransitiveLiveLocals;
 pub use self::liveness::TransferFunction as LivenessTransferFunction;
 pub use self::storage_liveness::{MaybeRequiresStorage, MaybeStorageDead, MaybeStorageLive};
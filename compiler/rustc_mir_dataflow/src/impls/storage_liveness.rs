@@ -1,11 +1,12 @@
 use rustc_index::bit_set::BitSet;
 use rustc_middle::mir::visit::{NonMutatingUseContext, PlaceContext, Visitor};
 use rustc_middle::mir::*;
+use rustc_middle::ty::TyCtxt;
 
 use std::borrow::Cow;
 
 use super::MaybeBorrowedLocals;
-use crate::{GenKill, ResultsClonedCursor};
+use crate::{Dependency, GenKill};
 
 #[derive(Clone)]
 pub struct MaybeStorageLive<'a> {
@@ -29,7 +30,7 @@ impl<'tcx, 'a> crate::AnalysisDomain<'tcx> for MaybeStorageLive<'a> {
 
     const NAME: &'static str = "maybe_storage_live";
 
-    fn bottom_value(&self, body: &Body<'tcx>) -> Self::Domain {
+    fn bottom_value(&self, _: TyCtxt<'tcx>, body: &Body<'tcx>) -> Self::Domain {
         // bottom = dead
         BitSet::new_empty(body.local_decls.len())
     }
@@ -102,7 +103,7 @@ impl<'tcx> crate::AnalysisDomain<'tcx> for MaybeStorageDead {
 
     const NAME: &'static str = "maybe_storage_dead";
 
-    fn bottom_value(&self, body: &Body<'tcx>) -> Self::Domain {
+    fn bottom_value(&self, _: TyCtxt<'tcx>, body: &Body<'tcx>) -> Self::Domain {
         // bottom = live
         BitSet::new_empty(body.local_decls.len())
     }
@@ -158,8 +159,7 @@ fn call_return_effect(
     }
 }
 
-type BorrowedLocalsResults<'res, 'mir, 'tcx> =
-    ResultsClonedCursor<'res, 'mir, 'tcx, MaybeBorrowedLocals>;
+type BorrowedLocalsResults<'res, 'mir, 'tcx> = Dependency<'res, 'mir, 'tcx, MaybeBorrowedLocals>;
 
 /// Dataflow analysis that determines whether each local requires storage at a
 /// given location; i.e. whether its storage can go away without being observed.
@@ -175,7 +175,7 @@ pub fn new(borrowed_locals: BorrowedLocalsResults<'res, 'mir, 'tcx>) -> Self {
 
 impl crate::CloneAnalysis for MaybeRequiresStorage<'_, '_, '_> {
     fn clone_analysis(&self) -> Self {
-        Self { borrowed_locals: self.borrowed_locals.new_cursor() }
+        Self { borrowed_locals: self.borrowed_locals.clone() }
     }
 }
 
@@ -184,7 +184,7 @@ impl<'tcx> crate::AnalysisDomain<'tcx> for MaybeRequiresStorage<'_, '_, 'tcx> {
 
     const NAME: &'static str = "requires_storage";
 
-    fn bottom_value(&self, body: &Body<'tcx>) -> Self::Domain {
+    fn bottom_value(&self, _: TyCtxt<'tcx>, body: &Body<'tcx>) -> Self::Domain {
         // bottom = dead
         BitSet::new_empty(body.local_decls.len())
     }
@@ -212,7 +212,7 @@ fn before_statement_effect(
         loc: Location,
     ) {
         // If a place is borrowed in a statement, it needs storage for that statement.
-        self.borrowed_locals.mut_analysis().statement_effect(trans, stmt, loc);
+        self.borrowed_locals.analysis_mut().statement_effect(trans, stmt, loc);
 
         match &stmt.kind {
             StatementKind::StorageDead(l) => trans.kill(*l),
@@ -257,7 +257,7 @@ fn before_terminator_effect(
     ) {
         // If a place is borrowed in a terminator, it needs storage for that terminator.
         self.borrowed_locals
-            .mut_analysis()
+            .analysis_mut()
             .transfer_function(trans)
             .visit_terminator(terminator, loc);
 
@@ -353,6 +353,17 @@ fn call_return_effect(
     ) {
         return_places.for_each(|place| trans.gen(place.local));
     }
+
+    fn yield_resume_effect(
+        &mut self,
+        trans: &mut impl GenKill<Self::Idx>,
+        _resume_block: BasicBlock,
+        resume_place: Place<'tcx>,
+    ) {
+        // The resume argument is written when the coroutine is resumed, so it requires storage
+        // along the resume edge (but not along the drop edge, see `before_terminator_effect`).
+        trans.gen(resume_place.local);
+    }
 }
 
 impl<'tcx> MaybeRequiresStorage<'_, '_, 'tcx> {
@@ -375,8 +386,7 @@ impl<'tcx, T> Visitor<'tcx> for MoveVisitor<'_, '_, '_, 'tcx, T>
 {
     fn visit_local(&mut self, local: Local, context: PlaceContext, loc: Location) {
         if PlaceContext::NonMutatingUse(NonMutatingUseContext::Move) == context {
-            self.borrowed_locals.seek_before_primary_effect(loc);
-            if !self.borrowed_locals.contains(local) {
+            if !self.borrowed_locals.state_before(loc).contains(local) {
                 self.trans.kill(local);
             }
         }
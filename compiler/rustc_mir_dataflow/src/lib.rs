@@ -3,6 +3,7 @@
 #![feature(exact_size_is_empty)]
 #![feature(let_chains)]
 #![feature(min_specialization)]
+#![feature(never_type)]
 #![feature(stmt_expr_attributes)]
 #![feature(trusted_step)]
 #![recursion_limit = "256"]
@@ -18,6 +19,7 @@
 use rustc_errors::{DiagnosticMessage, SubdiagnosticMessage};
 use rustc_fluent_macro::fluent_messages;
 use rustc_hir::def_id::DefId;
+use rustc_middle::query::Providers;
 use rustc_middle::ty::{self, TyCtxt};
 use rustc_span::symbol::{sym, Symbol};
 
@@ -26,10 +28,14 @@
     move_path_children_matching, on_all_children_bits, on_lookup_result_bits,
 };
 pub use self::framework::{
-    fmt, graphviz, lattice, visit_results, Analysis, AnalysisDomain, AnalysisResults, Backward,
-    CloneAnalysis, Direction, Engine, Forward, GenKill, GenKillAnalysis, JoinSemiLattice,
-    MaybeReachable, Results, ResultsCloned, ResultsClonedCursor, ResultsCursor, ResultsRefCursor,
-    ResultsVisitable, ResultsVisitor, SwitchIntEdgeEffects,
+    adhoc, append_dataflow_to_mir_dump, fmt, graphviz, lattice, visit_results, visit_results_dyn,
+    Analysis, AnalysisDomain, AnalysisResults, Backward, BidirectionalEngine, Chain, ChunkedRepr,
+    CloneAnalysis, ConvergenceInfo, CountingAnalysis, CoupledAnalysis, DataflowCache, DenseRepr,
+    Dependency, Direction, DirectionKind, Engine, ErasedAnalysisRunner, ErasedResults,
+    FalseEdgeSemantics, Forward, GenKill, GenKillAnalysis, JoinSemiLattice, MapDomain,
+    MaybeReachable, RenumberedBitsetAnalysis, Results, ResultsCloned, ResultsClonedCursor,
+    ResultsCursor, ResultsRefCursor, ResultsVisitable, ResultsVisitor, SwitchIntTarget,
+    TraceAnalysis, TraceGenKillAnalysis, TransferCounts,
 };
 
 use self::move_paths::MoveData;
@@ -52,6 +58,10 @@ pub(crate) mod indexes {
     pub(crate) use super::move_paths::MovePathIndex;
 }
 
+pub fn provide(providers: &mut Providers) {
+    providers.rustc_mir_attrs = framework::parse_rustc_mir_attrs;
+}
+
 pub struct MoveDataParamEnv<'tcx> {
     pub move_data: MoveData<'tcx>,
     pub param_env: ty::ParamEnv<'tcx>,
@@ -1,7 +1,7 @@
 use rustc_span::symbol::sym;
 use rustc_span::Span;
 
-use rustc_index::bit_set::ChunkedBitSet;
+use rustc_index::bit_set::{BitSet, ChunkedBitSet};
 use rustc_middle::mir::MirPass;
 use rustc_middle::mir::{self, Body, Local, Location};
 use rustc_middle::ty::{self, Ty, TyCtxt};
@@ -12,7 +12,9 @@
 };
 use crate::framework::BitSetExt;
 use crate::impls::{
-    DefinitelyInitializedPlaces, MaybeInitializedPlaces, MaybeLiveLocals, MaybeUninitializedPlaces,
+    AddressExposedLocals, DefinitelyInitializedPlaces, MaybeBorrowedLocalsByKind,
+    MaybeInitializedPlaces, MaybeLiveLocals, MaybeLivePlaces, MaybeUninitializedPlaces,
+    TrackedPlaceIndex,
 };
 use crate::move_paths::{HasMoveData, MoveData};
 use crate::move_paths::{LookupResult, MovePathIndex};
@@ -67,6 +69,51 @@ fn run_pass(&self, tcx: TyCtxt<'tcx>, body: &mut Body<'tcx>) {
             sanity_check_via_rustc_peek(tcx, flow_liveness.into_results_cursor(body));
         }
 
+        if has_rustc_mir_with(tcx, def_id, sym::rustc_peek_liveness_places).is_some() {
+            let flow_liveness =
+                MaybeLivePlaces::new(body).into_engine(tcx, body).iterate_to_fixpoint();
+
+            sanity_check_via_rustc_peek(tcx, flow_liveness.into_results_cursor(body));
+        }
+
+        if has_rustc_mir_with(tcx, def_id, sym::rustc_peek_shared_borrowed).is_some() {
+            let flow_borrowed =
+                MaybeBorrowedLocalsByKind::new(body).into_engine(tcx, body).iterate_to_fixpoint();
+
+            sanity_check_via_rustc_peek_with(
+                tcx,
+                flow_borrowed.into_results_cursor(body),
+                |_, tcx, place, state, call| {
+                    peek_at_local(
+                        tcx,
+                        place,
+                        MaybeBorrowedLocalsByKind::shared_borrowed(state),
+                        call,
+                    )
+                },
+            );
+        }
+
+        if has_rustc_mir_with(tcx, def_id, sym::rustc_peek_mut_borrowed).is_some() {
+            let flow_borrowed =
+                MaybeBorrowedLocalsByKind::new(body).into_engine(tcx, body).iterate_to_fixpoint();
+
+            sanity_check_via_rustc_peek_with(
+                tcx,
+                flow_borrowed.into_results_cursor(body),
+                |_, tcx, place, state, call| {
+                    peek_at_local(tcx, place, MaybeBorrowedLocalsByKind::mut_borrowed(state), call)
+                },
+            );
+        }
+
+        if has_rustc_mir_with(tcx, def_id, sym::rustc_peek_address_exposed).is_some() {
+            let flow_exposed =
+                AddressExposedLocals::new(body).into_engine(tcx, body).iterate_to_fixpoint();
+
+            sanity_check_via_rustc_peek(tcx, flow_exposed.into_results_cursor(body));
+        }
+
         if has_rustc_mir_with(tcx, def_id, sym::stop_after_dataflow).is_some() {
             tcx.sess.emit_fatal(StopAfterDataFlowEndedCompilation);
         }
@@ -89,11 +136,22 @@ fn run_pass(&self, tcx: TyCtxt<'tcx>, body: &mut Body<'tcx>) {
 /// (If there are any calls to `rustc_peek` that do not match the
 /// expression form above, then that emits an error as well, but those
 /// errors are not intended to be used for unit tests.)
-pub fn sanity_check_via_rustc_peek<'tcx, A>(
+pub fn sanity_check_via_rustc_peek<'tcx, A>(tcx: TyCtxt<'tcx>, cursor: ResultsCursor<'_, 'tcx, A>)
+where
+    A: RustcPeekAt<'tcx>,
+{
+    sanity_check_via_rustc_peek_with(tcx, cursor, A::peek_at)
+}
+
+/// Like `sanity_check_via_rustc_peek`, but checks the state with `peek_at` instead of
+/// `RustcPeekAt`. This lets one analysis be checked in different ways, e.g. for each component of
+/// its state.
+pub fn sanity_check_via_rustc_peek_with<'tcx, A>(
     tcx: TyCtxt<'tcx>,
     mut cursor: ResultsCursor<'_, 'tcx, A>,
+    peek_at: impl Fn(&A, TyCtxt<'tcx>, mir::Place<'tcx>, &A::Domain, PeekCall),
 ) where
-    A: RustcPeekAt<'tcx>,
+    A: Analysis<'tcx>,
 {
     let def_id = cursor.body().source.def_id();
     debug!("sanity_check_via_rustc_peek def_id: {:?}", def_id);
@@ -130,7 +188,7 @@ pub fn sanity_check_via_rustc_peek<'tcx, A>(
                 let loc = Location { block: bb, statement_index };
                 cursor.seek_before_primary_effect(loc);
                 let (state, analysis) = cursor.get_with_analysis();
-                analysis.peek_at(tcx, *place, state, call);
+                peek_at(analysis, tcx, *place, state, call);
             }
 
             _ => {
@@ -266,14 +324,56 @@ fn peek_at(
         place: mir::Place<'tcx>,
         flow_state: &ChunkedBitSet<Local>,
         call: PeekCall,
+    ) {
+        peek_at_local(tcx, place, flow_state, call)
+    }
+}
+
+impl<'tcx> RustcPeekAt<'tcx> for AddressExposedLocals {
+    fn peek_at(
+        &self,
+        tcx: TyCtxt<'tcx>,
+        place: mir::Place<'tcx>,
+        flow_state: &BitSet<Local>,
+        call: PeekCall,
+    ) {
+        peek_at_local(tcx, place, flow_state, call)
+    }
+}
+
+/// Checks that `place`, which must be a local, is in `flow_state`.
+fn peek_at_local<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    place: mir::Place<'tcx>,
+    flow_state: &impl BitSetExt<Local>,
+    call: PeekCall,
+) {
+    info!(?place, "peek_at");
+    let Some(local) = place.as_local() else {
+        tcx.sess.emit_err(PeekArgumentNotALocal { span: call.span });
+        return;
+    };
+
+    if !flow_state.contains(local) {
+        tcx.sess.emit_err(PeekBitNotSet { span: call.span });
+    }
+}
+
+impl<'tcx> RustcPeekAt<'tcx> for MaybeLivePlaces {
+    fn peek_at(
+        &self,
+        tcx: TyCtxt<'tcx>,
+        place: mir::Place<'tcx>,
+        flow_state: &ChunkedBitSet<TrackedPlaceIndex>,
+        call: PeekCall,
     ) {
         info!(?place, "peek_at");
-        let Some(local) = place.as_local() else {
-            tcx.sess.emit_err(PeekArgumentNotALocal { span: call.span });
+        let Some(tracked_place) = self.tracked_place(place.as_ref()) else {
+            tcx.sess.emit_err(PeekArgumentUntracked { span: call.span });
             return;
         };
 
-        if !flow_state.contains(local) {
+        if !self.is_live(flow_state, tracked_place) {
             tcx.sess.emit_err(PeekBitNotSet { span: call.span });
         }
     }
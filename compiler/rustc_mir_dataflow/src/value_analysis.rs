@@ -46,9 +46,7 @@
 use rustc_target::abi::{FieldIdx, VariantIdx};
 
 use crate::lattice::{HasBottom, HasTop};
-use crate::{
-    fmt::DebugWithContext, Analysis, AnalysisDomain, JoinSemiLattice, SwitchIntEdgeEffects,
-};
+use crate::{fmt::DebugWithContext, Analysis, AnalysisDomain, JoinSemiLattice};
 
 pub trait ValueAnalysis<'tcx> {
     /// For each place of interest, the analysis tracks a value of the given type.
@@ -336,7 +334,7 @@ impl<'tcx, T: ValueAnalysis<'tcx>> AnalysisDomain<'tcx> for ValueAnalysisWrapper
 
     const NAME: &'static str = T::NAME;
 
-    fn bottom_value(&self, _body: &Body<'tcx>) -> Self::Domain {
+    fn bottom_value(&self, _: TyCtxt<'tcx>, _body: &Body<'tcx>) -> Self::Domain {
         State(StateData::Unreachable)
     }
 
@@ -389,14 +387,6 @@ fn apply_call_return_effect(
             self.0.handle_call_return(return_places, state)
         }
     }
-
-    fn apply_switch_int_edge_effects(
-        &mut self,
-        _block: BasicBlock,
-        _discr: &Operand<'tcx>,
-        _apply_edge_effects: &mut impl SwitchIntEdgeEffects<Self::Domain>,
-    ) {
-    }
 }
 
 rustc_index::newtype_index!(
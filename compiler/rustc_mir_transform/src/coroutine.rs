@@ -73,7 +73,7 @@
     MaybeBorrowedLocals, MaybeLiveLocals, MaybeRequiresStorage, MaybeStorageLive,
 };
 use rustc_mir_dataflow::storage::always_storage_live_locals;
-use rustc_mir_dataflow::{self, Analysis};
+use rustc_mir_dataflow::{self, Analysis, Dependency};
 use rustc_span::def_id::{DefId, LocalDefId};
 use rustc_span::symbol::sym;
 use rustc_span::Span;
@@ -670,7 +670,7 @@ fn locals_live_across_suspend_points<'tcx>(
     // Calculate the MIR locals that we actually need to keep storage around
     // for.
     let mut requires_storage_results =
-        MaybeRequiresStorage::new(borrowed_locals_results.cloned_results_cursor(body))
+        MaybeRequiresStorage::new(Dependency::new(&borrowed_locals_results, body))
             .into_engine(tcx, body_ref)
             .iterate_to_fixpoint();
     let mut requires_storage_cursor = requires_storage_results.as_results_cursor(body_ref);
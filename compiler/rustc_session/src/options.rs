@@ -1559,6 +1559,9 @@ pub(crate) fn parse_llvm_module_flag(
     dump_mir_dataflow: bool = (false, parse_bool, [UNTRACKED],
         "in addition to `.mir` files, create graphviz `.dot` files with dataflow results \
         (default: no)"),
+    dump_mir_dataflow_phases: Option<Vec<String>> = (None, parse_opt_comma_list, [UNTRACKED],
+        "only write dataflow results for analysis runs in one of these comma-separated phases \
+        (default: all runs)"),
     dump_mir_dir: String = ("mir_dump".to_string(), parse_string, [UNTRACKED],
         "the directory the MIR is dumped into (default: `mir_dump`)"),
     dump_mir_exclude_pass_number: bool = (false, parse_bool, [UNTRACKED],
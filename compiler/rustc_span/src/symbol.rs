@@ -467,8 +467,13 @@
         black_box,
         block,
         bool,
+        borrowck_graphviz_blocks,
+        borrowck_graphviz_font_size,
         borrowck_graphviz_format,
         borrowck_graphviz_postflow,
+        borrowck_graphviz_track_bit,
+        borrowck_graphviz_wrap,
+        borrowck_uncached_block,
         box_new,
         box_patterns,
         box_syntax,
@@ -844,6 +849,7 @@
         global_allocator,
         global_asm,
         globs,
+        graphml,
         gt,
         half_open_range_patterns,
         half_open_range_patterns_in_slices,
@@ -1441,10 +1447,14 @@
         rustc_partition_reused,
         rustc_pass_by_value,
         rustc_peek,
+        rustc_peek_address_exposed,
         rustc_peek_definite_init,
         rustc_peek_liveness,
+        rustc_peek_liveness_places,
         rustc_peek_maybe_init,
         rustc_peek_maybe_uninit,
+        rustc_peek_mut_borrowed,
+        rustc_peek_shared_borrowed,
         rustc_polymorphize_error,
         rustc_private,
         rustc_proc_macro_decls,
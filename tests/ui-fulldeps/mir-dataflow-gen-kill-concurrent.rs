@@ -0,0 +1,83 @@
+// run-pass
+// Test that `Engine::new_gen_kill_concurrent` reaches the same fixpoint as `Engine::new_gen_kill`.
+// Whether the transfer functions are actually computed in parallel depends on the compiler.
+
+// ignore-stage1
+// ignore-cross-compile
+// ignore-remote
+// ignore-windows-gnu mingw has troubles with linking https://github.com/rust-lang/rust/pull/116837
+// edition: 2021
+
+#![feature(rustc_private)]
+
+extern crate rustc_driver;
+extern crate rustc_interface;
+extern crate rustc_middle;
+extern crate rustc_mir_dataflow;
+#[macro_use]
+extern crate rustc_smir;
+extern crate stable_mir;
+
+use rustc_middle::ty::TyCtxt;
+use rustc_mir_dataflow::impls::MaybeLiveLocals;
+use rustc_mir_dataflow::Engine;
+use rustc_smir::rustc_internal;
+use std::io::Write;
+use std::ops::ControlFlow;
+
+const CRATE_NAME: &str = "input";
+
+fn test_concurrent(tcx: TyCtxt<'_>) -> ControlFlow<()> {
+    for def_id in tcx.mir_keys(()) {
+        let body = tcx.optimized_mir(def_id.to_def_id());
+        let sequential = Engine::new_gen_kill(tcx, body, MaybeLiveLocals).iterate_to_fixpoint();
+        let concurrent =
+            Engine::new_gen_kill_concurrent(tcx, body, MaybeLiveLocals).iterate_to_fixpoint();
+        for bb in body.basic_blocks.indices() {
+            assert_eq!(sequential.entry_set_for_block(bb), concurrent.entry_set_for_block(bb));
+        }
+    }
+    ControlFlow::Continue(())
+}
+
+fn main() {
+    let path = "input.rs";
+    generate_input(&path).unwrap();
+    let args = vec![
+        "rustc".to_string(),
+        "--crate-type=lib".to_string(),
+        "--crate-name".to_string(),
+        CRATE_NAME.to_string(),
+        path.to_string(),
+    ];
+    run!(args, tcx, test_concurrent(tcx)).unwrap();
+}
+
+fn generate_input(path: &str) -> std::io::Result<()> {
+    let mut file = std::fs::File::create(path)?;
+    write!(
+        file,
+        r#"
+    pub fn sum(n: u32) -> u32 {{
+        let mut total = 0;
+        let mut i = 0;
+        while i < n {{
+            total += i;
+            i += 1;
+        }}
+        total
+    }}
+
+    pub fn max(xs: &[u32]) -> Option<u32> {{
+        let mut best = None;
+        for &x in xs {{
+            if best.map_or(true, |b| x > b) {{
+                best = Some(x);
+            }}
+        }}
+        best
+    }}
+    "#
+    )?;
+    Ok(())
+}
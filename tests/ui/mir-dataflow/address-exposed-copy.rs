@@ -0,0 +1,18 @@
+#![feature(core_intrinsics, rustc_attrs)]
+
+use std::intrinsics::{copy_nonoverlapping, rustc_peek};
+use std::ptr;
+
+#[rustc_mir(rustc_peek_address_exposed, stop_after_dataflow)]
+fn foo() {
+    // Copying a pointer to `x` with `copy_nonoverlapping` stores it behind `dst`, where this body
+    // cannot follow it, so it exposes `x` like an assignment through `dst` would.
+    let x = 0;
+    let src: *const i32 = &x;
+    let mut dst: *const i32 = ptr::null();
+    unsafe { rustc_peek(x); } //~ ERROR bit not set
+    unsafe { copy_nonoverlapping(&src, &mut dst, 1); }
+    unsafe { rustc_peek(x); }
+}
+
+fn main() {}
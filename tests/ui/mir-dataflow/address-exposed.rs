@@ -0,0 +1,48 @@
+#![feature(core_intrinsics, rustc_attrs)]
+
+use std::intrinsics::rustc_peek;
+
+fn opaque<T>(_: T) {}
+
+#[rustc_mir(rustc_peek_address_exposed, stop_after_dataflow)]
+fn foo() {
+    // A pointer derived from `x` through a chain of reborrows and casts exposes `x` once it is
+    // passed to a call.
+    let mut x = 0;
+    let r = &mut x;
+    let r2 = &mut *r;
+    let p = r2 as *mut i32;
+    unsafe { rustc_peek(x); } //~ ERROR bit not set
+    opaque(p);
+    unsafe { rustc_peek(x); }
+
+    // So does casting a pointer to an integer.
+    let y = 0;
+    let _addr = &y as *const i32 as usize;
+    unsafe { rustc_peek(y); }
+
+    // A borrow that is only dereferenced does not expose anything.
+    let z = 0;
+    let rz = &z;
+    let _v = *rz;
+    unsafe { rustc_peek(z); } //~ ERROR bit not set
+
+    // Passing a pointer to a pointer exposes everything reachable through it.
+    let v = 0;
+    let rv = &v;
+    let prv = &rv;
+    unsafe { rustc_peek(v); } //~ ERROR bit not set
+    opaque(prv);
+    unsafe { rustc_peek(v); }
+
+    // The exposure of `w` in one iteration ends with its storage, before the next iteration.
+    let mut i = 0;
+    while i < 2 {
+        let w = 0;
+        unsafe { rustc_peek(w); } //~ ERROR bit not set
+        opaque(&w);
+        i += 1;
+    }
+}
+
+fn main() {}
@@ -0,0 +1,34 @@
+#![feature(core_intrinsics, rustc_attrs)]
+
+use std::intrinsics::rustc_peek;
+use std::ptr;
+
+#[rustc_mir(rustc_peek_mut_borrowed, stop_after_dataflow)]
+fn foo() {
+    let x = 0;
+    let mut y = 0;
+    let z = 0;
+    let _r = &x;
+    let _m = &mut y;
+    let _p = ptr::addr_of!(z);
+
+    // Shared borrows are not mutable borrows, but raw pointers are, even `*const` ones.
+    unsafe { rustc_peek(x); } //~ ERROR bit not set
+    unsafe { rustc_peek(y); }
+    unsafe { rustc_peek(z); }
+
+    // A two-phase borrow becomes a mutable borrow when it is activated by the call.
+    let mut v = Vec::new();
+    v.push(v.len());
+    unsafe { rustc_peek(&v); }
+
+    // Until then, it is only a shared borrow.
+    let mut w = Vec::new();
+    w.push({
+        unsafe { rustc_peek(&w); } //~ ERROR bit not set
+        0
+    });
+    unsafe { rustc_peek(&w); }
+}
+
+fn main() {}
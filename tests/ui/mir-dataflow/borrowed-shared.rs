@@ -0,0 +1,22 @@
+#![feature(core_intrinsics, rustc_attrs)]
+
+use std::intrinsics::rustc_peek;
+use std::ptr;
+
+#[rustc_mir(rustc_peek_shared_borrowed, stop_after_dataflow)]
+fn foo() {
+    let x = 0;
+    let mut y = 0;
+    let z = 0;
+    let _r = &x;
+    let _m = &mut y;
+    let _p = ptr::addr_of!(z);
+
+    unsafe { rustc_peek(x); }
+
+    // Mutable borrows and raw pointers are not shared borrows.
+    unsafe { rustc_peek(y); } //~ ERROR bit not set
+    unsafe { rustc_peek(z); } //~ ERROR bit not set
+}
+
+fn main() {}
@@ -0,0 +1,22 @@
+// Test of maybe_inits state computed by MIR dataflow across the resume edge of a `yield`.
+
+#![feature(core_intrinsics, coroutines, rustc_attrs, stmt_expr_attributes)]
+
+use std::intrinsics::rustc_peek;
+
+struct S(i32);
+
+fn main() {
+    let _ = #[rustc_mir(rustc_peek_maybe_init, stop_after_dataflow)]
+    |x: S| {
+        let y: S;
+        // `y` starts off uninitialized, so we get an error report here.
+        rustc_peek(&y); //~ ERROR rustc_peek: bit not set
+
+        // The resume argument is written to `y` only when the coroutine is resumed, while `x` is
+        // moved out by the `yield`.
+        y = yield x;
+        rustc_peek(&y);
+        rustc_peek(&x); //~ ERROR rustc_peek: bit not set
+    };
+}
@@ -0,0 +1,42 @@
+#![feature(core_intrinsics, rustc_attrs)]
+
+use std::intrinsics::rustc_peek;
+
+struct S {
+    a: i32,
+    b: i32,
+}
+
+#[rustc_mir(rustc_peek_liveness_places, stop_after_dataflow)]
+fn foo() {
+    let mut x = S { a: 0, b: 0 };
+    let mut y: (i32, i32) = (42, 0);
+
+    // An assignment to a field kills that field. `MaybeLiveLocals` considers `x` live here, since
+    // such an assignment does not define `x` (see `liveness-projection.rs`).
+    unsafe { rustc_peek(x.a); } //~ ERROR bit not set
+    x.a = 1;
+
+    // The other fields of the same local are unaffected...
+    unsafe { rustc_peek(x.b); }
+
+    // ...and the local is live as long as any of its fields is.
+    unsafe { rustc_peek(&x); }
+    println!("{} {}", x.a, x.b);
+
+    // An assignment to a whole local kills all of its fields.
+    unsafe { rustc_peek(y.0); } //~ ERROR bit not set
+    unsafe { rustc_peek(y.1); } //~ ERROR bit not set
+    y = (1, 2);
+    println!("{}", y.0 + y.1);
+
+    // An assignment to a field kills that field even if the whole local is read afterwards, since
+    // that read sees the assigned value. The other fields stay live.
+    let mut z = S { a: 0, b: 0 };
+    unsafe { rustc_peek(z.a); } //~ ERROR bit not set
+    unsafe { rustc_peek(z.b); }
+    z.a = 1;
+    drop(z);
+}
+
+fn main() {}
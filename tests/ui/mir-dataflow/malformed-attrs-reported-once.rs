@@ -0,0 +1,16 @@
+// Borrowck runs several dataflow analyses on `foo`, each of which reads its `rustc_mir`
+// attributes before dumping its results. The malformed attribute must still be reported only once.
+
+// compile-flags: -Z dump-mir-dataflow -Z deduplicate-diagnostics=no
+
+#![feature(rustc_attrs)]
+
+#[rustc_mir(borrowck_graphviz_format = "bogus")] //~ ERROR unknown formatter
+fn foo(x: Option<String>) -> usize {
+    let y = x;
+    y.map_or(0, |s| s.len())
+}
+
+fn main() {
+    foo(None);
+}
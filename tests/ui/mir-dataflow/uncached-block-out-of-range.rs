@@ -0,0 +1,17 @@
+// `foo` has no `bb1000`, which is reported instead of being ignored. Borrowck runs several gen/kill
+// analyses on `foo`, but the error is only reported once.
+
+#![feature(rustc_attrs)]
+
+#[rustc_mir(borrowck_uncached_block = "bb1000")] //~ ERROR uncached block must be a basic block
+fn foo(n: usize) -> usize {
+    let mut sum = 0;
+    for i in 0..n {
+        sum += i;
+    }
+    sum
+}
+
+fn main() {
+    foo(3);
+}